@@ -0,0 +1,26 @@
+use criterion::{BatchSize, BenchmarkId, Criterion, criterion_group, criterion_main};
+use shooting_game::bench::CollisionBench;
+
+/// Bullet and enemy counts per scenario; each scenario spawns this many of both.
+const SIZES: [usize; 3] = [100, 1_000, 10_000];
+
+fn bullet_enemy_collision(c: &mut Criterion) {
+    let mut group = c.benchmark_group("bullet_enemy_collision");
+    // The pairwise check is quadratic, so the large scenario needs fewer samples.
+    group.sample_size(10);
+
+    for size in SIZES {
+        group.bench_with_input(BenchmarkId::from_parameter(size), &size, |b, &size| {
+            // Collisions despawn entities, so every iteration starts from a fresh world.
+            b.iter_batched_ref(
+                || CollisionBench::new(size, size),
+                CollisionBench::run,
+                BatchSize::LargeInput,
+            );
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bullet_enemy_collision);
+criterion_main!(benches);
@@ -0,0 +1,102 @@
+//! An opt-in local log of how each run went, for tuning the balance.
+//!
+//! With the setting on, every game over appends one JSON line to `ANALYTICS_PATH`.
+//! Nothing is sent anywhere. The write happens off the main thread, and any error is
+//! dropped, since a missing line is better than a stutter or a warning for something
+//! the player never sees.
+
+use bevy::prelude::*;
+use bevy::tasks::IoTaskPool;
+use serde::Serialize;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+
+use crate::{
+    Bullet, DailyChallenge, Difficulty, GameOverReason, Missed, Modifiers, Score, Settings,
+    WaveManager, format_day,
+};
+
+const ANALYTICS_PATH: &str = "analytics.jsonl";
+/// Once the log reaches this size it is moved here, replacing the last one, and a
+/// fresh log started.
+const ANALYTICS_ROTATED_PATH: &str = "analytics.1.jsonl";
+const ANALYTICS_MAX_BYTES: u64 = 1024 * 1024;
+
+/// Whole-run totals. `RunStats` starts over each wave, so it can't stand in. A resumed
+/// run counts from where it was resumed.
+#[derive(Resource, Default)]
+pub struct RunAnalytics {
+    shots: u32,
+    misses: u32,
+    secs: f32,
+}
+
+#[derive(Serialize)]
+struct RunRecord {
+    score: u32,
+    wave: u32,
+    /// Share of shots that hit something, or `None` if nothing was fired.
+    accuracy: Option<f32>,
+    secs: f32,
+    difficulty: f32,
+    cause: GameOverReason,
+    /// Date of the daily challenge, if the run was one.
+    daily: Option<String>,
+    /// Names of the modifiers the run was played with.
+    modifiers: Vec<String>,
+}
+
+pub fn track_analytics(
+    mut analytics: ResMut<RunAnalytics>,
+    bullet_query: Query<(), Added<Bullet>>,
+    mut missed_events: EventReader<Missed>,
+    time: Res<Time>,
+) {
+    analytics.shots += bullet_query.iter().count() as u32;
+    analytics.misses += missed_events.read().count() as u32;
+    analytics.secs += time.delta_seconds();
+}
+
+pub fn log_run(
+    settings: Res<Settings>,
+    analytics: Res<RunAnalytics>,
+    score: Res<Score>,
+    waves: Res<WaveManager>,
+    difficulty: Res<Difficulty>,
+    (reason, daily, modifiers): (Res<GameOverReason>, Res<DailyChallenge>, Res<Modifiers>),
+) {
+    if !settings.analytics {
+        return;
+    }
+    let record = RunRecord {
+        score: score.0,
+        wave: waves.wave,
+        accuracy: (analytics.shots > 0).then(|| {
+            analytics.shots.saturating_sub(analytics.misses) as f32 / analytics.shots as f32
+        }),
+        secs: analytics.secs,
+        difficulty: difficulty.level,
+        cause: *reason,
+        daily: daily.day.map(format_day),
+        modifiers: modifiers.names(),
+    };
+    let Ok(line) = serde_json::to_string(&record) else {
+        return;
+    };
+    IoTaskPool::get()
+        .spawn(async move {
+            let _ = append(&line);
+        })
+        .detach();
+}
+
+fn append(line: &str) -> std::io::Result<()> {
+    if fs::metadata(ANALYTICS_PATH).is_ok_and(|metadata| metadata.len() >= ANALYTICS_MAX_BYTES) {
+        fs::rename(ANALYTICS_PATH, ANALYTICS_ROTATED_PATH)?;
+    }
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(ANALYTICS_PATH)?;
+    writeln!(file, "{line}")
+}
@@ -0,0 +1,278 @@
+//! Spawn tuning loaded as an asset, so edits to `assets/balance.json` apply while
+//! the game is running.
+
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, AsyncReadExt, LoadContext};
+use bevy::prelude::*;
+use bevy::utils::BoxedFuture;
+use serde::Deserialize;
+use std::fmt;
+
+pub const BALANCE_PATH: &str = "balance.json";
+
+#[derive(Asset, TypePath, Clone, Copy, Debug, Deserialize)]
+pub struct Balance {
+    pub max_budget: f32,
+    pub refill_per_sec: f32,
+    /// Total cost of live enemies the director aims for at difficulty 1.0.
+    pub target_on_screen_cost: f32,
+    /// Spawns the director may make in one frame, a formation counting as one. Budget
+    /// left over after a hitch carries into the following frames instead.
+    pub max_spawns_per_frame: u32,
+    /// Seconds at the start of a run before anything spawns. Zero disables it.
+    pub grace_period_secs: f32,
+    /// Every this many waves, enemies arrive through portals that open half a second
+    /// ahead of them instead of popping in at the top. Zero never uses portals.
+    pub portal_wave_interval: u32,
+    /// Share of the graze meter each grazing enemy bullet fills.
+    pub graze_fill: f32,
+    /// Most player bullets on screen at once. Zero leaves shots unlimited.
+    pub player_bullet_cap: u32,
+    /// Quiet seconds between clearing a wave and the next one starting to spawn.
+    pub wave_break_secs: f32,
+    /// Score a thief takes when it reaches the ship, never more than the player has.
+    pub thief_steal: u32,
+    /// Seconds a freeze pulse holds enemies still.
+    pub freeze_secs: f32,
+    /// Seconds between a patrolling carrier's drops.
+    pub carrier_deploy_secs: f32,
+    /// Sideways pull a tractor puts on the ship right beneath it, in pixels a second.
+    /// It fades out towards `tractor_range`.
+    pub tractor_pull: f32,
+    pub tractor_range: f32,
+    /// Health a regenerator wins back a second, once it has gone `regen_stagger_secs`
+    /// without a hit.
+    pub regen_per_sec: f32,
+    pub regen_stagger_secs: f32,
+    /// Once a formation is down to this many enemies, its leader included, they break
+    /// off and dive at the ship. Zero keeps formations together to the last.
+    pub formation_break_at: u32,
+    /// How fast a broken formation's divers pick up speed, in pixels a second squared.
+    /// Divers proper use 900.
+    pub formation_dive_acceleration: f32,
+    /// How fast a formation marches from side to side at difficulty's starting enemy
+    /// speed, in pixels a second, and how far it steps down each time it turns at an
+    /// edge. A speed of zero leaves formations coming straight down.
+    pub formation_sweep_speed: f32,
+    pub formation_sweep_step: f32,
+    /// How hard the director evens out spawns between the left and right of the field,
+    /// from 0.0 for purely random sides to 1.0.
+    pub spawn_side_balancing: f32,
+    /// How often each kind of lone enemy spawns, against the others.
+    pub spawn_weights: SpawnWeights,
+    /// Width and height of the ship. Its hitbox is a circle as wide as the narrower side.
+    pub player_size: [f32; 2],
+    /// Size of a basic enemy and a standard shot. Other kinds keep their proportions to
+    /// these, and enemies are hit wherever their sprite is drawn.
+    pub enemy_size: [f32; 2],
+    pub bullet_size: [f32; 2],
+}
+
+impl Default for Balance {
+    fn default() -> Self {
+        Balance {
+            max_budget: 5.0,
+            refill_per_sec: 1.0,
+            target_on_screen_cost: 8.0,
+            max_spawns_per_frame: 2,
+            grace_period_secs: 2.0,
+            portal_wave_interval: 3,
+            graze_fill: 0.05,
+            player_bullet_cap: 0,
+            wave_break_secs: 3.0,
+            thief_steal: 100,
+            freeze_secs: 2.5,
+            carrier_deploy_secs: 2.5,
+            tractor_pull: 180.0,
+            tractor_range: 500.0,
+            regen_per_sec: 1.0,
+            regen_stagger_secs: 1.2,
+            formation_break_at: 2,
+            formation_dive_acceleration: 1200.0,
+            formation_sweep_speed: 60.0,
+            formation_sweep_step: 20.0,
+            spawn_side_balancing: 0.5,
+            spawn_weights: SpawnWeights::default(),
+            player_size: [50.0, 50.0],
+            enemy_size: [40.0, 40.0],
+            bullet_size: [5.0, 15.0],
+        }
+    }
+}
+
+/// A kind's share of lone spawns, relative to the other kinds' weights.
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct SpawnWeight {
+    /// Weight at difficulty 1.0.
+    pub base: f32,
+    /// Added for each point of difficulty above 1.0. The weight never drops below zero.
+    pub per_level: f32,
+}
+
+impl SpawnWeight {
+    const fn new(base: f32, per_level: f32) -> Self {
+        SpawnWeight { base, per_level }
+    }
+
+    pub fn at(self, level: f32) -> f32 {
+        (self.base + self.per_level * (level - 1.0)).max(0.0)
+    }
+}
+
+/// Basic enemies thin out as difficulty rises, leaving more room for the tougher kinds.
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct SpawnWeights {
+    pub basic: SpawnWeight,
+    pub miner: SpawnWeight,
+    pub diver: SpawnWeight,
+    pub gunner: SpawnWeight,
+    pub reflector: SpawnWeight,
+    pub armored: SpawnWeight,
+    pub cloaker: SpawnWeight,
+    pub thief: SpawnWeight,
+    pub carrier: SpawnWeight,
+    pub tractor: SpawnWeight,
+    pub regenerator: SpawnWeight,
+}
+
+impl SpawnWeights {
+    fn named(&self) -> [(&'static str, SpawnWeight); 11] {
+        [
+            ("spawn_weights.basic", self.basic),
+            ("spawn_weights.miner", self.miner),
+            ("spawn_weights.diver", self.diver),
+            ("spawn_weights.gunner", self.gunner),
+            ("spawn_weights.reflector", self.reflector),
+            ("spawn_weights.armored", self.armored),
+            ("spawn_weights.cloaker", self.cloaker),
+            ("spawn_weights.thief", self.thief),
+            ("spawn_weights.carrier", self.carrier),
+            ("spawn_weights.tractor", self.tractor),
+            ("spawn_weights.regenerator", self.regenerator),
+        ]
+    }
+}
+
+impl Default for SpawnWeights {
+    fn default() -> Self {
+        SpawnWeights {
+            basic: SpawnWeight::new(0.35, -0.06),
+            miner: SpawnWeight::new(0.15, 0.02),
+            diver: SpawnWeight::new(0.15, 0.03),
+            gunner: SpawnWeight::new(0.1, 0.03),
+            reflector: SpawnWeight::new(0.08, 0.03),
+            armored: SpawnWeight::new(0.06, 0.04),
+            cloaker: SpawnWeight::new(0.06, 0.03),
+            thief: SpawnWeight::new(0.05, 0.01),
+            carrier: SpawnWeight::new(0.02, 0.01),
+            tractor: SpawnWeight::new(0.03, 0.01),
+            regenerator: SpawnWeight::new(0.03, 0.02),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum BalanceError {
+    Io(std::io::Error),
+    Parse(serde_json::Error),
+    Invalid(&'static str),
+}
+
+impl fmt::Display for BalanceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BalanceError::Io(err) => write!(f, "could not read balance file: {err}"),
+            BalanceError::Parse(err) => write!(f, "could not parse balance file: {err}"),
+            BalanceError::Invalid(field) => write!(f, "{field} is out of range"),
+        }
+    }
+}
+
+impl std::error::Error for BalanceError {}
+
+#[derive(Default)]
+pub struct BalanceLoader;
+
+impl AssetLoader for BalanceLoader {
+    type Asset = Balance;
+    type Settings = ();
+    type Error = BalanceError;
+
+    // A failed load leaves the previous asset in place, so a half-saved file keeps
+    // the last good values.
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Balance, BalanceError>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader
+                .read_to_end(&mut bytes)
+                .await
+                .map_err(BalanceError::Io)?;
+            let balance: Balance = serde_json::from_slice(&bytes).map_err(BalanceError::Parse)?;
+
+            for (field, value) in [
+                ("max_budget", balance.max_budget),
+                ("refill_per_sec", balance.refill_per_sec),
+                ("target_on_screen_cost", balance.target_on_screen_cost),
+                ("graze_fill", balance.graze_fill),
+                ("freeze_secs", balance.freeze_secs),
+                ("carrier_deploy_secs", balance.carrier_deploy_secs),
+                ("tractor_pull", balance.tractor_pull),
+                ("tractor_range", balance.tractor_range),
+                ("regen_stagger_secs", balance.regen_stagger_secs),
+                (
+                    "formation_dive_acceleration",
+                    balance.formation_dive_acceleration,
+                ),
+                (
+                    "player_size",
+                    balance.player_size[0].min(balance.player_size[1]),
+                ),
+                (
+                    "enemy_size",
+                    balance.enemy_size[0].min(balance.enemy_size[1]),
+                ),
+                (
+                    "bullet_size",
+                    balance.bullet_size[0].min(balance.bullet_size[1]),
+                ),
+            ] {
+                if !(value.is_finite() && value > 0.0) {
+                    return Err(BalanceError::Invalid(field));
+                }
+            }
+            if balance.max_spawns_per_frame == 0 {
+                return Err(BalanceError::Invalid("max_spawns_per_frame"));
+            }
+            for (field, value) in [
+                ("grace_period_secs", balance.grace_period_secs),
+                ("wave_break_secs", balance.wave_break_secs),
+                ("regen_per_sec", balance.regen_per_sec),
+                ("formation_sweep_speed", balance.formation_sweep_speed),
+                ("formation_sweep_step", balance.formation_sweep_step),
+            ] {
+                if !(value.is_finite() && value >= 0.0) {
+                    return Err(BalanceError::Invalid(field));
+                }
+            }
+            if !(0.0..=1.0).contains(&balance.spawn_side_balancing) {
+                return Err(BalanceError::Invalid("spawn_side_balancing"));
+            }
+            for (field, weight) in balance.spawn_weights.named() {
+                if !(weight.base.is_finite() && weight.base >= 0.0 && weight.per_level.is_finite())
+                {
+                    return Err(BalanceError::Invalid(field));
+                }
+            }
+            Ok(balance)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["json"]
+    }
+}
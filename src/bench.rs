@@ -0,0 +1,99 @@
+//! Headless worlds for the Criterion benches in `benches/`. Not part of the game's API.
+
+use bevy::ecs::schedule::ExecutorKind;
+use bevy::prelude::*;
+use rand::prelude::*;
+use rand::rngs::StdRng;
+
+use crate::{
+    BULLET_MAX_LIFETIME_SECS, Bullet, BulletKind, CameraPunch, ChainKill, Combo, Enemy,
+    EnemyDestroyed, EnemyKind, GameRng, HitStop, Score, ScoringConfig, Settings, SpawnDirector,
+    bullet_enemy_collision,
+};
+
+/// A world holding `bullets` bullets and `enemies` basic enemies scattered over the
+/// playfield, with `bullet_enemy_collision` as its only system.
+pub struct CollisionBench {
+    world: World,
+    schedule: Schedule,
+}
+
+impl CollisionBench {
+    pub fn new(bullets: usize, enemies: usize) -> Self {
+        let mut world = World::new();
+        let mut rng = StdRng::seed_from_u64(0);
+
+        for _ in 0..bullets {
+            world.spawn((
+                Bullet {
+                    kind: BulletKind::Standard,
+                    velocity: Vec2::new(0.0, 500.0),
+                    damage: 1,
+                    lifetime: Timer::from_seconds(BULLET_MAX_LIFETIME_SECS, TimerMode::Once),
+                },
+                Sprite {
+                    custom_size: Some(BulletKind::Standard.size()),
+                    ..default()
+                },
+                random_transform(&mut rng),
+            ));
+        }
+        for _ in 0..enemies {
+            world.spawn((
+                Enemy {
+                    speed: 100.0,
+                    kind: EnemyKind::Basic,
+                    health: EnemyKind::Basic.max_health(),
+                },
+                Sprite {
+                    custom_size: Some(EnemyKind::Basic.size()),
+                    ..default()
+                },
+                random_transform(&mut rng),
+            ));
+        }
+
+        world.insert_resource(Score(0));
+        world.insert_resource(Combo::default());
+        world.insert_resource(CameraPunch::default());
+        world.insert_resource(HitStop::default());
+        world.insert_resource(ScoringConfig::default());
+        world.insert_resource(Settings::default());
+        world.insert_resource(SpawnDirector::default());
+        world.insert_resource(Time::<()>::default());
+        world.insert_resource(GameRng(rng));
+        world.init_resource::<Events<ChainKill>>();
+        world.init_resource::<Events<EnemyDestroyed>>();
+
+        let mut schedule = Schedule::default();
+        schedule.set_executor_kind(ExecutorKind::SingleThreaded);
+        schedule.add_systems(bullet_enemy_collision);
+
+        Self { world, schedule }
+    }
+
+    /// Runs one frame of collision checks, including applying the queued despawns.
+    pub fn run(&mut self) {
+        self.schedule.run(&mut self.world);
+    }
+}
+
+fn random_transform(rng: &mut StdRng) -> Transform {
+    Transform::from_xyz(
+        rng.gen_range(-400.0..400.0),
+        rng.gen_range(-300.0..300.0),
+        0.0,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_fixture_runs() {
+        let mut bench = CollisionBench::new(200, 50);
+        bench.run();
+        bench.run();
+    }
+}
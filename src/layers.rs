@@ -0,0 +1,69 @@
+//! Z depths for world sprites, from back to front. Everything spawned in the world
+//! should take its depth from here so overlaps stay predictable.
+
+/// Under everything, so a shadow never darkens a sprite or a bullet.
+pub const SHADOWS: f32 = 0.25;
+pub const MAGNET_RING: f32 = 0.5;
+pub const MINES: f32 = 1.0;
+pub const TELEGRAPHS: f32 = 1.5;
+pub const BLASTS: f32 = 2.0;
+pub const PORTALS: f32 = 2.5;
+/// Behind enemies, so a beam never covers the tractor it comes from.
+pub const TRACTOR_BEAMS: f32 = 2.75;
+pub const ENEMIES: f32 = 3.0;
+pub const HEALTH_BARS: f32 = 3.5;
+pub const POWER_UPS: f32 = 4.0;
+pub const PLAYER_BULLETS: f32 = 5.0;
+/// Below enemy fire, which must stay visible through the trail of a dash.
+pub const AFTERIMAGES: f32 = 5.5;
+pub const BARRIERS: f32 = 5.6;
+pub const AURAS: f32 = 5.75;
+pub const ENEMY_BULLETS: f32 = 6.0;
+pub const PLAYER: f32 = 7.0;
+pub const PARTICLES: f32 = 8.0;
+
+// Enemy fire must never hide behind anything it can hit.
+const _: () = assert!(
+    SHADOWS < MAGNET_RING
+        && MAGNET_RING < MINES
+        && MINES < TELEGRAPHS
+        && TELEGRAPHS < BLASTS
+        && BLASTS < PORTALS
+        && PORTALS < TRACTOR_BEAMS
+        && TRACTOR_BEAMS < ENEMIES
+        && ENEMIES < HEALTH_BARS
+        && HEALTH_BARS < POWER_UPS
+        && POWER_UPS < PLAYER_BULLETS
+        && PLAYER_BULLETS < AFTERIMAGES
+        && AFTERIMAGES < BARRIERS
+        && BARRIERS < AURAS
+        && AURAS < ENEMY_BULLETS
+        && ENEMY_BULLETS < PLAYER
+        && PLAYER < PARTICLES
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gameplay_layers_stack_in_order() {
+        let stack = [
+            MINES,
+            ENEMIES,
+            POWER_UPS,
+            PLAYER_BULLETS,
+            ENEMY_BULLETS,
+            PLAYER,
+            PARTICLES,
+        ];
+        assert!(stack.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn enemy_fire_stays_above_what_it_passes_over() {
+        for layer in [SHADOWS, TRACTOR_BEAMS, BLASTS, AFTERIMAGES, BARRIERS, AURAS] {
+            assert!(layer < ENEMY_BULLETS);
+        }
+    }
+}
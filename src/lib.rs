@@ -0,0 +1,13854 @@
+mod analytics;
+mod balance;
+#[doc(hidden)]
+pub mod bench;
+mod layers;
+mod patterns;
+mod replay;
+mod save;
+
+use analytics::{RunAnalytics, log_run, track_analytics};
+use balance::{BALANCE_PATH, Balance, BalanceLoader, SpawnWeights};
+use bevy::a11y::accesskit::{NodeBuilder, Role};
+use bevy::a11y::{AccessibilityNode, AccessibilitySystem, Focus};
+use bevy::app::AppExit;
+use bevy::asset::LoadState;
+use bevy::asset::io::file::FileAssetReader;
+use bevy::audio::{SpatialListener, Volume};
+use bevy::diagnostic::{DiagnosticsStore, FrameTimeDiagnosticsPlugin};
+use bevy::ecs::schedule::ExecutorKind;
+use bevy::ecs::system::SystemParam;
+use bevy::hierarchy::despawn_with_children_recursive;
+use bevy::math::cubic_splines::{CubicBezier, CubicCurve, CubicGenerator};
+use bevy::prelude::*;
+use bevy::render::camera::Viewport;
+use bevy::sprite::{Anchor, MaterialMesh2dBundle};
+use bevy::transform::TransformSystem;
+use bevy::utils::{HashMap, HashSet};
+use bevy::window::{PresentMode, PrimaryWindow, WindowCloseRequested, WindowFocused, WindowMode};
+use patterns::{Pattern, Zone, steer, thin};
+use rand::prelude::*;
+use rand::rngs::StdRng;
+use replay::{ReplayMode, finish_replay, play_input, record_aim, record_keys, replaying};
+use save::{
+    PendingSave, discard_save, drop_pending_save, handle_save_menu, offer_saved_game, save_game,
+    save_offered,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::f32::consts::{FRAC_PI_2, FRAC_PI_3, FRAC_PI_4, PI, TAU};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const BASE_WINDOW_TITLE: &str = "Shooting Game";
+const USAGE: &str = "\
+Usage: shooting-game [OPTIONS]
+
+Options:
+      --difficulty <LEVEL>  Starting difficulty level (1.0 is normal)
+      --weapon <WEAPON>     Start each run with a weapon power-up: bounce, rapid, pierce,
+                            allrange, flame, reflect
+      --seed <SEED>         Seed gameplay randomness for reproducible runs
+      --god                 Enable debug cheats with player damage disabled
+      --sandbox             Start in sandbox mode, for practice with no score
+      --record <FILE>       Record this session's input to a replay file on exit
+      --replay <FILE>       Play back a recorded replay, then exit
+  -h, --help                Print this help and exit";
+const MAX_BOUNCES: u32 = 3;
+const POWER_UP_DROP_CHANCE: f64 = 0.1;
+const POWER_UP_DURATION_SECS: f32 = 10.0;
+/// Most time a power-up that stacks its duration can bank.
+const MAX_POWER_UP_STACK_SECS: f32 = 25.0;
+const POWER_UP_SIZE: Vec2 = Vec2::new(20.0, 20.0);
+/// With score chips on, a kill drops one chip for every this many points it was worth,
+/// up to `SCORE_CHIP_MAX_COUNT`.
+const SCORE_CHIP_POINTS_EACH: u32 = 10;
+const SCORE_CHIP_MAX_COUNT: u32 = 5;
+const SCORE_CHIP_SIZE: Vec2 = Vec2::new(8.0, 8.0);
+const SCORE_CHIP_COLOR: Color = Color::GOLD;
+const SCORE_CHIP_LIFETIME_SECS: f32 = 4.0;
+/// Share of its value a chip still pays out if it runs out or falls away uncollected.
+const SCORE_CHIP_EXPIRED_SHARE: f32 = 0.5;
+/// Chips pop up and fan out from the kill, then fall.
+const SCORE_CHIP_POP_SPEED: f32 = 120.0;
+const SCORE_CHIP_SPREAD_SPEED: f32 = 40.0;
+const SCORE_CHIP_GRAVITY: f32 = 300.0;
+const SCORE_CHIP_FALL_SPEED: f32 = 120.0;
+const SCORE_CHIP_PICKUP_RADIUS: f32 = 30.0;
+const PLAYER_COLOR: Color = Color::BLUE;
+const THRUSTER_COLOR: Color = Color::ORANGE;
+const THRUSTER_SIZE: Vec2 = Vec2::new(14.0, 18.0);
+/// Flame length while idle, as a fraction of `THRUSTER_SIZE`.
+const THRUSTER_IDLE_SCALE: f32 = 0.4;
+/// Flame length at full speed; dashes stretch it further, up to twice this.
+const THRUSTER_MOVING_SCALE: f32 = 1.0;
+const THRUSTER_FLICKER_HZ: f32 = 18.0;
+const PLAYER_SPEED: f32 = 300.0;
+const PLAYER_FIRE_INTERVAL_SECS: f32 = 0.5;
+/// Where shots leave the ship's nose, relative to its centre.
+const CENTER_GUN: Vec2 = Vec2::new(0.0, 30.0);
+const RAPID_FIRE_INTERVAL_FACTOR: f32 = 0.4;
+/// Each rapid fire picked up while it is active adds a tier, up to the max, and each
+/// tier past the first cuts the interval by this much again.
+const RAPID_FIRE_MAX_TIER: u32 = 3;
+const RAPID_FIRE_TIER_FACTOR: f32 = 0.8;
+const MIN_FIRE_INTERVAL_SECS: f32 = 0.15;
+/// A fire press this long before the next shot is due still fires it.
+const FIRE_BUFFER_SECS: f32 = 0.1;
+const RAPID_FIRE_COLOR: Color = Color::ORANGE;
+const PIERCE_COLOR: Color = Color::PURPLE;
+const ALL_RANGE_COLOR: Color = Color::GOLD;
+const ALL_RANGE_DIRECTIONS: u32 = 8;
+/// All-range volleys are eight shots wide, so they come this much slower to keep the
+/// number of bullets on screen in check.
+const ALL_RANGE_INTERVAL_FACTOR: f32 = 2.5;
+const FLAME_COLOR: Color = Color::ORANGE_RED;
+const REFLECT_COLOR: Color = Color::SILVER;
+/// Enemy bullets that come this close to a reflecting ship are turned back.
+const REFLECT_RADIUS: f32 = 40.0;
+/// Opacity of the reflect aura, which shimmers up and down by `REFLECT_SHIMMER`.
+const REFLECT_AURA_ALPHA: f32 = 0.25;
+const REFLECT_SHIMMER: f32 = 0.1;
+const BARRIER_COLOR: Color = Color::TURQUOISE;
+/// A barrier stands this far above where the ship was when it was picked up.
+const BARRIER_OFFSET: f32 = 70.0;
+const BARRIER_SIZE: Vec2 = Vec2::new(120.0, 8.0);
+const BARRIER_HITS: u32 = 8;
+const BARRIER_SECS: f32 = 8.0;
+/// Crack marks across a barrier, shown one by one as it takes hits.
+const BARRIER_CRACKS: u32 = 4;
+/// How long after a shot hits an enemy before that same shot can hit it again. Longer
+/// than a piercing shot takes to pass through, so each one lands once per enemy.
+const HIT_IMMUNITY_SECS: f32 = 0.2;
+/// Damage a second dealt by a burn, for `BURN_SECS` after the last flame hit.
+const BURN_DPS: f32 = 1.5;
+const BURN_SECS: f32 = 3.0;
+/// How far a burning enemy's colour is pulled towards `FLAME_COLOR`.
+const BURN_TINT: f32 = 0.6;
+/// Embers given off a second by each burning enemy.
+const EMBER_RATE: f64 = 12.0;
+const CHAIN_POPUP_SECS: f32 = 0.8;
+const GUNNER_FIRE_INTERVAL_SECS: f32 = 2.5;
+/// Wind-up at difficulty 1.0; it shortens in proportion as difficulty rises.
+const GUNNER_WIND_UP_SECS: f32 = 0.8;
+const GUNNER_BULLET_SPEED: f32 = 220.0;
+const BASE_ENEMY_BULLET_CAP: usize = 120;
+const ENEMY_BULLET_CAP_PER_LEVEL: usize = 20;
+/// How fast a thief closes in sideways on the ship, and climbs away once it has stolen.
+const THIEF_CHASE_SPEED: f32 = 90.0;
+const THIEF_FLEE_SPEED: f32 = 260.0;
+const THIEF_CONTACT_DISTANCE: f32 = 35.0;
+/// Paid on top of the stolen score for shooting a thief down before it gets away.
+const THIEF_REFUND_BONUS: u32 = 50;
+const THIEF_STEAL_COLOR: Color = Color::rgb(1.0, 0.35, 0.35);
+const CARRIER_PATROL_Y: f32 = 220.0;
+const CARRIER_SPEED: f32 = 90.0;
+const CARRIER_DEPLOYS: u32 = 5;
+/// How far below a carrier's centre its enemies are dropped.
+const CARRIER_DEPLOY_OFFSET: f32 = 30.0;
+/// A tractor stops coming down here, holding still for `TRACTOR_HOVER_SECS`.
+const TRACTOR_HOVER_Y: f32 = 150.0;
+const TRACTOR_HOVER_SECS: f32 = 8.0;
+/// However many tractors pull at once, the ship can still move against them at this
+/// share of its speed.
+const TRACTOR_MAX_PULL_SHARE: f32 = 0.6;
+/// Horizontal gap under which the pull eases off, so the ship settles beneath a
+/// tractor instead of being tugged back and forth across it.
+const TRACTOR_PULL_EASE: f32 = 30.0;
+const TRACTOR_BEAM_WIDTH: f32 = 14.0;
+const TRACTOR_BEAM_COLOR: Color = Color::rgb(0.75, 0.45, 1.0);
+/// A regenerator glows towards this while it heals, pulsing `REGEN_PULSE_SPEED`
+/// radians a second, and dulls towards the other while a hit has it staggered.
+const REGEN_COLOR: Color = Color::rgb(0.6, 1.0, 0.6);
+const REGEN_PULSE_SPEED: f32 = 8.0;
+const STAGGERED_COLOR: Color = Color::rgb(0.45, 0.45, 0.45);
+const STAGGER_TINT: f32 = 0.5;
+/// Seconds without input on the pause or game-over screen before attract mode starts,
+/// and how long it shows each of its slides.
+const ATTRACT_IDLE_SECS: f32 = 20.0;
+const ATTRACT_SLIDE_SECS: f32 = 4.0;
+const ATTRACT_SLIDES: usize = 3;
+/// How quickly the score on screen counts up to the real one, per second.
+const SCORE_COUNT_RATE: f32 = 8.0;
+/// A cloaker stays visible, and can be shot, for the first span, then cloaks for the second.
+const CLOAK_VISIBLE_SECS: f32 = 2.0;
+const CLOAK_HIDDEN_SECS: f32 = 1.2;
+/// Alpha while cloaked, shimmering by up to `CLOAK_SHIMMER` either way.
+const CLOAK_ALPHA: f32 = 0.2;
+const CLOAK_SHIMMER: f32 = 0.1;
+const CLOAK_SHIMMER_SPEED: f32 = 12.0;
+const WEAK_POINT_COLOR: Color = Color::rgb(1.0, 0.85, 0.2);
+const WEAK_POINT_HALF_SIZE: Vec2 = Vec2::new(7.0, 7.0);
+/// How far the weak point slides either side of centre, and how fast, in radians/s.
+const WEAK_POINT_SWAY: f32 = 10.0;
+const WEAK_POINT_SWAY_SPEED: f32 = 2.5;
+/// Half a player bullet's width, so grazing the weak point still counts.
+const WEAK_POINT_HIT_MARGIN: f32 = 3.0;
+const SPARK_COUNT: usize = 5;
+const HEALTH_BAR_SIZE: Vec2 = Vec2::new(30.0, 4.0);
+/// Gap between the top of an enemy's sprite and its health bar.
+const HEALTH_BAR_GAP: f32 = 6.0;
+/// Behind the focused menu item, which is written in black over it.
+const MENU_FOCUS_COLOR: Color = Color::GOLD;
+const MENU_FOCUS_LABEL_HEIGHT: f32 = 24.0;
+/// Time to fade to black around a change of screen, and again to fade back in.
+const SCREEN_FADE_SECS: f32 = 0.25;
+const SANDBOX_PANEL_COLOR: Color = Color::rgb(0.6, 0.9, 1.0);
+/// How far below the top edge the sandbox's pattern preview fires from.
+const PREVIEW_EMITTER_INSET: f32 = 80.0;
+const PREVIEW_EMITTER_SIZE: f32 = 24.0;
+const MAX_PREVIEW_COUNT: u32 = 64;
+/// Steps for tweaking a previewed pattern's speed and angle, which also serve as the
+/// least either can be turned down to.
+const PREVIEW_SPEED_STEP: f32 = 20.0;
+const PREVIEW_ANGLE_STEP: f32 = 0.05;
+/// Share of each death effect's particles kept under reduce motion.
+const REDUCED_DEATH_PARTICLES: f32 = 0.35;
+/// Share of cosmetic particles kept while effects are thinned out.
+const LOW_EFFECTS_SCALE: f32 = 0.4;
+/// Effects are thinned once the frame rate has stayed below the first rate for
+/// `GOVERNOR_HOLD_SECS`, and restored once it has stayed above the second as long.
+/// The gap and the hold keep them from flickering on and off around one threshold.
+const GOVERNOR_LOW_FPS: f64 = 45.0;
+const GOVERNOR_HIGH_FPS: f64 = 55.0;
+const GOVERNOR_HOLD_SECS: f32 = 2.0;
+const ENEMY_BULLET_SIZE: Vec2 = Vec2::new(10.0, 10.0);
+/// Any bullet still around after this long is removed, in case bouncing or steering
+/// keeps it from ever leaving the screen.
+const BULLET_MAX_LIFETIME_SECS: f32 = 10.0;
+const ENEMY_BULLET_COLOR: Color = Color::ORANGE_RED;
+const TELEGRAPH_COLOR: Color = Color::rgb(1.0, 0.1, 0.1);
+const TELEGRAPH_ALPHA: f32 = 0.35;
+/// Warning given before a telegraphed attack lands at difficulty 1.0. Harder runs get
+/// proportionally less, down to `MIN_TELEGRAPH_SECS`.
+const TELEGRAPH_SECS: f32 = 1.0;
+const MIN_TELEGRAPH_SECS: f32 = 0.4;
+const TELEGRAPH_PULSE_HZ: f32 = 6.0;
+/// Width of the reflector's shield, in radians, centred on the side facing the player.
+const REFLECTOR_SHIELD_ARC: f32 = 2.0 * FRAC_PI_3;
+/// Reflected shots come back slower than they went out, so they can be dodged.
+const REFLECTED_SPEED_FACTOR: f32 = 0.5;
+const SHIELD_COLOR: Color = Color::CYAN;
+const FIRST_WAVE_SIZE: u32 = 15;
+const WAVE_SIZE_STEP: u32 = 5;
+const WAVE_CLEARED_COLOR: Color = Color::rgb(0.6, 1.0, 0.7);
+/// Longest the "wave cleared" banner stays up, cut short by a shorter break.
+const WAVE_CLEARED_BANNER_SECS: f32 = 2.0;
+const NEW_ENEMY_COLOR: Color = Color::rgb(1.0, 0.8, 0.5);
+const NEW_ENEMY_TOAST_SECS: f32 = 2.5;
+const FORMATION_SPAWN_CHANCE: f64 = 0.1;
+const SWARM_SPAWN_CHANCE: f64 = 0.05;
+/// How much of `SpawnDirector::lean` carries over to the next spawn; the rest comes
+/// from the side that spawn took, so only the last few spawns really count.
+const SPAWN_LEAN_CARRY: f32 = 0.75;
+const SWARM_SIZE: u32 = 8;
+/// Swarmers only steer by neighbours this close, and at most `SWARM_MAX_NEIGHBORS` of
+/// them, so a big swarm costs no more per enemy than a small one.
+const SWARM_NEIGHBOR_RADIUS: f32 = 90.0;
+const SWARM_MAX_NEIGHBORS: usize = 6;
+/// Pull toward the neighbours' centre, and push away from any closer than
+/// `SWARM_SEPARATION_RADIUS`, both per second.
+const SWARM_COHESION: f32 = 1.5;
+const SWARM_SEPARATION: f32 = 12.0;
+const SWARM_SEPARATION_RADIUS: f32 = 30.0;
+/// Sideways pull toward the player, in pixels per second squared per pixel apart.
+const SWARM_PLAYER_PULL: f32 = 0.6;
+const SWARM_MAX_STEER_SPEED: f32 = 140.0;
+/// Evasive enemies sidestep any shot that would pass within `DODGE_RADIUS` of them
+/// in the next `DODGE_LOOKAHEAD_SECS`, easing up to `DODGE_SPEED`.
+const DODGE_RADIUS: f32 = 32.0;
+const DODGE_LOOKAHEAD_SECS: f32 = 0.5;
+const DODGE_SPEED: f32 = 160.0;
+const DODGE_ACCELERATION: f32 = 900.0;
+/// Shots are bucketed into cells this size, and each evasive enemy checks only the
+/// cells beside and below it, and at most `DODGE_MAX_BULLETS` shots in them.
+const DODGE_CELL_SIZE: f32 = 100.0;
+const DODGE_MAX_BULLETS: usize = 8;
+/// Enough for a standard shot to cover in `DODGE_LOOKAHEAD_SECS`.
+const DODGE_CELLS_BELOW: i32 = 3;
+/// Slot offsets from the leader, which flies at the point of the V.
+const FORMATION_OFFSETS: [Vec2; 4] = [
+    Vec2::new(-45.0, 30.0),
+    Vec2::new(45.0, 30.0),
+    Vec2::new(-90.0, 60.0),
+    Vec2::new(90.0, 60.0),
+];
+/// Kills needed per step of the combo multiplier.
+const COMBO_STEP: u32 = 5;
+const MAX_COMBO_MULTIPLIER: u32 = 4;
+/// How much faster, and so higher, the milestone sound plays for each tier above x2.
+const COMBO_PITCH_STEP: f32 = 0.2;
+const COMBO_GLOW_WIDTH: f32 = 10.0;
+/// Border glow for the x2 tier and up, warming as the multiplier climbs.
+const COMBO_GLOW_COLORS: [Color; 3] = [
+    Color::rgb(1.0, 0.85, 0.3),
+    Color::rgb(1.0, 0.55, 0.1),
+    Color::rgb(1.0, 0.2, 0.35),
+];
+/// Opacity at the top tier; lower tiers glow proportionally fainter.
+const COMBO_GLOW_MAX_ALPHA: f32 = 0.6;
+/// How quickly the glow eases towards its tier's brightness, and away when the combo
+/// breaks.
+const COMBO_GLOW_FADE_SPEED: f32 = 4.0;
+const COMBO_GLOW_PULSE_HZ: f32 = 1.5;
+const COMBO_GLOW_PULSE: f32 = 0.3;
+/// Reduce motion holds the glow steady at this share of its brightness.
+const COMBO_GLOW_REDUCED_SHARE: f32 = 0.5;
+/// Spawn cues are kept quiet and at least this far apart, so a swarm arriving is one
+/// cue rather than a wall of them.
+const SPAWN_CUE_VOLUME: f32 = 0.3;
+const SPECIAL_SPAWN_CUE_VOLUME: f32 = 0.6;
+const FIRE_SOUND_VOLUME: f32 = 0.4;
+const SPAWN_CUE_COOLDOWN_SECS: f32 = 0.4;
+/// Panning stops this far short of hard left or right, so a sound from the very edge
+/// still reaches both ears.
+const SOUND_MAX_PAN: f32 = 0.6;
+/// Rodio plays a spatial sound centred between the ears at three quarters volume; this
+/// brings panned sounds back up to match the rest.
+const PANNED_SOUND_GAIN: f32 = 4.0 / 3.0;
+const LEADER_COLOR: Color = Color::CRIMSON;
+const LEADER_SIZE: Vec2 = Vec2::new(48.0, 48.0);
+const LEADER_COLORBLIND_COLOR: Color = Color::rgb(0.8, 0.47, 0.65);
+const SCATTER_SPEED: f32 = 220.0;
+const DIVE_START_Y: f32 = 150.0;
+const DIVE_TELEGRAPH_SECS: f32 = 0.4;
+const DIVE_ACCELERATION: f32 = 900.0;
+const DIVE_HORIZONTAL_SPEED: f32 = 250.0;
+const DIFFICULTY_RAMP_PER_SEC: f32 = 0.02;
+const ENEMY_SPEED: f32 = 100.0;
+const SPAWN_FADE_SECS: f32 = 0.2;
+const SPEED_RAMP_PER_SEC: f32 = 0.01;
+const MAX_SPEED_MULTIPLIER: f32 = 2.0;
+const DANGER_RISING_COLOR: Color = Color::ORANGE_RED;
+const DANGER_RISING_BANNER_SECS: f32 = 1.5;
+const DAILY_CHALLENGE_COLOR: Color = Color::AQUAMARINE;
+/// Furthest adaptive difficulty may pull the difficulty level either way.
+const DDA_MAX_OFFSET: f32 = 0.5;
+/// Most the adaptive offset moves in one wave, so changes creep in.
+const DDA_STEP: f32 = 0.15;
+/// Share of the adaptive offset applied to enemy speed as well as spawn pressure.
+const DDA_SPEED_SHARE: f32 = 0.5;
+const BOSS_SCORE_INTERVAL: u32 = 500;
+const BOSS_CONFIG_PATH: &str = "assets/boss.json";
+const SCORING_CONFIG_PATH: &str = "assets/scoring.json";
+const ENTRANCE_CONFIG_PATH: &str = "assets/entrances.json";
+const LEVEL_PATH: &str = "assets/level.json";
+const MODIFIERS_PATH: &str = "assets/modifiers.json";
+const BOSS_SIZE: Vec2 = Vec2::new(120.0, 60.0);
+const BOSS_COLOR: Color = Color::FUCHSIA;
+const BOSS_INTRO_SECS: f32 = 2.0;
+/// How long a boss fight may run, from the end of the intro, before the boss enrages.
+/// Divided by the difficulty level, down to `MIN_BOSS_ENRAGE_SECS`.
+const BOSS_ENRAGE_SECS: f32 = 75.0;
+const MINI_BOSS_ENRAGE_SECS: f32 = 35.0;
+const MIN_BOSS_ENRAGE_SECS: f32 = 20.0;
+/// The boss starts throbbing red this long before it enrages.
+const BOSS_ENRAGE_WARNING_SECS: f32 = 8.0;
+/// An enraged boss moves this much faster, and fires this much more often with
+/// bullets this much faster.
+const BOSS_ENRAGE_SPEED: f32 = 1.6;
+const BOSS_ENRAGE_FIRE_RATE: f32 = 1.75;
+const BOSS_ENRAGE_BULLET_SPEED: f32 = 1.3;
+const BOSS_ENRAGE_COLOR: Color = Color::CRIMSON;
+const BOSS_ENRAGE_SHAKE: f32 = 0.4;
+const BOSS_ENRAGE_BANNER_SECS: f32 = 1.5;
+const MINI_BOSS_SIZE: Vec2 = Vec2::new(80.0, 40.0);
+const MINI_BOSS_COLOR: Color = Color::VIOLET;
+const MINI_BOSS_NAME: &str = "Mini-Boss";
+/// Bevy's default clear colour, named so the boss backdrop has something to return to.
+const BACKGROUND_COLOR: Color = Color::rgb(0.4, 0.4, 0.4);
+/// Scores at which the background has fully become each tier's colour, drifting from
+/// one to the next in between: the plain start, dusk, night, then deep space.
+const BACKGROUND_TIER_SCORES: [u32; 4] = [0, 1000, 3000, 6000];
+const BACKGROUND_TIER_COLORS: [Color; 4] = [
+    BACKGROUND_COLOR,
+    Color::rgb(0.42, 0.33, 0.35),
+    Color::rgb(0.2, 0.22, 0.34),
+    Color::rgb(0.14, 0.14, 0.24),
+];
+/// The tiers darken in steps without leaning on hue.
+const BACKGROUND_TIER_COLORBLIND_COLORS: [Color; 4] = [
+    BACKGROUND_COLOR,
+    Color::rgb(0.33, 0.33, 0.35),
+    Color::rgb(0.25, 0.25, 0.29),
+    Color::rgb(0.17, 0.17, 0.21),
+];
+const BOSS_BACKDROP_COLOR: Color = Color::rgb(0.12, 0.1, 0.16);
+const BACKDROP_FADE_SPEED: f32 = 2.0;
+/// Length of the boss's defeat sequence; reduce motion cuts it short.
+const BOSS_DEFEAT_SECS: f32 = 2.5;
+const BOSS_DEFEAT_REDUCED_SECS: f32 = 1.0;
+/// Gap between the blasts breaking up the boss.
+const BOSS_DEFEAT_BLAST_SECS: f32 = 0.2;
+/// Enemy-side time scale while the boss goes down.
+const BOSS_DEFEAT_TIME_SCALE: f32 = 0.3;
+const BOSS_DEFEAT_BLAST_PUNCH: f32 = 0.03;
+const BOSS_DEFEAT_BANNER_SECS: f32 = 2.0;
+const BOSS_DEFEAT_COLOR: Color = Color::GOLD;
+/// Escapes allowed per run at difficulty 1.0, shrinking as difficulty rises.
+const BASE_ESCAPE_QUOTA: f32 = 20.0;
+const MAX_DIFFICULTY: f32 = 5.0;
+const MINE_TRIGGER_RADIUS: f32 = 60.0;
+const MINE_BLAST_RADIUS: f32 = 90.0;
+const MINE_LIFETIME_SECS: f32 = 6.0;
+/// Enemies this close to an explosion are shoved away from it, harder the closer they are.
+const KNOCKBACK_RADIUS: f32 = 110.0;
+const KNOCKBACK_SPEED: f32 = 260.0;
+/// Ceiling on an enemy's knockback, however many explosions stack up on it.
+const KNOCKBACK_MAX_SPEED: f32 = 360.0;
+/// How quickly knockback dies away, per second.
+const KNOCKBACK_DAMPING: f32 = 6.0;
+const STARTING_LIVES: u32 = 3;
+const MAX_STARTING_LIVES: u32 = 9;
+const HIT_IFRAME_SECS: f32 = 1.5;
+/// Enemies and hazards this close to where a hit ship comes back are cleared away.
+const RESPAWN_CLEAR_RADIUS: f32 = 160.0;
+const RESPAWN_TOAST_COLOR: Color = Color::AQUAMARINE;
+/// How far back a rewind takes the ship.
+const REWIND_SECS: f32 = 2.0;
+const MAX_REWINDS: u32 = 3;
+const REWIND_TOAST_COLOR: Color = Color::rgb(0.7, 0.6, 1.0);
+/// How long the continue offer stays up before the run ends anyway.
+const CONTINUE_COUNTDOWN_SECS: f32 = 9.0;
+/// Continues a run gets before each one costs coins.
+const FREE_CONTINUES: u32 = 1;
+const CONTINUE_COST: u32 = 25;
+/// Share of the score a continued run keeps.
+const CONTINUE_SCORE_FACTOR: f32 = 0.75;
+/// How long a portal stays up; its enemy emerges halfway through.
+const PORTAL_SECS: f32 = 1.0;
+/// Portals open this far down so the whole animation is on screen.
+const PORTAL_Y: f32 = 240.0;
+const PORTAL_SIZE: f32 = 60.0;
+const PORTAL_COLOR: Color = Color::rgba(0.6, 0.2, 1.0, 0.8);
+/// Radians per second.
+const PORTAL_SPIN: f32 = 4.0;
+const KILL_FEED_LEN: usize = 5;
+/// How long a kill stays in the feed; it fades out over the last second.
+const KILL_FEED_SECS: f32 = 4.0;
+/// Largest fraction an enemy's sprite is scaled or brightened by, either way.
+const ENEMY_SIZE_JITTER: f32 = 0.1;
+const ENEMY_TINT_JITTER: f32 = 0.1;
+/// Largest tilt given to an enemy's sprite, in radians.
+const ENEMY_TILT_JITTER: f32 = 0.08;
+/// Range of an enemy's idle spin, in radians per second, either way round.
+const ENEMY_SPIN_SPEED: std::ops::RangeInclusive<f32> = 0.2..=0.8;
+/// Where a drop shadow sits relative to its owner, and how dark it is at full opacity.
+const SHADOW_OFFSET: Vec2 = Vec2::new(5.0, -7.0);
+const SHADOW_ALPHA: f32 = 0.35;
+const SPAWN_MARKER_SECS: f32 = 5.0;
+const SPAWN_MARKER_COLOR: Color = Color::FUCHSIA;
+/// Share of the player's speed into an edge that comes back as recoil.
+const EDGE_BOUNCE_RESTITUTION: f32 = 0.35;
+/// How quickly edge recoil dies away, per second.
+const EDGE_BOUNCE_DAMPING: f32 = 8.0;
+const DASH_IFRAME_SECS: f32 = 0.25;
+/// Enemy bullets passing closer than this without hitting count as a graze.
+const GRAZE_RADIUS: f32 = 45.0;
+/// Shield granted when the graze meter fills.
+const GRAZE_SHIELD_SECS: f32 = 2.0;
+const GRAZE_COLOR: Color = Color::VIOLET;
+const DASH_DURATION_SECS: f32 = 0.15;
+const DASH_COOLDOWN_SECS: f32 = 1.0;
+const DASH_SPEED_MULTIPLIER: f32 = 4.0;
+/// Seconds between afterimages left along a dash, and how long each takes to fade.
+const AFTERIMAGE_INTERVAL_SECS: f32 = 0.03;
+const AFTERIMAGE_SECS: f32 = 0.25;
+const AFTERIMAGE_ALPHA: f32 = 0.5;
+const AIM_LINE_LENGTH: f32 = 220.0;
+/// Smallest upward component an aim direction may have, so shots never point downward.
+const MIN_AIM_Y: f32 = 0.2;
+const GAMEPAD_AIM_DEADZONE: f32 = 0.3;
+/// Widest angle either side of the aim that aim assist looks for an enemy in.
+const AIM_ASSIST_CONE: f32 = 0.35;
+const BULLET_TIME_SCALE: f32 = 0.3;
+const BULLET_TIME_DRAIN_PER_SEC: f32 = 0.5;
+const BULLET_TIME_REFILL_PER_SEC: f32 = 0.1;
+/// Seconds between freeze pulses. How long a pulse holds enemies is `freeze_secs` in
+/// the balance file.
+const FREEZE_COOLDOWN_SECS: f32 = 12.0;
+const FROZEN_COLOR: Color = Color::rgb(0.6, 0.85, 1.0);
+/// How far a frozen enemy's colour is pulled towards `FROZEN_COLOR`.
+const FROZEN_TINT: f32 = 0.7;
+/// Shards given off by each enemy as it freezes and again as it thaws.
+const ICE_CRYSTAL_COUNT: usize = 6;
+const HIGH_SCORE_PATH: &str = "highscore.txt";
+const SETTINGS_PATH: &str = "settings.json";
+const PROFILE_PATH: &str = "profile.json";
+const SCORE_PER_COIN: u32 = 10;
+const BASE_MAGNET_RADIUS: f32 = 60.0;
+const MAGNET_RADIUS_STEP: f32 = 25.0;
+const MAX_MAGNET_LEVEL: u32 = 4;
+/// Level 1 costs this; each level after costs this much more than the last.
+const MAGNET_UPGRADE_BASE_COST: u32 = 50;
+const MAGNET_PULL_SPEED: f32 = 250.0;
+/// Colour a badly damaged enemy fades toward. The colorblind palette darkens each hue
+/// instead, since lightness stays readable where a hue shift might not.
+const DAMAGED_ENEMY_COLOR: Color = Color::rgb(0.3, 0.3, 0.3);
+const COLORBLIND_DAMAGED_BRIGHTNESS: f32 = 0.35;
+const CAMERA_PUNCH_SECS: f32 = 0.25;
+/// How far the view zooms in, as a fraction of the normal projection scale.
+const BOSS_KILL_PUNCH: f32 = 0.08;
+const LEADER_KILL_PUNCH: f32 = 0.04;
+/// Real-time seconds enemies hold completely still to sell a big hit. The player keeps
+/// moving and shooting through it.
+const LEADER_KILL_HIT_STOP_SECS: f32 = 0.03;
+const BOSS_PHASE_HIT_STOP_SECS: f32 = 0.06;
+const BOSS_KILL_HIT_STOP_SECS: f32 = 0.08;
+/// Screen shake is driven by trauma from 0.0 to 1.0, which drains at this rate a
+/// second. The camera strays up to `SHAKE_MAX_OFFSET` pixels at full trauma.
+const SHAKE_DECAY_PER_SEC: f32 = 2.0;
+const SHAKE_MAX_OFFSET: f32 = 10.0;
+const PLAYER_HIT_SHAKE: f32 = 0.6;
+const BOSS_KILL_SHAKE: f32 = 0.8;
+const SCREEN_SHAKE_STEP: f32 = 0.25;
+const MAX_SCREEN_SHAKE: f32 = 2.0;
+const MAGNET_RING_COLOR: Color = Color::rgba(1.0, 1.0, 1.0, 0.08);
+const MIN_BRIGHTNESS: f32 = 0.3;
+const VOLUME_STEP: f32 = 0.1;
+const DIFFICULTY_SETTING_STEP: f32 = 0.5;
+const BASE_DIFFICULTY_STEP: f32 = 0.1;
+const MIN_BASE_DIFFICULTY: f32 = 0.5;
+const MAX_BASE_DIFFICULTY: f32 = 2.0;
+const BRIGHTNESS_STEP: f32 = 0.1;
+const MAX_MOVE_SPREAD_DEGREES: f32 = 10.0;
+/// Ship speed and fire rate settings, as multiples of `PLAYER_SPEED` and of the rate
+/// `PLAYER_FIRE_INTERVAL_SECS` gives.
+const SHIP_TUNING_STEP: f32 = 0.1;
+const MIN_SHIP_SPEED: f32 = 0.6;
+const MAX_SHIP_SPEED: f32 = 1.5;
+const MIN_FIRE_RATE: f32 = 0.5;
+const MAX_FIRE_RATE: f32 = 2.0;
+/// Window sizes offered in settings; the first is the default and the fallback for
+/// anything else found in the settings file.
+const RESOLUTIONS: [(u32, u32); 5] = [
+    (800, 600),
+    (1024, 768),
+    (1280, 720),
+    (1600, 900),
+    (1920, 1080),
+];
+const UI_SCALE_STEP: f32 = 0.25;
+const MIN_UI_SCALE: f32 = 0.75;
+const MAX_UI_SCALE: f32 = 2.0;
+/// The HUD is scaled on top of `Settings::ui_scale`, which covers the menus too.
+const HUD_SCALE_STEP: f32 = 0.1;
+const MIN_HUD_SCALE: f32 = 0.6;
+const MAX_HUD_SCALE: f32 = 1.5;
+// Touch control layout, in percent of the shorter window side so it scales with the screen.
+const TOUCH_CONTROL_MARGIN_VMIN: f32 = 6.0;
+const JOYSTICK_SIZE_VMIN: f32 = 24.0;
+const FIRE_BUTTON_SIZE_VMIN: f32 = 18.0;
+const CONFETTI_COUNT: usize = 120;
+const CONFETTI_GRAVITY: f32 = -400.0;
+const MUSIC_STEMS: [&str; 3] = [
+    "audio/music_base.ogg",
+    "audio/music_layer1.ogg",
+    "audio/music_layer2.ogg",
+];
+/// On-screen enemy count at which each stem fades in; the base stem always plays.
+const MUSIC_LAYER_THRESHOLDS: [usize; 3] = [0, 6, 12];
+const MUSIC_FADE_SPEED: f32 = 1.5;
+/// Share of full volume the music settles to during the break between waves.
+const MUSIC_BREAK_VOLUME: f32 = 0.6;
+/// Where the asset server reads from, relative to its base path.
+const ASSET_DIR: &str = "assets";
+
+// Components
+#[derive(Component)]
+struct Player {
+    speed: f32,
+    /// Seconds between shots before power-ups take their share off.
+    fire_interval: f32,
+    /// Horizontal speed this frame, including dashes.
+    velocity_x: f32,
+    /// Push back from the last edge bounce, decaying to nothing.
+    recoil_x: f32,
+    shoot_timer: Timer,
+    /// Seconds left on the last fire press, if it came before a shot was due.
+    fire_buffer: f32,
+    dash_cooldown: Timer,
+    /// Muzzles relative to the ship's centre, each firing the whole volley. Only
+    /// where shots start from; the hitbox stays the ship's own.
+    gun_offsets: Vec<Vec2>,
+}
+
+/// Opens, lets `spawn` out at its midpoint, then closes and despawns.
+#[derive(Component)]
+struct Portal {
+    spawn: SpawnChoice,
+    timer: Timer,
+    emerged: bool,
+}
+
+/// Flame sprite parented below the player's ship.
+#[derive(Component)]
+struct Thruster;
+
+#[derive(Component)]
+struct Dashing {
+    timer: Timer,
+    direction: f32,
+}
+
+/// A fading copy of the player's ship left behind while dashing. Purely decorative:
+/// nothing collides with an afterimage.
+#[derive(Component)]
+struct Afterimage(Timer);
+
+/// Blinks the entity's sprite while the timer runs; collision systems ignore the
+/// entity until it expires and is removed.
+#[derive(Component)]
+struct Invincible {
+    timer: Timer,
+}
+
+impl Invincible {
+    fn from_seconds(secs: f32) -> Self {
+        Invincible {
+            timer: Timer::from_seconds(secs, TimerMode::Once),
+        }
+    }
+}
+
+#[derive(Component)]
+struct Bullet {
+    kind: BulletKind,
+    velocity: Vec2,
+    damage: u32,
+    lifetime: Timer,
+}
+
+/// What a player bullet looks and sounds like. A new weapon adds a variant here with
+/// its size, both palettes' colours and its firing sound.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+enum BulletKind {
+    Standard,
+    Bouncing,
+    Piercing,
+    Burst,
+}
+
+impl BulletKind {
+    const ALL: [BulletKind; 4] = [
+        BulletKind::Standard,
+        BulletKind::Bouncing,
+        BulletKind::Piercing,
+        BulletKind::Burst,
+    ];
+
+    /// Played once a volley; loaded up front into `GameAudio::weapon_fire`.
+    fn fire_sound(self) -> &'static str {
+        match self {
+            BulletKind::Standard => "audio/fire_standard.ogg",
+            BulletKind::Bouncing => "audio/fire_bounce.ogg",
+            BulletKind::Piercing => "audio/fire_pierce.ogg",
+            BulletKind::Burst => "audio/fire_burst.ogg",
+        }
+    }
+
+    fn size(self) -> Vec2 {
+        match self {
+            BulletKind::Standard => Vec2::new(5.0, 15.0),
+            BulletKind::Bouncing => Vec2::new(9.0, 9.0),
+            BulletKind::Piercing => Vec2::new(4.0, 28.0),
+            BulletKind::Burst => Vec2::new(7.0, 11.0),
+        }
+    }
+
+    fn color(self) -> Color {
+        match self {
+            BulletKind::Standard => Color::YELLOW,
+            BulletKind::Bouncing => Color::GREEN,
+            BulletKind::Piercing => Color::rgb(0.3, 0.6, 1.0),
+            BulletKind::Burst => ALL_RANGE_COLOR,
+        }
+    }
+
+    /// Okabe-Ito hues, as in `EnemyKind::colorblind_color`.
+    fn colorblind_color(self) -> Color {
+        match self {
+            BulletKind::Standard => Color::rgb(0.94, 0.89, 0.26),
+            BulletKind::Bouncing => Color::rgb(0.0, 0.62, 0.45),
+            BulletKind::Piercing => Color::rgb(0.0, 0.45, 0.7),
+            BulletKind::Burst => Color::rgb(0.9, 0.6, 0.0),
+        }
+    }
+}
+
+/// Carries a bullet on through every enemy it hits, kills or not; `RecentHits` keeps
+/// it from hitting one again while it passes through. `chain` counts the kills so far.
+#[derive(Component, Default)]
+struct Pierce {
+    chain: u32,
+    /// Whether it has hit anything yet, so it doesn't count as a miss on leaving.
+    landed: bool,
+}
+
+/// Sets an enemy burning when it hits; fired while the flame power-up is active.
+#[derive(Component)]
+struct Incendiary;
+
+/// Held still by a freeze pulse: no moving, spinning or firing until `timer` ends.
+/// Shots still land as normal.
+#[derive(Component)]
+struct Frozen {
+    timer: Timer,
+}
+
+/// Damage over time from a flame hit. Another hit restarts `timer` rather than adding
+/// a second burn.
+#[derive(Component)]
+struct Burning {
+    dps: f32,
+    timer: Timer,
+    /// Damage built up but not yet a whole point of health.
+    carry: f32,
+}
+
+impl Burning {
+    fn new() -> Self {
+        Burning {
+            dps: BURN_DPS,
+            timer: Timer::from_seconds(BURN_SECS, TimerMode::Once),
+            carry: 0.0,
+        }
+    }
+}
+
+/// Shots that hit this enemy in the last `HIT_IMMUNITY_SECS`, with the time each has
+/// left; none of them can hit it again until then.
+#[derive(Component, Default)]
+struct RecentHits(Vec<(Entity, Timer)>);
+
+impl RecentHits {
+    fn contains(&self, bullet: Entity) -> bool {
+        self.0.iter().any(|(hit, _)| *hit == bullet)
+    }
+
+    fn record(&mut self, bullet: Entity) {
+        self.0.push((
+            bullet,
+            Timer::from_seconds(HIT_IMMUNITY_SECS, TimerMode::Once),
+        ));
+    }
+}
+
+/// Floating "xN chain!" text that rises and fades out.
+#[derive(Component)]
+struct ChainPopup(Timer);
+
+#[derive(Component)]
+struct Bounce {
+    remaining: u32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+enum EnemyKind {
+    Basic,
+    Miner,
+    Diver,
+    Gunner,
+    Reflector,
+    /// Only takes damage through its `WeakPoint`.
+    Armored,
+    /// Bullets pass straight through it while its `Cloak` is up.
+    Cloaker,
+    /// Arrives in a group that weaves along together; see `Swarm`.
+    Swarmer,
+    /// Takes score instead of a life on contact, then flees; see `Thief`.
+    Thief,
+    /// Patrols the top of the screen dropping basic enemies; see `Carrier`.
+    Carrier,
+    /// Drags the ship towards itself while it is in range; see `Tractor`.
+    Tractor,
+    /// Heals back up unless it keeps getting hit; see `Regenerator`.
+    Regenerator,
+}
+
+impl EnemyKind {
+    const ALL: [EnemyKind; 12] = [
+        EnemyKind::Basic,
+        EnemyKind::Miner,
+        EnemyKind::Diver,
+        EnemyKind::Gunner,
+        EnemyKind::Reflector,
+        EnemyKind::Armored,
+        EnemyKind::Cloaker,
+        EnemyKind::Swarmer,
+        EnemyKind::Thief,
+        EnemyKind::Carrier,
+        EnemyKind::Tractor,
+        EnemyKind::Regenerator,
+    ];
+
+    /// How much of the director's budget this kind costs to spawn.
+    fn cost(self) -> f32 {
+        match self {
+            EnemyKind::Basic => 1.0,
+            EnemyKind::Miner => 2.5,
+            EnemyKind::Diver => 2.0,
+            EnemyKind::Gunner => 2.0,
+            EnemyKind::Reflector => 2.0,
+            EnemyKind::Armored => 2.5,
+            EnemyKind::Cloaker => 2.0,
+            EnemyKind::Swarmer => 0.5,
+            EnemyKind::Thief => 2.0,
+            EnemyKind::Carrier => 3.5,
+            EnemyKind::Tractor => 3.0,
+            EnemyKind::Regenerator => 3.0,
+        }
+    }
+
+    fn max_health(self) -> u32 {
+        match self {
+            EnemyKind::Basic => 2,
+            EnemyKind::Miner => 4,
+            EnemyKind::Diver => 2,
+            EnemyKind::Gunner => 3,
+            EnemyKind::Reflector => 2,
+            EnemyKind::Armored => 2,
+            EnemyKind::Cloaker => 2,
+            EnemyKind::Swarmer => 1,
+            EnemyKind::Thief => 2,
+            EnemyKind::Carrier => 12,
+            EnemyKind::Tractor => 5,
+            EnemyKind::Regenerator => 6,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            EnemyKind::Basic => "Basic",
+            EnemyKind::Miner => "Miner",
+            EnemyKind::Diver => "Diver",
+            EnemyKind::Gunner => "Gunner",
+            EnemyKind::Reflector => "Reflector",
+            EnemyKind::Armored => "Armored",
+            EnemyKind::Cloaker => "Cloaker",
+            EnemyKind::Swarmer => "Swarmer",
+            EnemyKind::Thief => "Thief",
+            EnemyKind::Carrier => "Carrier",
+            EnemyKind::Tractor => "Tractor",
+            EnemyKind::Regenerator => "Regenerator",
+        }
+    }
+
+    /// Sprite size before balance scaling and jitter. Each kind has its own outline so
+    /// kinds can be told apart by shape as well as colour.
+    fn size(self) -> Vec2 {
+        match self {
+            EnemyKind::Basic => Vec2::new(40.0, 40.0),
+            EnemyKind::Miner => Vec2::new(50.0, 34.0),
+            EnemyKind::Diver => Vec2::new(28.0, 48.0),
+            EnemyKind::Gunner => Vec2::new(46.0, 30.0),
+            EnemyKind::Reflector => Vec2::new(44.0, 40.0),
+            EnemyKind::Armored => Vec2::new(46.0, 46.0),
+            EnemyKind::Cloaker => Vec2::new(34.0, 42.0),
+            EnemyKind::Swarmer => Vec2::new(22.0, 22.0),
+            EnemyKind::Thief => Vec2::new(30.0, 30.0),
+            EnemyKind::Carrier => Vec2::new(90.0, 36.0),
+            EnemyKind::Tractor => Vec2::new(36.0, 50.0),
+            EnemyKind::Regenerator => Vec2::new(42.0, 42.0),
+        }
+    }
+
+    /// Where shots land: the outline at `Balance::enemy_size`'s scale, or a leader's
+    /// larger ship, whatever jitter `vary_enemy_sprites` gave the sprite.
+    fn hitbox(self, leader: bool, balance: &Balance) -> Vec2 {
+        if leader {
+            return LEADER_SIZE;
+        }
+        self.size() * Vec2::from(balance.enemy_size) / EnemyKind::Basic.size()
+    }
+
+    fn color(self) -> Color {
+        match self {
+            EnemyKind::Basic => Color::RED,
+            EnemyKind::Miner => Color::ORANGE,
+            EnemyKind::Diver => Color::TEAL,
+            EnemyKind::Gunner => Color::OLIVE,
+            EnemyKind::Reflector => Color::SILVER,
+            EnemyKind::Armored => Color::DARK_GRAY,
+            EnemyKind::Cloaker => Color::INDIGO,
+            EnemyKind::Swarmer => Color::LIME_GREEN,
+            EnemyKind::Thief => Color::GOLD,
+            EnemyKind::Carrier => Color::MAROON,
+            EnemyKind::Tractor => Color::PURPLE,
+            EnemyKind::Regenerator => Color::SEA_GREEN,
+        }
+    }
+
+    /// Okabe-Ito hues, which stay distinct under the common colour-vision deficiencies.
+    fn colorblind_color(self) -> Color {
+        match self {
+            EnemyKind::Basic => Color::rgb(0.84, 0.37, 0.0),
+            EnemyKind::Miner => Color::rgb(0.94, 0.89, 0.26),
+            EnemyKind::Diver => Color::rgb(0.34, 0.71, 0.91),
+            EnemyKind::Gunner => Color::rgb(0.0, 0.62, 0.45),
+            EnemyKind::Reflector => Color::rgb(0.8, 0.47, 0.65),
+            EnemyKind::Armored => Color::rgb(0.0, 0.45, 0.7),
+            EnemyKind::Cloaker => Color::rgb(0.9, 0.6, 0.0),
+            // The palette has run out of hues; the swarmer's size sets it apart.
+            EnemyKind::Swarmer => Color::rgb(0.95, 0.95, 0.95),
+            EnemyKind::Thief => Color::rgb(0.1, 0.1, 0.1),
+            // Like the swarmer's, the carrier's size sets it apart.
+            EnemyKind::Carrier => Color::rgb(0.6, 0.6, 0.6),
+            // And the tractor is the one with a beam.
+            EnemyKind::Tractor => Color::rgb(0.35, 0.35, 0.35),
+            // And the regenerator by its glow while it heals.
+            EnemyKind::Regenerator => Color::rgb(0.75, 0.75, 0.75),
+        }
+    }
+
+    fn death_effect(self) -> DeathEffect {
+        match self {
+            EnemyKind::Basic | EnemyKind::Gunner | EnemyKind::Swarmer | EnemyKind::Thief => {
+                DeathEffect::Puff
+            }
+            EnemyKind::Miner | EnemyKind::Armored | EnemyKind::Carrier | EnemyKind::Regenerator => {
+                DeathEffect::Explosion
+            }
+            EnemyKind::Diver => DeathEffect::Streak,
+            EnemyKind::Reflector | EnemyKind::Cloaker | EnemyKind::Tractor => DeathEffect::Shatter,
+        }
+    }
+
+    /// Paid on top of the usual kill score.
+    fn bonus_score(self, scoring: &ScoringConfig) -> u32 {
+        match self {
+            EnemyKind::Carrier => scoring.carrier_bonus,
+            _ => 0,
+        }
+    }
+
+    /// First wave this kind can spawn in, so each run brings its enemies in a few at
+    /// a time.
+    fn unlock_wave(self) -> u32 {
+        match self {
+            EnemyKind::Basic | EnemyKind::Diver => 1,
+            EnemyKind::Miner | EnemyKind::Gunner => 2,
+            EnemyKind::Reflector | EnemyKind::Swarmer | EnemyKind::Thief => 3,
+            EnemyKind::Armored | EnemyKind::Cloaker => 4,
+            EnemyKind::Tractor | EnemyKind::Regenerator => 5,
+            EnemyKind::Carrier => 6,
+        }
+    }
+
+    /// Kinds that sidestep shots headed their way; see `dodge_bullets`.
+    fn evasive(self) -> bool {
+        matches!(self, EnemyKind::Gunner | EnemyKind::Cloaker)
+    }
+}
+
+/// How an enemy goes when it dies, so kinds can be told apart even as they go.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum DeathEffect {
+    /// A small soft cloud.
+    Puff,
+    /// A big flash with heavy debris falling out of it.
+    Explosion,
+    /// A quick fan of sparks thrown on down the line of a dive.
+    Streak,
+    /// Shards that spin off and tumble away.
+    Shatter,
+}
+
+/// One layer of particles in a death effect.
+struct DeathParticles {
+    count: usize,
+    size: Vec2,
+    speed: (f32, f32),
+    /// Direction the particles head in, and how far either side of it they scatter.
+    heading: f32,
+    spread: f32,
+    gravity: f32,
+    secs: f32,
+    spin: f32,
+    /// `None` takes on the enemy's own colour.
+    color: Option<Color>,
+}
+
+impl DeathEffect {
+    fn particles(self) -> Vec<DeathParticles> {
+        match self {
+            DeathEffect::Puff => vec![DeathParticles {
+                count: 6,
+                size: Vec2::splat(5.0),
+                speed: (30.0, 70.0),
+                heading: 0.0,
+                spread: PI,
+                gravity: 0.0,
+                secs: 0.35,
+                spin: 0.0,
+                color: None,
+            }],
+            DeathEffect::Explosion => vec![
+                DeathParticles {
+                    count: 24,
+                    size: Vec2::splat(6.0),
+                    speed: (120.0, 300.0),
+                    heading: 0.0,
+                    spread: PI,
+                    gravity: 0.0,
+                    secs: 0.5,
+                    spin: 0.0,
+                    color: Some(Color::ORANGE),
+                },
+                DeathParticles {
+                    count: 8,
+                    size: Vec2::new(9.0, 6.0),
+                    speed: (150.0, 260.0),
+                    heading: FRAC_PI_2,
+                    spread: PI,
+                    gravity: -400.0,
+                    secs: 1.0,
+                    spin: 12.0,
+                    color: None,
+                },
+            ],
+            DeathEffect::Streak => vec![DeathParticles {
+                count: 8,
+                size: Vec2::new(12.0, 2.0),
+                speed: (250.0, 450.0),
+                heading: -FRAC_PI_2,
+                spread: 0.35,
+                gravity: 0.0,
+                secs: 0.2,
+                spin: 0.0,
+                color: Some(Color::WHITE),
+            }],
+            DeathEffect::Shatter => vec![DeathParticles {
+                count: 10,
+                size: Vec2::new(7.0, 4.0),
+                speed: (100.0, 220.0),
+                heading: 0.0,
+                spread: PI,
+                gravity: -250.0,
+                secs: 0.7,
+                spin: 15.0,
+                color: None,
+            }],
+        }
+    }
+}
+
+#[derive(Component)]
+struct Enemy {
+    speed: f32,
+    kind: EnemyKind,
+    health: u32,
+}
+
+/// Per-enemy brightness rolled at spawn, kept so `enemy_tint` can reapply it. Size
+/// and tilt are baked into the sprite once; collisions keep the fixed hitbox.
+#[derive(Component)]
+struct SpriteJitter {
+    brightness: f32,
+}
+
+/// Idle rotation in radians per second. Hitboxes stay axis-aligned whatever the angle.
+#[derive(Component)]
+struct Spin {
+    speed: f32,
+}
+
+/// Attack run for `EnemyKind::Diver`: drift in, flash a warning, swoop at the player,
+/// then carry on down like any other enemy.
+#[derive(Component)]
+enum Dive {
+    Entering,
+    Telegraph(Timer),
+    Diving { target_x: f32, speed: f32 },
+    Resumed,
+}
+
+/// A formation survivor sent diving by `break_formations`, at the balance's dive
+/// acceleration rather than `DIVE_ACCELERATION`.
+#[derive(Component)]
+struct Breakaway;
+
+/// Last horizontal position seen by `enemy_facing`, for working out which way an
+/// enemy is moving. Art faces right; `Sprite::flip_x` turns it to face left.
+#[derive(Component)]
+struct Heading {
+    last_x: f32,
+}
+
+/// Fades a new enemy in from nothing, so it doesn't pop in at the edge. Shots pass
+/// through it, and a thief can't reach the ship, until it has fully appeared.
+#[derive(Component)]
+struct SpawnFade(Timer);
+
+/// Marks the enemy leading a formation; killing it scatters the rest.
+#[derive(Component)]
+struct Leader;
+
+#[derive(Component)]
+struct FormationMember {
+    leader: Entity,
+}
+
+/// Which way a leader's formation is marching, 1.0 for right and -1.0 for left. The
+/// whole formation moves with it; see `formation_sweep`.
+#[derive(Component)]
+struct Sweep {
+    direction: f32,
+}
+
+/// A formation enemy still flying in along its entrance curve. Normal movement takes
+/// over once it reaches its slot.
+#[derive(Component)]
+struct Entering {
+    curve: CubicCurve<Vec2>,
+    timer: Timer,
+}
+
+/// Velocity from nearby explosions, added to an enemy's own movement as it decays.
+#[derive(Component)]
+struct Knockback(Vec2);
+
+/// Steering for `EnemyKind::Swarmer`, set by `swarm_steering` and added to the
+/// enemy's usual descent.
+#[derive(Component, Default)]
+struct Swarm {
+    velocity: Vec2,
+}
+
+/// Sideways speed of an evasive enemy, eased toward by `dodge_bullets`.
+#[derive(Component, Default)]
+struct Evasive {
+    velocity_x: f32,
+}
+
+/// Erratic side-to-side movement for formation members whose leader died.
+#[derive(Component)]
+struct Scatter {
+    velocity_x: f32,
+    timer: Timer,
+}
+
+/// Aimed fire for `EnemyKind::Gunner`. Every shot is preceded by a wind-up during
+/// which the gunner flashes, so the player has time to react.
+#[derive(Component)]
+struct Gunner {
+    cooldown: Timer,
+    wind_up: Option<Timer>,
+}
+
+/// Front shield of `EnemyKind::Reflector`: shots landing within `arc` of straight
+/// down are sent back as enemy bullets, so it has to be hit from the side or behind.
+#[derive(Component)]
+struct Shield {
+    arc: f32,
+}
+
+impl Shield {
+    fn covers(&self, offset: Vec2) -> bool {
+        offset.angle_between(Vec2::NEG_Y).abs() <= self.arc / 2.0
+    }
+}
+
+/// The one spot on `EnemyKind::Armored` that takes damage; shots anywhere else spark
+/// off. It sways along the enemy's underside, and `offset` is where it is right now.
+#[derive(Component)]
+struct WeakPoint {
+    offset: Vec2,
+    phase: f32,
+}
+
+impl WeakPoint {
+    fn covers(&self, offset: Vec2) -> bool {
+        let reach = WEAK_POINT_HALF_SIZE + Vec2::splat(WEAK_POINT_HIT_MARGIN);
+        (offset - self.offset).abs().cmple(reach).all()
+    }
+}
+
+/// Cycles `EnemyKind::Cloaker` between visible and cloaked, `timer` counting down
+/// whichever is current.
+#[derive(Component)]
+struct Cloak {
+    cloaked: bool,
+    timer: Timer,
+}
+
+/// Score an `EnemyKind::Thief` has taken, if it has reached the ship yet. Once it has,
+/// it flees off the top with it, and gives it back with a bonus if shot down first.
+#[derive(Component, Default)]
+struct Thief {
+    stolen: Option<u32>,
+}
+
+impl Thief {
+    fn refund(&self) -> u32 {
+        self.stolen.map_or(0, |stolen| stolen + THIEF_REFUND_BONUS)
+    }
+}
+
+/// An `EnemyKind::Carrier` comes down to `CARRIER_PATROL_Y` and sweeps from side to
+/// side there, dropping a basic enemy every `Balance::carrier_deploy_secs`. Once
+/// `deploys_left` runs out it climbs away off the top.
+#[derive(Component)]
+struct Carrier {
+    since_deploy: f32,
+    deploys_left: u32,
+    direction: f32,
+}
+
+impl Default for Carrier {
+    fn default() -> Self {
+        Carrier {
+            since_deploy: 0.0,
+            deploys_left: CARRIER_DEPLOYS,
+            direction: 1.0,
+        }
+    }
+}
+
+/// An `EnemyKind::Tractor` comes down to `TRACTOR_HOVER_Y` and holds there for a
+/// while before carrying on. Whenever the ship is within `Balance::tractor_range` of
+/// it, `player_movement` pulls the ship sideways towards it.
+#[derive(Component)]
+struct Tractor {
+    hover: Timer,
+    /// Its descent speed, put back once it is done hovering.
+    speed: f32,
+}
+
+impl Default for Tractor {
+    fn default() -> Self {
+        Tractor {
+            hover: Timer::from_seconds(TRACTOR_HOVER_SECS, TimerMode::Once),
+            speed: 0.0,
+        }
+    }
+}
+
+/// An `EnemyKind::Regenerator` heals `Balance::regen_per_sec` while it has gone
+/// `Balance::regen_stagger_secs` without losing health, so only steady fire brings
+/// it down.
+#[derive(Component, Default)]
+struct Regenerator {
+    /// Health as of last frame, to tell when it has been hurt by anything at all.
+    last_health: u32,
+    /// Ticks from the last hit; healing waits for it to finish.
+    stagger: Option<Timer>,
+    /// Healing built up towards the next whole point.
+    carry: f32,
+}
+
+impl Regenerator {
+    fn staggered(&self) -> bool {
+        self.stagger.as_ref().is_some_and(|timer| !timer.finished())
+    }
+}
+
+/// Child of a tractor, stretched out to the ship while it pulls.
+#[derive(Component)]
+struct TractorBeam;
+
+/// How hard a tractor at `tractor` pulls a ship at `player`, from 1.0 right under it
+/// down to nothing at the edge of `range`.
+fn tractor_strength(tractor: Vec2, player: Vec2, range: f32) -> Option<f32> {
+    let distance = tractor.distance(player);
+    (distance < range).then(|| 1.0 - distance / range)
+}
+
+/// Sprite showing where an armored enemy's `WeakPoint` is.
+#[derive(Component)]
+struct WeakPointMarker;
+
+#[derive(Component)]
+struct MineLayer {
+    timer: Timer,
+}
+
+#[derive(Component)]
+struct Mine {
+    lifetime: Timer,
+}
+
+#[derive(Component)]
+struct Blast {
+    timer: Timer,
+}
+
+#[derive(Component)]
+struct EnemyBullet {
+    velocity: Vec2,
+    kind: EnemyBulletKind,
+    lifetime: Timer,
+}
+
+/// How an enemy bullet behaves, which sets its colour so dense patterns can be read
+/// at a glance.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+enum EnemyBulletKind {
+    /// Fired at where the player was.
+    Aimed,
+    /// Part of a fixed shape, like a ring or a curtain, that ignores the player.
+    Pattern,
+    Homing,
+    /// A player shot turned back by a shield.
+    Reflected,
+}
+
+impl EnemyBulletKind {
+    fn of(pattern: &Pattern) -> Self {
+        match pattern {
+            Pattern::Aimed { .. } => EnemyBulletKind::Aimed,
+            Pattern::Homing { .. } => EnemyBulletKind::Homing,
+            Pattern::Spiral { .. }
+            | Pattern::Radial { .. }
+            | Pattern::Column { .. }
+            | Pattern::Burst { .. } => EnemyBulletKind::Pattern,
+        }
+    }
+
+    fn color(self) -> Color {
+        match self {
+            EnemyBulletKind::Aimed => ENEMY_BULLET_COLOR,
+            EnemyBulletKind::Pattern => Color::rgb(0.7, 0.35, 1.0),
+            EnemyBulletKind::Homing => Color::PINK,
+            EnemyBulletKind::Reflected => Color::rgb(0.3, 1.0, 0.8),
+        }
+    }
+
+    /// Okabe-Ito hues, as in `EnemyKind::colorblind_color`.
+    fn colorblind_color(self) -> Color {
+        match self {
+            EnemyBulletKind::Aimed => Color::rgb(0.84, 0.37, 0.0),
+            EnemyBulletKind::Pattern => Color::rgb(0.34, 0.71, 0.91),
+            EnemyBulletKind::Homing => Color::rgb(0.8, 0.47, 0.65),
+            EnemyBulletKind::Reflected => Color::rgb(0.95, 0.95, 0.95),
+        }
+    }
+}
+
+/// Steers an enemy bullet after the player, from a `Pattern::Homing` volley.
+#[derive(Component)]
+struct HomingBullet {
+    turn_rate: f32,
+    lifetime: Timer,
+}
+
+/// Marks where a telegraphed attack will land, fading in over `timer` and firing the
+/// attack when it runs out.
+#[derive(Component)]
+struct TelegraphZone {
+    pattern: Pattern,
+    zone: Zone,
+    timer: Timer,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct BossPhase {
+    /// The phase begins once boss health falls to this fraction of its maximum.
+    health_fraction: f32,
+    move_speed: f32,
+    fire_interval: f32,
+    pattern: Pattern,
+}
+
+#[derive(Component)]
+struct Boss {
+    health: u32,
+    max_health: u32,
+    phase: usize,
+    phases: Vec<BossPhase>,
+    direction: f32,
+    fire_timer: Timer,
+    volleys: u32,
+}
+
+/// Marks a `Boss` as a mini-boss: one phase, a health bar, and a power-up when it
+/// goes down instead of the full send-off.
+#[derive(Component)]
+struct MiniBoss;
+
+#[derive(Component)]
+struct BossFlash {
+    timer: Timer,
+}
+
+/// The boss's entrance: it fades in over the stinger, and neither attacks nor takes
+/// damage until this is removed.
+#[derive(Component)]
+struct BossIntro {
+    timer: Timer,
+}
+
+/// Runs out once the boss has been up too long, and from then on it's enraged.
+#[derive(Component)]
+struct BossEnrage {
+    timer: Timer,
+}
+
+impl BossEnrage {
+    fn new(mini_boss: bool, difficulty: &Difficulty) -> Self {
+        let secs = if mini_boss {
+            MINI_BOSS_ENRAGE_SECS
+        } else {
+            BOSS_ENRAGE_SECS
+        };
+        let secs = (secs / difficulty.level).max(MIN_BOSS_ENRAGE_SECS);
+        BossEnrage {
+            timer: Timer::from_seconds(secs, TimerMode::Once),
+        }
+    }
+
+    fn enraged(&self) -> bool {
+        self.timer.finished()
+    }
+}
+
+/// A beaten boss's way out: it stops fighting and breaks up in a string of blasts,
+/// and is only removed and scored once `timer` runs out. Spawning stays held off
+/// meanwhile, as it is for the rest of the fight.
+#[derive(Component)]
+struct BossDefeat {
+    timer: Timer,
+    blast_timer: Timer,
+}
+
+impl BossDefeat {
+    fn new(reduce_motion: bool) -> Self {
+        let secs = if reduce_motion {
+            BOSS_DEFEAT_REDUCED_SECS
+        } else {
+            BOSS_DEFEAT_SECS
+        };
+        BossDefeat {
+            timer: Timer::from_seconds(secs, TimerMode::Once),
+            blast_timer: Timer::from_seconds(BOSS_DEFEAT_BLAST_SECS, TimerMode::Repeating),
+        }
+    }
+}
+
+/// The two corners the HUD is built around: the score, combo and gauges on one side,
+/// and lives, escapes and the kill feed on the other.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum HudGroup {
+    Score,
+    Status,
+}
+
+/// Puts a HUD element `offset` pixels in from its group's corner, both scaled by
+/// `HudConfig::scale`. `far_edge` keeps it to the group's side of the screen but at
+/// the opposite edge, out from under the corners. Sizes are scaled if given.
+#[derive(Component)]
+struct HudAnchor {
+    group: HudGroup,
+    offset: Vec2,
+    far_edge: bool,
+    size: Option<Vec2>,
+}
+
+impl HudAnchor {
+    fn new(group: HudGroup, x: f32, y: f32) -> Self {
+        HudAnchor {
+            group,
+            offset: Vec2::new(x, y),
+            far_edge: false,
+            size: None,
+        }
+    }
+
+    fn far_edge(mut self) -> Self {
+        self.far_edge = true;
+        self
+    }
+
+    fn sized(mut self, width: f32, height: f32) -> Self {
+        self.size = Some(Vec2::new(width, height));
+        self
+    }
+}
+
+/// HUD text drawn at this font size times `HudConfig::scale`.
+#[derive(Component)]
+struct HudText(f32);
+
+#[derive(Component)]
+struct ScoreText;
+
+#[derive(Component)]
+struct ComboText {
+    /// Tints the text red for a moment after a missed shot cost combo.
+    miss_flash: Timer,
+}
+
+#[derive(Component)]
+struct LivesText;
+
+#[derive(Component)]
+struct EscapesText;
+
+/// Shots left under `Balance::player_bullet_cap`, hidden while there is no cap.
+#[derive(Component)]
+struct BulletIndicator;
+
+#[derive(Component)]
+struct Particle {
+    velocity: Vec2,
+    angular_velocity: f32,
+    gravity: f32,
+    lifetime: Timer,
+}
+
+#[derive(Component)]
+struct GameOverScreen;
+
+#[derive(Component)]
+struct PauseScreen;
+
+/// Root of the attract-mode overlay, stepping through its slides.
+#[derive(Component)]
+struct AttractScreen {
+    slide: usize,
+    timer: Timer,
+}
+
+#[derive(Component)]
+struct AttractText;
+
+/// The "are you sure" box laid over whichever screen Quit was picked from.
+#[derive(Component)]
+struct QuitDialog;
+
+#[derive(Component)]
+struct SettingsScreen;
+
+#[derive(Component)]
+struct ControlsScreen;
+
+#[derive(Component)]
+struct StatsScreen;
+
+#[derive(Component)]
+struct ModifiersScreen;
+
+#[derive(Component)]
+struct ContinueScreen;
+
+/// Seconds left on the continue offer.
+#[derive(Component)]
+struct ContinueCountdownText;
+
+#[derive(Component)]
+struct RebindWarningText;
+
+#[derive(Component)]
+struct CheatsIndicator;
+
+#[derive(Component)]
+struct SandboxPanel;
+
+#[derive(Component)]
+struct MusicStem {
+    layer: usize,
+    volume: f32,
+}
+
+#[derive(Component)]
+struct BrightnessOverlay;
+
+/// One of the pair of black bars either side of a letterboxed play area.
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+enum LetterboxBar {
+    /// Left or top.
+    Start,
+    /// Right or bottom.
+    End,
+}
+
+#[derive(Component)]
+struct TouchControlsUi;
+
+#[derive(Component)]
+struct JoystickKnob;
+
+#[derive(Component)]
+struct BulletTimeGauge;
+
+#[derive(Component)]
+struct BulletTimeTint;
+
+/// Screen border lit up by a running combo; see `update_combo_glow`.
+#[derive(Component)]
+struct ComboGlow {
+    /// Eased towards the current tier's brightness, before any pulse.
+    alpha: f32,
+    /// Kept from the last tier lit, so a broken combo fades out in its own colour.
+    color: Color,
+}
+
+/// A shield put up by `PowerUpKind::Barrier`, stopping enemy bullets until it has
+/// taken `BARRIER_HITS` of them or `timer` runs out.
+#[derive(Component)]
+struct Barrier {
+    hits_remaining: u32,
+    timer: Timer,
+}
+
+/// One of a barrier's crack marks, shown once it has lost enough of its hits.
+#[derive(Component)]
+struct BarrierCrack(u32);
+
+/// Fullscreen black node faded by `fade_transitions`.
+#[derive(Component)]
+struct FadeOverlay;
+
+#[derive(Component)]
+struct FreezeGaugeFill;
+
+#[derive(Component)]
+struct RapidFireGauge;
+
+#[derive(Component)]
+struct RapidFireGaugeFill;
+
+#[derive(Component)]
+struct GrazeMeterFill;
+
+#[derive(Component)]
+struct WaveProgressUi;
+
+#[derive(Component)]
+struct WaveText;
+
+#[derive(Component)]
+struct KillFeedUi;
+
+/// One line of the kill feed, 0 being the newest.
+#[derive(Component)]
+struct KillFeedLine(usize);
+
+#[derive(Component)]
+struct WaveProgressFill;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+enum PowerUpKind {
+    Bounce,
+    RapidFire,
+    Pierce,
+    AllRange,
+    Flame,
+    /// Enemy bullets that reach the ship fly back up as the player's own.
+    Reflect,
+    /// Puts up a `Barrier` above the ship instead of changing how it fires.
+    Barrier,
+}
+
+impl PowerUpKind {
+    const ALL: [PowerUpKind; 7] = [
+        PowerUpKind::Bounce,
+        PowerUpKind::RapidFire,
+        PowerUpKind::Pierce,
+        PowerUpKind::AllRange,
+        PowerUpKind::Flame,
+        PowerUpKind::Reflect,
+        PowerUpKind::Barrier,
+    ];
+
+    /// Whether this stays with the ship on a timer in `ActivePowerUps`, rather than
+    /// being deployed as soon as it is picked up.
+    fn held(self) -> bool {
+        self != PowerUpKind::Barrier
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            PowerUpKind::Bounce => "Bounce",
+            PowerUpKind::RapidFire => "Rapid Fire",
+            PowerUpKind::Pierce => "Pierce",
+            PowerUpKind::AllRange => "All-Range",
+            PowerUpKind::Flame => "Flame",
+            PowerUpKind::Reflect => "Reflect",
+            PowerUpKind::Barrier => "Barrier",
+        }
+    }
+
+    /// What picking this up again does while it is still active.
+    fn stacking(self) -> Stacking {
+        match self {
+            // A fresh barrier replaces any still standing.
+            PowerUpKind::Bounce
+            | PowerUpKind::Pierce
+            | PowerUpKind::AllRange
+            | PowerUpKind::Barrier => Stacking::Refresh,
+            PowerUpKind::RapidFire => Stacking::Tier,
+            PowerUpKind::Flame | PowerUpKind::Reflect => Stacking::Extend,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Stacking {
+    /// The timer starts over at the full duration.
+    Refresh,
+    /// A full duration is added to the time left, up to `MAX_POWER_UP_STACK_SECS`.
+    Extend,
+    /// The power-up steps up a tier, up to its max, and its timer starts over.
+    Tier,
+}
+
+#[derive(Component)]
+struct PowerUp {
+    kind: PowerUpKind,
+}
+
+/// Points from a kill, waiting to be picked up.
+#[derive(Component)]
+struct ScoreChip {
+    value: u32,
+    velocity: Vec2,
+    lifetime: Timer,
+}
+
+#[derive(Resource)]
+struct Score(u32);
+
+/// A rule change a run can be started with, in trade for a score multiplier. Listed in
+/// `MODIFIERS_PATH`, where fields left out leave that part of play alone.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+struct Modifier {
+    name: String,
+    /// Multiplies how fast enemies move.
+    enemy_speed: f32,
+    /// Off, nothing drops power-ups.
+    power_ups: bool,
+    /// Lives the run starts with, and a continue refills to, at most.
+    max_lives: Option<u32>,
+    /// Multiplies all score earned while it's on.
+    score: f32,
+}
+
+impl Default for Modifier {
+    fn default() -> Self {
+        Modifier {
+            name: String::new(),
+            enemy_speed: 1.0,
+            power_ups: true,
+            max_lives: None,
+            score: 1.0,
+        }
+    }
+}
+
+impl Modifier {
+    fn is_valid(&self) -> bool {
+        !self.name.is_empty()
+            && self.enemy_speed.is_finite()
+            && self.enemy_speed > 0.0
+            && self.max_lives != Some(0)
+            && self.score.is_finite()
+            && self.score > 0.0
+    }
+}
+
+/// Every modifier on offer, loaded from `MODIFIERS_PATH` when present.
+#[derive(Resource)]
+struct ModifierConfig(Vec<Modifier>);
+
+impl Default for ModifierConfig {
+    fn default() -> Self {
+        ModifierConfig(vec![
+            Modifier {
+                name: "Double Time".into(),
+                enemy_speed: 2.0,
+                score: 2.0,
+                ..default()
+            },
+            Modifier {
+                name: "Unarmed".into(),
+                power_ups: false,
+                score: 1.5,
+                ..default()
+            },
+            Modifier {
+                name: "One Life".into(),
+                max_lives: Some(1),
+                score: 1.5,
+                ..default()
+            },
+        ])
+    }
+}
+
+impl ModifierConfig {
+    fn load() -> Self {
+        let Ok(contents) = fs::read_to_string(MODIFIERS_PATH) else {
+            return ModifierConfig::default();
+        };
+        match serde_json::from_str::<Vec<Modifier>>(&contents) {
+            Ok(modifiers)
+                if modifiers.iter().all(Modifier::is_valid)
+                    && modifiers.iter().enumerate().all(|(index, modifier)| {
+                        modifiers[..index]
+                            .iter()
+                            .all(|other| other.name != modifier.name)
+                    }) =>
+            {
+                ModifierConfig(modifiers)
+            }
+            Ok(_) => {
+                warn!(
+                    "modifiers need distinct names and positive speeds, lives and scores, using defaults"
+                );
+                ModifierConfig::default()
+            }
+            Err(err) => {
+                warn!("invalid modifiers, using defaults: {err}");
+                ModifierConfig::default()
+            }
+        }
+    }
+}
+
+/// The modifiers the current run was started with, always none for a daily challenge.
+/// Picking them on the modifiers screen only changes `Settings::modifiers`, so they
+/// apply from the next run.
+#[derive(Resource, Clone, Default, Serialize, Deserialize)]
+struct Modifiers(Vec<Modifier>);
+
+impl Modifiers {
+    /// The modifiers in `config` named in `picked`, in the order they are listed.
+    fn pick(config: &ModifierConfig, picked: &[String]) -> Self {
+        Modifiers(
+            config
+                .0
+                .iter()
+                .filter(|modifier| picked.contains(&modifier.name))
+                .cloned()
+                .collect(),
+        )
+    }
+
+    fn enemy_speed(&self) -> f32 {
+        self.0.iter().map(|modifier| modifier.enemy_speed).product()
+    }
+
+    fn power_ups(&self) -> bool {
+        self.0.iter().all(|modifier| modifier.power_ups)
+    }
+
+    fn starting_lives(&self, settings: &Settings) -> u32 {
+        self.0
+            .iter()
+            .filter_map(|modifier| modifier.max_lives)
+            .fold(settings.starting_lives, u32::min)
+    }
+
+    fn score(&self) -> f32 {
+        self.0.iter().map(|modifier| modifier.score).product()
+    }
+
+    fn names(&self) -> Vec<String> {
+        self.0
+            .iter()
+            .map(|modifier| modifier.name.clone())
+            .collect()
+    }
+}
+
+/// Fills as enemy bullets graze the player; a full meter becomes a brief shield.
+#[derive(Resource, Default)]
+struct GrazeMeter(f32);
+
+/// An enemy bullet that has already counted towards the graze meter.
+#[derive(Component)]
+struct Grazed;
+
+/// Score earned just for staying alive, paid out once a second of play.
+#[derive(Resource)]
+struct SurvivalBonus {
+    timer: Timer,
+    /// Fractional points not yet paid into `Score`.
+    carry: f32,
+    earned: u32,
+}
+
+impl Default for SurvivalBonus {
+    fn default() -> Self {
+        SurvivalBonus {
+            timer: Timer::from_seconds(1.0, TimerMode::Repeating),
+            carry: 0.0,
+            earned: 0,
+        }
+    }
+}
+
+/// Continues taken this run, and the countdown while one is on offer.
+#[derive(Resource)]
+struct Continues {
+    used: u32,
+    countdown: Timer,
+}
+
+impl Default for Continues {
+    fn default() -> Self {
+        Continues {
+            used: 0,
+            countdown: Timer::from_seconds(CONTINUE_COUNTDOWN_SECS, TimerMode::Once),
+        }
+    }
+}
+
+impl Continues {
+    /// Coins the next continue costs; free ones come first.
+    fn cost(&self) -> u32 {
+        if self.used < FREE_CONTINUES {
+            0
+        } else {
+            CONTINUE_COST
+        }
+    }
+}
+
+/// What a rewind puts back.
+#[derive(Clone)]
+struct RewindState {
+    translation: Vec3,
+    power_ups: ActivePowerUps,
+}
+
+/// The last `REWIND_SECS` of the ship, recorded each frame, and the rewinds left this
+/// run. A fatal hit with one left sends the ship back to the oldest state instead.
+#[derive(Resource, Default)]
+struct Rewind {
+    /// Oldest first, each with the `clock` reading it was taken at.
+    history: VecDeque<(f32, RewindState)>,
+    /// Seconds recorded this run. Elapsed time would count pauses too, and age the
+    /// whole history out over a long one.
+    clock: f32,
+    uses: u32,
+}
+
+impl Rewind {
+    fn new(uses: u32) -> Self {
+        Rewind {
+            history: VecDeque::new(),
+            clock: 0.0,
+            uses,
+        }
+    }
+
+    /// Spends a use for the oldest recorded state. The history starts over, so a
+    /// second rewind straight after can't go back further.
+    fn take(&mut self) -> Option<RewindState> {
+        if self.uses == 0 {
+            return None;
+        }
+        let (_, state) = self.history.pop_front()?;
+        self.history.clear();
+        self.uses -= 1;
+        Some(state)
+    }
+}
+
+/// Enemies that got past the player this run. One more than the quota ends it.
+#[derive(Resource, Default)]
+struct Escapes(u32);
+
+impl Escapes {
+    fn quota(difficulty: &Difficulty) -> u32 {
+        (BASE_ESCAPE_QUOTA / difficulty.level).ceil() as u32
+    }
+
+    fn remaining(&self, difficulty: &Difficulty) -> u32 {
+        Escapes::quota(difficulty).saturating_sub(self.0)
+    }
+}
+
+/// Why the last run ended, shown on the game-over screen.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+enum GameOverReason {
+    #[default]
+    Unknown,
+    ShipDestroyed,
+    TooManyEscaped,
+    // No time limit exists yet; this is here for when one does.
+    #[allow(dead_code)]
+    TimeUp,
+}
+
+impl GameOverReason {
+    fn message(self) -> &'static str {
+        match self {
+            GameOverReason::Unknown => "The run is over",
+            GameOverReason::ShipDestroyed => "Ship Destroyed",
+            GameOverReason::TooManyEscaped => "Too Many Escaped",
+            GameOverReason::TimeUp => "Time's Up",
+        }
+    }
+}
+
+/// Consecutive kills by the player's bullets. Escaped enemies break it; missed
+/// shots chip away at it when `Settings::miss_penalty` is on.
+#[derive(Resource, Default)]
+struct Combo {
+    count: u32,
+}
+
+impl Combo {
+    fn multiplier(&self) -> u32 {
+        (1 + self.count / COMBO_STEP).min(MAX_COMBO_MULTIPLIER)
+    }
+}
+
+/// Jolts the camera about, harder the more trauma has built up. `camera_shake` owns
+/// the camera's x and y; nothing else moves it.
+#[derive(Resource, Default)]
+struct ScreenShake {
+    trauma: f32,
+}
+
+impl ScreenShake {
+    fn add(&mut self, trauma: f32) {
+        self.trauma = (self.trauma + trauma).min(1.0);
+    }
+}
+
+/// Zoom-in on big kills that eases back out. It only touches the projection's scale,
+/// so anything shaking the camera transform is left alone.
+#[derive(Resource, Default)]
+struct CameraPunch {
+    remaining: f32,
+    magnitude: f32,
+}
+
+impl CameraPunch {
+    /// A stronger punch than the one playing takes over; a weaker one just restarts it.
+    fn trigger(&mut self, magnitude: f32) {
+        if self.remaining <= 0.0 {
+            self.magnitude = 0.0;
+        }
+        self.magnitude = self.magnitude.max(magnitude);
+        self.remaining = CAMERA_PUNCH_SECS;
+    }
+}
+
+/// A beat where the enemy side of the game stops dead; `bullet_time` holds `TimeScale`
+/// at zero while any of it remains.
+#[derive(Resource, Default)]
+struct HitStop {
+    remaining: f32,
+}
+
+impl HitStop {
+    /// Overlapping hits don't add up; the longest one still to run wins.
+    fn trigger(&mut self, secs: f32) {
+        self.remaining = self.remaining.max(secs);
+    }
+}
+
+#[derive(Resource)]
+struct Lives(u32);
+
+/// The highest whole difficulty level the run has reached, so climbing back to one
+/// after adaptive difficulty pulled it down isn't announced twice. `None` until the
+/// run's first frame.
+#[derive(Resource, Default)]
+struct DangerTier(Option<u32>);
+
+#[derive(Resource)]
+struct GameAudio {
+    music_stems: Vec<Handle<AudioSource>>,
+    boss_phase: Handle<AudioSource>,
+    boss_intro: Handle<AudioSource>,
+    combo_milestone: Handle<AudioSource>,
+    danger_rising: Handle<AudioSource>,
+    enemy_spawn: Handle<AudioSource>,
+    /// Played instead of `enemy_spawn` when anything but a basic enemy arrives.
+    special_spawn: Handle<AudioSource>,
+    weapon_fire: HashMap<BulletKind, Handle<AudioSource>>,
+}
+
+/// Boss tuning, loaded from `BOSS_CONFIG_PATH` when present.
+#[derive(Resource, Clone, Debug, Deserialize)]
+struct BossConfig {
+    health: u32,
+    phases: Vec<BossPhase>,
+    /// Waves that end in a mini-boss. A config without the list has none.
+    #[serde(default)]
+    mini_bosses: Vec<MiniBossConfig>,
+}
+
+/// A smaller boss with a single attack that comes out once the rest of `wave` is gone.
+#[derive(Clone, Debug, Deserialize)]
+struct MiniBossConfig {
+    wave: u32,
+    health: u32,
+    move_speed: f32,
+    fire_interval: f32,
+    pattern: Pattern,
+}
+
+impl Default for BossConfig {
+    fn default() -> Self {
+        BossConfig {
+            health: 120,
+            phases: vec![
+                BossPhase {
+                    health_fraction: 1.0,
+                    move_speed: 80.0,
+                    fire_interval: 1.2,
+                    pattern: Pattern::Aimed {
+                        count: 3,
+                        spread: 0.5,
+                        speed: 220.0,
+                    },
+                },
+                BossPhase {
+                    health_fraction: 0.6,
+                    move_speed: 160.0,
+                    fire_interval: 0.25,
+                    pattern: Pattern::Spiral {
+                        count: 4,
+                        speed: 200.0,
+                        turn: 0.3,
+                    },
+                },
+                BossPhase {
+                    health_fraction: 0.25,
+                    move_speed: 220.0,
+                    fire_interval: 0.8,
+                    pattern: Pattern::Radial {
+                        count: 16,
+                        speed: 260.0,
+                    },
+                },
+            ],
+            mini_bosses: vec![
+                MiniBossConfig {
+                    wave: 2,
+                    health: 30,
+                    move_speed: 120.0,
+                    fire_interval: 1.0,
+                    pattern: Pattern::Aimed {
+                        count: 3,
+                        spread: 0.4,
+                        speed: 200.0,
+                    },
+                },
+                MiniBossConfig {
+                    wave: 4,
+                    health: 45,
+                    move_speed: 100.0,
+                    fire_interval: 1.5,
+                    pattern: Pattern::Radial {
+                        count: 10,
+                        speed: 200.0,
+                    },
+                },
+            ],
+        }
+    }
+}
+
+impl BossConfig {
+    fn load() -> Self {
+        let Ok(contents) = fs::read_to_string(BOSS_CONFIG_PATH) else {
+            return BossConfig::default();
+        };
+        match serde_json::from_str::<BossConfig>(&contents) {
+            Ok(config)
+                if config.health > 0
+                    && !config.phases.is_empty()
+                    && config.phases.iter().all(|phase| phase.pattern.is_valid())
+                    && config
+                        .mini_bosses
+                        .iter()
+                        .all(|mini_boss| mini_boss.health > 0 && mini_boss.pattern.is_valid()) =>
+            {
+                config
+            }
+            Ok(_) => {
+                warn!(
+                    "boss config needs health, at least one phase and valid patterns, for mini-bosses too, using defaults"
+                );
+                BossConfig::default()
+            }
+            Err(err) => {
+                warn!("invalid boss config, using defaults: {err}");
+                BossConfig::default()
+            }
+        }
+    }
+}
+
+/// Score values, loaded from `SCORING_CONFIG_PATH` when present. Fields left out of
+/// the file keep their defaults.
+#[derive(Resource, Clone, Debug, Deserialize)]
+#[serde(default)]
+struct ScoringConfig {
+    /// Score for a kill, before the combo multiplier.
+    kill: u32,
+    leader_bonus: u32,
+    /// Added on top of `kill` for a carrier.
+    carrier_bonus: u32,
+    /// For each kill after the first in a single piercing shot's chain.
+    chain_bonus: u32,
+    mini_boss_kill: u32,
+    boss_kill: u32,
+    /// For each enemy bullet shot down.
+    bullet_cancel: u32,
+    /// Paid out as each wave is cleared.
+    wave_clear: u32,
+    /// Awarded per second survived at difficulty 1.0, rising with difficulty.
+    survival_per_sec: f32,
+    /// Taken off the score, never below zero, for each enemy that gets past.
+    escape_penalty: u32,
+}
+
+impl Default for ScoringConfig {
+    fn default() -> Self {
+        ScoringConfig {
+            kill: 10,
+            leader_bonus: 50,
+            carrier_bonus: 100,
+            chain_bonus: 15,
+            mini_boss_kill: 100,
+            boss_kill: 200,
+            bullet_cancel: 1,
+            wave_clear: 0,
+            survival_per_sec: 1.0,
+            escape_penalty: 0,
+        }
+    }
+}
+
+impl ScoringConfig {
+    fn load() -> Self {
+        let Ok(contents) = fs::read_to_string(SCORING_CONFIG_PATH) else {
+            return ScoringConfig::default();
+        };
+        match serde_json::from_str::<ScoringConfig>(&contents) {
+            Ok(config)
+                if config.kill > 0
+                    && config.survival_per_sec.is_finite()
+                    && config.survival_per_sec >= 0.0 =>
+            {
+                config
+            }
+            Ok(_) => {
+                warn!(
+                    "scoring config needs kills to score and a survival rate of zero or more, using defaults"
+                );
+                ScoringConfig::default()
+            }
+            Err(err) => {
+                warn!("invalid scoring config, using defaults: {err}");
+                ScoringConfig::default()
+            }
+        }
+    }
+}
+
+/// A formation's flight in to its slots, as a cubic Bézier. Points are relative to
+/// each enemy's slot; `mirror` flips them for enemies right of the leader, so the two
+/// wings sweep in from opposite sides.
+#[derive(Clone, Copy, Debug, Deserialize)]
+struct EntrancePath {
+    start: Vec2,
+    control_a: Vec2,
+    control_b: Vec2,
+    secs: f32,
+    mirror: bool,
+}
+
+impl EntrancePath {
+    fn curve(&self, slot: Vec2, flip: bool) -> CubicCurve<Vec2> {
+        let sign = if self.mirror && flip { -1.0 } else { 1.0 };
+        let point = |offset: Vec2| slot + Vec2::new(offset.x * sign, offset.y);
+        CubicBezier::new([[
+            point(self.start),
+            point(self.control_a),
+            point(self.control_b),
+            slot,
+        ]])
+        .to_curve()
+    }
+}
+
+/// Formation entrances, loaded from `ENTRANCE_CONFIG_PATH` when present. Wave `n` uses
+/// path `n - 1`, cycling; no paths means formations simply drop in.
+#[derive(Resource, Clone, Debug, Deserialize)]
+struct EntranceConfig {
+    paths: Vec<EntrancePath>,
+}
+
+impl Default for EntranceConfig {
+    fn default() -> Self {
+        EntranceConfig {
+            paths: vec![
+                EntrancePath {
+                    start: Vec2::new(-800.0, -250.0),
+                    control_a: Vec2::new(-200.0, -450.0),
+                    control_b: Vec2::new(0.0, -250.0),
+                    secs: 1.6,
+                    mirror: true,
+                },
+                EntrancePath {
+                    start: Vec2::new(0.0, 150.0),
+                    control_a: Vec2::new(350.0, -300.0),
+                    control_b: Vec2::new(-350.0, -300.0),
+                    secs: 1.4,
+                    mirror: true,
+                },
+                EntrancePath {
+                    start: Vec2::new(-800.0, 0.0),
+                    control_a: Vec2::new(-100.0, 0.0),
+                    control_b: Vec2::new(200.0, -350.0),
+                    secs: 1.8,
+                    mirror: false,
+                },
+            ],
+        }
+    }
+}
+
+impl EntranceConfig {
+    fn load() -> Self {
+        let Ok(contents) = fs::read_to_string(ENTRANCE_CONFIG_PATH) else {
+            return EntranceConfig::default();
+        };
+        match serde_json::from_str::<EntranceConfig>(&contents) {
+            Ok(config) if config.paths.iter().all(|path| path.secs > 0.0) => config,
+            Ok(_) => {
+                warn!("entrance paths need a positive duration, using defaults");
+                EntranceConfig::default()
+            }
+            Err(err) => {
+                warn!("invalid entrance config, using defaults: {err}");
+                EntranceConfig::default()
+            }
+        }
+    }
+
+    fn for_wave(&self, wave: u32) -> Option<&EntrancePath> {
+        if self.paths.is_empty() {
+            return None;
+        }
+        self.paths
+            .get((wave.max(1) - 1) as usize % self.paths.len())
+    }
+}
+
+/// One authored spawn, `time` seconds into a run.
+#[derive(Clone, Copy, Debug, Deserialize)]
+struct TimelineEvent {
+    time: f32,
+    spawn: SpawnChoice,
+    x: f32,
+}
+
+/// Hand-placed spawns from `LEVEL_PATH`, fired on top of the director's as the run
+/// clock reaches each one. They don't count towards the wave. No level file means
+/// every spawn is the director's.
+#[derive(Resource, Debug, Default, Deserialize)]
+struct SpawnTimeline {
+    events: Vec<TimelineEvent>,
+    #[serde(skip)]
+    elapsed: f32,
+    /// Index of the first event still to fire.
+    #[serde(skip)]
+    next: usize,
+}
+
+impl SpawnTimeline {
+    fn load() -> Self {
+        let Ok(contents) = fs::read_to_string(LEVEL_PATH) else {
+            return SpawnTimeline::default();
+        };
+        match serde_json::from_str::<SpawnTimeline>(&contents) {
+            Ok(mut timeline)
+                if timeline
+                    .events
+                    .iter()
+                    .all(|event| event.time >= 0.0 && event.time.is_finite()) =>
+            {
+                timeline.events.sort_by(|a, b| a.time.total_cmp(&b.time));
+                timeline
+            }
+            Ok(_) => {
+                warn!("level events need a time of zero or more, ignoring the level");
+                SpawnTimeline::default()
+            }
+            Err(err) => {
+                warn!("invalid level, ignoring it: {err}");
+                SpawnTimeline::default()
+            }
+        }
+    }
+
+    /// Moves the run clock to `secs`, skipping over every event due before then.
+    fn seek(&mut self, secs: f32) {
+        self.elapsed = secs;
+        self.next = self.events.partition_point(|event| event.time <= secs);
+    }
+
+    /// Advances the run clock, returning the events that came due on the way, in order.
+    fn advance(&mut self, secs: f32) -> &[TimelineEvent] {
+        let start = self.next;
+        self.seek(self.elapsed + secs);
+        &self.events[start..self.next]
+    }
+}
+
+#[derive(Resource)]
+struct BossSchedule {
+    next_score: u32,
+}
+
+impl Default for BossSchedule {
+    fn default() -> Self {
+        BossSchedule {
+            next_score: BOSS_SCORE_INTERVAL,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum GameAction {
+    MoveLeft,
+    MoveRight,
+    Fire,
+    Dash,
+    BulletTime,
+    Freeze,
+    Pause,
+}
+
+impl GameAction {
+    const ALL: [GameAction; 7] = [
+        GameAction::MoveLeft,
+        GameAction::MoveRight,
+        GameAction::Fire,
+        GameAction::Dash,
+        GameAction::BulletTime,
+        GameAction::Freeze,
+        GameAction::Pause,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            GameAction::MoveLeft => "Move Left",
+            GameAction::MoveRight => "Move Right",
+            GameAction::Fire => "Fire",
+            GameAction::Dash => "Dash",
+            GameAction::BulletTime => "Bullet Time",
+            GameAction::Freeze => "Freeze Pulse",
+            GameAction::Pause => "Pause",
+        }
+    }
+}
+
+/// Menu keys, which always work and so can't be bound to a gameplay action. The
+/// arrow keys also keep moving the player whatever movement is bound to.
+const RESERVED_KEYS: [KeyCode; 10] = [
+    KeyCode::Up,
+    KeyCode::Down,
+    KeyCode::Left,
+    KeyCode::Right,
+    KeyCode::Escape,
+    KeyCode::Return,
+    KeyCode::Back,
+    KeyCode::Tab,
+    KeyCode::Home,
+    KeyCode::End,
+];
+
+/// Keyboard bindings for gameplay actions. Saved as part of `Settings`, which is
+/// the copy to edit; this resource follows it.
+#[derive(Resource, Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+struct KeyBindings {
+    move_left: KeyCode,
+    move_right: KeyCode,
+    fire: KeyCode,
+    dash: KeyCode,
+    bullet_time: KeyCode,
+    freeze: KeyCode,
+    pause: KeyCode,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        KeyBindings {
+            move_left: KeyCode::A,
+            move_right: KeyCode::D,
+            fire: KeyCode::Space,
+            dash: KeyCode::ShiftLeft,
+            bullet_time: KeyCode::F,
+            freeze: KeyCode::G,
+            pause: KeyCode::P,
+        }
+    }
+}
+
+impl KeyBindings {
+    fn key(&self, action: GameAction) -> KeyCode {
+        match action {
+            GameAction::MoveLeft => self.move_left,
+            GameAction::MoveRight => self.move_right,
+            GameAction::Fire => self.fire,
+            GameAction::Dash => self.dash,
+            GameAction::BulletTime => self.bullet_time,
+            GameAction::Freeze => self.freeze,
+            GameAction::Pause => self.pause,
+        }
+    }
+
+    fn key_mut(&mut self, action: GameAction) -> &mut KeyCode {
+        match action {
+            GameAction::MoveLeft => &mut self.move_left,
+            GameAction::MoveRight => &mut self.move_right,
+            GameAction::Fire => &mut self.fire,
+            GameAction::Dash => &mut self.dash,
+            GameAction::BulletTime => &mut self.bullet_time,
+            GameAction::Freeze => &mut self.freeze,
+            GameAction::Pause => &mut self.pause,
+        }
+    }
+
+    fn action_for(&self, key: KeyCode) -> Option<GameAction> {
+        GameAction::ALL
+            .into_iter()
+            .find(|&action| self.key(action) == key)
+    }
+}
+
+/// Keyboard input looked up through the player's key bindings.
+#[derive(SystemParam)]
+struct Controls<'w> {
+    keyboard: Res<'w, Input<KeyCode>>,
+    bindings: Res<'w, KeyBindings>,
+}
+
+impl Controls<'_> {
+    fn pressed(&self, action: GameAction) -> bool {
+        self.keyboard.pressed(self.bindings.key(action))
+    }
+
+    fn just_pressed(&self, action: GameAction) -> bool {
+        self.keyboard.just_pressed(self.bindings.key(action))
+    }
+}
+
+/// Key capture on the controls screen: the action waiting for a key, and why the
+/// last key pressed was turned down.
+#[derive(Resource, Default)]
+struct Rebinding {
+    action: Option<GameAction>,
+    warning: Option<String>,
+}
+
+/// The menu the quit dialog covered, put back if the player backs out.
+#[derive(Resource, Default)]
+struct QuitPrompt {
+    covered: Option<Menu>,
+}
+
+#[derive(Resource, Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+struct Settings {
+    brightness: f32,
+    /// Pause automatically when the window loses focus.
+    auto_pause: bool,
+    /// Quit asks for a yes or no before closing the game.
+    confirm_quit: bool,
+    /// Missed shots knock a quarter off the combo.
+    miss_penalty: bool,
+    music_volume: f32,
+    sfx_volume: f32,
+    /// Difficulty each run starts at; `--difficulty` overrides it.
+    difficulty: f32,
+    /// The next run's `BaseDifficulty`.
+    base_difficulty: f32,
+    /// Lives each run starts with, and what a continue refills to.
+    starting_lives: u32,
+    /// Fatal hits a run can rewind out of; see `Rewind`.
+    rewinds: u32,
+    /// Names of the `Modifier`s picked for the next run.
+    modifiers: Vec<String>,
+    fullscreen: bool,
+    /// Window size while windowed, one of `RESOLUTIONS`.
+    resolution: (u32, u32),
+    /// Black bars keep the play area at this aspect ratio when the window doesn't match.
+    letterbox: Letterbox,
+    /// Multiplies the size of all UI text and panels, for high-DPI displays.
+    ui_scale: f32,
+    /// Which corners the HUD sits in, and how big it is on top of `ui_scale`.
+    hud_layout: HudLayout,
+    hud_scale: f32,
+    /// Multiples of the ship's normal speed and fire rate, picked up by the next run.
+    ship_speed: f32,
+    fire_rate: f32,
+    vsync: bool,
+    /// Drop shadows under ships and pickups. Off saves a sprite for each of them.
+    shadows: bool,
+    effects_quality: EffectsQuality,
+    /// Holds the background at one colour instead of shifting with score and bosses.
+    static_background: bool,
+    colorblind: bool,
+    /// Skips confetti and swaps flashing effects for steady ones.
+    reduce_motion: bool,
+    /// Multiplies screen shake and the zoom punch on big kills; zero turns them off.
+    /// Reduce motion turns them off whatever this is set to.
+    screen_shake: f32,
+    wrap_mode: WrapMode,
+    /// Largest random angle added to shots fired on the move; 0 keeps them straight.
+    move_spread_degrees: f32,
+    /// The ship springs back a little off the side bounds instead of stopping dead.
+    elastic_edges: bool,
+    /// Lists the last few kills and what they scored.
+    kill_feed: bool,
+    /// Fires continuously without holding the fire button.
+    auto_fire: bool,
+    /// Bends mouse and stick aim toward the nearest enemy close to the line of fire.
+    aim_assist: AimAssist,
+    /// Player and enemy bullets that meet destroy each other.
+    bullet_cancel: bool,
+    /// Gunners and cloakers sidestep shots headed their way.
+    dodging_enemies: bool,
+    /// Nudges difficulty each wave by how well the run is going.
+    adaptive_difficulty: bool,
+    /// Appends a line about each finished run to the local balancing log.
+    analytics: bool,
+    /// Power-ups carry on through a lost life instead of running out with it.
+    keep_power_ups: bool,
+    /// Losing a life or the run clears every enemy bullet on screen, not just those
+    /// near where the ship comes back.
+    clear_bullets_on_hit: bool,
+    /// Kills drop their points as chips to fly over and collect, instead of scoring
+    /// them at once.
+    score_chips: bool,
+    /// A run that ends offers a continue before game over.
+    continues: bool,
+    /// Turning a hazard off also clears any already on screen.
+    enemy_bullets: bool,
+    mines: bool,
+    key_bindings: KeyBindings,
+}
+
+/// The option after `current` in `all`, or before it when `direction` is negative,
+/// cycling past either end. Settings that pick one of a fixed list step with this.
+fn cycle<T: Copy + PartialEq>(all: &[T], current: T, direction: f32) -> T {
+    let index = all
+        .iter()
+        .position(|&option| option == current)
+        .unwrap_or(0);
+    let len = all.len();
+    let next = if direction < 0.0 {
+        (index + len - 1) % len
+    } else {
+        (index + 1) % len
+    };
+    all[next]
+}
+
+/// The aspect ratio the play area is held to, if any, whatever shape the window is.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum Letterbox {
+    #[default]
+    Off,
+    Standard,
+    Wide,
+}
+
+impl Letterbox {
+    const ALL: [Letterbox; 3] = [Letterbox::Off, Letterbox::Standard, Letterbox::Wide];
+
+    fn label(self) -> &'static str {
+        match self {
+            Letterbox::Off => "Off",
+            Letterbox::Standard => "4:3",
+            Letterbox::Wide => "16:9",
+        }
+    }
+
+    /// Width over height.
+    fn aspect(self) -> Option<f32> {
+        match self {
+            Letterbox::Off => None,
+            Letterbox::Standard => Some(4.0 / 3.0),
+            Letterbox::Wide => Some(16.0 / 9.0),
+        }
+    }
+
+    fn step(self, direction: f32) -> Self {
+        cycle(&Self::ALL, self, direction)
+    }
+}
+
+/// How far aimed shots are pulled toward the nearest enemy in front of them.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum AimAssist {
+    #[default]
+    Off,
+    Weak,
+    Strong,
+}
+
+impl AimAssist {
+    const ALL: [AimAssist; 3] = [AimAssist::Off, AimAssist::Weak, AimAssist::Strong];
+
+    fn label(self) -> &'static str {
+        match self {
+            AimAssist::Off => "Off",
+            AimAssist::Weak => "Weak",
+            AimAssist::Strong => "Strong",
+        }
+    }
+
+    /// Share of the way from the raw aim to the enemy the shot is turned.
+    fn strength(self) -> f32 {
+        match self {
+            AimAssist::Off => 0.0,
+            AimAssist::Weak => 0.3,
+            AimAssist::Strong => 0.6,
+        }
+    }
+
+    fn step(self, direction: f32) -> Self {
+        cycle(&Self::ALL, self, direction)
+    }
+}
+
+/// Preset corners for the two HUD groups.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum HudLayout {
+    /// Score top left, lives top right.
+    #[default]
+    Classic,
+    /// Score top right, lives top left.
+    Mirrored,
+    /// Both along the bottom, clear of the wave progress.
+    Bottom,
+}
+
+impl HudLayout {
+    const ALL: [HudLayout; 3] = [HudLayout::Classic, HudLayout::Mirrored, HudLayout::Bottom];
+
+    fn label(self) -> &'static str {
+        match self {
+            HudLayout::Classic => "Classic",
+            HudLayout::Mirrored => "Mirrored",
+            HudLayout::Bottom => "Bottom",
+        }
+    }
+
+    /// Whether the score and status groups are on the left, and at the top.
+    fn corners(self) -> [(bool, bool); 2] {
+        match self {
+            HudLayout::Classic => [(true, true), (false, true)],
+            HudLayout::Mirrored => [(false, true), (true, true)],
+            HudLayout::Bottom => [(true, false), (false, false)],
+        }
+    }
+
+    fn step(self, direction: f32) -> Self {
+        cycle(&Self::ALL, self, direction)
+    }
+}
+
+/// The HUD layout the settings ask for, kept apart from them so `layout_hud` only
+/// runs when it has actually changed.
+#[derive(Resource, PartialEq)]
+struct HudConfig {
+    layout: HudLayout,
+    scale: f32,
+}
+
+impl HudConfig {
+    fn new(settings: &Settings) -> Self {
+        HudConfig {
+            layout: settings.hud_layout,
+            scale: settings.hud_scale,
+        }
+    }
+
+    /// Whether `group`'s corner is on the left, and at the top.
+    fn corner(&self, group: HudGroup) -> (bool, bool) {
+        let [score, status] = self.layout.corners();
+        match group {
+            HudGroup::Score => score,
+            HudGroup::Status => status,
+        }
+    }
+}
+
+/// How many purely cosmetic particles and afterimages are drawn. Auto leaves it to
+/// `PerformanceGovernor`, which thins them out while the frame rate is struggling.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum EffectsQuality {
+    #[default]
+    Auto,
+    High,
+    Low,
+}
+
+impl EffectsQuality {
+    const ALL: [EffectsQuality; 3] = [
+        EffectsQuality::Auto,
+        EffectsQuality::High,
+        EffectsQuality::Low,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            EffectsQuality::Auto => "Auto",
+            EffectsQuality::High => "High",
+            EffectsQuality::Low => "Low",
+        }
+    }
+
+    fn step(self, direction: f32) -> Self {
+        cycle(&Self::ALL, self, direction)
+    }
+}
+
+/// What wraps around the screen edges instead of leaving the screen.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum WrapMode {
+    #[default]
+    Off,
+    Enemies,
+    EnemiesAndPlayer,
+    /// Player bullets too, sideways only: shots still leave through the top.
+    Everything,
+}
+
+impl WrapMode {
+    const ALL: [WrapMode; 4] = [
+        WrapMode::Off,
+        WrapMode::Enemies,
+        WrapMode::EnemiesAndPlayer,
+        WrapMode::Everything,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            WrapMode::Off => "Off",
+            WrapMode::Enemies => "Enemies",
+            WrapMode::EnemiesAndPlayer => "Enemies + Player",
+            WrapMode::Everything => "Everything",
+        }
+    }
+
+    fn enemies(self) -> bool {
+        self != WrapMode::Off
+    }
+
+    fn player(self) -> bool {
+        matches!(self, WrapMode::EnemiesAndPlayer | WrapMode::Everything)
+    }
+
+    fn bullets(self) -> bool {
+        self == WrapMode::Everything
+    }
+
+    fn step(self, direction: f32) -> Self {
+        cycle(&Self::ALL, self, direction)
+    }
+}
+
+/// Moves `value` to the opposite edge once it is past `half_extent` either side.
+fn wrap(value: f32, half_extent: f32) -> f32 {
+    if value > half_extent {
+        value - 2.0 * half_extent
+    } else if value < -half_extent {
+        value + 2.0 * half_extent
+    } else {
+        value
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            brightness: 1.0,
+            auto_pause: true,
+            confirm_quit: true,
+            miss_penalty: true,
+            music_volume: 1.0,
+            sfx_volume: 1.0,
+            difficulty: 1.0,
+            base_difficulty: 1.0,
+            starting_lives: STARTING_LIVES,
+            modifiers: Vec::new(),
+            rewinds: 0,
+            fullscreen: false,
+            resolution: RESOLUTIONS[0],
+            letterbox: Letterbox::Off,
+            ui_scale: 1.0,
+            hud_layout: HudLayout::Classic,
+            hud_scale: 1.0,
+            ship_speed: 1.0,
+            fire_rate: 1.0,
+            vsync: true,
+            shadows: true,
+            effects_quality: EffectsQuality::Auto,
+            static_background: false,
+            colorblind: false,
+            reduce_motion: false,
+            screen_shake: 1.0,
+            wrap_mode: WrapMode::Off,
+            move_spread_degrees: 4.0,
+            elastic_edges: false,
+            kill_feed: false,
+            auto_fire: false,
+            aim_assist: AimAssist::Off,
+            bullet_cancel: false,
+            dodging_enemies: true,
+            adaptive_difficulty: true,
+            analytics: false,
+            keep_power_ups: true,
+            clear_bullets_on_hit: true,
+            score_chips: false,
+            continues: false,
+            enemy_bullets: true,
+            mines: true,
+            key_bindings: KeyBindings::default(),
+        }
+    }
+}
+
+impl Settings {
+    fn load() -> Self {
+        let mut settings: Settings = fs::read_to_string(SETTINGS_PATH)
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default();
+        settings.clamp();
+        settings
+    }
+
+    fn clamp(&mut self) {
+        self.brightness = self.brightness.clamp(MIN_BRIGHTNESS, 1.0);
+        self.music_volume = self.music_volume.clamp(0.0, 1.0);
+        self.sfx_volume = self.sfx_volume.clamp(0.0, 1.0);
+        self.difficulty = self.difficulty.clamp(1.0, MAX_DIFFICULTY);
+        self.base_difficulty = self
+            .base_difficulty
+            .clamp(MIN_BASE_DIFFICULTY, MAX_BASE_DIFFICULTY);
+        self.starting_lives = self.starting_lives.clamp(1, MAX_STARTING_LIVES);
+        self.rewinds = self.rewinds.min(MAX_REWINDS);
+        self.move_spread_degrees = self.move_spread_degrees.clamp(0.0, MAX_MOVE_SPREAD_DEGREES);
+        self.ui_scale = self.ui_scale.clamp(MIN_UI_SCALE, MAX_UI_SCALE);
+        self.hud_scale = self.hud_scale.clamp(MIN_HUD_SCALE, MAX_HUD_SCALE);
+        self.screen_shake = self.screen_shake.clamp(0.0, MAX_SCREEN_SHAKE);
+        self.ship_speed = self.ship_speed.clamp(MIN_SHIP_SPEED, MAX_SHIP_SPEED);
+        self.fire_rate = self.fire_rate.clamp(MIN_FIRE_RATE, MAX_FIRE_RATE);
+        if !RESOLUTIONS.contains(&self.resolution) {
+            self.resolution = RESOLUTIONS[0];
+        }
+    }
+
+    /// Steps a slider or flips a toggle; `direction` is -1.0 or 1.0. Returns whether
+    /// `action` is a setting at all.
+    fn adjust(&mut self, action: MenuAction, direction: f32) -> bool {
+        match action {
+            MenuAction::MusicVolume => self.music_volume += VOLUME_STEP * direction,
+            MenuAction::SfxVolume => self.sfx_volume += VOLUME_STEP * direction,
+            MenuAction::Brightness => self.brightness += BRIGHTNESS_STEP * direction,
+            MenuAction::Difficulty => self.difficulty += DIFFICULTY_SETTING_STEP * direction,
+            MenuAction::BaseDifficulty => self.base_difficulty += BASE_DIFFICULTY_STEP * direction,
+            MenuAction::StartingLives => {
+                self.starting_lives = self.starting_lives.saturating_add_signed(direction as i32)
+            }
+            MenuAction::Rewinds => {
+                self.rewinds = self.rewinds.saturating_add_signed(direction as i32)
+            }
+            MenuAction::Fullscreen => self.fullscreen = !self.fullscreen,
+            MenuAction::Resolution => {
+                let index = RESOLUTIONS
+                    .iter()
+                    .position(|resolution| *resolution == self.resolution)
+                    .unwrap_or(0);
+                let index =
+                    (index as isize + direction as isize).clamp(0, RESOLUTIONS.len() as isize - 1);
+                self.resolution = RESOLUTIONS[index as usize];
+            }
+            MenuAction::UiScale => self.ui_scale += UI_SCALE_STEP * direction,
+            MenuAction::HudLayout => self.hud_layout = self.hud_layout.step(direction),
+            MenuAction::HudScale => self.hud_scale += HUD_SCALE_STEP * direction,
+            MenuAction::ShipSpeed => self.ship_speed += SHIP_TUNING_STEP * direction,
+            MenuAction::FireRate => self.fire_rate += SHIP_TUNING_STEP * direction,
+            MenuAction::Vsync => self.vsync = !self.vsync,
+            MenuAction::Shadows => self.shadows = !self.shadows,
+            MenuAction::EffectsQuality => {
+                self.effects_quality = self.effects_quality.step(direction)
+            }
+            MenuAction::StaticBackground => self.static_background = !self.static_background,
+            MenuAction::Colorblind => self.colorblind = !self.colorblind,
+            MenuAction::ReduceMotion => self.reduce_motion = !self.reduce_motion,
+            MenuAction::ScreenShake => self.screen_shake += SCREEN_SHAKE_STEP * direction,
+            MenuAction::AutoPause => self.auto_pause = !self.auto_pause,
+            MenuAction::ConfirmQuitSetting => self.confirm_quit = !self.confirm_quit,
+            MenuAction::MissPenalty => self.miss_penalty = !self.miss_penalty,
+            MenuAction::WrapMode => self.wrap_mode = self.wrap_mode.step(direction),
+            MenuAction::Letterbox => self.letterbox = self.letterbox.step(direction),
+            MenuAction::MoveSpread => self.move_spread_degrees += direction,
+            MenuAction::ElasticEdges => self.elastic_edges = !self.elastic_edges,
+            MenuAction::KillFeed => self.kill_feed = !self.kill_feed,
+            MenuAction::BulletCancel => self.bullet_cancel = !self.bullet_cancel,
+            MenuAction::DodgingEnemies => self.dodging_enemies = !self.dodging_enemies,
+            MenuAction::AutoFire => self.auto_fire = !self.auto_fire,
+            MenuAction::AimAssist => self.aim_assist = self.aim_assist.step(direction),
+            MenuAction::AdaptiveDifficulty => self.adaptive_difficulty = !self.adaptive_difficulty,
+            MenuAction::KeepPowerUps => self.keep_power_ups = !self.keep_power_ups,
+            MenuAction::ClearBulletsOnHit => self.clear_bullets_on_hit = !self.clear_bullets_on_hit,
+            MenuAction::ScoreChips => self.score_chips = !self.score_chips,
+            MenuAction::Continues => self.continues = !self.continues,
+            MenuAction::Analytics => self.analytics = !self.analytics,
+            MenuAction::EnemyBullets => self.enemy_bullets = !self.enemy_bullets,
+            MenuAction::Mines => self.mines = !self.mines,
+            _ => return false,
+        }
+        self.clamp();
+        true
+    }
+
+    fn save(&self) {
+        self.save_in(Path::new(""));
+    }
+
+    fn save_in(&self, dir: &Path) {
+        let result = serde_json::to_string_pretty(self)
+            .map_err(|err| err.to_string())
+            .and_then(|json| {
+                fs::write(dir.join(SETTINGS_PATH), json).map_err(|err| err.to_string())
+            });
+        if let Err(err) = result {
+            warn!("failed to save settings: {err}");
+        }
+    }
+}
+
+/// Aim direction from the mouse (while the right button is held) or the right stick.
+/// `None` means the default straight-up fire.
+#[derive(Resource, Default)]
+struct Aim(Option<Vec2>);
+
+/// Input from the on-screen controls, shown only once a touch has been seen.
+#[derive(Resource, Default)]
+struct TouchControls {
+    visible: bool,
+    movement: f32,
+    fire: bool,
+}
+
+/// Gameplay randomness. Cosmetic effects keep using `thread_rng` so they don't
+/// perturb seeded runs.
+#[derive(Resource)]
+struct GameRng(StdRng);
+
+/// Tester-facing overrides parsed from the command line.
+#[derive(Resource, Default, Debug, Clone, Serialize, Deserialize)]
+struct LaunchOptions {
+    difficulty: Option<f32>,
+    weapon: Option<PowerUpKind>,
+    seed: Option<u64>,
+    god_mode: bool,
+    sandbox: bool,
+    #[serde(skip)]
+    record: Option<PathBuf>,
+    #[serde(skip)]
+    replay: Option<PathBuf>,
+}
+
+impl LaunchOptions {
+    fn from_args(args: impl IntoIterator<Item = String>) -> Self {
+        let mut options = LaunchOptions::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-h" | "--help" => {
+                    println!("{USAGE}");
+                    std::process::exit(0);
+                }
+                "--god" => options.god_mode = true,
+                "--sandbox" => options.sandbox = true,
+                "--difficulty" => match args.next().and_then(|value| value.parse().ok()) {
+                    Some(level) => {
+                        options.difficulty = Some(f32::clamp(level, 1.0, MAX_DIFFICULTY))
+                    }
+                    None => eprintln!("--difficulty expects a number\n\n{USAGE}"),
+                },
+                "--weapon" => match args.next().as_deref() {
+                    Some("bounce") => options.weapon = Some(PowerUpKind::Bounce),
+                    Some("rapid") => options.weapon = Some(PowerUpKind::RapidFire),
+                    Some("pierce") => options.weapon = Some(PowerUpKind::Pierce),
+                    Some("allrange") => options.weapon = Some(PowerUpKind::AllRange),
+                    Some("flame") => options.weapon = Some(PowerUpKind::Flame),
+                    Some("reflect") => options.weapon = Some(PowerUpKind::Reflect),
+                    _ => eprintln!(
+                        "--weapon expects one of: bounce, rapid, pierce, allrange, flame, reflect\n\n{USAGE}"
+                    ),
+                },
+                "--seed" => match args.next().and_then(|value| value.parse().ok()) {
+                    Some(seed) => options.seed = Some(seed),
+                    None => eprintln!("--seed expects an integer\n\n{USAGE}"),
+                },
+                "--record" => match args.next() {
+                    Some(path) => options.record = Some(path.into()),
+                    None => eprintln!("--record expects a file\n\n{USAGE}"),
+                },
+                "--replay" => match args.next() {
+                    Some(path) => options.replay = Some(path.into()),
+                    None => eprintln!("--replay expects a file\n\n{USAGE}"),
+                },
+                unknown => eprintln!("unknown argument `{unknown}`\n\n{USAGE}"),
+            }
+        }
+
+        options
+    }
+
+    fn difficulty(&self, settings: &Settings) -> Difficulty {
+        Difficulty {
+            level: self.difficulty.unwrap_or(settings.difficulty),
+            ..default()
+        }
+    }
+
+    fn power_ups(&self) -> ActivePowerUps {
+        let mut power_ups = ActivePowerUps::default();
+        if let Some(kind) = self.weapon {
+            power_ups.grant(kind);
+        }
+        power_ups
+    }
+
+    fn rng(&self) -> GameRng {
+        GameRng(match self.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        })
+    }
+}
+
+/// Debug-only shortcuts, off in normal play. Enabled with `--god` or Ctrl+Shift+D.
+///
+/// F1 toggles invincibility, F2 adds score, F3 spawns a Miner, F4 spawns a boss and
+/// F5 clears the screen. Recent spawn points are also marked while enabled.
+#[derive(Resource, Default)]
+struct DebugCheats {
+    enabled: bool,
+    invincible: bool,
+}
+
+/// Practice play, entered from the pause menu or with `--sandbox`. Nothing scores and
+/// the run never ends; the director stays quiet and the player brings on what they
+/// want to practise against instead, with the keys listed on the sandbox panel.
+#[derive(Resource, Default)]
+struct Sandbox {
+    on: bool,
+    invincible: bool,
+    /// Kept topped up for as long as it is picked.
+    weapon: Option<PowerUpKind>,
+    preview: Option<PatternPreview>,
+}
+
+/// One of the boss's or mini-bosses' attacks, fired on its own from the top of the
+/// screen so it can be watched and tuned without anything else in the way. Tweaks only
+/// last until another attack is picked.
+#[derive(Clone, Copy)]
+struct PatternPreview {
+    /// Index into `preview_sources`.
+    source: usize,
+    pattern: Pattern,
+    fire_interval: f32,
+}
+
+impl PatternPreview {
+    /// `None` if the boss config has no attacks at all.
+    fn new(config: &BossConfig, source: usize) -> Option<Self> {
+        let sources = preview_sources(config);
+        let len = sources.len();
+        let source = source.checked_rem(len)?;
+        let (_, fire_interval, pattern) = sources[source];
+        Some(PatternPreview {
+            source,
+            pattern,
+            fire_interval,
+        })
+    }
+}
+
+/// Every attack the boss config describes: the boss's phases in order, then each
+/// mini-boss's single attack.
+fn preview_sources(config: &BossConfig) -> Vec<(String, f32, Pattern)> {
+    let phases = config.phases.iter().enumerate().map(|(index, phase)| {
+        (
+            format!("Boss phase {}", index + 1),
+            phase.fire_interval,
+            phase.pattern,
+        )
+    });
+    let mini_bosses = config.mini_bosses.iter().map(|mini_boss| {
+        (
+            format!("Mini-boss, wave {}", mini_boss.wave),
+            mini_boss.fire_interval,
+            mini_boss.pattern,
+        )
+    });
+    phases.chain(mini_bosses).collect()
+}
+
+/// Where the sandbox's pattern preview fires from.
+#[derive(Component)]
+struct PatternEmitter {
+    fire_timer: Timer,
+    volleys: u32,
+}
+
+impl Sandbox {
+    /// The weapon after (or, with `direction` -1, before) the current one, with no
+    /// weapon at all between the last and the first.
+    fn step_weapon(&mut self, direction: isize) {
+        let choices: Vec<Option<PowerUpKind>> = std::iter::once(None)
+            .chain(
+                PowerUpKind::ALL
+                    .into_iter()
+                    .filter(|kind| kind.held())
+                    .map(Some),
+            )
+            .collect();
+        let index = choices
+            .iter()
+            .position(|&weapon| weapon == self.weapon)
+            .unwrap_or(0);
+        let len = choices.len() as isize;
+        self.weapon = choices[(index as isize + direction).rem_euclid(len) as usize];
+    }
+}
+
+/// A run seeded from the date, so everyone meets the same spawns that day. Entered from
+/// the pause menu, and like the sandbox it carries on through restarts until it is
+/// left again. Each attempt starts at difficulty 1.0 with no modifiers.
+#[derive(Resource, Default)]
+struct DailyChallenge {
+    /// The day being played, counted in UTC days since the Unix epoch.
+    day: Option<u32>,
+}
+
+impl DailyChallenge {
+    fn today() -> u32 {
+        let secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs());
+        (secs / 86_400) as u32
+    }
+
+    fn rng(day: u32) -> GameRng {
+        GameRng(StdRng::seed_from_u64(day.into()))
+    }
+}
+
+/// The date of a day counted from the Unix epoch, as "2024-03-09". Howard Hinnant's
+/// `civil_from_days`, with years starting in March so leap days fall at the end.
+fn format_day(day: u32) -> String {
+    let days = i64::from(day) + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day_of_month = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{year:04}-{month:02}-{day_of_month:02}")
+}
+
+/// Recent kills, newest first, each with the time left before it drops off.
+#[derive(Resource, Default)]
+struct KillFeed(VecDeque<(String, Timer)>);
+
+/// Where the spawn director recently placed enemies, drawn as fading dots in debug
+/// mode to check the spread for bias. Each marker keeps its spawn time.
+#[derive(Resource, Default)]
+struct SpawnHeatmap(Vec<(Vec2, f32)>);
+
+/// Window title shown outside of play; the live score is appended during a run.
+#[derive(Resource)]
+struct WindowTitle(String);
+
+#[derive(Resource)]
+struct HighScore(u32);
+
+/// Progress kept across runs: coins earned from score, the upgrades bought with them,
+/// and lifetime kill counts.
+#[derive(Resource, Serialize, Deserialize, Default)]
+#[serde(default)]
+struct Profile {
+    coins: u32,
+    magnet_level: u32,
+    /// Lifetime kills, added to at the end of each run.
+    kills: HashMap<EnemyKind, u32>,
+    boss_kills: u32,
+    /// Best daily challenge score for each day played, keyed by date.
+    daily_best: HashMap<String, u32>,
+    /// Lifetime seconds of unpaused play, banked at each game over and on quitting.
+    play_secs: f64,
+    stats: LifetimeStats,
+}
+
+/// The rest of the lifetime totals on the stats screen, beside the kills and play time
+/// `Profile` already keeps.
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+struct LifetimeStats {
+    /// Runs that reached game over.
+    games: u32,
+    best_score: u32,
+    best_wave: u32,
+    /// Power-ups picked up, by kind, to tell the favourite weapon by.
+    pickups: HashMap<PowerUpKind, u32>,
+}
+
+impl LifetimeStats {
+    fn record_run(&mut self, score: u32, wave: u32) {
+        self.games += 1;
+        self.best_score = self.best_score.max(score);
+        self.best_wave = self.best_wave.max(wave);
+    }
+
+    /// The most picked-up held power-up, with ties going to the first in
+    /// `PowerUpKind::ALL`.
+    fn favorite_weapon(&self) -> Option<PowerUpKind> {
+        PowerUpKind::ALL
+            .into_iter()
+            .filter(|kind| kind.held())
+            .filter_map(|kind| Some((kind, *self.pickups.get(&kind)?)))
+            .rev()
+            .max_by_key(|&(_, count)| count)
+            .map(|(kind, _)| kind)
+    }
+}
+
+impl Profile {
+    fn load() -> Self {
+        let mut profile: Profile = fs::read_to_string(PROFILE_PATH)
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default();
+        profile.magnet_level = profile.magnet_level.min(MAX_MAGNET_LEVEL);
+        profile
+    }
+
+    fn save(&self) {
+        self.save_in(Path::new(""));
+    }
+
+    fn save_in(&self, dir: &Path) {
+        let result = serde_json::to_string_pretty(self)
+            .map_err(|err| err.to_string())
+            .and_then(|json| {
+                fs::write(dir.join(PROFILE_PATH), json).map_err(|err| err.to_string())
+            });
+        if let Err(err) = result {
+            warn!("failed to save profile: {err}");
+        }
+    }
+
+    fn magnet_radius(&self) -> f32 {
+        BASE_MAGNET_RADIUS + MAGNET_RADIUS_STEP * self.magnet_level as f32
+    }
+
+    /// `None` once the magnet is fully upgraded.
+    fn magnet_upgrade_cost(&self) -> Option<u32> {
+        (self.magnet_level < MAX_MAGNET_LEVEL)
+            .then(|| MAGNET_UPGRADE_BASE_COST * (self.magnet_level + 1))
+    }
+
+    /// Returns whether the upgrade was bought.
+    fn buy_magnet_upgrade(&mut self) -> bool {
+        match self.magnet_upgrade_cost() {
+            Some(cost) if self.coins >= cost => {
+                self.coins -= cost;
+                self.magnet_level += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Watches the frame rate for `EffectsQuality::Auto`. Only cosmetic effects are
+/// thinned, never bullets or enemies, so a run plays the same on any machine.
+#[derive(Resource, Default)]
+struct PerformanceGovernor {
+    struggling: bool,
+    /// How long the frame rate has been past the threshold for changing `struggling`.
+    crossing: f32,
+}
+
+impl PerformanceGovernor {
+    fn low_effects(&self, settings: &Settings) -> bool {
+        match settings.effects_quality {
+            EffectsQuality::Auto => self.struggling,
+            EffectsQuality::High => false,
+            EffectsQuality::Low => true,
+        }
+    }
+
+    /// `count` cosmetic particles cut down to the current budget.
+    fn budget(&self, settings: &Settings, count: usize) -> usize {
+        if self.low_effects(settings) {
+            (count as f32 * LOW_EFFECTS_SCALE).ceil() as usize
+        } else {
+            count
+        }
+    }
+}
+
+/// Time spent in unpaused play since the game was started, in real seconds.
+#[derive(Resource, Default)]
+struct PlayTime {
+    session: f64,
+    /// Played since the last time it was added to `Profile::play_secs`.
+    unbanked: f64,
+}
+
+impl PlayTime {
+    fn bank(&mut self, profile: &mut Profile) {
+        profile.play_secs += self.unbanked;
+        self.unbanked = 0.0;
+    }
+
+    /// "Played 0:12:05, 3:40:51 in total", counting what isn't banked yet.
+    fn summary(&self, profile: &Profile) -> String {
+        format!(
+            "Played {}, {} in total",
+            format_play_time(self.session),
+            format_play_time(profile.play_secs + self.unbanked)
+        )
+    }
+}
+
+/// Whole seconds as "H:MM:SS".
+fn format_play_time(secs: f64) -> String {
+    let secs = secs as u64;
+    format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+/// How close a pickup has to drift before it is pulled in to the player.
+#[derive(Resource)]
+struct MagnetRadius(f32);
+
+/// Faint disc showing `MagnetRadius` while there is a pickup on screen.
+#[derive(Component)]
+struct MagnetRing;
+
+/// The disc around the ship while the reflect power-up is active.
+#[derive(Component)]
+struct ReflectAura;
+
+impl HighScore {
+    fn load() -> Self {
+        let best = fs::read_to_string(HIGH_SCORE_PATH)
+            .ok()
+            .and_then(|contents| contents.trim().parse().ok())
+            .unwrap_or(0);
+        HighScore(best)
+    }
+
+    fn save(&self) {
+        self.save_in(Path::new(""));
+    }
+
+    fn save_in(&self, dir: &Path) {
+        if let Err(err) = fs::write(dir.join(HIGH_SCORE_PATH), self.0.to_string()) {
+            warn!("failed to save high score: {err}");
+        }
+    }
+}
+
+#[derive(Event)]
+struct PlayerHit;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum MenuAction {
+    Resume,
+    Restart,
+    DailyChallenge,
+    LeaveDailyChallenge,
+    Sandbox,
+    LeaveSandbox,
+    Settings,
+    Stats,
+    Modifiers,
+    /// Picks or drops `ModifierConfig`'s modifier at this index.
+    ToggleModifier(usize),
+    Quit,
+    ConfirmQuit,
+    CancelQuit,
+    MusicVolume,
+    SfxVolume,
+    Brightness,
+    Difficulty,
+    BaseDifficulty,
+    StartingLives,
+    Rewinds,
+    AdaptiveDifficulty,
+    ShipSpeed,
+    FireRate,
+    Fullscreen,
+    Resolution,
+    Letterbox,
+    UiScale,
+    HudLayout,
+    HudScale,
+    Vsync,
+    Shadows,
+    EffectsQuality,
+    StaticBackground,
+    Colorblind,
+    ReduceMotion,
+    ScreenShake,
+    AutoPause,
+    ConfirmQuitSetting,
+    MissPenalty,
+    WrapMode,
+    MoveSpread,
+    ElasticEdges,
+    KillFeed,
+    AutoFire,
+    AimAssist,
+    BulletCancel,
+    DodgingEnemies,
+    KeepPowerUps,
+    ClearBulletsOnHit,
+    ScoreChips,
+    Continues,
+    EnemyBullets,
+    Mines,
+    Analytics,
+    Controls,
+    ResetSettings,
+    Rebind(GameAction),
+    ResetBindings,
+    UpgradeMagnet,
+    ContinueRun,
+    NewRun,
+    TakeContinue,
+    GiveUp,
+    Back,
+}
+
+impl MenuAction {
+    fn label(self, settings: &Settings, profile: &Profile, modifiers: &ModifierConfig) -> String {
+        let percent = |value: f32| format!("{:.0}%", value * 100.0);
+        let on_off = |value: bool| if value { "On" } else { "Off" };
+        match self {
+            MenuAction::Resume => "Resume".into(),
+            MenuAction::Restart => "Restart".into(),
+            MenuAction::DailyChallenge => "Daily Challenge".into(),
+            MenuAction::LeaveDailyChallenge => "Leave Daily Challenge".into(),
+            MenuAction::Sandbox => "Sandbox".into(),
+            MenuAction::LeaveSandbox => "Leave Sandbox".into(),
+            MenuAction::Settings => "Settings".into(),
+            MenuAction::Quit => "Quit".into(),
+            MenuAction::ConfirmQuit => "Yes, Quit".into(),
+            MenuAction::CancelQuit => "No".into(),
+            MenuAction::MusicVolume => format!("Music Volume: {}", percent(settings.music_volume)),
+            MenuAction::SfxVolume => format!("Sound Volume: {}", percent(settings.sfx_volume)),
+            MenuAction::Brightness => format!("Brightness: {}", percent(settings.brightness)),
+            MenuAction::Difficulty => format!("Difficulty: {:.1}", settings.difficulty),
+            MenuAction::BaseDifficulty => {
+                format!("Base Difficulty: {}", percent(settings.base_difficulty))
+            }
+            MenuAction::StartingLives => format!("Starting Lives: {}", settings.starting_lives),
+            MenuAction::Rewinds => match settings.rewinds {
+                0 => "Rewinds: Off".to_string(),
+                rewinds => format!("Rewinds: {rewinds}"),
+            },
+            MenuAction::AdaptiveDifficulty => {
+                format!(
+                    "Adaptive Difficulty: {}",
+                    on_off(settings.adaptive_difficulty)
+                )
+            }
+            MenuAction::ShipSpeed => format!("Ship Speed: {}", percent(settings.ship_speed)),
+            MenuAction::FireRate => format!("Fire Rate: {}", percent(settings.fire_rate)),
+            MenuAction::Fullscreen => format!("Fullscreen: {}", on_off(settings.fullscreen)),
+            MenuAction::Resolution => {
+                let (width, height) = settings.resolution;
+                format!("Resolution: {width}x{height}")
+            }
+            MenuAction::Letterbox => format!("Letterbox: {}", settings.letterbox.label()),
+            MenuAction::UiScale => format!("UI Scale: {}", percent(settings.ui_scale)),
+            MenuAction::HudLayout => format!("HUD Layout: {}", settings.hud_layout.label()),
+            MenuAction::HudScale => format!("HUD Scale: {}", percent(settings.hud_scale)),
+            MenuAction::Vsync => format!("VSync: {}", on_off(settings.vsync)),
+            MenuAction::Shadows => format!("Shadows: {}", on_off(settings.shadows)),
+            MenuAction::EffectsQuality => {
+                format!("Effects: {}", settings.effects_quality.label())
+            }
+            MenuAction::StaticBackground => {
+                format!("Static Background: {}", on_off(settings.static_background))
+            }
+            MenuAction::Colorblind => {
+                format!("Colorblind Palette: {}", on_off(settings.colorblind))
+            }
+            MenuAction::ReduceMotion => {
+                format!("Reduce Motion: {}", on_off(settings.reduce_motion))
+            }
+            MenuAction::ScreenShake => format!("Screen Shake: {}", percent(settings.screen_shake)),
+            MenuAction::AutoPause => {
+                format!("Pause on Focus Loss: {}", on_off(settings.auto_pause))
+            }
+            MenuAction::ConfirmQuitSetting => {
+                format!("Confirm Before Quitting: {}", on_off(settings.confirm_quit))
+            }
+            MenuAction::MissPenalty => {
+                format!("Miss Breaks Combo: {}", on_off(settings.miss_penalty))
+            }
+            MenuAction::WrapMode => format!("Screen Wrap: {}", settings.wrap_mode.label()),
+            MenuAction::MoveSpread if settings.move_spread_degrees == 0.0 => {
+                "Spread While Moving: Off".into()
+            }
+            MenuAction::MoveSpread => {
+                format!("Spread While Moving: {:.0}°", settings.move_spread_degrees)
+            }
+            MenuAction::ElasticEdges => {
+                format!("Elastic Edges: {}", on_off(settings.elastic_edges))
+            }
+            MenuAction::KillFeed => format!("Kill Feed: {}", on_off(settings.kill_feed)),
+            MenuAction::BulletCancel => {
+                format!("Shoot Down Bullets: {}", on_off(settings.bullet_cancel))
+            }
+            MenuAction::DodgingEnemies => {
+                format!("Enemies Dodge: {}", on_off(settings.dodging_enemies))
+            }
+            MenuAction::AutoFire => format!("Auto-Fire: {}", on_off(settings.auto_fire)),
+            MenuAction::AimAssist => format!("Aim Assist: {}", settings.aim_assist.label()),
+            MenuAction::KeepPowerUps => {
+                format!("Keep Power-Ups on Hit: {}", on_off(settings.keep_power_ups))
+            }
+            MenuAction::ClearBulletsOnHit => {
+                format!(
+                    "Clear Bullets on Hit: {}",
+                    on_off(settings.clear_bullets_on_hit)
+                )
+            }
+            MenuAction::ScoreChips => format!("Score Chips: {}", on_off(settings.score_chips)),
+            MenuAction::Continues => format!("Continues: {}", on_off(settings.continues)),
+            MenuAction::EnemyBullets => {
+                format!("Enemy Bullets: {}", on_off(settings.enemy_bullets))
+            }
+            MenuAction::Mines => format!("Mines: {}", on_off(settings.mines)),
+            MenuAction::Analytics => format!("Balancing Log: {}", on_off(settings.analytics)),
+            MenuAction::Controls => "Controls".into(),
+            MenuAction::Stats => "Stats".into(),
+            MenuAction::Modifiers => "Modifiers".into(),
+            MenuAction::ToggleModifier(index) => match modifiers.0.get(index) {
+                Some(modifier) => format!(
+                    "{} (x{:.1} score): {}",
+                    modifier.name,
+                    modifier.score,
+                    on_off(settings.modifiers.contains(&modifier.name))
+                ),
+                None => String::new(),
+            },
+            MenuAction::ResetSettings => "Reset to Defaults".into(),
+            MenuAction::Rebind(action) => {
+                format!(
+                    "{}: {:?}",
+                    action.label(),
+                    settings.key_bindings.key(action)
+                )
+            }
+            MenuAction::ResetBindings => "Reset Controls".into(),
+            MenuAction::ContinueRun => "Continue Saved Run".into(),
+            MenuAction::NewRun => "New Run".into(),
+            MenuAction::TakeContinue => "Continue".into(),
+            MenuAction::GiveUp => "Give Up".into(),
+            MenuAction::UpgradeMagnet => match profile.magnet_upgrade_cost() {
+                Some(cost) => format!(
+                    "Magnet Lv {}/{MAX_MAGNET_LEVEL}: upgrade for {cost} ({} coins)",
+                    profile.magnet_level, profile.coins
+                ),
+                None => format!("Magnet Lv {MAX_MAGNET_LEVEL}: maxed"),
+            },
+            MenuAction::Back => "Back".into(),
+        }
+    }
+}
+
+/// The menu currently on screen. Each screen installs its own when it opens.
+#[derive(Resource, Default)]
+struct Menu {
+    items: Vec<MenuAction>,
+    selected: usize,
+    /// Triggered by the back button, if the screen has somewhere to go back to.
+    back: Option<MenuAction>,
+}
+
+impl Menu {
+    fn new(items: Vec<MenuAction>, back: Option<MenuAction>) -> Self {
+        Menu {
+            items,
+            selected: 0,
+            back,
+        }
+    }
+}
+
+#[derive(Component)]
+struct MenuItem {
+    index: usize,
+}
+
+/// Spells out the focused menu item and where it sits in the menu, under the menu.
+#[derive(Component)]
+struct MenuFocusLabel;
+
+#[derive(Event)]
+struct MenuSelected(MenuAction);
+
+/// Left/right on a menu item; `direction` is -1.0 or 1.0.
+#[derive(Event)]
+struct MenuAdjusted {
+    action: MenuAction,
+    direction: f32,
+}
+
+/// Sent once for each enemy that leaves the bottom of the screen alive.
+#[derive(Event)]
+struct EnemyEscaped;
+
+/// Sent when Restart is picked from the pause menu, which unlike game over has no
+/// `OnExit` reset of its own.
+#[derive(Event)]
+struct RestartRun;
+
+/// Sent for every enemy or boss killed, with the score it was worth.
+#[derive(Event)]
+struct EnemyDestroyed {
+    name: &'static str,
+    /// `None` for the boss and mini-bosses, which have their own send-off.
+    kind: Option<EnemyKind>,
+    position: Vec2,
+    points: u32,
+    multiplier: u32,
+}
+
+/// Sent each time a piercing bullet adds a kill to its chain, from the second on.
+#[derive(Event)]
+struct ChainKill {
+    length: u32,
+    position: Vec3,
+}
+
+/// Sent once for each player bullet that leaves the screen without hitting anything.
+#[derive(Event)]
+struct Missed;
+
+/// Half the size of the area play happens in, in world units: the window, or the part
+/// of it inside the letterbox. Bounds are read from here rather than the window.
+#[derive(Resource, Clone, Copy, PartialEq)]
+struct PlayArea {
+    half_size: Vec2,
+}
+
+impl Default for PlayArea {
+    fn default() -> Self {
+        PlayArea {
+            half_size: Vec2::new(400.0, 300.0),
+        }
+    }
+}
+
+/// Space left past the edge of the play area before anything leaving it is despawned.
+const OFF_SCREEN_MARGIN: f32 = 10.0;
+
+impl PlayArea {
+    /// How far from the centre, along each axis, something `size` across has to be to
+    /// be wholly out of view, with `OFF_SCREEN_MARGIN` to spare.
+    fn off_screen(&self, size: Vec2) -> Vec2 {
+        self.half_size + size / 2.0 + OFF_SCREEN_MARGIN
+    }
+
+    /// Where enemies come in: just out of view above the top, clear of the biggest
+    /// sprite any kind can be given at `balance`'s scale, however it is turned.
+    fn enemy_spawn_y(&self, balance: &Balance) -> f32 {
+        let largest = EnemyKind::ALL
+            .iter()
+            .map(|kind| kind.hitbox(false, balance).length())
+            .fold(LEADER_SIZE.length(), f32::max);
+        self.off_screen(Vec2::splat(largest * (1.0 + ENEMY_SIZE_JITTER)))
+            .y
+    }
+}
+
+/// Speed multiplier for enemy-side systems; the player and UI always run at 1.0.
+#[derive(Resource)]
+struct TimeScale(f32);
+
+impl Default for TimeScale {
+    fn default() -> Self {
+        TimeScale(1.0)
+    }
+}
+
+#[derive(Resource)]
+struct BulletTime {
+    gauge: f32,
+    active: bool,
+}
+
+impl Default for BulletTime {
+    fn default() -> Self {
+        BulletTime {
+            gauge: 1.0,
+            active: false,
+        }
+    }
+}
+
+/// Recharge for the freeze pulse; ready whenever the timer has finished.
+#[derive(Resource)]
+struct FreezePulse {
+    cooldown: Timer,
+}
+
+impl Default for FreezePulse {
+    fn default() -> Self {
+        FreezePulse {
+            cooldown: ready_timer(FREEZE_COOLDOWN_SECS),
+        }
+    }
+}
+
+/// Grows over the course of a run; 1.0 is the starting difficulty. The two curves
+/// ramp separately so spawn pressure and enemy speed can be tuned on their own.
+#[derive(Resource)]
+struct Difficulty {
+    /// Scales the spawn director's refill rate and on-screen target.
+    level: f32,
+    /// Scales how fast enemies move.
+    speed_multiplier: f32,
+}
+
+/// A fixed scale on top of `Difficulty` for the whole run, from the settings, for
+/// players who find the game too easy or too hard from the start. 1.0 plays as
+/// intended; it scales spawn pressure and enemy speed alike, and can go below what
+/// the difficulty setting will. Daily challenges always play at 1.0, so every attempt
+/// at a day meets the same pressure.
+#[derive(Resource)]
+struct BaseDifficulty(f32);
+
+impl BaseDifficulty {
+    fn for_run(settings: &Settings, daily: &DailyChallenge) -> Self {
+        match daily.day {
+            Some(_) => BaseDifficulty(1.0),
+            None => BaseDifficulty(settings.base_difficulty),
+        }
+    }
+}
+
+/// How the player did over the current wave.
+#[derive(Resource, Default)]
+struct RunStats {
+    shots: u32,
+    misses: u32,
+    hits_taken: u32,
+}
+
+impl RunStats {
+    /// Share of shots that found something; a wave without shots counts as average.
+    fn accuracy(&self) -> f32 {
+        if self.shots == 0 {
+            return 0.5;
+        }
+        1.0 - self.misses.min(self.shots) as f32 / self.shots as f32
+    }
+}
+
+/// Kills over the whole run, for the game-over breakdown. `RunStats` starts over each
+/// wave; this only starts over with the run, or counts from where a save was resumed.
+#[derive(Resource, Default)]
+struct RunKills {
+    by_kind: HashMap<EnemyKind, u32>,
+    mini_bosses: u32,
+    bosses: u32,
+}
+
+impl RunKills {
+    fn record(&mut self, event: &EnemyDestroyed) {
+        match event.kind {
+            Some(kind) => *self.by_kind.entry(kind).or_default() += 1,
+            None if event.name == MINI_BOSS_NAME => self.mini_bosses += 1,
+            None => self.bosses += 1,
+        }
+    }
+
+    fn total(&self) -> u32 {
+        self.by_kind.values().sum::<u32>() + self.mini_bosses + self.bosses
+    }
+
+    /// "Basic 12, Diver 3, Boss 1", leaving out kinds never killed.
+    fn breakdown(&self) -> String {
+        let mut parts: Vec<String> = EnemyKind::ALL
+            .into_iter()
+            .filter_map(|kind| {
+                let count = self.by_kind.get(&kind).copied().unwrap_or(0);
+                (count > 0).then(|| format!("{} {count}", kind.name()))
+            })
+            .collect();
+        if self.mini_bosses > 0 {
+            parts.push(format!("{MINI_BOSS_NAME} {}", self.mini_bosses));
+        }
+        if self.bosses > 0 {
+            parts.push(format!("Boss {}", self.bosses));
+        }
+        parts.join(", ")
+    }
+}
+
+/// Adaptive difficulty: the offset currently added to `Difficulty`, so it can be
+/// taken back out when the setting is turned off.
+#[derive(Resource, Default)]
+struct Dda {
+    offset: f32,
+}
+
+impl Default for Difficulty {
+    fn default() -> Self {
+        Difficulty {
+            level: 1.0,
+            speed_multiplier: 1.0,
+        }
+    }
+}
+
+/// Most enemy bullets allowed on screen at once. Emitters at the cap thin or skip
+/// their volleys, which keeps dense boss patterns both fair and cheap.
+#[derive(Resource)]
+struct EnemyBulletCap {
+    base: usize,
+    /// Extra bullets allowed per difficulty level above 1.0.
+    per_level: usize,
+}
+
+impl Default for EnemyBulletCap {
+    fn default() -> Self {
+        EnemyBulletCap {
+            base: BASE_ENEMY_BULLET_CAP,
+            per_level: ENEMY_BULLET_CAP_PER_LEVEL,
+        }
+    }
+}
+
+impl EnemyBulletCap {
+    fn at(&self, difficulty: &Difficulty) -> usize {
+        self.base + (self.per_level as f32 * (difficulty.level - 1.0).max(0.0)) as usize
+    }
+}
+
+/// What enemy shooting systems need to stay under `EnemyBulletCap`.
+#[derive(SystemParam)]
+struct EnemyFire<'w, 's> {
+    cap: Res<'w, EnemyBulletCap>,
+    difficulty: Res<'w, Difficulty>,
+    settings: Res<'w, Settings>,
+    bullet_query: Query<'w, 's, (), With<EnemyBullet>>,
+}
+
+impl EnemyFire<'_, '_> {
+    /// Lead time for telegraphed attacks at the current difficulty.
+    fn telegraph_secs(&self) -> f32 {
+        (TELEGRAPH_SECS / self.difficulty.level).max(MIN_TELEGRAPH_SECS)
+    }
+
+    /// How many more enemy bullets fit under the cap this frame.
+    fn room(&self) -> usize {
+        if !self.settings.enemy_bullets {
+            return 0;
+        }
+        self.cap
+            .at(&self.difficulty)
+            .saturating_sub(self.bullet_query.iter().count())
+    }
+}
+
+/// Paces enemy spawns by spending a budget that refills faster as difficulty rises.
+#[derive(Resource, Default)]
+struct SpawnDirector {
+    budget: f32,
+    balance: Balance,
+    next_spawn: SpawnChoice,
+    /// Kinds this run has brought in so far; see `EnemyKind::unlock_wave`.
+    unlocked: HashSet<EnemyKind>,
+    /// Which side recent spawns have favoured, from -1.0 for all left to 1.0 for all
+    /// right. `balanced_spawn_x` leans the next spawn the other way.
+    lean: f32,
+}
+
+impl SpawnDirector {
+    /// Starts a fresh run, keeping whatever balance was last loaded.
+    fn reset(&mut self) {
+        *self = SpawnDirector {
+            balance: self.balance,
+            ..default()
+        };
+    }
+}
+
+/// Holds off spawning for the first moments of a run.
+#[derive(Resource)]
+struct GracePeriod(Timer);
+
+impl GracePeriod {
+    fn new(balance: &Balance) -> Self {
+        GracePeriod(Timer::from_seconds(
+            balance.grace_period_secs,
+            TimerMode::Once,
+        ))
+    }
+}
+
+/// Keeps the balance asset loaded so edits to it are picked up.
+#[derive(Resource)]
+struct BalanceHandle(#[allow(dead_code)] Handle<Balance>);
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+enum SpawnChoice {
+    Single(EnemyKind),
+    /// A leader plus one basic enemy per `FORMATION_OFFSETS` slot.
+    Formation,
+    /// `SWARM_SIZE` swarmers in a loose clump.
+    Swarm,
+}
+
+impl SpawnChoice {
+    fn cost(self) -> f32 {
+        match self {
+            SpawnChoice::Single(kind) => kind.cost(),
+            SpawnChoice::Formation => self.enemy_count() as f32 * EnemyKind::Basic.cost(),
+            SpawnChoice::Swarm => self.enemy_count() as f32 * EnemyKind::Swarmer.cost(),
+        }
+    }
+
+    fn enemy_count(self) -> u32 {
+        match self {
+            SpawnChoice::Single(_) => 1,
+            SpawnChoice::Formation => FORMATION_OFFSETS.len() as u32 + 1,
+            SpawnChoice::Swarm => SWARM_SIZE,
+        }
+    }
+}
+
+/// Splits the director's spawns into waves, with a short break once a wave is gone.
+#[derive(Resource)]
+struct WaveManager {
+    wave: u32,
+    /// Enemies in the current wave.
+    total: u32,
+    spawned: u32,
+    /// Runs between waves, once every enemy of the last one is dead or has escaped.
+    /// Nothing spawns and the music dips until it finishes.
+    break_timer: Option<Timer>,
+    /// Whether this wave's mini-boss, if it has one, has come out yet.
+    mini_boss_spawned: bool,
+}
+
+impl WaveManager {
+    fn new(wave: u32) -> Self {
+        WaveManager {
+            wave,
+            total: FIRST_WAVE_SIZE + WAVE_SIZE_STEP * (wave - 1),
+            spawned: 0,
+            break_timer: None,
+            mini_boss_spawned: false,
+        }
+    }
+
+    fn remaining(&self) -> u32 {
+        self.total.saturating_sub(self.spawned)
+    }
+
+    fn uses_portals(&self, balance: &Balance) -> bool {
+        balance.portal_wave_interval > 0 && self.wave.is_multiple_of(balance.portal_wave_interval)
+    }
+
+    /// Fraction of the wave already cleared, given how many enemies are still alive.
+    fn progress(&self, alive: u32) -> f32 {
+        (self.spawned.saturating_sub(alive) as f32 / self.total as f32).min(1.0)
+    }
+}
+
+impl Default for WaveManager {
+    fn default() -> Self {
+        WaveManager::new(1)
+    }
+}
+
+impl Default for SpawnChoice {
+    fn default() -> Self {
+        SpawnChoice::Single(EnemyKind::Basic)
+    }
+}
+
+#[derive(Resource, Default, Clone)]
+struct ActivePowerUps {
+    bounce: Option<Timer>,
+    rapid_fire: Option<Timer>,
+    pierce: Option<Timer>,
+    all_range: Option<Timer>,
+    flame: Option<Timer>,
+    reflect: Option<Timer>,
+    /// From 1 to `RAPID_FIRE_MAX_TIER` while rapid fire is active, otherwise 0.
+    rapid_fire_tier: u32,
+}
+
+impl ActivePowerUps {
+    /// Starts `kind` afresh at the bottom tier, whether or not it was already active.
+    fn grant(&mut self, kind: PowerUpKind) {
+        let Some(slot) = self.slot(kind) else {
+            return;
+        };
+        *slot = Some(Timer::from_seconds(POWER_UP_DURATION_SECS, TimerMode::Once));
+        if kind == PowerUpKind::RapidFire {
+            self.rapid_fire_tier = 1;
+        }
+    }
+
+    /// A pickup: grants `kind` if it isn't active yet, or stacks it by its rule.
+    /// Deployed kinds are left to `player_power_up_collision`.
+    fn apply_power_up(&mut self, kind: PowerUpKind) {
+        let Some(timer) = self.slot(kind).and_then(Option::as_mut) else {
+            self.grant(kind);
+            return;
+        };
+        match kind.stacking() {
+            Stacking::Refresh => timer.reset(),
+            Stacking::Extend => {
+                let secs =
+                    (timer.remaining_secs() + POWER_UP_DURATION_SECS).min(MAX_POWER_UP_STACK_SECS);
+                *timer = Timer::from_seconds(secs, TimerMode::Once);
+            }
+            Stacking::Tier => {
+                timer.reset();
+                self.rapid_fire_tier = (self.rapid_fire_tier + 1).min(RAPID_FIRE_MAX_TIER);
+            }
+        }
+    }
+
+    /// `None` for kinds that aren't `PowerUpKind::held`.
+    fn slot(&mut self, kind: PowerUpKind) -> Option<&mut Option<Timer>> {
+        Some(match kind {
+            PowerUpKind::Bounce => &mut self.bounce,
+            PowerUpKind::RapidFire => &mut self.rapid_fire,
+            PowerUpKind::Pierce => &mut self.pierce,
+            PowerUpKind::AllRange => &mut self.all_range,
+            PowerUpKind::Flame => &mut self.flame,
+            PowerUpKind::Reflect => &mut self.reflect,
+            PowerUpKind::Barrier => return None,
+        })
+    }
+
+    /// Multiplier on the fire interval from rapid fire and its tier.
+    fn rapid_fire_factor(&self) -> f32 {
+        match self.rapid_fire_tier {
+            0 => 1.0,
+            tier => RAPID_FIRE_INTERVAL_FACTOR * RAPID_FIRE_TIER_FACTOR.powi(tier as i32 - 1),
+        }
+    }
+
+    fn is_active(&self, kind: PowerUpKind) -> bool {
+        match kind {
+            PowerUpKind::Bounce => self.bounce.is_some(),
+            PowerUpKind::RapidFire => self.rapid_fire.is_some(),
+            PowerUpKind::Pierce => self.pierce.is_some(),
+            PowerUpKind::AllRange => self.all_range.is_some(),
+            PowerUpKind::Flame => self.flame.is_some(),
+            PowerUpKind::Reflect => self.reflect.is_some(),
+            PowerUpKind::Barrier => false,
+        }
+    }
+
+    /// All-range takes over from bounce while both are active.
+    fn weapon_mode(&self) -> WeaponMode {
+        if self.all_range.is_some() {
+            WeaponMode::AllRange
+        } else if self.bounce.is_some() {
+            WeaponMode::Bounce
+        } else {
+            WeaponMode::Single
+        }
+    }
+}
+
+/// The firing pattern the active power-ups add up to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum WeaponMode {
+    Single,
+    Bounce,
+    /// A burst in `ALL_RANGE_DIRECTIONS` evenly spaced directions.
+    AllRange,
+}
+
+impl WeaponMode {
+    /// Pierce changes the look of whatever pattern is firing.
+    fn bullet_kind(self, piercing: bool) -> BulletKind {
+        match self {
+            _ if piercing => BulletKind::Piercing,
+            WeaponMode::Single => BulletKind::Standard,
+            WeaponMode::Bounce => BulletKind::Bouncing,
+            WeaponMode::AllRange => BulletKind::Burst,
+        }
+    }
+
+    /// One shot's bullet velocities, authored pointing straight up, and the damage each
+    /// bullet deals.
+    fn volley(self) -> (Vec<Vec2>, u32) {
+        match self {
+            WeaponMode::Single => (vec![Vec2::new(0.0, 500.0)], 2),
+            // Bouncing shots leave at an angle so they actually reach the walls, and
+            // hit softer to make up for coming in pairs.
+            WeaponMode::Bounce => (vec![Vec2::new(-150.0, 480.0), Vec2::new(150.0, 480.0)], 1),
+            WeaponMode::AllRange => {
+                let step = TAU / ALL_RANGE_DIRECTIONS as f32;
+                let velocities = (0..ALL_RANGE_DIRECTIONS)
+                    .map(|i| Vec2::from_angle(step * i as f32).rotate(Vec2::new(0.0, 500.0)))
+                    .collect();
+                (velocities, 1)
+            }
+        }
+    }
+}
+
+/// Everything that moves a run on, from score and timers to enemies and the ship, only
+/// runs in `Playing`. Virtual time keeps going outside it, so nothing gameplay-side may
+/// go by elapsed time either; a pause or menu of any length leaves the run exactly as
+/// it was, and resuming has nothing to catch up on. The other states only change
+/// settings and the profile, or the run as a whole (a continue, a restart).
+#[derive(States, Debug, Clone, Copy, Eq, PartialEq, Hash, Default)]
+enum GameState {
+    #[default]
+    Playing,
+    Paused,
+    Settings,
+    Controls,
+    /// Lifetime totals, from the pause menu.
+    Stats,
+    /// Picking run modifiers, from the pause menu.
+    Modifiers,
+    /// The run has ended and a continue is on offer, counting down to game over.
+    Continue,
+    GameOver,
+}
+
+/// A slideshow of the title, high score and controls laid over an idle menu, running
+/// alongside whatever `GameState` it covers. Any input ends it.
+#[derive(States, Default, Clone, Copy, Eq, PartialEq, Hash, Debug)]
+enum AttractMode {
+    #[default]
+    Off,
+    On,
+}
+
+impl GameState {
+    /// Pausing and unpausing swap straight over; every other change of screen fades
+    /// through black.
+    fn fades_to(self, next: GameState) -> bool {
+        !matches!(
+            (self, next),
+            (GameState::Playing, GameState::Paused) | (GameState::Paused, GameState::Playing)
+        ) && self != next
+    }
+}
+
+/// A fade through black around a change of state; see `fade_transitions`.
+#[derive(Resource, Default)]
+struct ScreenFade {
+    /// The state being faded out to, held back from `NextState` until the screen is black.
+    target: Option<GameState>,
+    /// From 0.0, clear, to 1.0, black.
+    alpha: f32,
+}
+
+trait DespawnExt {
+    /// Despawns `entity` only if it still exists. Several systems can claim the same
+    /// bullet or enemy in one frame, and a plain despawn would then log an error.
+    fn despawn_if_exists(&mut self, entity: Entity);
+}
+
+impl DespawnExt for Commands<'_, '_> {
+    fn despawn_if_exists(&mut self, entity: Entity) {
+        self.add(move |world: &mut World| {
+            if world.get_entity(entity).is_some() {
+                despawn_with_children_recursive(world, entity);
+            }
+        });
+    }
+}
+
+/// The systems that move a run on, every one of them gated on `GameState::Playing`.
+struct GameplayPlugin;
+
+impl Plugin for GameplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                player_movement,
+                confine_player_movement,
+                player_shooting,
+                bullet_movement,
+                ramp_difficulty,
+                // The director holds off for the duration of a boss fight.
+                spawn_enemies
+                    .run_if(no_boss)
+                    .run_if(grace_period_over)
+                    .run_if(not(in_sandbox)),
+                update_waves,
+                enemy_movement,
+                enemy_escape.after(enemy_movement),
+                bullet_enemy_collision,
+                power_up_movement,
+                player_power_up_collision,
+                tick_power_ups,
+                rapid_fire_feedback,
+                enemy_mine_laying,
+                mine_proximity,
+                blast_fade,
+                player_hit,
+                invincibility_flash,
+                bullet_time,
+            )
+                .run_if(in_state(GameState::Playing)),
+        )
+        .add_systems(
+            Update,
+            (
+                spawn_boss.run_if(not(in_sandbox)),
+                boss_intro,
+                enemy_facing.after(enemy_movement),
+                boss_movement,
+                boss_shooting,
+                // After enemy collisions, whose despawns are applied first, so a shot
+                // spent on an enemy can't go on to hit a boss behind it too.
+                bullet_boss_collision.after(bullet_enemy_collision),
+                boss_phase_transition,
+                boss_flash,
+                // Ordered after collisions, whose despawns are applied first, so a
+                // gunner killed mid-wind-up never gets its shot off.
+                gunner_shooting.after(bullet_enemy_collision),
+                update_combo,
+                combo_milestone_sound.after(update_combo),
+                chain_kill_feedback.after(bullet_enemy_collision),
+                track_escapes.after(enemy_escape),
+                vary_enemy_sprites,
+                move_weak_points,
+                portal_update,
+                tick_grace_period,
+                magnet_pickups.after(power_up_movement),
+                enemy_bullet_movement,
+                enemy_bullet_player_collision,
+            )
+                .run_if(in_state(GameState::Playing)),
+        )
+        .add_systems(
+            Update,
+            (
+                graze_enemy_bullets.after(enemy_bullet_movement),
+                survival_bonus,
+                formation_entrance,
+                track_run_stats,
+                adapt_difficulty.after(track_run_stats),
+                explosion_knockback,
+                death_effects,
+                bullet_vs_enemy_bullet,
+                enemy_spawn_sound.after(spawn_enemies),
+                cloak_cycle,
+                spawn_timeline.run_if(not(in_sandbox)),
+                clear_disabled_hazards,
+                spin_enemies,
+                boss_defeat.after(bullet_time),
+                track_analytics,
+                swarm_steering.before(enemy_movement),
+                homing_bullets.before(enemy_bullet_movement),
+                telegraph_zones.after(boss_shooting),
+                burn_tick.after(bullet_enemy_collision),
+                thief_contact
+                    .after(enemy_movement)
+                    .after(bullet_enemy_collision)
+                    .after(burn_tick),
+            )
+                .run_if(in_state(GameState::Playing)),
+        )
+        .add_systems(
+            Update,
+            (
+                freeze_pulse.before(enemy_movement),
+                dodge_bullets.before(enemy_movement),
+                carrier_deploy.after(enemy_movement),
+                record_rewind.before(player_hit),
+                unlock_enemy_kinds
+                    .before(spawn_enemies)
+                    .after(update_waves)
+                    .run_if(not(in_sandbox)),
+                tractor_hover.after(enemy_movement),
+                regenerate_enemies
+                    .after(bullet_enemy_collision)
+                    .after(burn_tick),
+                tractor_beams.after(enemy_movement).after(player_movement),
+                barrier_block
+                    .after(enemy_bullet_movement)
+                    .before(enemy_bullet_player_collision),
+                boss_enrage.after(boss_flash).before(boss_shooting),
+                break_formations
+                    .after(bullet_enemy_collision)
+                    .before(enemy_movement),
+                thaw_frozen.after(freeze_pulse),
+                formation_sweep
+                    .after(break_formations)
+                    .after(formation_entrance)
+                    .before(enemy_movement),
+                tick_hit_stop.before(bullet_time),
+                track_kills,
+                danger_rising_cue.after(ramp_difficulty),
+                score_chips.after(magnet_pickups),
+                dash_afterimages.after(player_movement),
+                track_play_time,
+                spawn_fades,
+            )
+                .run_if(in_state(GameState::Playing)),
+        )
+        .add_systems(
+            Update,
+            (
+                update_score_text.after(survival_bonus),
+                update_lives_text,
+                update_escapes_text,
+                update_bullet_indicator,
+                update_bullet_time_ui,
+                update_freeze_gauge.after(freeze_pulse),
+                update_wave_progress,
+                update_combo_text.after(update_combo),
+                update_thruster.after(player_movement),
+                update_kill_feed,
+                update_graze_meter.after(graze_enemy_bullets),
+                update_magnet_ring.after(magnet_pickups),
+                update_reflect_aura.after(tick_power_ups),
+                update_window_title.run_if(resource_changed::<Score>()),
+            )
+                .run_if(in_state(GameState::Playing)),
+        )
+        .add_systems(
+            Update,
+            (
+                update_aim.before(player_shooting).run_if(not(replaying)),
+                aim_line,
+            )
+                .run_if(in_state(GameState::Playing)),
+        )
+        .add_systems(
+            PostUpdate,
+            apply_score_modifiers
+                .before(sandbox_rules)
+                .run_if(in_state(GameState::Playing)),
+        );
+    }
+}
+
+pub fn run() {
+    let mut launch_options = LaunchOptions::from_args(std::env::args().skip(1));
+    let mut settings = Settings::load();
+    let profile = Profile::load();
+    let mut magnet_radius = profile.magnet_radius();
+    let replay_mode = ReplayMode::start(&mut launch_options, &mut settings, &mut magnet_radius);
+    // Unordered systems can otherwise run in a different order from one launch to the
+    // next, which a replay couldn't reproduce.
+    let single_threaded = replay_mode.is_on();
+    let set_executor = move |schedule: &mut Schedule| {
+        if single_threaded {
+            schedule.set_executor_kind(ExecutorKind::SingleThreaded);
+        }
+    };
+    // Recording and playback both start straight into a fresh run.
+    let pending_save = if replay_mode.is_on() {
+        PendingSave(None)
+    } else {
+        PendingSave::load()
+    };
+    let modifier_config = ModifierConfig::load();
+    let modifiers = Modifiers::pick(&modifier_config, &settings.modifiers);
+
+    App::new()
+        .add_plugins(DefaultPlugins.set(WindowPlugin {
+            primary_window: Some(Window {
+                title: BASE_WINDOW_TITLE.into(),
+                resolution: (settings.resolution.0 as f32, settings.resolution.1 as f32).into(),
+                ..default()
+            }),
+            ..default()
+        }))
+        .add_plugins(FrameTimeDiagnosticsPlugin)
+        .add_state::<GameState>()
+        .add_state::<AttractMode>()
+        .insert_resource(ClearColor(BACKGROUND_COLOR))
+        .init_asset::<Balance>()
+        .init_asset_loader::<BalanceLoader>()
+        .insert_resource(Score(0))
+        .insert_resource(HighScore::load())
+        .init_resource::<SaveDir>()
+        .insert_resource(pending_save)
+        .insert_resource(MagnetRadius(magnet_radius))
+        .insert_resource(profile)
+        .insert_resource(WindowTitle(BASE_WINDOW_TITLE.into()))
+        .add_event::<PlayerHit>()
+        .add_event::<MenuSelected>()
+        .add_event::<MenuAdjusted>()
+        .init_resource::<Menu>()
+        .add_event::<EnemyEscaped>()
+        .add_event::<Missed>()
+        .add_event::<ChainKill>()
+        .add_event::<EnemyDestroyed>()
+        .add_event::<RestartRun>()
+        .init_resource::<KillFeed>()
+        .init_resource::<Escapes>()
+        .init_resource::<Continues>()
+        .insert_resource(Rewind::new(settings.rewinds))
+        .init_resource::<GrazeMeter>()
+        .init_resource::<SurvivalBonus>()
+        .init_resource::<RunStats>()
+        .init_resource::<RunKills>()
+        .init_resource::<Dda>()
+        .init_resource::<Combo>()
+        .init_resource::<GameOverReason>()
+        .init_resource::<CameraPunch>()
+        .init_resource::<ScreenShake>()
+        .init_resource::<HitStop>()
+        .init_resource::<DangerTier>()
+        .init_resource::<TimeScale>()
+        .init_resource::<PlayArea>()
+        .init_resource::<BulletTime>()
+        .init_resource::<FreezePulse>()
+        .insert_resource(launch_options.difficulty(&settings))
+        // No daily challenge is running at launch; a resumed one restores its own.
+        .insert_resource(BaseDifficulty(settings.base_difficulty))
+        .insert_resource(settings.key_bindings.clone())
+        .insert_resource(HudConfig::new(&settings))
+        .insert_resource(Lives(modifiers.starting_lives(&settings)))
+        .insert_resource(modifiers)
+        .insert_resource(modifier_config)
+        .insert_resource(settings)
+        .init_resource::<Rebinding>()
+        .init_resource::<QuitPrompt>()
+        .insert_resource(launch_options.rng())
+        .init_resource::<SpawnDirector>()
+        .init_resource::<SpawnHeatmap>()
+        .init_resource::<EnemyBulletCap>()
+        .insert_resource(GracePeriod::new(&Balance::default()))
+        .init_resource::<WaveManager>()
+        .init_resource::<RunAnalytics>()
+        .init_resource::<BossSchedule>()
+        .insert_resource(BossConfig::load())
+        .insert_resource(ScoringConfig::load())
+        .insert_resource(EntranceConfig::load())
+        .insert_resource(SpawnTimeline::load())
+        .insert_resource(launch_options.power_ups())
+        .init_resource::<TouchControls>()
+        .init_resource::<Aim>()
+        .insert_resource(DebugCheats {
+            enabled: launch_options.god_mode,
+            invincible: launch_options.god_mode,
+        })
+        .insert_resource(Sandbox {
+            on: launch_options.sandbox,
+            ..default()
+        })
+        .init_resource::<DailyChallenge>()
+        .init_resource::<PlayTime>()
+        .init_resource::<PerformanceGovernor>()
+        .init_resource::<ScreenFade>()
+        .insert_resource(launch_options)
+        .insert_resource(replay_mode.time_strategy())
+        .insert_resource(replay_mode)
+        .add_systems(
+            Startup,
+            (setup, setup_audio, offer_saved_game.run_if(not(in_sandbox))),
+        )
+        .add_systems(
+            PreUpdate,
+            (play_input, record_keys).after(bevy::input::InputSystem),
+        )
+        .add_systems(
+            PreUpdate,
+            leave_attract_mode
+                .after(bevy::input::InputSystem)
+                .after(play_input)
+                .run_if(in_state(AttractMode::On)),
+        )
+        .add_systems(PreUpdate, (apply_letterbox, govern_performance))
+        .add_systems(PostUpdate, record_aim)
+        .add_systems(
+            PostUpdate,
+            sync_menu_focus.before(AccessibilitySystem::Update),
+        )
+        .add_systems(
+            Last,
+            (
+                finish_replay,
+                bank_on_exit,
+                save_on_exit.after(bank_on_exit),
+                fade_transitions,
+            ),
+        )
+        .add_plugins(GameplayPlugin)
+        .add_systems(
+            Update,
+            (
+                particle_update,
+                chain_popup_update,
+                update_music_intensity,
+                background_tint,
+                apply_balance,
+                apply_profile.run_if(resource_changed::<Profile>()),
+                camera_punch,
+                camera_shake,
+                update_combo_glow,
+            ),
+        )
+        .add_systems(
+            Update,
+            (
+                read_touch_controls
+                    .before(player_movement)
+                    .before(player_shooting),
+                update_touch_controls_ui,
+            ),
+        )
+        .add_systems(
+            Update,
+            (
+                adjust_brightness,
+                (
+                    apply_brightness,
+                    apply_window_settings,
+                    apply_key_bindings,
+                    apply_hud_settings,
+                )
+                    .run_if(resource_changed::<Settings>()),
+                layout_hud.run_if(resource_changed::<HudConfig>()),
+            )
+                .chain(),
+        )
+        .add_systems(
+            Update,
+            (
+                capture_rebind,
+                menu_navigation.run_if(not_rebinding),
+                highlight_menu,
+                handle_menu_action,
+                handle_settings_menu,
+                handle_controls_menu,
+                handle_shop_menu,
+                handle_modifiers_menu,
+                handle_quit_menu,
+                handle_save_menu,
+                handle_continue_menu,
+                update_rebind_warning,
+            )
+                .chain()
+                .run_if(
+                    in_state(GameState::Paused)
+                        .or_else(in_state(GameState::Settings))
+                        .or_else(in_state(GameState::Controls))
+                        .or_else(in_state(GameState::Stats))
+                        .or_else(in_state(GameState::Modifiers))
+                        .or_else(in_state(GameState::Continue))
+                        .or_else(in_state(GameState::GameOver)),
+                ),
+        )
+        .add_systems(
+            PostUpdate,
+            offer_continue
+                .run_if(in_state(GameState::Playing))
+                .after(sandbox_rules),
+        )
+        .add_systems(OnEnter(GameState::Continue), show_continue_screen)
+        .add_systems(
+            Update,
+            continue_countdown.run_if(in_state(GameState::Continue)),
+        )
+        .add_systems(
+            OnExit(GameState::Continue),
+            despawn_screen::<ContinueScreen>,
+        )
+        .add_systems(
+            OnEnter(GameState::GameOver),
+            (game_over, reset_window_title, log_run, clear_enemy_bullets),
+        )
+        .add_systems(OnExit(GameState::GameOver), reset_game)
+        .add_systems(Update, reset_game.run_if(on_event::<RestartRun>()))
+        .add_systems(
+            Update,
+            (
+                toggle_pause
+                    .run_if(quit_dialog_closed)
+                    .run_if(not(save_offered)),
+                pause_on_focus_lost,
+            )
+                .run_if(in_state(GameState::Playing).or_else(in_state(GameState::Paused))),
+        )
+        .add_systems(
+            Update,
+            (
+                toggle_debug_cheats,
+                debug_cheats.run_if(in_state(GameState::Playing)),
+                update_cheats_indicator,
+                spawn_debug,
+            )
+                .chain(),
+        )
+        .add_systems(
+            Update,
+            (
+                sandbox_controls
+                    .run_if(in_state(GameState::Playing))
+                    .run_if(in_sandbox),
+                pattern_preview.run_if(in_state(GameState::Playing)),
+                update_sandbox_panel,
+            )
+                .chain(),
+        )
+        .add_systems(PostUpdate, sandbox_rules.run_if(in_sandbox))
+        .add_systems(
+            PostUpdate,
+            (
+                enemy_tint,
+                bullet_tint,
+                enemy_bullet_tint,
+                size_bullets,
+                (spawn_shadows, apply_deferred, shadow_update)
+                    .chain()
+                    .after(enemy_tint)
+                    .before(TransformSystem::TransformPropagate),
+                enemy_health_bar
+                    .after(enemy_tint)
+                    .before(TransformSystem::TransformPropagate),
+            ),
+        )
+        .add_systems(
+            OnEnter(GameState::Paused),
+            (show_pause_screen, save_game.run_if(not(in_sandbox))),
+        )
+        .add_systems(
+            OnTransition {
+                from: GameState::Paused,
+                to: GameState::Playing,
+            },
+            drop_pending_save,
+        )
+        .add_systems(
+            OnExit(GameState::Paused),
+            (despawn_screen::<PauseScreen>, despawn_screen::<QuitDialog>),
+        )
+        .add_systems(OnEnter(GameState::Settings), show_settings_screen)
+        .add_systems(
+            OnExit(GameState::Settings),
+            despawn_screen::<SettingsScreen>,
+        )
+        .add_systems(OnEnter(GameState::Controls), show_controls_screen)
+        .add_systems(
+            OnExit(GameState::Controls),
+            despawn_screen::<ControlsScreen>,
+        )
+        .add_systems(OnEnter(GameState::Stats), show_stats_screen)
+        .add_systems(OnExit(GameState::Stats), despawn_screen::<StatsScreen>)
+        .add_systems(OnEnter(GameState::Modifiers), show_modifiers_screen)
+        .add_systems(
+            OnExit(GameState::Modifiers),
+            despawn_screen::<ModifiersScreen>,
+        )
+        .add_systems(
+            Update,
+            (
+                enter_attract_mode.run_if(in_state(AttractMode::Off)),
+                attract_slideshow.run_if(in_state(AttractMode::On)),
+            ),
+        )
+        .add_systems(OnEnter(AttractMode::On), show_attract_screen)
+        .add_systems(OnExit(AttractMode::On), despawn_screen::<AttractScreen>)
+        .edit_schedule(PreUpdate, set_executor)
+        .edit_schedule(Update, set_executor)
+        .edit_schedule(PostUpdate, set_executor)
+        .run();
+}
+
+fn setup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    settings: Res<Settings>,
+) {
+    // Camera
+    commands.spawn(Camera2dBundle::default());
+    // Ears for `play_sound_panned`
+    commands.spawn((SpatialListener::new(2.0), TransformBundle::default()));
+
+    // A unit disc, scaled to the magnet radius by `update_magnet_ring`.
+    commands.spawn((
+        MaterialMesh2dBundle {
+            mesh: meshes.add(shape::Circle::new(1.0).into()).into(),
+            material: materials.add(ColorMaterial::from(MAGNET_RING_COLOR)),
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        MagnetRing,
+    ));
+    commands.spawn((
+        MaterialMesh2dBundle {
+            mesh: meshes.add(shape::Circle::new(REFLECT_RADIUS).into()).into(),
+            material: materials.add(ColorMaterial::from(
+                REFLECT_COLOR.with_a(REFLECT_AURA_ALPHA),
+            )),
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        ReflectAura,
+    ));
+
+    commands.insert_resource(BalanceHandle(load_optional(&asset_server, BALANCE_PATH)));
+
+    // Player
+    commands
+        .spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    custom_size: Some(Vec2::from(Balance::default().player_size)),
+                    color: PLAYER_COLOR,
+                    ..default()
+                },
+                transform: Transform::from_xyz(0.0, -200.0, layers::PLAYER),
+                ..default()
+            },
+            Player {
+                speed: PLAYER_SPEED * settings.ship_speed,
+                fire_interval: PLAYER_FIRE_INTERVAL_SECS / settings.fire_rate,
+                velocity_x: 0.0,
+                recoil_x: 0.0,
+                shoot_timer: Timer::new(
+                    Duration::from_secs_f32(PLAYER_FIRE_INTERVAL_SECS),
+                    TimerMode::Repeating,
+                ),
+                fire_buffer: 0.0,
+                dash_cooldown: ready_timer(DASH_COOLDOWN_SECS),
+                gun_offsets: vec![CENTER_GUN],
+            },
+        ))
+        .with_children(|ship| {
+            // Anchored at its top edge so scaling only lengthens the flame downward.
+            ship.spawn((
+                SpriteBundle {
+                    sprite: Sprite {
+                        custom_size: Some(THRUSTER_SIZE),
+                        color: THRUSTER_COLOR,
+                        anchor: Anchor::TopCenter,
+                        ..default()
+                    },
+                    transform: Transform::from_xyz(0.0, -25.0, -0.5),
+                    ..default()
+                },
+                Thruster,
+            ));
+        });
+
+    // Score text
+    commands.spawn((
+        TextBundle::from_section(
+            "Score: 0",
+            TextStyle {
+                font_size: 30.0,
+                color: Color::WHITE,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.0),
+            left: Val::Px(10.0),
+            ..default()
+        }),
+        ScoreText,
+        HudAnchor::new(HudGroup::Score, 10.0, 10.0),
+        HudText(30.0),
+    ));
+
+    // Combo text
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 22.0,
+                color: Color::WHITE,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(80.0),
+            left: Val::Px(10.0),
+            ..default()
+        }),
+        ComboText {
+            miss_flash: ready_timer(0.3),
+        },
+        HudAnchor::new(HudGroup::Score, 10.0, 80.0),
+        HudText(22.0),
+    ));
+
+    // Lives text
+    commands.spawn((
+        TextBundle::from_section(
+            format!("Lives: {0}/{0}", settings.starting_lives),
+            TextStyle {
+                font_size: 30.0,
+                color: Color::WHITE,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.0),
+            right: Val::Px(10.0),
+            ..default()
+        }),
+        LivesText,
+        HudAnchor::new(HudGroup::Status, 10.0, 10.0),
+        HudText(30.0),
+    ));
+
+    // Bullet time gauge
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(50.0),
+                    left: Val::Px(10.0),
+                    width: Val::Px(150.0),
+                    height: Val::Px(10.0),
+                    ..default()
+                },
+                background_color: Color::DARK_GRAY.into(),
+                ..default()
+            },
+            HudAnchor::new(HudGroup::Score, 10.0, 50.0).sized(150.0, 10.0),
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                NodeBundle {
+                    style: Style {
+                        width: Val::Percent(100.0),
+                        height: Val::Percent(100.0),
+                        ..default()
+                    },
+                    background_color: Color::CYAN.into(),
+                    ..default()
+                },
+                BulletTimeGauge,
+            ));
+        });
+
+    // Rapid fire countdown
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(66.0),
+                    left: Val::Px(10.0),
+                    width: Val::Px(150.0),
+                    height: Val::Px(6.0),
+                    ..default()
+                },
+                background_color: Color::DARK_GRAY.into(),
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+            RapidFireGauge,
+            HudAnchor::new(HudGroup::Score, 10.0, 66.0).sized(150.0, 6.0),
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                NodeBundle {
+                    style: Style {
+                        width: Val::Percent(100.0),
+                        height: Val::Percent(100.0),
+                        ..default()
+                    },
+                    background_color: RAPID_FIRE_COLOR.into(),
+                    ..default()
+                },
+                RapidFireGaugeFill,
+            ));
+        });
+
+    // Graze meter
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(76.0),
+                    left: Val::Px(10.0),
+                    width: Val::Px(150.0),
+                    height: Val::Px(6.0),
+                    ..default()
+                },
+                background_color: Color::DARK_GRAY.into(),
+                ..default()
+            },
+            HudAnchor::new(HudGroup::Score, 10.0, 76.0).sized(150.0, 6.0),
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                NodeBundle {
+                    style: Style {
+                        width: Val::Percent(0.0),
+                        height: Val::Percent(100.0),
+                        ..default()
+                    },
+                    background_color: GRAZE_COLOR.into(),
+                    ..default()
+                },
+                GrazeMeterFill,
+            ));
+        });
+
+    // Freeze pulse recharge
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(86.0),
+                    left: Val::Px(10.0),
+                    width: Val::Px(150.0),
+                    height: Val::Px(6.0),
+                    ..default()
+                },
+                background_color: Color::DARK_GRAY.into(),
+                ..default()
+            },
+            HudAnchor::new(HudGroup::Score, 10.0, 86.0).sized(150.0, 6.0),
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                NodeBundle {
+                    style: Style {
+                        width: Val::Percent(100.0),
+                        height: Val::Percent(100.0),
+                        ..default()
+                    },
+                    background_color: FROZEN_COLOR.into(),
+                    ..default()
+                },
+                FreezeGaugeFill,
+            ));
+        });
+
+    // Wave progress
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(10.0),
+                    width: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    row_gap: Val::Px(4.0),
+                    ..default()
+                },
+                ..default()
+            },
+            WaveProgressUi,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    "Wave 1",
+                    TextStyle {
+                        font_size: 20.0,
+                        color: Color::WHITE,
+                        ..default()
+                    },
+                ),
+                WaveText,
+                HudText(20.0),
+            ));
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        width: Val::Px(200.0),
+                        height: Val::Px(8.0),
+                        ..default()
+                    },
+                    background_color: Color::DARK_GRAY.into(),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn((
+                        NodeBundle {
+                            style: Style {
+                                width: Val::Percent(0.0),
+                                height: Val::Percent(100.0),
+                                ..default()
+                            },
+                            background_color: Color::LIME_GREEN.into(),
+                            ..default()
+                        },
+                        WaveProgressFill,
+                    ));
+                });
+        });
+
+    // Escape allowance
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 20.0,
+                color: Color::WHITE,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(45.0),
+            right: Val::Px(10.0),
+            ..default()
+        }),
+        EscapesText,
+        HudAnchor::new(HudGroup::Status, 10.0, 45.0),
+        HudText(20.0),
+    ));
+
+    // Bullet cap readout
+    commands.spawn((
+        TextBundle {
+            visibility: Visibility::Hidden,
+            ..TextBundle::from_section(
+                "",
+                TextStyle {
+                    font_size: 18.0,
+                    color: Color::rgba(1.0, 1.0, 1.0, 0.6),
+                    ..default()
+                },
+            )
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(10.0),
+                right: Val::Px(10.0),
+                ..default()
+            })
+        },
+        BulletIndicator,
+        HudAnchor::new(HudGroup::Status, 10.0, 10.0).far_edge(),
+        HudText(18.0),
+    ));
+
+    // Kill feed, under the escape allowance
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(75.0),
+                    right: Val::Px(10.0),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::FlexEnd,
+                    ..default()
+                },
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+            KillFeedUi,
+            HudAnchor::new(HudGroup::Status, 10.0, 75.0),
+        ))
+        .with_children(|parent| {
+            for line in 0..KILL_FEED_LEN {
+                parent.spawn((
+                    TextBundle::from_section(
+                        "",
+                        TextStyle {
+                            font_size: 18.0,
+                            color: Color::WHITE,
+                            ..default()
+                        },
+                    ),
+                    KillFeedLine(line),
+                    HudText(18.0),
+                ));
+            }
+        });
+
+    // Cheats indicator, so recordings of cheated runs are clearly marked
+    commands.spawn((
+        TextBundle {
+            visibility: Visibility::Hidden,
+            ..TextBundle::from_section(
+                "CHEATS",
+                TextStyle {
+                    font_size: 20.0,
+                    color: Color::ORANGE,
+                    ..default()
+                },
+            )
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(10.0),
+                left: Val::Px(10.0),
+                ..default()
+            })
+        },
+        CheatsIndicator,
+        HudAnchor::new(HudGroup::Score, 10.0, 10.0).far_edge(),
+        HudText(20.0),
+    ));
+
+    // Sandbox panel, filled in by `update_sandbox_panel`
+    commands.spawn((
+        TextBundle {
+            visibility: Visibility::Hidden,
+            ..TextBundle::from_section(
+                "",
+                TextStyle {
+                    font_size: 16.0,
+                    color: SANDBOX_PANEL_COLOR,
+                    ..default()
+                },
+            )
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(40.0),
+                left: Val::Px(10.0),
+                ..default()
+            })
+        },
+        SandboxPanel,
+        HudAnchor::new(HudGroup::Score, 10.0, 40.0).far_edge(),
+        HudText(16.0),
+    ));
+
+    // Touch controls
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    left: Val::VMin(TOUCH_CONTROL_MARGIN_VMIN),
+                    bottom: Val::VMin(TOUCH_CONTROL_MARGIN_VMIN),
+                    width: Val::VMin(JOYSTICK_SIZE_VMIN),
+                    height: Val::VMin(JOYSTICK_SIZE_VMIN),
+                    ..default()
+                },
+                background_color: Color::rgba(1.0, 1.0, 1.0, 0.15).into(),
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+            TouchControlsUi,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        left: Val::Percent(30.0),
+                        top: Val::Percent(30.0),
+                        width: Val::Percent(40.0),
+                        height: Val::Percent(40.0),
+                        ..default()
+                    },
+                    background_color: Color::rgba(1.0, 1.0, 1.0, 0.4).into(),
+                    ..default()
+                },
+                JoystickKnob,
+            ));
+        });
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                right: Val::VMin(TOUCH_CONTROL_MARGIN_VMIN),
+                bottom: Val::VMin(TOUCH_CONTROL_MARGIN_VMIN),
+                width: Val::VMin(FIRE_BUTTON_SIZE_VMIN),
+                height: Val::VMin(FIRE_BUTTON_SIZE_VMIN),
+                ..default()
+            },
+            background_color: Color::rgba(1.0, 0.3, 0.3, 0.3).into(),
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        TouchControlsUi,
+    ));
+
+    // Brightness overlay, dims gameplay but stays under the HUD
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                ..default()
+            },
+            background_color: Color::rgba(0.0, 0.0, 0.0, 0.0).into(),
+            z_index: ZIndex::Global(-2),
+            ..default()
+        },
+        BrightnessOverlay,
+    ));
+
+    // Letterbox bars, sized by `apply_letterbox`; under the HUD so it stays readable
+    for bar in [LetterboxBar::Start, LetterboxBar::End] {
+        commands.spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    ..default()
+                },
+                background_color: Color::BLACK.into(),
+                z_index: ZIndex::Global(-3),
+                ..default()
+            },
+            bar,
+        ));
+    }
+
+    // Bullet time screen tint
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                ..default()
+            },
+            background_color: Color::rgba(0.0, 0.3, 1.0, 0.15).into(),
+            visibility: Visibility::Hidden,
+            z_index: ZIndex::Global(-1),
+            ..default()
+        },
+        BulletTimeTint,
+    ));
+
+    // Combo border glow
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                border: UiRect::all(Val::Px(COMBO_GLOW_WIDTH)),
+                ..default()
+            },
+            border_color: Color::NONE.into(),
+            z_index: ZIndex::Global(-1),
+            ..default()
+        },
+        ComboGlow {
+            alpha: 0.0,
+            color: COMBO_GLOW_COLORS[0],
+        },
+    ));
+
+    // Screen fade, over everything including menus
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                ..default()
+            },
+            background_color: Color::rgba(0.0, 0.0, 0.0, 0.0).into(),
+            visibility: Visibility::Hidden,
+            z_index: ZIndex::Global(i32::MAX),
+            ..default()
+        },
+        FadeOverlay,
+    ));
+}
+
+/// Loads `path` only if it is on disk. A missing file logs one warning and gets the
+/// default handle instead of an asset server error: sounds stay silent, and a sprite
+/// given it as a texture draws as its plain colour.
+fn load_optional<A: Asset>(asset_server: &AssetServer, path: &'static str) -> Handle<A> {
+    let full_path = FileAssetReader::get_base_path().join(ASSET_DIR).join(path);
+    if full_path.exists() {
+        asset_server.load(path)
+    } else {
+        warn!("missing asset {path}, using a placeholder");
+        Handle::default()
+    }
+}
+
+/// Plays a one-shot sound, unless it is a placeholder for a missing file, which would
+/// otherwise leave an entity waiting forever for audio that never loads.
+fn play_sound(commands: &mut Commands, source: &Handle<AudioSource>, volume: f32) {
+    play_sound_at_speed(commands, source, volume, 1.0);
+}
+
+/// Where in the stereo field a sound from world `x` plays, from `-SOUND_MAX_PAN` on
+/// the left to `SOUND_MAX_PAN` on the right. The camera stays centred on the play
+/// area, so this is relative to the middle of the screen.
+fn sound_pan(x: f32, play_area: &PlayArea) -> f32 {
+    (x / play_area.half_size.x).clamp(-SOUND_MAX_PAN, SOUND_MAX_PAN)
+}
+
+/// Like `play_sound`, but panned by `pan` from `sound_pan`. The listener's ears sit one
+/// unit either side of the origin, and rodio quietens a sound with the square of its
+/// distance past one unit, so the emitter goes just `pan` units along rather than
+/// anywhere near where the sound came from in the world.
+fn play_sound_panned(commands: &mut Commands, source: &Handle<AudioSource>, volume: f32, pan: f32) {
+    if *source == Handle::default() {
+        return;
+    }
+    commands.spawn((
+        AudioBundle {
+            source: source.clone(),
+            settings: PlaybackSettings::DESPAWN
+                .with_volume(Volume::new_relative(volume * PANNED_SOUND_GAIN))
+                .with_spatial(true),
+        },
+        TransformBundle::from_transform(Transform::from_xyz(pan, 0.0, 0.0)),
+    ));
+}
+
+/// Like `play_sound`, but faster playback also raises the pitch.
+fn play_sound_at_speed(
+    commands: &mut Commands,
+    source: &Handle<AudioSource>,
+    volume: f32,
+    speed: f32,
+) {
+    if *source == Handle::default() {
+        return;
+    }
+    commands.spawn(AudioBundle {
+        source: source.clone(),
+        settings: PlaybackSettings::DESPAWN
+            .with_volume(Volume::new_relative(volume))
+            .with_speed(speed),
+    });
+}
+
+fn setup_audio(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let music_stems: Vec<Handle<AudioSource>> = MUSIC_STEMS
+        .iter()
+        .map(|path| load_optional(&asset_server, path))
+        .collect();
+
+    // Every stem starts in the same frame so they stay in sync; only the base is audible.
+    for (layer, source) in music_stems.iter().enumerate() {
+        let volume = if layer == 0 { 1.0 } else { 0.0 };
+        commands.spawn((
+            AudioBundle {
+                source: source.clone(),
+                settings: PlaybackSettings::LOOP.with_volume(Volume::new_relative(volume)),
+            },
+            MusicStem { layer, volume },
+        ));
+    }
+
+    commands.insert_resource(GameAudio {
+        music_stems,
+        boss_phase: load_optional(&asset_server, "audio/boss_phase.ogg"),
+        boss_intro: load_optional(&asset_server, "audio/boss_intro.ogg"),
+        combo_milestone: load_optional(&asset_server, "audio/combo_milestone.ogg"),
+        danger_rising: load_optional(&asset_server, "audio/danger_rising.ogg"),
+        enemy_spawn: load_optional(&asset_server, "audio/enemy_spawn.ogg"),
+        special_spawn: load_optional(&asset_server, "audio/special_spawn.ogg"),
+        weapon_fire: BulletKind::ALL
+            .into_iter()
+            .map(|kind| (kind, load_optional(&asset_server, kind.fire_sound())))
+            .collect(),
+    });
+}
+
+fn read_touch_controls(
+    touches: Res<Touches>,
+    keyboard_input: Res<Input<KeyCode>>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    mut touch_controls: ResMut<TouchControls>,
+) {
+    if touches.any_just_pressed() {
+        touch_controls.visible = true;
+    } else if keyboard_input.get_just_pressed().next().is_some()
+        || gamepad_buttons.get_just_pressed().next().is_some()
+    {
+        touch_controls.visible = false;
+    }
+
+    touch_controls.movement = 0.0;
+    touch_controls.fire = false;
+
+    let Ok(window) = window_query.get_single() else {
+        return;
+    };
+    // Mirror the Vmin-based layout from `setup` in window coordinates (y grows downward).
+    let vmin = window.width().min(window.height()) / 100.0;
+    let joystick_radius = JOYSTICK_SIZE_VMIN / 2.0 * vmin;
+    let joystick_center = Vec2::new(
+        (TOUCH_CONTROL_MARGIN_VMIN * vmin) + joystick_radius,
+        window.height() - (TOUCH_CONTROL_MARGIN_VMIN * vmin) - joystick_radius,
+    );
+    let fire_radius = FIRE_BUTTON_SIZE_VMIN / 2.0 * vmin;
+    let fire_center = Vec2::new(
+        window.width() - (TOUCH_CONTROL_MARGIN_VMIN * vmin) - fire_radius,
+        window.height() - (TOUCH_CONTROL_MARGIN_VMIN * vmin) - fire_radius,
+    );
+
+    for touch in touches.iter() {
+        // Drags that started on the joystick keep steering even if the finger slides off it.
+        if touch.start_position().distance(joystick_center) < joystick_radius * 1.5 {
+            touch_controls.movement =
+                ((touch.position().x - joystick_center.x) / joystick_radius).clamp(-1.0, 1.0);
+        } else if touch.position().distance(fire_center) < fire_radius * 1.5 {
+            touch_controls.fire = true;
+        }
+    }
+}
+
+fn update_touch_controls_ui(
+    touch_controls: Res<TouchControls>,
+    mut ui_query: Query<&mut Visibility, With<TouchControlsUi>>,
+    mut knob_query: Query<&mut Style, With<JoystickKnob>>,
+) {
+    for mut visibility in ui_query.iter_mut() {
+        *visibility = if touch_controls.visible {
+            Visibility::Visible
+        } else {
+            Visibility::Hidden
+        };
+    }
+    if let Ok(mut style) = knob_query.get_single_mut() {
+        style.left = Val::Percent(30.0 + touch_controls.movement * 30.0);
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn player_movement(
+    mut commands: Commands,
+    controls: Controls,
+    touch_controls: Res<TouchControls>,
+    mut player_query: Query<(Entity, &mut Player, &mut Transform, Option<&mut Dashing>)>,
+    tractor_query: Query<
+        &Transform,
+        (
+            With<Tractor>,
+            Without<Player>,
+            Without<Entering>,
+            Without<Frozen>,
+        ),
+    >,
+    director: Res<SpawnDirector>,
+    time: Res<Time>,
+) {
+    if let Ok((entity, mut player, mut transform, dashing)) = player_query.get_single_mut() {
+        player.dash_cooldown.tick(time.delta());
+
+        transform.translation.x += player.recoil_x * time.delta_seconds();
+        player.recoil_x *= (-EDGE_BOUNCE_DAMPING * time.delta_seconds()).exp();
+
+        if let Some(mut dashing) = dashing {
+            player.velocity_x = dashing.direction * player.speed * DASH_SPEED_MULTIPLIER;
+            transform.translation.x += player.velocity_x * time.delta_seconds();
+            if dashing.timer.tick(time.delta()).finished() {
+                commands.entity(entity).remove::<Dashing>();
+            }
+            return;
+        }
+
+        let mut direction = Vec3::ZERO;
+
+        if controls.keyboard.pressed(KeyCode::Left) || controls.pressed(GameAction::MoveLeft) {
+            direction.x -= 1.0;
+        }
+        if controls.keyboard.pressed(KeyCode::Right) || controls.pressed(GameAction::MoveRight) {
+            direction.x += 1.0;
+        }
+
+        direction.x += touch_controls.movement;
+        direction = direction.clamp_length_max(1.0);
+
+        if controls.just_pressed(GameAction::Dash)
+            && player.dash_cooldown.finished()
+            && direction.x != 0.0
+        {
+            player.dash_cooldown.reset();
+            commands.entity(entity).insert((
+                Dashing {
+                    timer: Timer::from_seconds(DASH_DURATION_SECS, TimerMode::Once),
+                    direction: direction.x.signum(),
+                },
+                Invincible::from_seconds(DASH_IFRAME_SECS),
+            ));
+        }
+
+        player.velocity_x = direction.x * player.speed;
+        transform.translation += direction * player.speed * time.delta_seconds();
+
+        // A dash breaks free of any tractor for as long as it lasts.
+        let position = transform.translation.truncate();
+        let pull: f32 = tractor_query
+            .iter()
+            .filter_map(|tractor| {
+                let tractor = tractor.translation.truncate();
+                let strength = tractor_strength(tractor, position, director.balance.tractor_range)?;
+                let towards = ((tractor.x - position.x) / TRACTOR_PULL_EASE).clamp(-1.0, 1.0);
+                Some(director.balance.tractor_pull * strength * towards)
+            })
+            .sum();
+        let max_pull = player.speed * TRACTOR_MAX_PULL_SHARE;
+        transform.translation.x += pull.clamp(-max_pull, max_pull) * time.delta_seconds();
+    }
+}
+
+/// Drops an afterimage of the ship every `AFTERIMAGE_INTERVAL_SECS` while it dashes,
+/// and fades the ones already left. Reduce motion turns them off.
+#[allow(clippy::type_complexity)]
+fn dash_afterimages(
+    mut commands: Commands,
+    mut spawn_timer: Local<Timer>,
+    player_query: Query<(&Transform, &Sprite, &Handle<Image>), (With<Player>, With<Dashing>)>,
+    mut afterimage_query: Query<(Entity, &mut Afterimage, &mut Sprite), Without<Player>>,
+    (settings, governor): (Res<Settings>, Res<PerformanceGovernor>),
+    time: Res<Time>,
+) {
+    for (entity, mut afterimage, mut sprite) in afterimage_query.iter_mut() {
+        afterimage.0.tick(time.delta());
+        sprite
+            .color
+            .set_a(AFTERIMAGE_ALPHA * afterimage.0.percent_left());
+        if afterimage.0.finished() {
+            commands.despawn_if_exists(entity);
+        }
+    }
+
+    let Ok((transform, sprite, texture)) = player_query.get_single() else {
+        // Due straight away when the next dash starts.
+        *spawn_timer = Timer::from_seconds(AFTERIMAGE_INTERVAL_SECS, TimerMode::Repeating);
+        spawn_timer.set_elapsed(Duration::from_secs_f32(AFTERIMAGE_INTERVAL_SECS));
+        return;
+    };
+    if settings.reduce_motion
+        || governor.low_effects(&settings)
+        || !spawn_timer.tick(time.delta()).just_finished()
+    {
+        return;
+    }
+    commands.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                // The ship blinks while dashing, so its own alpha can't be trusted.
+                color: sprite.color.with_a(AFTERIMAGE_ALPHA),
+                ..sprite.clone()
+            },
+            texture: texture.clone(),
+            transform: Transform {
+                translation: transform.translation.truncate().extend(layers::AFTERIMAGES),
+                ..*transform
+            },
+            ..default()
+        },
+        Afterimage(Timer::from_seconds(AFTERIMAGE_SECS, TimerMode::Once)),
+    ));
+}
+
+/// Grows the thruster flame with horizontal speed and flickers it. With reduce motion
+/// on the flame holds a steady idle size.
+fn update_thruster(
+    player_query: Query<&Player>,
+    mut thruster_query: Query<(&mut Transform, &mut Sprite), With<Thruster>>,
+    settings: Res<Settings>,
+    time: Res<Time>,
+) {
+    let Ok(player) = player_query.get_single() else {
+        return;
+    };
+    for (mut transform, mut sprite) in thruster_query.iter_mut() {
+        if settings.reduce_motion {
+            transform.scale = Vec3::new(1.0, THRUSTER_IDLE_SCALE, 1.0);
+            sprite.color.set_a(1.0);
+            continue;
+        }
+        let throttle = (player.velocity_x.abs() / player.speed).min(2.0);
+        let length = THRUSTER_IDLE_SCALE + (THRUSTER_MOVING_SCALE - THRUSTER_IDLE_SCALE) * throttle;
+        let flicker = (time.elapsed_seconds() * THRUSTER_FLICKER_HZ * TAU).sin();
+        transform.scale = Vec3::new(1.0 + 0.1 * flicker, length * (1.0 + 0.15 * flicker), 1.0);
+        sprite.color.set_a(0.8 + 0.2 * flicker);
+    }
+}
+
+/// A one-shot timer that starts out already finished.
+fn ready_timer(secs: f32) -> Timer {
+    let mut timer = Timer::from_seconds(secs, TimerMode::Once);
+    timer.tick(timer.duration());
+    timer
+}
+
+fn confine_player_movement(
+    mut player_query: Query<(&mut Player, &mut Transform)>,
+    play_area: Res<PlayArea>,
+    settings: Res<Settings>,
+) {
+    if let Ok((mut player, mut player_transform)) = player_query.get_single_mut() {
+        let x = player_transform.translation.x;
+        let half_width = play_area.half_size.x;
+        player_transform.translation.x = if settings.wrap_mode.player() {
+            // Wrap once the ship is fully off one side so it slides in from the other.
+            wrap(x, half_width + 25.0)
+        } else {
+            // The clamp still applies when bouncing, so the ship never passes the bounds;
+            // the recoil only carries it back inward over the next few frames.
+            let limit = half_width - 50.0;
+            let incoming = player.velocity_x + player.recoil_x;
+            if settings.elastic_edges && x.abs() > limit && incoming * x > 0.0 {
+                player.recoil_x = -incoming * EDGE_BOUNCE_RESTITUTION;
+            }
+            x.clamp(-limit, limit)
+        };
+    }
+}
+
+fn player_shooting(
+    mut commands: Commands,
+    (time, game_audio, play_area): (Res<Time>, Res<GameAudio>, Res<PlayArea>),
+    mut query: Query<(&mut Player, &Transform)>,
+    controls: Controls,
+    (touch_controls, power_ups): (Res<TouchControls>, Res<ActivePowerUps>),
+    (aim, settings, mut rng): (Res<Aim>, Res<Settings>, ResMut<GameRng>),
+    (director, bullet_query): (Res<SpawnDirector>, Query<(), With<Bullet>>),
+) {
+    if let Ok((mut player, transform)) = query.get_single_mut() {
+        // Derived from the base cadence every frame, so the original interval is
+        // restored exactly once rapid fire runs out.
+        let weapon_mode = power_ups.weapon_mode();
+        let mut interval = player.fire_interval;
+        if power_ups.rapid_fire.is_some() {
+            interval = (interval * power_ups.rapid_fire_factor()).max(MIN_FIRE_INTERVAL_SECS);
+        }
+        if weapon_mode == WeaponMode::AllRange {
+            interval *= ALL_RANGE_INTERVAL_FACTOR;
+        }
+        player
+            .shoot_timer
+            .set_duration(Duration::from_secs_f32(interval));
+        // The timer repeats rather than being reset on each shot, so time past the
+        // interval carries into the next one and sustained fire holds the exact cadence
+        // whatever the frame rate.
+        player.shoot_timer.tick(time.delta());
+
+        // A tap between shots would otherwise be lost if the key is up again by the
+        // time the next one is due.
+        if controls.just_pressed(GameAction::Fire) {
+            player.fire_buffer = FIRE_BUFFER_SECS;
+        } else {
+            player.fire_buffer = (player.fire_buffer - time.delta_seconds()).max(0.0);
+        }
+        let fire = settings.auto_fire
+            || controls.pressed(GameAction::Fire)
+            || touch_controls.fire
+            || player.fire_buffer > 0.0;
+        if fire && player.shoot_timer.just_finished() {
+            player.fire_buffer = 0.0;
+            let (velocities, damage) = weapon_mode.volley();
+            let mut shots: Vec<(Vec2, Vec2)> = player
+                .gun_offsets
+                .iter()
+                .flat_map(|&offset| velocities.iter().map(move |&velocity| (offset, velocity)))
+                .collect();
+            // At the cap a volley is thinned to what fits, keeping its shape.
+            let cap = director.balance.player_bullet_cap as usize;
+            if cap > 0 {
+                shots = thin(shots, cap.saturating_sub(bullet_query.iter().count()));
+            }
+            let kind = weapon_mode.bullet_kind(power_ups.pierce.is_some());
+            if !shots.is_empty()
+                && let Some(sound) = game_audio.weapon_fire.get(&kind)
+            {
+                play_sound_panned(
+                    &mut commands,
+                    sound,
+                    settings.sfx_volume * FIRE_SOUND_VOLUME,
+                    sound_pan(transform.translation.x, &play_area),
+                );
+            }
+            // Patterns are authored pointing straight up and rotated onto the aim.
+            let aim_rotation = Vec2::Y.angle_between(aim.0.unwrap_or(Vec2::Y));
+            // Full spread at walking speed or faster; none standing still.
+            let max_spread = settings.move_spread_degrees.to_radians()
+                * (player.velocity_x.abs() / player.speed).min(1.0);
+
+            for (offset, velocity) in shots {
+                let spread = if max_spread > 0.0 {
+                    rng.0.gen_range(-max_spread..=max_spread)
+                } else {
+                    0.0
+                };
+                let velocity = Vec2::from_angle(aim_rotation + spread).rotate(velocity);
+                let position = transform.translation.truncate() + offset;
+                let bounces = power_ups.bounce.is_some().then_some(MAX_BOUNCES);
+                let bullet = spawn_bullet(&mut commands, kind, position, velocity, damage, bounces);
+                if power_ups.pierce.is_some() {
+                    commands.entity(bullet).insert(Pierce::default());
+                }
+                if power_ups.flame.is_some() {
+                    commands.entity(bullet).insert(Incendiary);
+                }
+            }
+        }
+    }
+}
+
+/// Spawns in `kind`'s normal colour; `bullet_tint` swaps in the colorblind palette.
+fn spawn_bullet(
+    commands: &mut Commands,
+    kind: BulletKind,
+    position: Vec2,
+    velocity: Vec2,
+    damage: u32,
+    bounces: Option<u32>,
+) -> Entity {
+    let mut bullet = commands.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                custom_size: Some(kind.size()),
+                color: kind.color(),
+                ..default()
+            },
+            transform: Transform::from_translation(position.extend(layers::PLAYER_BULLETS))
+                .with_rotation(Quat::from_rotation_z(Vec2::Y.angle_between(velocity))),
+            ..default()
+        },
+        Bullet {
+            kind,
+            velocity,
+            damage,
+            lifetime: Timer::from_seconds(BULLET_MAX_LIFETIME_SECS, TimerMode::Once),
+        },
+    ));
+    if let Some(remaining) = bounces {
+        bullet.insert(Bounce { remaining });
+    }
+    bullet.id()
+}
+
+fn update_aim(
+    (mouse_buttons, gamepads, axes): (
+        Res<Input<MouseButton>>,
+        Res<Gamepads>,
+        Res<Axis<GamepadAxis>>,
+    ),
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform)>,
+    player_query: Query<&Transform, With<Player>>,
+    enemy_query: Query<&Transform, (With<Enemy>, Without<Player>)>,
+    settings: Res<Settings>,
+    mut aim: ResMut<Aim>,
+) {
+    let Ok(player_transform) = player_query.get_single() else {
+        return;
+    };
+
+    let stick = gamepads.iter().find_map(|gamepad| {
+        let x = axes.get(GamepadAxis::new(gamepad, GamepadAxisType::RightStickX))?;
+        let y = axes.get(GamepadAxis::new(gamepad, GamepadAxisType::RightStickY))?;
+        let stick = Vec2::new(x, y);
+        (stick.length() > GAMEPAD_AIM_DEADZONE).then_some(stick)
+    });
+
+    let cursor = || {
+        let window = window_query.get_single().ok()?;
+        let (camera, camera_transform) = camera_query.get_single().ok()?;
+        let cursor = window.cursor_position()?;
+        let target = camera.viewport_to_world_2d(camera_transform, cursor)?;
+        Some(target - player_transform.translation.truncate())
+    };
+
+    let direction = if mouse_buttons.pressed(MouseButton::Right) {
+        cursor()
+    } else {
+        stick
+    };
+    let position = player_transform.translation.truncate();
+    let strength = settings.aim_assist.strength();
+    aim.0 = direction
+        .and_then(|direction| direction.try_normalize())
+        .map(|direction| {
+            if strength <= 0.0 {
+                return direction;
+            }
+            // A nudge partway toward the closest enemy near the line of fire, never a lock.
+            let nearest = enemy_query
+                .iter()
+                .map(|transform| transform.translation.truncate() - position)
+                .filter(|offset| offset.angle_between(direction).abs() <= AIM_ASSIST_CONE)
+                .min_by(|a, b| a.length_squared().total_cmp(&b.length_squared()));
+            match nearest.and_then(|offset| offset.try_normalize()) {
+                Some(toward) => direction.lerp(toward, strength).normalize(),
+                None => direction,
+            }
+        })
+        .map(|direction| Vec2::new(direction.x, direction.y.max(MIN_AIM_Y)).normalize());
+}
+
+fn aim_line(aim: Res<Aim>, player_query: Query<&Transform, With<Player>>, mut gizmos: Gizmos) {
+    let (Some(direction), Ok(transform)) = (aim.0, player_query.get_single()) else {
+        return;
+    };
+
+    let start = transform.translation.truncate() + CENTER_GUN;
+    gizmos.line_2d(
+        start,
+        start + direction * AIM_LINE_LENGTH,
+        Color::rgba(1.0, 1.0, 0.4, 0.35),
+    );
+}
+
+#[allow(clippy::type_complexity)]
+fn bullet_movement(
+    mut commands: Commands,
+    mut query: Query<(
+        Entity,
+        &mut Bullet,
+        &mut Transform,
+        &Sprite,
+        Option<&mut Bounce>,
+        Option<&Pierce>,
+    )>,
+    play_area: Res<PlayArea>,
+    time: Res<Time>,
+    settings: Res<Settings>,
+    mut missed_events: EventWriter<Missed>,
+) {
+    let half_width = play_area.half_size.x;
+
+    for (entity, mut bullet, mut transform, sprite, bounce, pierce) in query.iter_mut() {
+        transform.translation += bullet.velocity.extend(0.0) * time.delta_seconds();
+        // A piercing shot that hit something on its way through did not miss.
+        let missed = pierce.is_none_or(|pierce| !pierce.landed);
+        if bullet.lifetime.tick(time.delta()).finished() {
+            commands.despawn_if_exists(entity);
+            if missed {
+                missed_events.send(Missed);
+            }
+            continue;
+        }
+
+        let x = transform.translation.x;
+        if x.abs() > half_width {
+            match bounce {
+                _ if settings.wrap_mode.bullets() => {
+                    transform.translation.x = wrap(x, half_width);
+                }
+                Some(mut bounce) if bounce.remaining > 0 => {
+                    bounce.remaining -= 1;
+                    bullet.velocity.x = -bullet.velocity.x;
+                    transform.translation.x = x.clamp(-half_width, half_width);
+                }
+                _ => {}
+            }
+        }
+
+        // All-range bursts send shots downward too. Shots turn to face their heading, so
+        // their longer side is allowed for whichever way they are going.
+        let length = sprite.custom_size.unwrap_or_default().max_element();
+        let reach = play_area.off_screen(Vec2::splat(length));
+        let position = transform.translation.truncate().abs();
+        if position.x > reach.x || position.y > reach.y {
+            commands.despawn_if_exists(entity);
+            if missed {
+                missed_events.send(Missed);
+            }
+        }
+    }
+}
+
+fn apply_balance(
+    mut events: EventReader<AssetEvent<Balance>>,
+    balances: Res<Assets<Balance>>,
+    mut director: ResMut<SpawnDirector>,
+    mut grace: ResMut<GracePeriod>,
+    mut player_query: Query<&mut Sprite, With<Player>>,
+) {
+    for event in events.read() {
+        if let AssetEvent::Added { id } | AssetEvent::Modified { id } = event
+            && let Some(balance) = balances.get(*id)
+        {
+            director.balance = *balance;
+            let duration = Duration::from_secs_f32(balance.grace_period_secs);
+            grace.0.set_duration(duration);
+            // Enemies and shots already out keep the size they were spawned at.
+            if let Ok(mut sprite) = player_query.get_single_mut() {
+                sprite.custom_size = Some(Vec2::from(balance.player_size));
+            }
+        }
+    }
+}
+
+/// Half the narrower side of the ship as drawn.
+fn player_hit_radius(sprite: &Sprite) -> f32 {
+    sprite
+        .custom_size
+        .map_or(25.0, |size| size.min_element() / 2.0)
+}
+
+fn ramp_difficulty(mut difficulty: ResMut<Difficulty>, time: Res<Time>) {
+    difficulty.level =
+        (difficulty.level + DIFFICULTY_RAMP_PER_SEC * time.delta_seconds()).min(MAX_DIFFICULTY);
+    difficulty.speed_multiplier = (difficulty.speed_multiplier
+        + SPEED_RAMP_PER_SEC * time.delta_seconds())
+    .min(MAX_SPEED_MULTIPLIER);
+}
+
+/// Marks each whole difficulty level the run climbs past with a sting and a banner.
+/// At the usual ramp that comes less than once a minute, so it stays worth noticing.
+fn danger_rising_cue(
+    mut commands: Commands,
+    difficulty: Res<Difficulty>,
+    mut peak: ResMut<DangerTier>,
+    game_audio: Res<GameAudio>,
+    settings: Res<Settings>,
+) {
+    let tier = difficulty.level.floor() as u32;
+    // A run starting at a higher difficulty has nothing to announce yet.
+    let Some(peak_tier) = peak.0 else {
+        peak.0 = Some(tier);
+        return;
+    };
+    if tier <= peak_tier {
+        return;
+    }
+    peak.0 = Some(tier);
+    play_sound(
+        &mut commands,
+        &game_audio.danger_rising,
+        settings.sfx_volume,
+    );
+    commands.spawn((
+        Text2dBundle {
+            text: Text::from_section(
+                "DANGER RISING",
+                TextStyle {
+                    font_size: 32.0,
+                    color: DANGER_RISING_COLOR,
+                    ..default()
+                },
+            ),
+            transform: Transform::from_xyz(0.0, 120.0, layers::PARTICLES),
+            ..default()
+        },
+        ChainPopup(Timer::from_seconds(
+            DANGER_RISING_BANNER_SECS,
+            TimerMode::Once,
+        )),
+    ));
+}
+
+fn track_run_stats(
+    mut stats: ResMut<RunStats>,
+    mut missed_events: EventReader<Missed>,
+    shot_query: Query<(), Added<Bullet>>,
+    lives: Res<Lives>,
+    mut last_lives: Local<Option<u32>>,
+) {
+    stats.shots += shot_query.iter().count() as u32;
+    stats.misses += missed_events.read().count() as u32;
+    if let Some(last) = *last_lives
+        && lives.0 < last
+    {
+        stats.hits_taken += last - lives.0;
+    }
+    *last_lives = Some(lives.0);
+}
+
+fn track_kills(mut kills: ResMut<RunKills>, mut destroyed_events: EventReader<EnemyDestroyed>) {
+    for event in destroyed_events.read() {
+        kills.record(event);
+    }
+}
+
+/// Re-judges the adaptive offset as each wave starts, from how the last one went.
+/// Turning the setting off takes the whole offset back out at once.
+fn adapt_difficulty(
+    waves: Res<WaveManager>,
+    mut last_wave: Local<u32>,
+    (settings, cheats): (Res<Settings>, Res<DebugCheats>),
+    lives: Res<Lives>,
+    mut stats: ResMut<RunStats>,
+    mut dda: ResMut<Dda>,
+    mut difficulty: ResMut<Difficulty>,
+) {
+    let target = if !settings.adaptive_difficulty {
+        0.0
+    } else if waves.wave > *last_wave && *last_wave > 0 {
+        // Missing lives and hits pull the offset down; clean, accurate play pushes it up.
+        let performance = (stats.accuracy() - 0.5) * 0.4 - stats.hits_taken as f32 * 0.15
+            + (lives.0 as f32 - settings.starting_lives as f32) * 0.05;
+        let target = performance.clamp(-DDA_MAX_OFFSET, DDA_MAX_OFFSET);
+        dda.offset + (target - dda.offset).clamp(-DDA_STEP, DDA_STEP)
+    } else {
+        dda.offset
+    };
+    if waves.wave != *last_wave {
+        *last_wave = waves.wave;
+        *stats = RunStats::default();
+    }
+    if target == dda.offset {
+        return;
+    }
+
+    let delta = target - dda.offset;
+    dda.offset = target;
+    difficulty.level = (difficulty.level + delta).clamp(0.1, MAX_DIFFICULTY);
+    difficulty.speed_multiplier =
+        (difficulty.speed_multiplier + delta * DDA_SPEED_SHARE).clamp(0.1, MAX_SPEED_MULTIPLIER);
+    if cheats.enabled {
+        info!(
+            "adaptive difficulty offset {:.2} (level {:.2}, speed x{:.2})",
+            dda.offset, difficulty.level, difficulty.speed_multiplier
+        );
+    }
+}
+
+fn spawn_enemies(
+    mut commands: Commands,
+    mut director: ResMut<SpawnDirector>,
+    (difficulty, base, entrances): (Res<Difficulty>, Res<BaseDifficulty>, Res<EntranceConfig>),
+    mut waves: ResMut<WaveManager>,
+    (enemy_query, portal_query): (Query<&Enemy>, Query<&Portal>),
+    (mut rng, mut heatmap): (ResMut<GameRng>, ResMut<SpawnHeatmap>),
+    (time, play_area): (Res<Time>, Res<PlayArea>),
+) {
+    let level = difficulty.level * base.0;
+    director.budget = (director.budget
+        + director.balance.refill_per_sec * level * time.delta_seconds())
+    .min(director.balance.max_budget);
+
+    // Enemies still waiting behind a portal already count as on screen.
+    let mut on_screen_cost: f32 = enemy_query
+        .iter()
+        .map(|enemy| enemy.kind.cost())
+        .sum::<f32>()
+        + portal_query
+            .iter()
+            .filter(|portal| !portal.emerged)
+            .map(|portal| portal.spawn.cost())
+            .sum::<f32>();
+    let portals = waves.uses_portals(&director.balance);
+    let target_cost = director.balance.target_on_screen_cost * level;
+    let rng = &mut rng.0;
+
+    // Costlier spawns are rolled up front and saved for, rather than skipped when unaffordable.
+    for _ in 0..director.balance.max_spawns_per_frame {
+        if waves.remaining() == 0 {
+            break;
+        }
+        // A formation that would overflow the wave is swapped for a single enemy.
+        if director.next_spawn.enemy_count() > waves.remaining() {
+            director.next_spawn = SpawnChoice::Single(EnemyKind::Basic);
+        }
+
+        let cost = director.next_spawn.cost();
+        if director.budget < cost || on_screen_cost + cost > target_cost {
+            break;
+        }
+
+        let half_width = match director.next_spawn {
+            SpawnChoice::Single(_) => 350.0,
+            SpawnChoice::Formation | SpawnChoice::Swarm => 260.0,
+        };
+        let x = balanced_spawn_x(&mut director, rng, half_width);
+        let position = if portals {
+            let position = Vec2::new(x, PORTAL_Y);
+            spawn_portal(&mut commands, director.next_spawn, position);
+            position
+        } else {
+            // A portal is an entrance of its own, so only these formations fly in.
+            let position = Vec2::new(x, play_area.enemy_spawn_y(&director.balance));
+            let entrance = entrances.for_wave(waves.wave);
+            spawn_choice(&mut commands, director.next_spawn, position, entrance);
+            position
+        };
+        heatmap.0.push((position, time.elapsed_seconds()));
+        director.budget -= cost;
+        on_screen_cost += cost;
+        waves.spawned += director.next_spawn.enemy_count();
+
+        director.next_spawn = roll_spawn_choice(
+            rng,
+            &director.balance.spawn_weights,
+            difficulty.level,
+            &director.unlocked,
+        );
+    }
+}
+
+/// Unlocks the kinds due by the current wave, announcing each one that arrives after
+/// the start of the run.
+fn unlock_enemy_kinds(
+    mut commands: Commands,
+    mut director: ResMut<SpawnDirector>,
+    waves: Res<WaveManager>,
+) {
+    let starting = director.unlocked.is_empty();
+    let mut announced = 0;
+    for kind in EnemyKind::ALL {
+        if kind.unlock_wave() > waves.wave || !director.unlocked.insert(kind) || starting {
+            continue;
+        }
+        commands.spawn((
+            Text2dBundle {
+                text: Text::from_section(
+                    format!("New Enemy: {}", kind.name()),
+                    TextStyle {
+                        font_size: 32.0,
+                        color: NEW_ENEMY_COLOR,
+                        ..default()
+                    },
+                ),
+                transform: Transform::from_xyz(
+                    0.0,
+                    -20.0 - 40.0 * announced as f32,
+                    layers::PARTICLES,
+                ),
+                ..default()
+            },
+            ChainPopup(Timer::from_seconds(NEW_ENEMY_TOAST_SECS, TimerMode::Once)),
+        ));
+        announced += 1;
+    }
+}
+
+fn spawn_timeline(
+    mut commands: Commands,
+    mut timeline: ResMut<SpawnTimeline>,
+    director: Res<SpawnDirector>,
+    play_area: Res<PlayArea>,
+    time: Res<Time>,
+) {
+    let y = play_area.enemy_spawn_y(&director.balance);
+    for event in timeline.advance(time.delta_seconds()) {
+        spawn_choice(&mut commands, event.spawn, Vec2::new(event.x, y), None);
+    }
+}
+
+fn no_boss(boss_query: Query<(), With<Boss>>) -> bool {
+    boss_query.is_empty()
+}
+
+fn grace_period_over(grace: Res<GracePeriod>) -> bool {
+    grace.0.finished()
+}
+
+fn survival_bonus(
+    mut bonus: ResMut<SurvivalBonus>,
+    mut score: ResMut<Score>,
+    scoring: Res<ScoringConfig>,
+    difficulty: Res<Difficulty>,
+    time: Res<Time>,
+) {
+    bonus.timer.tick(time.delta());
+    let seconds = bonus.timer.times_finished_this_tick();
+    if seconds == 0 {
+        return;
+    }
+
+    bonus.carry += scoring.survival_per_sec * difficulty.level * seconds as f32;
+    let points = bonus.carry.floor();
+    bonus.carry -= points;
+    bonus.earned += points as u32;
+    score.0 += points as u32;
+}
+
+fn tick_grace_period(mut grace: ResMut<GracePeriod>, time: Res<Time>) {
+    grace.0.tick(time.delta());
+}
+
+#[allow(clippy::type_complexity)]
+fn update_waves(
+    mut commands: Commands,
+    mut waves: ResMut<WaveManager>,
+    enemy_query: Query<(), Or<(With<Enemy>, With<Portal>, With<MiniBoss>)>>,
+    boss_query: Query<(), With<Boss>>,
+    (director, boss_config, scoring): (Res<SpawnDirector>, Res<BossConfig>, Res<ScoringConfig>),
+    (mut score, time): (ResMut<Score>, Res<Time>),
+) {
+    if waves.remaining() > 0 || !enemy_query.is_empty() {
+        return;
+    }
+    // A mini-boss waits for any boss fight still going before it closes out the wave.
+    if !waves.mini_boss_spawned
+        && let Some(mini_boss) = boss_config
+            .mini_bosses
+            .iter()
+            .find(|mini_boss| mini_boss.wave == waves.wave)
+    {
+        if boss_query.is_empty() {
+            spawn_mini_boss(&mut commands, mini_boss);
+            waves.mini_boss_spawned = true;
+        }
+        return;
+    }
+
+    let wave = waves.wave;
+    let break_secs = director.balance.wave_break_secs;
+    if waves.break_timer.is_none() {
+        score.0 += scoring.wave_clear;
+    }
+    if waves.break_timer.is_none() && break_secs > 0.0 {
+        commands.spawn((
+            Text2dBundle {
+                text: Text::from_section(
+                    format!("WAVE {wave} CLEARED"),
+                    TextStyle {
+                        font_size: 40.0,
+                        color: WAVE_CLEARED_COLOR,
+                        ..default()
+                    },
+                ),
+                transform: Transform::from_xyz(0.0, 40.0, layers::PARTICLES),
+                ..default()
+            },
+            ChainPopup(Timer::from_seconds(
+                break_secs.min(WAVE_CLEARED_BANNER_SECS),
+                TimerMode::Once,
+            )),
+        ));
+    }
+    let timer = waves
+        .break_timer
+        .get_or_insert_with(|| Timer::from_seconds(break_secs, TimerMode::Once));
+    if timer.tick(time.delta()).finished() {
+        *waves = WaveManager::new(wave + 1);
+    }
+}
+
+/// A spawn's x, somewhere within `half_width` of the centre. With
+/// `Balance::spawn_side_balancing` on, a spawn on the side recent spawns have
+/// favoured may be mirrored to the other, more likely the stronger the lean.
+fn balanced_spawn_x(director: &mut SpawnDirector, rng: &mut StdRng, half_width: f32) -> f32 {
+    let mut x = rng.gen_range(-half_width..half_width);
+    let flip_chance = director.balance.spawn_side_balancing * director.lean.abs();
+    // Nothing extra is drawn at zero, so pure random spawns roll as they always have.
+    if flip_chance > 0.0
+        && x.signum() == director.lean.signum()
+        && rng.gen_bool(flip_chance.min(1.0) as f64)
+    {
+        x = -x;
+    }
+    director.lean = director.lean * SPAWN_LEAN_CARRY + x.signum() * (1.0 - SPAWN_LEAN_CARRY);
+    x
+}
+
+/// Only kinds in `unlocked` are picked; a swarm waits for swarmers to be unlocked,
+/// and formations are made of basic enemies so are always open.
+fn roll_spawn_choice(
+    rng: &mut StdRng,
+    weights: &SpawnWeights,
+    level: f32,
+    unlocked: &HashSet<EnemyKind>,
+) -> SpawnChoice {
+    let roll = rng.gen_range(0.0..1.0);
+    if roll < FORMATION_SPAWN_CHANCE {
+        return SpawnChoice::Formation;
+    }
+    if roll < FORMATION_SPAWN_CHANCE + SWARM_SPAWN_CHANCE && unlocked.contains(&EnemyKind::Swarmer)
+    {
+        return SpawnChoice::Swarm;
+    }
+
+    let kinds = [
+        (EnemyKind::Basic, weights.basic),
+        (EnemyKind::Miner, weights.miner),
+        (EnemyKind::Diver, weights.diver),
+        (EnemyKind::Gunner, weights.gunner),
+        (EnemyKind::Reflector, weights.reflector),
+        (EnemyKind::Armored, weights.armored),
+        (EnemyKind::Cloaker, weights.cloaker),
+        (EnemyKind::Thief, weights.thief),
+        (EnemyKind::Carrier, weights.carrier),
+        (EnemyKind::Tractor, weights.tractor),
+        (EnemyKind::Regenerator, weights.regenerator),
+    ]
+    .map(|(kind, weight)| {
+        let weight = if unlocked.contains(&kind) {
+            weight.at(level)
+        } else {
+            0.0
+        };
+        (kind, weight)
+    });
+    SpawnChoice::Single(weighted_pick(rng, &kinds).unwrap_or(EnemyKind::Basic))
+}
+
+/// Picks one option with odds in proportion to its weight, or `None` if every weight
+/// is zero.
+fn weighted_pick<T: Copy>(rng: &mut StdRng, options: &[(T, f32)]) -> Option<T> {
+    let total: f32 = options.iter().map(|(_, weight)| weight).sum();
+    if total <= 0.0 {
+        return None;
+    }
+    let mut roll = rng.gen_range(0.0..total);
+    for &(option, weight) in options {
+        if roll < weight {
+            return Some(option);
+        }
+        roll -= weight;
+    }
+    // Rounding can leave a sliver past the end, which belongs to the last real option.
+    options
+        .iter()
+        .rev()
+        .find(|(_, weight)| *weight > 0.0)
+        .map(|(option, _)| *option)
+}
+
+fn spawn_choice(
+    commands: &mut Commands,
+    choice: SpawnChoice,
+    position: Vec2,
+    entrance: Option<&EntrancePath>,
+) {
+    match choice {
+        SpawnChoice::Single(kind) => {
+            spawn_enemy_at(commands, kind, position);
+        }
+        SpawnChoice::Formation => spawn_formation(commands, position, entrance),
+        SpawnChoice::Swarm => {
+            // A ring of slots, so the swarm starts out spread rather than stacked.
+            for i in 0..SWARM_SIZE {
+                let offset = Vec2::from_angle(TAU * i as f32 / SWARM_SIZE as f32) * 35.0;
+                spawn_enemy_at(commands, EnemyKind::Swarmer, position + offset);
+            }
+        }
+    }
+}
+
+fn spawn_portal(commands: &mut Commands, spawn: SpawnChoice, position: Vec2) {
+    commands.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                custom_size: Some(Vec2::splat(PORTAL_SIZE)),
+                color: PORTAL_COLOR,
+                ..default()
+            },
+            transform: Transform::from_translation(position.extend(layers::PORTALS))
+                .with_scale(Vec3::ZERO),
+            ..default()
+        },
+        Portal {
+            spawn,
+            timer: Timer::from_seconds(PORTAL_SECS, TimerMode::Once),
+            emerged: false,
+        },
+    ));
+}
+
+/// Swells each portal open and shut again, releasing its enemy at full size. Reduce
+/// motion keeps the swell but drops the spin.
+fn portal_update(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut Portal, &mut Transform)>,
+    settings: Res<Settings>,
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+) {
+    let delta = time.delta().mul_f32(time_scale.0);
+    for (entity, mut portal, mut transform) in query.iter_mut() {
+        portal.timer.tick(delta);
+        let progress = portal.timer.percent();
+        transform.scale = Vec3::splat((progress * PI).sin());
+        if !settings.reduce_motion {
+            transform.rotate_z(PORTAL_SPIN * delta.as_secs_f32());
+        }
+
+        if !portal.emerged && progress >= 0.5 {
+            portal.emerged = true;
+            spawn_choice(
+                &mut commands,
+                portal.spawn,
+                transform.translation.truncate(),
+                None,
+            );
+        }
+        if portal.timer.finished() {
+            commands.despawn_if_exists(entity);
+        }
+    }
+}
+
+fn spawn_formation(commands: &mut Commands, position: Vec2, entrance: Option<&EntrancePath>) {
+    let enter = |commands: &mut Commands, enemy: Entity, slot: Vec2, flip: bool| {
+        let Some(path) = entrance else {
+            return;
+        };
+        let curve = path.curve(slot, flip);
+        let start = curve.position(0.0).extend(layers::ENEMIES);
+        commands.entity(enemy).insert((
+            Entering {
+                curve,
+                timer: Timer::from_seconds(path.secs, TimerMode::Once),
+            },
+            Transform::from_translation(start),
+        ));
+    };
+
+    let leader = spawn_enemy_at(commands, EnemyKind::Basic, position);
+    promote_to_leader(commands, leader);
+    enter(commands, leader, position, false);
+
+    for offset in FORMATION_OFFSETS {
+        let member = spawn_enemy_at(commands, EnemyKind::Basic, position + offset);
+        commands.entity(member).insert(FormationMember { leader });
+        enter(commands, member, position + offset, offset.x > 0.0);
+    }
+}
+
+/// Breaks up formations worn down to `Balance::formation_break_at` enemies, counting
+/// the leader while it lives, sending the survivors into a diver's attack run. Those
+/// still flying in start theirs once they reach their slot.
+fn break_formations(
+    mut commands: Commands,
+    leader_query: Query<Entity, (With<Leader>, Without<Breakaway>)>,
+    member_query: Query<(Entity, &FormationMember)>,
+    director: Res<SpawnDirector>,
+) {
+    let threshold = director.balance.formation_break_at as usize;
+    if threshold == 0 {
+        return;
+    }
+    let mut formations: HashMap<Entity, Vec<Entity>> = HashMap::new();
+    for leader in leader_query.iter() {
+        formations.entry(leader).or_default().push(leader);
+    }
+    for (member, formation) in member_query.iter() {
+        formations.entry(formation.leader).or_default().push(member);
+    }
+    for survivors in formations.into_values() {
+        if survivors.len() > threshold {
+            continue;
+        }
+        for enemy in survivors {
+            commands
+                .entity(enemy)
+                .remove::<(FormationMember, Scatter)>()
+                .try_insert((
+                    Dive::Telegraph(Timer::from_seconds(DIVE_TELEGRAPH_SECS, TimerMode::Once)),
+                    Breakaway,
+                ));
+        }
+    }
+}
+
+/// Marches each formation from side to side as one, on top of its descent, turning
+/// and stepping down whenever its outermost enemy reaches the edge of the play area.
+/// A formation waits until every member has flown in, and holds while any of it is
+/// frozen, so no one drifts out of line. Once its leader is gone or it breaks up, what
+/// is left moves on its own.
+#[allow(clippy::type_complexity)]
+fn formation_sweep(
+    mut leader_query: Query<
+        (Entity, &mut Sweep, &mut Transform, &Sprite),
+        (Without<Breakaway>, Without<Entering>, Without<Frozen>),
+    >,
+    mut member_query: Query<
+        (
+            &FormationMember,
+            &mut Transform,
+            &Sprite,
+            Has<Entering>,
+            Has<Frozen>,
+        ),
+        Without<Sweep>,
+    >,
+    (play_area, director): (Res<PlayArea>, Res<SpawnDirector>),
+    (difficulty, base, modifiers): (Res<Difficulty>, Res<BaseDifficulty>, Res<Modifiers>),
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+) {
+    let balance = &director.balance;
+    if balance.formation_sweep_speed <= 0.0 {
+        return;
+    }
+    let speed = balance.formation_sweep_speed
+        * difficulty.speed_multiplier
+        * base.0
+        * modifiers.enemy_speed();
+    let dx = speed * time.delta_seconds() * time_scale.0;
+    let edge = play_area.half_size.x;
+
+    for (leader, mut sweep, mut leader_transform, leader_sprite) in leader_query.iter_mut() {
+        let extent = |transform: &Transform, sprite: &Sprite| {
+            let half_width = sprite.custom_size.unwrap_or_default().x / 2.0;
+            let x = transform.translation.x;
+            (x - half_width, x + half_width)
+        };
+        let (mut left, mut right) = extent(&leader_transform, leader_sprite);
+        let mut settled = true;
+        for (formation, transform, sprite, entering, frozen) in member_query.iter() {
+            if formation.leader != leader {
+                continue;
+            }
+            settled &= !entering && !frozen;
+            let (member_left, member_right) = extent(transform, sprite);
+            left = left.min(member_left);
+            right = right.max(member_right);
+        }
+        if !settled {
+            continue;
+        }
+
+        let step = sweep.direction * dx;
+        // Only the edge it is heading for turns it, so one that arrived partly past an
+        // edge marches back in rather than stepping down on every frame.
+        let at_edge = if sweep.direction > 0.0 {
+            right + step > edge
+        } else {
+            left + step < -edge
+        };
+        let offset = if at_edge {
+            sweep.direction = -sweep.direction;
+            Vec3::new(0.0, -balance.formation_sweep_step, 0.0)
+        } else {
+            Vec3::new(step, 0.0, 0.0)
+        };
+        leader_transform.translation += offset;
+        for (formation, mut transform, ..) in member_query.iter_mut() {
+            if formation.leader == leader {
+                transform.translation += offset;
+            }
+        }
+    }
+}
+
+/// Flies entering enemies along their curves, handing them back to `enemy_movement`
+/// on arrival. They can be shot on the way in like any other enemy.
+fn formation_entrance(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut Entering, &mut Transform)>,
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+) {
+    for (entity, mut entering, mut transform) in query.iter_mut() {
+        entering.timer.tick(time.delta().mul_f32(time_scale.0));
+        let position = entering.curve.position(entering.timer.percent());
+        transform.translation = position.extend(transform.translation.z);
+        if entering.timer.finished() {
+            commands.entity(entity).remove::<Entering>();
+        }
+    }
+}
+
+fn promote_to_leader(commands: &mut Commands, enemy: Entity) {
+    commands.entity(enemy).insert((
+        Leader,
+        Sweep { direction: 1.0 },
+        Sprite {
+            custom_size: Some(LEADER_SIZE),
+            color: LEADER_COLOR,
+            ..default()
+        },
+    ));
+}
+
+fn spawn_enemy_at(commands: &mut Commands, kind: EnemyKind, position: Vec2) -> Entity {
+    let mut enemy = commands.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                custom_size: Some(kind.size()),
+                // Brought up by `enemy_tint`, at once with reduce motion on.
+                color: kind.color().with_a(0.0),
+                ..default()
+            },
+            transform: Transform::from_translation(position.extend(layers::ENEMIES)),
+            ..default()
+        },
+        Enemy {
+            speed: ENEMY_SPEED,
+            kind,
+            health: kind.max_health(),
+        },
+        Heading { last_x: position.x },
+        SpawnFade(Timer::from_seconds(SPAWN_FADE_SECS, TimerMode::Once)),
+    ));
+    match kind {
+        EnemyKind::Miner => {
+            enemy.insert(MineLayer {
+                timer: Timer::from_seconds(1.5, TimerMode::Repeating),
+            });
+        }
+        EnemyKind::Diver => {
+            enemy.insert(Dive::Entering);
+        }
+        EnemyKind::Gunner => {
+            enemy.insert(Gunner {
+                cooldown: Timer::from_seconds(GUNNER_FIRE_INTERVAL_SECS, TimerMode::Repeating),
+                wind_up: None,
+            });
+        }
+        EnemyKind::Reflector => {
+            let shield = Shield {
+                arc: REFLECTOR_SHIELD_ARC,
+            };
+            // The bar spans the chord of the arc, drawn just outside the enemy's front.
+            let width = 2.0 * 26.0 * (shield.arc / 2.0).min(FRAC_PI_2).sin();
+            enemy.insert(shield).with_children(|parent| {
+                parent.spawn(SpriteBundle {
+                    sprite: Sprite {
+                        custom_size: Some(Vec2::new(width, 6.0)),
+                        color: SHIELD_COLOR,
+                        ..default()
+                    },
+                    transform: Transform::from_xyz(0.0, -26.0, 0.1),
+                    ..default()
+                });
+            });
+        }
+        EnemyKind::Armored => {
+            let weak_point = WeakPoint {
+                offset: Vec2::new(0.0, -14.0),
+                phase: position.x,
+            };
+            let marker_position = weak_point.offset.extend(0.1);
+            enemy.insert(weak_point).with_children(|parent| {
+                parent.spawn((
+                    SpriteBundle {
+                        sprite: Sprite {
+                            custom_size: Some(WEAK_POINT_HALF_SIZE * 2.0),
+                            color: WEAK_POINT_COLOR,
+                            ..default()
+                        },
+                        transform: Transform::from_translation(marker_position),
+                        ..default()
+                    },
+                    WeakPointMarker,
+                ));
+            });
+        }
+        EnemyKind::Cloaker => {
+            enemy.insert(Cloak {
+                cloaked: false,
+                timer: Timer::from_seconds(CLOAK_VISIBLE_SECS, TimerMode::Once),
+            });
+        }
+        EnemyKind::Swarmer => {
+            enemy.insert(Swarm::default());
+        }
+        EnemyKind::Thief => {
+            enemy.insert(Thief::default());
+        }
+        EnemyKind::Carrier => {
+            // Heads for the middle first, so it sweeps the whole width.
+            enemy.insert(Carrier {
+                direction: if position.x > 0.0 { -1.0 } else { 1.0 },
+                ..default()
+            });
+        }
+        EnemyKind::Tractor => {
+            enemy.insert(Tractor::default()).with_children(|parent| {
+                parent.spawn((
+                    SpriteBundle {
+                        sprite: Sprite {
+                            color: TRACTOR_BEAM_COLOR,
+                            ..default()
+                        },
+                        visibility: Visibility::Hidden,
+                        ..default()
+                    },
+                    TractorBeam,
+                ));
+            });
+        }
+        EnemyKind::Regenerator => {
+            enemy.insert(Regenerator {
+                last_health: kind.max_health(),
+                ..default()
+            });
+        }
+        EnemyKind::Basic => {}
+    }
+    if kind.evasive() {
+        enemy.insert(Evasive::default());
+    }
+    enemy.id()
+}
+
+/// Boids-style steering for swarmers: each is drawn toward its neighbours, kept from
+/// crowding them, and pulled sideways toward the player, so the swarm weaves after
+/// the ship as one. Neighbours are found through a grid of `SWARM_NEIGHBOR_RADIUS`
+/// cells, checking only the cells around each swarmer.
+fn swarm_steering(
+    mut query: Query<(Entity, &mut Swarm, &Transform)>,
+    player_query: Query<&Transform, With<Player>>,
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+) {
+    let dt = time.delta_seconds() * time_scale.0;
+    let player_x = player_query
+        .get_single()
+        .map_or(0.0, |transform| transform.translation.x);
+    let swarmers: Vec<(Entity, Vec2)> = query
+        .iter()
+        .map(|(entity, _, transform)| (entity, transform.translation.truncate()))
+        .collect();
+    let cell = |position: Vec2| (position / SWARM_NEIGHBOR_RADIUS).floor().as_ivec2();
+    let mut grid: HashMap<IVec2, Vec<Vec2>> = HashMap::new();
+    for &(_, position) in &swarmers {
+        grid.entry(cell(position)).or_default().push(position);
+    }
+
+    for (entity, position) in swarmers {
+        let home = cell(position);
+        let neighbors = (-1..=1)
+            .flat_map(|x| (-1..=1).map(move |y| home + IVec2::new(x, y)))
+            .filter_map(|cell| grid.get(&cell))
+            .flatten()
+            .filter(|&&other| other != position && other.distance(position) < SWARM_NEIGHBOR_RADIUS)
+            .take(SWARM_MAX_NEIGHBORS);
+
+        let mut centre = Vec2::ZERO;
+        let mut separation = Vec2::ZERO;
+        let mut count = 0;
+        for &other in neighbors {
+            centre += other;
+            count += 1;
+            let away = position - other;
+            let distance = away.length();
+            if distance < SWARM_SEPARATION_RADIUS {
+                separation += away.normalize_or_zero() * (SWARM_SEPARATION_RADIUS - distance);
+            }
+        }
+        let mut steer =
+            Vec2::X * (player_x - position.x) * SWARM_PLAYER_PULL + separation * SWARM_SEPARATION;
+        if count > 0 {
+            steer += (centre / count as f32 - position) * SWARM_COHESION;
+        }
+
+        if let Ok((_, mut swarm, _)) = query.get_mut(entity) {
+            swarm.velocity = (swarm.velocity + steer * dt).clamp_length_max(SWARM_MAX_STEER_SPEED);
+        }
+    }
+}
+
+/// Steers evasive enemies out of the path of incoming shots, dodging the soonest
+/// one due to pass too close. Shots are found through a grid of `DODGE_CELL_SIZE`
+/// cells as in `swarm_steering`, and where each will pass is predicted from its
+/// velocity.
+#[allow(clippy::type_complexity)]
+fn dodge_bullets(
+    mut query: Query<(&mut Evasive, &mut Transform), (Without<Entering>, Without<Frozen>)>,
+    bullet_query: Query<(&Bullet, &Transform), Without<Evasive>>,
+    play_area: Res<PlayArea>,
+    (time, time_scale, settings): (Res<Time>, Res<TimeScale>, Res<Settings>),
+) {
+    let dt = time.delta_seconds() * time_scale.0;
+    let cell = |position: Vec2| (position / DODGE_CELL_SIZE).floor().as_ivec2();
+    let mut grid: HashMap<IVec2, Vec<(Vec2, Vec2)>> = HashMap::new();
+    if settings.dodging_enemies {
+        for (bullet, transform) in bullet_query.iter() {
+            let position = transform.translation.truncate();
+            grid.entry(cell(position))
+                .or_default()
+                .push((position, bullet.velocity));
+        }
+    }
+    let wrapping = settings.wrap_mode.enemies();
+    let half_width = play_area.half_size.x - 20.0;
+
+    for (mut evasive, mut transform) in query.iter_mut() {
+        let position = transform.translation.truncate();
+        let home = cell(position);
+        let threat = (-DODGE_CELLS_BELOW..=1)
+            .flat_map(|y| (-1..=1).map(move |x| home + IVec2::new(x, y)))
+            .filter_map(|cell| grid.get(&cell))
+            .flatten()
+            .take(DODGE_MAX_BULLETS)
+            .filter_map(|&(bullet, velocity)| {
+                let to_enemy = position - bullet;
+                let t = (to_enemy.dot(velocity) / velocity.length_squared().max(1.0))
+                    .clamp(0.0, DODGE_LOOKAHEAD_SECS);
+                let miss = to_enemy - velocity * t;
+                (t > 0.0 && miss.length() < DODGE_RADIUS).then_some((t, miss.x))
+            })
+            .min_by(|a, b| a.0.total_cmp(&b.0));
+
+        let target = match threat {
+            // Dead on: step away from the nearer wall.
+            Some((_, 0.0)) => -position.x.signum() * DODGE_SPEED,
+            Some((_, miss_x)) => miss_x.signum() * DODGE_SPEED,
+            None => 0.0,
+        };
+        let max_change = DODGE_ACCELERATION * dt;
+        evasive.velocity_x += (target - evasive.velocity_x).clamp(-max_change, max_change);
+        transform.translation.x += evasive.velocity_x * dt;
+        let x = transform.translation.x;
+        if !wrapping && x.abs() > half_width {
+            transform.translation.x = x.clamp(-half_width, half_width);
+            evasive.velocity_x = 0.0;
+        }
+    }
+}
+
+/// Moves carriers along their patrol and drops their enemies, which are ordinary
+/// basic enemies from then on. Until it reaches its patrol height a carrier comes
+/// down like any other enemy.
+#[allow(clippy::type_complexity)]
+fn carrier_deploy(
+    mut commands: Commands,
+    mut query: Query<
+        (Entity, &mut Carrier, &mut Enemy, &mut Transform),
+        (Without<Entering>, Without<Frozen>),
+    >,
+    play_area: Res<PlayArea>,
+    director: Res<SpawnDirector>,
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+) {
+    let dt = time.delta_seconds() * time_scale.0;
+    let (half_width, half_height) = (play_area.half_size.x - 45.0, play_area.half_size.y);
+
+    for (entity, mut carrier, mut enemy, mut transform) in query.iter_mut() {
+        if carrier.deploys_left == 0 {
+            transform.translation.y += CARRIER_SPEED * dt;
+            if transform.translation.y > half_height + 60.0 {
+                commands.despawn_if_exists(entity);
+            }
+            continue;
+        }
+        if transform.translation.y > CARRIER_PATROL_Y {
+            continue;
+        }
+        enemy.speed = 0.0;
+
+        transform.translation.x += carrier.direction * CARRIER_SPEED * dt;
+        let x = transform.translation.x;
+        if x.abs() > half_width {
+            transform.translation.x = x.clamp(-half_width, half_width);
+            carrier.direction = -x.signum();
+        }
+
+        carrier.since_deploy += dt;
+        if carrier.since_deploy >= director.balance.carrier_deploy_secs {
+            carrier.since_deploy = 0.0;
+            carrier.deploys_left -= 1;
+            let position = transform.translation.truncate() - Vec2::Y * CARRIER_DEPLOY_OFFSET;
+            spawn_enemy_at(&mut commands, EnemyKind::Basic, position);
+        }
+    }
+}
+
+/// Heals regenerators that have gone long enough without a hit, whatever the hit came
+/// from. A frozen regenerator neither heals nor recovers from its stagger.
+fn regenerate_enemies(
+    mut query: Query<(&mut Regenerator, &mut Enemy), Without<Frozen>>,
+    director: Res<SpawnDirector>,
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+) {
+    let delta = time.delta().mul_f32(time_scale.0);
+    let balance = &director.balance;
+    for (mut regenerator, mut enemy) in query.iter_mut() {
+        if enemy.health < regenerator.last_health {
+            regenerator.stagger = Some(Timer::from_seconds(
+                balance.regen_stagger_secs,
+                TimerMode::Once,
+            ));
+            regenerator.carry = 0.0;
+        }
+        if let Some(stagger) = &mut regenerator.stagger {
+            stagger.tick(delta);
+        }
+        let max_health = enemy.kind.max_health();
+        if !regenerator.staggered() && enemy.health < max_health {
+            regenerator.carry += balance.regen_per_sec * delta.as_secs_f32();
+            let healed = regenerator.carry as u32;
+            regenerator.carry -= healed as f32;
+            enemy.health = (enemy.health + healed).min(max_health);
+        }
+        regenerator.last_health = enemy.health;
+    }
+}
+
+/// Holds tractors at their hover height until they have hovered long enough.
+#[allow(clippy::type_complexity)]
+fn tractor_hover(
+    mut query: Query<(&mut Tractor, &mut Enemy, &Transform), (Without<Entering>, Without<Frozen>)>,
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+) {
+    let delta = time.delta().mul_f32(time_scale.0);
+    for (mut tractor, mut enemy, transform) in query.iter_mut() {
+        if transform.translation.y > TRACTOR_HOVER_Y || tractor.hover.finished() {
+            continue;
+        }
+        if enemy.speed > 0.0 {
+            tractor.speed = enemy.speed;
+            enemy.speed = 0.0;
+        }
+        if tractor.hover.tick(delta).finished() {
+            enemy.speed = tractor.speed;
+        }
+    }
+}
+
+/// Stretches each tractor's beam out to the ship while it pulls, brighter the
+/// stronger the pull.
+#[allow(clippy::type_complexity)]
+fn tractor_beams(
+    tractor_query: Query<(&Transform, &Children, Has<Entering>, Has<Frozen>), With<Tractor>>,
+    mut beam_query: Query<
+        (&mut Transform, &mut Sprite, &mut Visibility),
+        (With<TractorBeam>, Without<Tractor>),
+    >,
+    player_query: Query<&Transform, (With<Player>, Without<TractorBeam>)>,
+    director: Res<SpawnDirector>,
+) {
+    let player = player_query
+        .get_single()
+        .ok()
+        .map(|transform| transform.translation.truncate());
+    for (transform, children, entering, frozen) in tractor_query.iter() {
+        let tractor = transform.translation.truncate();
+        let strength = player.filter(|_| !entering && !frozen).and_then(|player| {
+            tractor_strength(tractor, player, director.balance.tractor_range)
+                .map(|strength| (player, strength))
+        });
+        for &child in children.iter() {
+            let Ok((mut beam_transform, mut sprite, mut visibility)) = beam_query.get_mut(child)
+            else {
+                continue;
+            };
+            let Some((player, strength)) = strength else {
+                *visibility = Visibility::Hidden;
+                continue;
+            };
+            let offset = player - tractor;
+            sprite.custom_size = Some(Vec2::new(TRACTOR_BEAM_WIDTH, offset.length()));
+            sprite.color.set_a(0.15 + 0.35 * strength);
+            // Worked out in world space, then taken into the tractor's own, since a
+            // spinning tractor turns its children with it.
+            let world =
+                Transform::from_translation((tractor + offset / 2.0).extend(layers::TRACTOR_BEAMS))
+                    .with_rotation(Quat::from_rotation_z(Vec2::Y.angle_between(offset)));
+            *beam_transform = Transform::from_matrix(
+                transform.compute_matrix().inverse() * world.compute_matrix(),
+            );
+            *visibility = Visibility::Inherited;
+        }
+    }
+}
+
+fn cloak_cycle(mut query: Query<&mut Cloak>, time: Res<Time>, time_scale: Res<TimeScale>) {
+    let delta = time.delta().mul_f32(time_scale.0);
+    for mut cloak in query.iter_mut() {
+        if cloak.timer.tick(delta).finished() {
+            cloak.cloaked = !cloak.cloaked;
+            let secs = if cloak.cloaked {
+                CLOAK_HIDDEN_SECS
+            } else {
+                CLOAK_VISIBLE_SECS
+            };
+            cloak.timer = Timer::from_seconds(secs, TimerMode::Once);
+        }
+    }
+}
+
+fn move_weak_points(
+    mut query: Query<(&mut WeakPoint, &Children)>,
+    mut marker_query: Query<&mut Transform, With<WeakPointMarker>>,
+    time: Res<Time>,
+) {
+    for (mut weak_point, children) in query.iter_mut() {
+        let angle = time.elapsed_seconds() * WEAK_POINT_SWAY_SPEED + weak_point.phase;
+        weak_point.offset.x = angle.sin() * WEAK_POINT_SWAY;
+        for &child in children.iter() {
+            if let Ok(mut transform) = marker_query.get_mut(child) {
+                transform.translation.x = weak_point.offset.x;
+            }
+        }
+    }
+}
+
+/// A short burst of sparks where a shot glanced off armour.
+fn spawn_sparks(commands: &mut Commands, position: Vec3, color: Color) {
+    let mut rng = rand::thread_rng();
+    for _ in 0..SPARK_COUNT {
+        let angle = rng.gen_range(0.0..TAU);
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    custom_size: Some(Vec2::new(4.0, 2.0)),
+                    color,
+                    ..default()
+                },
+                transform: Transform::from_translation(
+                    position.truncate().extend(layers::PARTICLES),
+                ),
+                ..default()
+            },
+            Particle {
+                velocity: Vec2::from_angle(angle) * rng.gen_range(80.0..160.0),
+                angular_velocity: 0.0,
+                gravity: 0.0,
+                lifetime: Timer::from_seconds(0.25, TimerMode::Once),
+            },
+        ));
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn enemy_movement(
+    mut query: Query<
+        (
+            &Enemy,
+            &mut Transform,
+            Option<&mut Dive>,
+            Option<&mut Scatter>,
+            Option<&mut Knockback>,
+            Option<&mut Swarm>,
+            Option<&Thief>,
+            Has<Breakaway>,
+        ),
+        (Without<Player>, Without<Entering>, Without<Frozen>),
+    >,
+    player_query: Query<&Transform, With<Player>>,
+    play_area: Res<PlayArea>,
+    time: Res<Time>,
+    (time_scale, settings): (Res<TimeScale>, Res<Settings>),
+    (difficulty, base, modifiers): (Res<Difficulty>, Res<BaseDifficulty>, Res<Modifiers>),
+    (mut rng, director): (ResMut<GameRng>, Res<SpawnDirector>),
+) {
+    let dt = time.delta_seconds() * time_scale.0;
+    let wrapping = settings.wrap_mode.enemies();
+    let speed_multiplier = difficulty.speed_multiplier * base.0 * modifiers.enemy_speed();
+    let player_position = player_query
+        .get_single()
+        .map_or(Vec3::new(0.0, -200.0, 0.0), |transform| {
+            transform.translation
+        });
+    let (half_width, half_height) = (play_area.half_size.x - 20.0, play_area.half_size.y);
+
+    for (enemy, mut transform, dive, scatter, knockback, swarm, thief, breakaway) in
+        query.iter_mut()
+    {
+        if let Some(mut knockback) = knockback {
+            // Knockback never carries an enemy off the sides, nor back up out of sight.
+            if transform.translation.y > half_height - 20.0 {
+                knockback.0.y = knockback.0.y.min(0.0);
+            }
+            transform.translation += (knockback.0 * dt).extend(0.0);
+            let x = transform.translation.x;
+            if !wrapping && x.abs() > half_width {
+                transform.translation.x = x.clamp(-half_width, half_width);
+                knockback.0.x = 0.0;
+            }
+            knockback.0 *= (-KNOCKBACK_DAMPING * dt).exp();
+        }
+
+        if wrapping {
+            // `half_width` is inset by the enemy's half size; wrap once it is fully off.
+            transform.translation.x = wrap(transform.translation.x, half_width + 40.0);
+        }
+
+        if let Some(mut scatter) = scatter {
+            if scatter
+                .timer
+                .tick(time.delta().mul_f32(time_scale.0))
+                .just_finished()
+            {
+                scatter.velocity_x = rng.0.gen_range(-SCATTER_SPEED..SCATTER_SPEED);
+            }
+            transform.translation.x += scatter.velocity_x * dt;
+
+            let x = transform.translation.x;
+            if !wrapping && x.abs() > half_width {
+                transform.translation.x = x.clamp(-half_width, half_width);
+                scatter.velocity_x = -scatter.velocity_x;
+            }
+        }
+
+        if let Some(mut swarm) = swarm {
+            transform.translation += (swarm.velocity * dt).extend(0.0);
+            let x = transform.translation.x;
+            if !wrapping && x.abs() > half_width {
+                transform.translation.x = x.clamp(-half_width, half_width);
+                swarm.velocity.x = 0.0;
+            }
+        }
+
+        let speed = enemy.speed * speed_multiplier;
+        if let Some(thief) = thief {
+            if thief.stolen.is_some() {
+                transform.translation.y += THIEF_FLEE_SPEED * dt;
+                continue;
+            }
+            let max_step = THIEF_CHASE_SPEED * dt;
+            transform.translation.x +=
+                (player_position.x - transform.translation.x).clamp(-max_step, max_step);
+        }
+        let Some(mut dive) = dive else {
+            transform.translation.y -= speed * dt;
+            continue;
+        };
+
+        match &mut *dive {
+            Dive::Entering => {
+                transform.translation.y -= speed * dt;
+                if transform.translation.y < DIVE_START_Y {
+                    *dive =
+                        Dive::Telegraph(Timer::from_seconds(DIVE_TELEGRAPH_SECS, TimerMode::Once));
+                }
+            }
+            Dive::Telegraph(timer) => {
+                if timer.tick(time.delta().mul_f32(time_scale.0)).finished() {
+                    *dive = Dive::Diving {
+                        target_x: player_position.x,
+                        speed,
+                    };
+                }
+            }
+            Dive::Diving { target_x, speed } => {
+                let acceleration = if breakaway {
+                    director.balance.formation_dive_acceleration
+                } else {
+                    DIVE_ACCELERATION
+                };
+                *speed += acceleration * dt;
+                let max_step = DIVE_HORIZONTAL_SPEED * dt;
+                transform.translation.x +=
+                    (*target_x - transform.translation.x).clamp(-max_step, max_step);
+                transform.translation.y -= *speed * dt;
+
+                if transform.translation.y < player_position.y {
+                    *dive = Dive::Resumed;
+                }
+            }
+            Dive::Resumed => {
+                transform.translation.y -= speed * dt;
+            }
+        }
+    }
+}
+
+/// Flips enemies to face the way they are drifting. Enemies moving straight down keep
+/// whichever way they last faced.
+fn enemy_facing(mut query: Query<(&mut Heading, &mut Sprite, &Transform), Changed<Transform>>) {
+    for (mut heading, mut sprite, transform) in query.iter_mut() {
+        let dx = transform.translation.x - heading.last_x;
+        heading.last_x = transform.translation.x;
+
+        // A jump this big is a screen wrap, not movement.
+        if dx == 0.0 || dx.abs() > 100.0 {
+            continue;
+        }
+        let facing_left = dx < 0.0;
+        if sprite.flip_x != facing_left {
+            sprite.flip_x = facing_left;
+        }
+    }
+}
+
+/// Rolls each new enemy's size, tilt, spin and brightness so swarms look less uniform.
+/// Shields and weak points only guard one side, so their enemies don't spin.
+#[allow(clippy::type_complexity)]
+fn vary_enemy_sprites(
+    mut commands: Commands,
+    mut query: Query<
+        (
+            Entity,
+            &mut Sprite,
+            &mut Transform,
+            Has<Shield>,
+            Has<WeakPoint>,
+        ),
+        Added<Enemy>,
+    >,
+    mut rng: ResMut<GameRng>,
+    director: Res<SpawnDirector>,
+) {
+    let base = Vec2::from(director.balance.enemy_size) / EnemyKind::Basic.size();
+
+    for (entity, mut sprite, mut transform, shielded, weak_point) in query.iter_mut() {
+        let scale = 1.0 + rng.0.gen_range(-ENEMY_SIZE_JITTER..=ENEMY_SIZE_JITTER);
+        sprite.custom_size = sprite.custom_size.map(|size| size * base * scale);
+        transform.rotation =
+            Quat::from_rotation_z(rng.0.gen_range(-ENEMY_TILT_JITTER..=ENEMY_TILT_JITTER));
+        let mut enemy = commands.entity(entity);
+        enemy.insert(SpriteJitter {
+            brightness: 1.0 + rng.0.gen_range(-ENEMY_TINT_JITTER..=ENEMY_TINT_JITTER),
+        });
+        if !shielded && !weak_point {
+            let direction = if rng.0.gen_bool(0.5) { 1.0 } else { -1.0 };
+            enemy.insert(Spin {
+                speed: direction * rng.0.gen_range(ENEMY_SPIN_SPEED),
+            });
+        }
+    }
+}
+
+/// Reduce motion leaves enemies at their spawn tilt.
+fn spin_enemies(
+    mut query: Query<(&Spin, &mut Transform), Without<Frozen>>,
+    settings: Res<Settings>,
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+) {
+    if settings.reduce_motion {
+        return;
+    }
+    let delta = time.delta_seconds() * time_scale.0;
+    for (spin, mut transform) in query.iter_mut() {
+        transform.rotate_z(spin.speed * delta);
+    }
+}
+
+/// The only system that sets enemy colours, so the palette setting, the damage fade
+/// and the diver telegraph can't fight over them.
+#[allow(clippy::type_complexity)]
+fn enemy_tint(
+    settings: Res<Settings>,
+    time: Res<Time>,
+    mut query: Query<(
+        &Enemy,
+        &mut Sprite,
+        &Transform,
+        Option<&Dive>,
+        Option<&Gunner>,
+        Has<Leader>,
+        Option<&SpriteJitter>,
+        Option<&Cloak>,
+        Has<Burning>,
+        Has<Frozen>,
+        Option<&SpawnFade>,
+        Option<&Regenerator>,
+    )>,
+) {
+    for (
+        enemy,
+        mut sprite,
+        transform,
+        dive,
+        gunner,
+        is_leader,
+        jitter,
+        cloak,
+        burning,
+        frozen,
+        spawn_fade,
+        regenerator,
+    ) in query.iter_mut()
+    {
+        let winding_up = gunner
+            .and_then(|gunner| gunner.wind_up.as_ref())
+            .is_some_and(|wind_up| (wind_up.elapsed_secs() * 25.0).sin() > 0.0);
+
+        if matches!(dive, Some(Dive::Telegraph(_))) || winding_up {
+            sprite.color = Color::WHITE;
+            continue;
+        }
+
+        let base = if is_leader && settings.colorblind {
+            LEADER_COLORBLIND_COLOR
+        } else if is_leader {
+            LEADER_COLOR
+        } else if settings.colorblind {
+            enemy.kind.colorblind_color()
+        } else {
+            enemy.kind.color()
+        };
+        let damaged = if settings.colorblind {
+            let [r, g, b, a] = base.as_rgba_f32();
+            let k = COLORBLIND_DAMAGED_BRIGHTNESS;
+            Color::rgba(r * k, g * k, b * k, a)
+        } else {
+            DAMAGED_ENEMY_COLOR
+        };
+        let damage = 1.0 - enemy.health as f32 / enemy.kind.max_health() as f32;
+        let mut color = lerp_color(base, damaged, damage);
+        if burning {
+            color = lerp_color(color, FLAME_COLOR, BURN_TINT);
+        }
+        // Reduce motion holds the healing glow steady instead of pulsing it.
+        if let Some(regenerator) = regenerator {
+            if regenerator.staggered() {
+                color = lerp_color(color, STAGGERED_COLOR, STAGGER_TINT);
+            } else if damage > 0.0 {
+                let glow = if settings.reduce_motion {
+                    0.5
+                } else {
+                    (time.elapsed_seconds() * REGEN_PULSE_SPEED).sin() * 0.5 + 0.5
+                };
+                color = lerp_color(color, REGEN_COLOR, glow);
+            }
+        }
+        if frozen {
+            color = lerp_color(color, FROZEN_COLOR, FROZEN_TINT);
+        }
+        sprite.color = match jitter {
+            Some(jitter) => {
+                let [r, g, b, a] = color.as_rgba_f32();
+                let k = jitter.brightness;
+                Color::rgba((r * k).min(1.0), (g * k).min(1.0), (b * k).min(1.0), a)
+            }
+            None => color,
+        };
+
+        // A faint shimmer gives a cloaked enemy away; reduce motion holds it steady.
+        if cloak.is_some_and(|cloak| cloak.cloaked) {
+            let shimmer = if settings.reduce_motion {
+                0.0
+            } else {
+                let phase = time.elapsed_seconds() * CLOAK_SHIMMER_SPEED + transform.translation.x;
+                phase.sin() * CLOAK_SHIMMER
+            };
+            sprite.color.set_a(CLOAK_ALPHA + shimmer);
+        }
+        if let Some(spawn_fade) = spawn_fade
+            && !settings.reduce_motion
+        {
+            let alpha = sprite.color.a();
+            sprite.color.set_a(alpha * spawn_fade.0.percent());
+        }
+    }
+}
+
+/// Lets enemies finish fading in; reduce motion skips the fade.
+fn spawn_fades(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut SpawnFade)>,
+    settings: Res<Settings>,
+    (time, time_scale): (Res<Time>, Res<TimeScale>),
+) {
+    for (entity, mut spawn_fade) in query.iter_mut() {
+        if settings.reduce_motion
+            || spawn_fade
+                .0
+                .tick(time.delta().mul_f32(time_scale.0))
+                .finished()
+        {
+            commands.entity(entity).remove::<SpawnFade>();
+        }
+    }
+}
+
+/// Scales new shots from their kind's size to the balance's standard shot size.
+fn size_bullets(
+    director: Res<SpawnDirector>,
+    mut query: Query<(&Bullet, &mut Sprite), Added<Bullet>>,
+) {
+    let base = Vec2::from(director.balance.bullet_size) / BulletKind::Standard.size();
+    for (bullet, mut sprite) in query.iter_mut() {
+        sprite.custom_size = Some(bullet.kind.size() * base);
+    }
+}
+
+fn bullet_tint(settings: Res<Settings>, mut query: Query<(&Bullet, &mut Sprite)>) {
+    for (bullet, mut sprite) in query.iter_mut() {
+        sprite.color = if settings.colorblind {
+            bullet.kind.colorblind_color()
+        } else {
+            bullet.kind.color()
+        };
+    }
+}
+
+fn enemy_bullet_tint(settings: Res<Settings>, mut query: Query<(&EnemyBullet, &mut Sprite)>) {
+    for (bullet, mut sprite) in query.iter_mut() {
+        sprite.color = if settings.colorblind {
+            bullet.kind.colorblind_color()
+        } else {
+            bullet.kind.color()
+        };
+    }
+}
+
+/// A darkened copy of `owner`'s sprite drawn offset beneath it. Purely decorative:
+/// nothing collides with a shadow.
+#[derive(Component)]
+struct Shadow {
+    owner: Entity,
+}
+
+/// Gives every ship and pickup without a shadow one, or takes them all away while
+/// shadows are off.
+#[allow(clippy::type_complexity)]
+fn spawn_shadows(
+    mut commands: Commands,
+    settings: Res<Settings>,
+    owner_query: Query<Entity, Or<(With<Enemy>, With<Player>, With<PowerUp>)>>,
+    shadow_query: Query<(Entity, &Shadow)>,
+) {
+    if !settings.shadows {
+        for (entity, _) in shadow_query.iter() {
+            commands.despawn_if_exists(entity);
+        }
+        return;
+    }
+    let shadowed: HashSet<Entity> = shadow_query
+        .iter()
+        .map(|(_, shadow)| shadow.owner)
+        .collect();
+    for owner in owner_query.iter().filter(|owner| !shadowed.contains(owner)) {
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color: Color::NONE,
+                    ..default()
+                },
+                ..default()
+            },
+            Shadow { owner },
+        ));
+    }
+}
+
+/// Keeps each shadow under its owner, matching its shape and fading with it, and
+/// clears away shadows whose owner is gone.
+fn shadow_update(
+    mut commands: Commands,
+    mut shadow_query: Query<(Entity, &Shadow, &mut Transform, &mut Sprite)>,
+    owner_query: Query<(&Transform, &Sprite), Without<Shadow>>,
+) {
+    for (entity, shadow, mut transform, mut sprite) in shadow_query.iter_mut() {
+        let Ok((owner_transform, owner_sprite)) = owner_query.get(shadow.owner) else {
+            commands.despawn_if_exists(entity);
+            continue;
+        };
+        *transform = Transform {
+            translation: (owner_transform.translation.truncate() + SHADOW_OFFSET)
+                .extend(layers::SHADOWS),
+            ..*owner_transform
+        };
+        sprite.custom_size = owner_sprite.custom_size;
+        sprite.flip_x = owner_sprite.flip_x;
+        sprite.flip_y = owner_sprite.flip_y;
+        sprite.color = Color::rgba(0.0, 0.0, 0.0, SHADOW_ALPHA * owner_sprite.color.a());
+    }
+}
+
+/// Floats over an enemy that has taken a hit but not yet died. A separate entity
+/// rather than a child, so it stays level while its enemy spins.
+#[derive(Component)]
+struct HealthBar {
+    owner: Entity,
+}
+
+/// Gives a bar to every enemy that can take more than one hit once it has taken one,
+/// and to a mini-boss from the start, keeps each bar over its owner, and clears away
+/// bars whose owner is gone.
+#[allow(clippy::type_complexity)]
+fn enemy_health_bar(
+    mut commands: Commands,
+    enemy_query: Query<(Entity, &Enemy, &Transform, &Sprite), Without<HealthBar>>,
+    mini_boss_query: Query<
+        (Entity, &Boss, &Transform, &Sprite),
+        (With<MiniBoss>, Without<HealthBar>),
+    >,
+    mut bar_query: Query<(Entity, &HealthBar, &mut Transform, &mut Sprite)>,
+) {
+    let mut tracked = HashSet::new();
+    for (entity, bar, mut transform, mut sprite) in bar_query.iter_mut() {
+        let owner = enemy_query
+            .get(bar.owner)
+            .map(|(_, enemy, transform, sprite)| (enemy_health(enemy), transform, sprite))
+            .or_else(|_| {
+                mini_boss_query
+                    .get(bar.owner)
+                    .map(|(_, boss, transform, sprite)| (boss_health(boss), transform, sprite))
+            });
+        let Ok((health, owner_transform, owner_sprite)) = owner else {
+            commands.despawn_if_exists(entity);
+            continue;
+        };
+        tracked.insert(bar.owner);
+        place_health_bar(
+            health,
+            owner_transform,
+            owner_sprite,
+            &mut transform,
+            &mut sprite,
+        );
+    }
+
+    let damaged = enemy_query
+        .iter()
+        .filter(|(_, enemy, ..)| {
+            let max_health = enemy.kind.max_health();
+            max_health > 1 && enemy.health < max_health
+        })
+        .map(|(owner, enemy, transform, sprite)| (owner, enemy_health(enemy), transform, sprite));
+    let mini_bosses = mini_boss_query
+        .iter()
+        .map(|(owner, boss, transform, sprite)| (owner, boss_health(boss), transform, sprite));
+    for (owner, health, owner_transform, owner_sprite) in damaged.chain(mini_bosses) {
+        if tracked.contains(&owner) {
+            continue;
+        }
+        let mut transform = Transform::default();
+        let mut sprite = Sprite {
+            anchor: Anchor::CenterLeft,
+            ..default()
+        };
+        place_health_bar(
+            health,
+            owner_transform,
+            owner_sprite,
+            &mut transform,
+            &mut sprite,
+        );
+        commands.spawn((
+            SpriteBundle {
+                sprite,
+                transform,
+                ..default()
+            },
+            HealthBar { owner },
+        ));
+    }
+}
+
+fn enemy_health(enemy: &Enemy) -> f32 {
+    enemy.health as f32 / enemy.kind.max_health() as f32
+}
+
+fn boss_health(boss: &Boss) -> f32 {
+    boss.health as f32 / boss.max_health as f32
+}
+
+/// Shrinks towards its left end and shades from green to red as `fraction` of health
+/// runs out, fading along with a cloaked enemy.
+fn place_health_bar(
+    fraction: f32,
+    owner_transform: &Transform,
+    owner_sprite: &Sprite,
+    transform: &mut Transform,
+    sprite: &mut Sprite,
+) {
+    let owner_height = owner_sprite.custom_size.map_or(0.0, |size| size.y);
+    let offset = Vec2::new(
+        -HEALTH_BAR_SIZE.x / 2.0,
+        owner_height / 2.0 + HEALTH_BAR_GAP,
+    );
+    transform.translation =
+        (owner_transform.translation.truncate() + offset).extend(layers::HEALTH_BARS);
+    sprite.custom_size = Some(HEALTH_BAR_SIZE * Vec2::new(fraction, 1.0));
+    let mut color = lerp_color(Color::RED, Color::GREEN, fraction);
+    color.set_a(owner_sprite.color.a());
+    sprite.color = color;
+}
+
+/// Whether two axis-aligned boxes, given by centre and full size, overlap.
+fn aabb_overlap(a: Vec2, a_size: Vec2, b: Vec2, b_size: Vec2) -> bool {
+    let reach = (a_size + b_size) / 2.0;
+    (a - b).abs().cmplt(reach).all()
+}
+
+fn lerp_color(from: Color, to: Color, t: f32) -> Color {
+    let from = Vec4::from(from.as_rgba_f32());
+    let to = Vec4::from(to.as_rgba_f32());
+    Color::from(from.lerp(to, t.clamp(0.0, 1.0)))
+}
+
+fn gunner_shooting(
+    mut commands: Commands,
+    mut query: Query<(&mut Gunner, &Transform), Without<Frozen>>,
+    player_query: Query<&Transform, With<Player>>,
+    fire: EnemyFire,
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+) {
+    let Ok(player_transform) = player_query.get_single() else {
+        return;
+    };
+    let delta = time.delta().mul_f32(time_scale.0);
+    let mut room = fire.room();
+
+    for (mut gunner, transform) in query.iter_mut() {
+        if let Some(wind_up) = gunner.wind_up.as_mut() {
+            if wind_up.tick(delta).finished() {
+                gunner.wind_up = None;
+                // At the cap the shot is simply lost; the gunner winds up again as normal.
+                if room == 0 {
+                    continue;
+                }
+                room -= 1;
+                let aim = (player_transform.translation - transform.translation)
+                    .truncate()
+                    .try_normalize()
+                    .unwrap_or(Vec2::NEG_Y);
+                spawn_enemy_bullet(
+                    &mut commands,
+                    transform.translation,
+                    aim * GUNNER_BULLET_SPEED,
+                    EnemyBulletKind::Aimed,
+                );
+            }
+        } else if gunner.cooldown.tick(delta).just_finished() {
+            gunner.wind_up = Some(Timer::from_seconds(
+                GUNNER_WIND_UP_SECS / fire.difficulty.level,
+                TimerMode::Once,
+            ));
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn enemy_escape(
+    mut commands: Commands,
+    mut query: Query<
+        (
+            Entity,
+            &Sprite,
+            &mut Transform,
+            Option<&mut Dive>,
+            Option<&Thief>,
+            Has<Spin>,
+        ),
+        With<Enemy>,
+    >,
+    play_area: Res<PlayArea>,
+    settings: Res<Settings>,
+    mut escaped_events: EventWriter<EnemyEscaped>,
+) {
+    for (entity, sprite, mut transform, dive, thief, spinning) in query.iter_mut() {
+        // Wrapping waits until an enemy is fully off the bottom, too. A spinning one
+        // reaches as far as its corners.
+        let size = sprite.custom_size.unwrap_or_default();
+        let extent = if spinning {
+            Vec2::splat(size.length())
+        } else {
+            size
+        };
+        let half_height = play_area.off_screen(extent).y;
+        // A thief that gets away keeps what it took, but doesn't count as an escape.
+        if thief.is_some_and(|thief| thief.stolen.is_some())
+            && transform.translation.y > half_height
+        {
+            commands.despawn_if_exists(entity);
+            continue;
+        }
+        if settings.wrap_mode.enemies() {
+            if transform.translation.y < -half_height {
+                transform.translation.y += 2.0 * half_height;
+                // Divers get to line up another dive on the way back down.
+                if let Some(mut dive) = dive {
+                    *dive = Dive::Entering;
+                }
+            }
+        } else if transform.translation.y < -half_height {
+            commands.despawn_if_exists(entity);
+            escaped_events.send(EnemyEscaped);
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn bullet_enemy_collision(
+    mut commands: Commands,
+    (mut score, mut combo, scoring): (ResMut<Score>, ResMut<Combo>, Res<ScoringConfig>),
+    (mut punch, mut hit_stop, settings): (ResMut<CameraPunch>, ResMut<HitStop>, Res<Settings>),
+    (mut rng, mut chain_events, mut destroyed_events): (
+        ResMut<GameRng>,
+        EventWriter<ChainKill>,
+        EventWriter<EnemyDestroyed>,
+    ),
+    (mut bullet_query, director, time): (
+        Query<(
+            Entity,
+            &Bullet,
+            &Transform,
+            Option<&mut Pierce>,
+            Has<Incendiary>,
+        )>,
+        Res<SpawnDirector>,
+        Res<Time>,
+    ),
+    mut enemy_query: Query<
+        (
+            Entity,
+            &mut Enemy,
+            &Transform,
+            Has<Leader>,
+            Option<&Shield>,
+            Option<&WeakPoint>,
+            (
+                Option<&Cloak>,
+                Option<&mut Burning>,
+                Option<&Thief>,
+                Option<&mut RecentHits>,
+            ),
+        ),
+        Without<SpawnFade>,
+    >,
+    member_query: Query<(Entity, &FormationMember)>,
+) {
+    // Two bullets can reach the same enemy in one frame; once one of them kills it
+    // the other must pass through rather than scoring it again.
+    let mut destroyed = HashSet::new();
+
+    for (.., (_, _, _, recent_hits)) in enemy_query.iter_mut() {
+        if let Some(mut recent_hits) = recent_hits {
+            recent_hits
+                .0
+                .retain_mut(|(_, timer)| !timer.tick(time.delta()).finished());
+        }
+    }
+
+    for (bullet_entity, bullet, bullet_transform, mut pierce, incendiary) in bullet_query.iter_mut()
+    {
+        for (
+            enemy_entity,
+            mut enemy,
+            enemy_transform,
+            is_leader,
+            shield,
+            weak_point,
+            (cloak, burning, thief, mut recent_hits),
+        ) in enemy_query.iter_mut()
+        {
+            if destroyed.contains(&enemy_entity)
+                || cloak.is_some_and(|cloak| cloak.cloaked)
+                || recent_hits
+                    .as_ref()
+                    .is_some_and(|recent_hits| recent_hits.contains(bullet_entity))
+            {
+                continue;
+            }
+            let offset = (bullet_transform.translation - enemy_transform.translation).truncate();
+            let size = enemy.kind.hitbox(is_leader, &director.balance);
+
+            if offset.abs().cmplt(size / 2.0).all() {
+                if let Some(shield) = shield
+                    && shield.covers(offset)
+                {
+                    let normal = offset.normalize_or_zero();
+                    let reflected = bullet.velocity - 2.0 * bullet.velocity.dot(normal) * normal;
+                    commands.despawn_if_exists(bullet_entity);
+                    if settings.enemy_bullets {
+                        spawn_enemy_bullet(
+                            &mut commands,
+                            bullet_transform.translation,
+                            reflected * REFLECTED_SPEED_FACTOR,
+                            EnemyBulletKind::Reflected,
+                        );
+                    }
+                    break;
+                }
+                if let Some(weak_point) = weak_point
+                    && !weak_point.covers(offset)
+                {
+                    commands.despawn_if_exists(bullet_entity);
+                    spawn_sparks(
+                        &mut commands,
+                        bullet_transform.translation,
+                        WEAK_POINT_COLOR,
+                    );
+                    break;
+                }
+
+                enemy.health = enemy.health.saturating_sub(bullet.damage);
+                if let Some(pierce) = pierce.as_mut() {
+                    pierce.landed = true;
+                }
+
+                // Score is only awarded on the killing blow.
+                if enemy.health == 0 {
+                    destroyed.insert(enemy_entity);
+                    commands.despawn_if_exists(enemy_entity);
+                    combo.count += 1;
+                    let mut points = scoring.kill * combo.multiplier()
+                        + thief.map_or(0, Thief::refund)
+                        + enemy.kind.bonus_score(&scoring);
+
+                    if is_leader {
+                        points += scoring.leader_bonus;
+                        punch.trigger(LEADER_KILL_PUNCH);
+                        hit_stop.trigger(LEADER_KILL_HIT_STOP_SECS);
+                        scatter_formation(&mut commands, &mut rng.0, enemy_entity, &member_query);
+                    }
+                    award_kill(
+                        &mut commands,
+                        &mut score,
+                        &settings,
+                        enemy_transform.translation,
+                        points,
+                    );
+                    destroyed_events.send(EnemyDestroyed {
+                        kind: Some(enemy.kind),
+                        name: if is_leader {
+                            "Leader"
+                        } else {
+                            enemy.kind.name()
+                        },
+                        position: enemy_transform.translation.truncate(),
+                        points,
+                        multiplier: combo.multiplier(),
+                    });
+
+                    if rng.0.gen_bool(POWER_UP_DROP_CHANCE) {
+                        let kind = *PowerUpKind::ALL.choose(&mut rng.0).unwrap();
+                        spawn_power_up(&mut commands, enemy_transform.translation, kind);
+                    }
+
+                    // Piercing shots carry on through the kill to the next enemy.
+                    if let Some(pierce) = pierce.as_mut() {
+                        pierce.chain += 1;
+                        if pierce.chain > 1 {
+                            chain_events.send(ChainKill {
+                                length: pierce.chain,
+                                position: enemy_transform.translation,
+                            });
+                        }
+                        continue;
+                    }
+                } else if incendiary {
+                    match burning {
+                        Some(mut burning) => burning.timer.reset(),
+                        None => {
+                            commands.entity(enemy_entity).try_insert(Burning::new());
+                        }
+                    }
+                }
+                if pierce.is_some() {
+                    match recent_hits.as_mut() {
+                        Some(recent_hits) => recent_hits.record(bullet_entity),
+                        None => {
+                            let mut recent_hits = RecentHits::default();
+                            recent_hits.record(bullet_entity);
+                            commands.entity(enemy_entity).try_insert(recent_hits);
+                        }
+                    }
+                    continue;
+                }
+                // Any other bullet is spent on the first enemy it touches.
+                commands.despawn_if_exists(bullet_entity);
+                break;
+            }
+        }
+    }
+}
+
+/// Wears down burning enemies and gives off embers. A kill by fire scores and counts
+/// towards the combo just like one by a bullet.
+#[allow(clippy::type_complexity)]
+fn burn_tick(
+    mut commands: Commands,
+    (mut score, mut combo, mut punch): (ResMut<Score>, ResMut<Combo>, ResMut<CameraPunch>),
+    (mut rng, mut destroyed_events, mut hit_stop): (
+        ResMut<GameRng>,
+        EventWriter<EnemyDestroyed>,
+        ResMut<HitStop>,
+    ),
+    mut query: Query<(
+        Entity,
+        &mut Enemy,
+        &mut Burning,
+        &Transform,
+        Has<Leader>,
+        Option<&Thief>,
+    )>,
+    member_query: Query<(Entity, &FormationMember)>,
+    (time, time_scale, settings, governor): (
+        Res<Time>,
+        Res<TimeScale>,
+        Res<Settings>,
+        Res<PerformanceGovernor>,
+    ),
+    scoring: Res<ScoringConfig>,
+) {
+    let delta = time.delta().mul_f32(time_scale.0);
+    let mut embers = rand::thread_rng();
+    let ember_rate = if governor.low_effects(&settings) {
+        EMBER_RATE * LOW_EFFECTS_SCALE as f64
+    } else {
+        EMBER_RATE
+    };
+
+    for (entity, mut enemy, mut burning, transform, is_leader, thief) in query.iter_mut() {
+        let burning = &mut *burning;
+        if burning.timer.tick(delta).finished() {
+            commands.entity(entity).remove::<Burning>();
+            continue;
+        }
+        if embers.gen_bool((ember_rate * delta.as_secs_f64()).min(1.0)) {
+            spawn_ember(&mut commands, &mut embers, transform.translation);
+        }
+
+        burning.carry += burning.dps * delta.as_secs_f32();
+        let damage = burning.carry as u32;
+        if damage == 0 {
+            continue;
+        }
+        burning.carry -= damage as f32;
+        enemy.health = enemy.health.saturating_sub(damage);
+        if enemy.health > 0 {
+            continue;
+        }
+
+        commands.despawn_if_exists(entity);
+        spawn_sparks(&mut commands, transform.translation, FLAME_COLOR);
+        combo.count += 1;
+        let mut points = scoring.kill * combo.multiplier()
+            + thief.map_or(0, Thief::refund)
+            + enemy.kind.bonus_score(&scoring);
+        if is_leader {
+            points += scoring.leader_bonus;
+            punch.trigger(LEADER_KILL_PUNCH);
+            hit_stop.trigger(LEADER_KILL_HIT_STOP_SECS);
+            scatter_formation(&mut commands, &mut rng.0, entity, &member_query);
+        }
+        award_kill(
+            &mut commands,
+            &mut score,
+            &settings,
+            transform.translation,
+            points,
+        );
+        destroyed_events.send(EnemyDestroyed {
+            kind: Some(enemy.kind),
+            name: if is_leader {
+                "Leader"
+            } else {
+                enemy.kind.name()
+            },
+            position: transform.translation.truncate(),
+            points,
+            multiplier: combo.multiplier(),
+        });
+    }
+}
+
+/// A spark that drifts up off a burning enemy.
+fn spawn_ember(commands: &mut Commands, rng: &mut impl Rng, position: Vec3) {
+    let offset = Vec2::new(rng.gen_range(-12.0..12.0), rng.gen_range(-8.0..8.0));
+    commands.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                custom_size: Some(Vec2::splat(3.0)),
+                color: FLAME_COLOR,
+                ..default()
+            },
+            transform: Transform::from_translation(
+                (position.truncate() + offset).extend(layers::PARTICLES),
+            ),
+            ..default()
+        },
+        Particle {
+            velocity: Vec2::new(rng.gen_range(-20.0..20.0), rng.gen_range(40.0..80.0)),
+            angular_velocity: 0.0,
+            gravity: 0.0,
+            lifetime: Timer::from_seconds(rng.gen_range(0.3..0.6), TimerMode::Once),
+        },
+    ));
+}
+
+/// Pays out the bonus for each link in a piercing chain and pops up its length.
+fn chain_kill_feedback(
+    mut commands: Commands,
+    mut chain_events: EventReader<ChainKill>,
+    mut score: ResMut<Score>,
+    scoring: Res<ScoringConfig>,
+) {
+    for chain in chain_events.read() {
+        score.0 += scoring.chain_bonus;
+        commands.spawn((
+            Text2dBundle {
+                text: Text::from_section(
+                    format!("x{} chain!", chain.length),
+                    TextStyle {
+                        font_size: 22.0,
+                        color: PIERCE_COLOR,
+                        ..default()
+                    },
+                ),
+                transform: Transform::from_translation(
+                    chain.position.truncate().extend(layers::PARTICLES),
+                ),
+                ..default()
+            },
+            ChainPopup(Timer::from_seconds(CHAIN_POPUP_SECS, TimerMode::Once)),
+        ));
+    }
+}
+
+fn chain_popup_update(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut ChainPopup, &mut Transform, &mut Text)>,
+    time: Res<Time>,
+) {
+    for (entity, mut popup, mut transform, mut text) in query.iter_mut() {
+        if popup.0.tick(time.delta()).finished() {
+            commands.despawn_if_exists(entity);
+            continue;
+        }
+        transform.translation.y += 40.0 * time.delta_seconds();
+        text.sections[0].style.color.set_a(popup.0.percent_left());
+    }
+}
+
+fn scatter_formation(
+    commands: &mut Commands,
+    rng: &mut StdRng,
+    leader: Entity,
+    member_query: &Query<(Entity, &FormationMember)>,
+) {
+    for (member, formation) in member_query.iter() {
+        if formation.leader != leader {
+            continue;
+        }
+        // Members killed this frame are already queued for despawn.
+        commands.entity(member).try_insert(Scatter {
+            velocity_x: rng.gen_range(-SCATTER_SPEED..SCATTER_SPEED),
+            timer: Timer::from_seconds(0.4, TimerMode::Repeating),
+        });
+    }
+}
+
+fn spawn_boss(
+    mut commands: Commands,
+    score: Res<Score>,
+    config: Res<BossConfig>,
+    mut schedule: ResMut<BossSchedule>,
+    boss_query: Query<(), With<Boss>>,
+) {
+    if score.0 < schedule.next_score || !boss_query.is_empty() {
+        return;
+    }
+    schedule.next_score = score.0 + BOSS_SCORE_INTERVAL;
+    spawn_boss_entity(&mut commands, &config);
+}
+
+fn spawn_boss_entity(commands: &mut Commands, config: &BossConfig) -> Entity {
+    let first_phase = &config.phases[0];
+    commands
+        .spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    custom_size: Some(BOSS_SIZE),
+                    color: BOSS_COLOR.with_a(0.0),
+                    ..default()
+                },
+                transform: Transform::from_xyz(0.0, 220.0, layers::ENEMIES),
+                ..default()
+            },
+            BossIntro {
+                timer: Timer::from_seconds(BOSS_INTRO_SECS, TimerMode::Once),
+            },
+            Boss {
+                health: config.health,
+                max_health: config.health,
+                phase: 0,
+                phases: config.phases.clone(),
+                direction: 1.0,
+                fire_timer: Timer::from_seconds(first_phase.fire_interval, TimerMode::Repeating),
+                volleys: 0,
+            },
+        ))
+        .id()
+}
+
+fn spawn_mini_boss(commands: &mut Commands, config: &MiniBossConfig) -> Entity {
+    commands
+        .spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    custom_size: Some(MINI_BOSS_SIZE),
+                    color: MINI_BOSS_COLOR.with_a(0.0),
+                    ..default()
+                },
+                transform: Transform::from_xyz(0.0, 220.0, layers::ENEMIES),
+                ..default()
+            },
+            BossIntro {
+                timer: Timer::from_seconds(BOSS_INTRO_SECS, TimerMode::Once),
+            },
+            Boss {
+                health: config.health,
+                max_health: config.health,
+                phase: 0,
+                phases: vec![BossPhase {
+                    health_fraction: 1.0,
+                    move_speed: config.move_speed,
+                    fire_interval: config.fire_interval,
+                    pattern: config.pattern,
+                }],
+                direction: 1.0,
+                fire_timer: Timer::from_seconds(config.fire_interval, TimerMode::Repeating),
+                volleys: 0,
+            },
+            MiniBoss,
+        ))
+        .id()
+}
+
+fn boss_intro(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut BossIntro, &mut Sprite, Has<MiniBoss>)>,
+    game_audio: Res<GameAudio>,
+    settings: Res<Settings>,
+    time: Res<Time>,
+    difficulty: Res<Difficulty>,
+) {
+    for (entity, mut intro, mut sprite, mini_boss) in query.iter_mut() {
+        if intro.is_added() {
+            play_sound(&mut commands, &game_audio.boss_intro, settings.sfx_volume);
+        }
+        intro.timer.tick(time.delta());
+        sprite.color.set_a(intro.timer.percent());
+        if intro.timer.finished() {
+            commands
+                .entity(entity)
+                .remove::<BossIntro>()
+                .insert(BossEnrage::new(mini_boss, &difficulty));
+        }
+    }
+}
+
+/// Darkens the background as the score climbs through its tiers, and further for the
+/// length of a boss fight, intro included. A static background stays put, and so does
+/// it for a mini-boss.
+fn background_tint(
+    boss_query: Query<(), (With<Boss>, Without<MiniBoss>)>,
+    mut clear_color: ResMut<ClearColor>,
+    score: Res<Score>,
+    settings: Res<Settings>,
+    time: Res<Time>,
+) {
+    let target = if settings.static_background {
+        BACKGROUND_COLOR
+    } else if !boss_query.is_empty() {
+        BOSS_BACKDROP_COLOR
+    } else {
+        let colors = if settings.colorblind {
+            BACKGROUND_TIER_COLORBLIND_COLORS
+        } else {
+            BACKGROUND_TIER_COLORS
+        };
+        let tier = BACKGROUND_TIER_SCORES
+            .iter()
+            .rposition(|&threshold| score.0 >= threshold)
+            .unwrap_or(0);
+        match BACKGROUND_TIER_SCORES.get(tier + 1) {
+            Some(&next) => {
+                let start = BACKGROUND_TIER_SCORES[tier];
+                let t = (score.0 - start) as f32 / (next - start) as f32;
+                lerp_color(colors[tier], colors[tier + 1], t)
+            }
+            None => colors[tier],
+        }
+    };
+    let blend = (BACKDROP_FADE_SPEED * time.delta_seconds()).min(1.0);
+    let color = lerp_color(clear_color.0, target, blend);
+    // Settled colours are left alone so the clear colour isn't marked changed every frame.
+    if color != clear_color.0 {
+        clear_color.0 = color;
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn boss_movement(
+    mut query: Query<
+        (&mut Boss, &mut Transform, &Sprite, Option<&BossEnrage>),
+        (Without<BossIntro>, Without<BossDefeat>),
+    >,
+    play_area: Res<PlayArea>,
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+) {
+    let half_width = play_area.half_size.x;
+
+    for (mut boss, mut transform, sprite, enrage) in query.iter_mut() {
+        let limit = half_width - boss_size(sprite).x / 2.0;
+        let mut speed = boss.phases[boss.phase].move_speed;
+        if enrage.is_some_and(BossEnrage::enraged) {
+            speed *= BOSS_ENRAGE_SPEED;
+        }
+        transform.translation.x += boss.direction * speed * time.delta_seconds() * time_scale.0;
+
+        if transform.translation.x.abs() > limit {
+            transform.translation.x = transform.translation.x.clamp(-limit, limit);
+            boss.direction = -boss.direction;
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn boss_shooting(
+    mut commands: Commands,
+    mut boss_query: Query<
+        (&mut Boss, &Transform, Option<&BossEnrage>),
+        (Without<BossIntro>, Without<BossDefeat>),
+    >,
+    player_query: Query<&Transform, With<Player>>,
+    fire: EnemyFire,
+    (mut meshes, mut materials): (ResMut<Assets<Mesh>>, ResMut<Assets<ColorMaterial>>),
+    play_area: Res<PlayArea>,
+    (time, time_scale): (Res<Time>, Res<TimeScale>),
+) {
+    let target = player_query
+        .get_single()
+        .map_or(Vec2::ZERO, |transform| transform.translation.truncate());
+    let mut room = fire.room();
+
+    for (mut boss, transform, enrage) in boss_query.iter_mut() {
+        let enraged = enrage.is_some_and(BossEnrage::enraged);
+        let rate = if enraged { BOSS_ENRAGE_FIRE_RATE } else { 1.0 };
+        if !boss
+            .fire_timer
+            .tick(time.delta().mul_f32(time_scale.0 * rate))
+            .just_finished()
+        {
+            continue;
+        }
+
+        let mut pattern = boss.phases[boss.phase].pattern;
+        if enraged {
+            *pattern.params_mut().1 *= BOSS_ENRAGE_BULLET_SPEED;
+        }
+        room -= fire_pattern(
+            &mut commands,
+            (&mut meshes, &mut materials),
+            &fire,
+            pattern,
+            (transform.translation, target),
+            boss.volleys,
+            (room, play_area.half_size.y),
+        );
+        boss.volleys += 1;
+    }
+}
+
+/// Fires one volley of `pattern` from `origin` at `target`, keeping to `room`, and
+/// returns how many bullets that took. Area attacks are marked out first instead and
+/// fire when the warning runs out.
+fn fire_pattern(
+    commands: &mut Commands,
+    (meshes, materials): (&mut Assets<Mesh>, &mut Assets<ColorMaterial>),
+    fire: &EnemyFire,
+    pattern: Pattern,
+    (origin, target): (Vec3, Vec2),
+    volley: u32,
+    (room, half_height): (usize, f32),
+) -> usize {
+    if let Some(zone) = pattern.zone(target) {
+        if room > 0 {
+            spawn_telegraph(
+                commands,
+                (meshes, materials),
+                TelegraphZone {
+                    pattern,
+                    zone,
+                    timer: Timer::from_seconds(fire.telegraph_secs(), TimerMode::Once),
+                },
+                half_height,
+            );
+        }
+        return 0;
+    }
+    let velocities = thin(pattern.velocities(origin.truncate(), target, volley), room);
+    let fired = velocities.len();
+    let kind = EnemyBulletKind::of(&pattern);
+    for velocity in velocities {
+        let bullet = spawn_enemy_bullet(commands, origin, velocity, kind);
+        if let Pattern::Homing {
+            turn_rate,
+            lifetime,
+            ..
+        } = pattern
+        {
+            commands.entity(bullet).insert(HomingBullet {
+                turn_rate,
+                lifetime: Timer::from_seconds(lifetime, TimerMode::Once),
+            });
+        }
+    }
+    fired
+}
+
+/// Columns are plain sprites the height of the play area; circles need a mesh.
+fn spawn_telegraph(
+    commands: &mut Commands,
+    (meshes, materials): (&mut Assets<Mesh>, &mut Assets<ColorMaterial>),
+    telegraph: TelegraphZone,
+    half_height: f32,
+) {
+    let color = TELEGRAPH_COLOR.with_a(0.0);
+    match telegraph.zone {
+        Zone::Column { x, width } => commands.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    custom_size: Some(Vec2::new(width, half_height * 2.0)),
+                    color,
+                    ..default()
+                },
+                transform: Transform::from_xyz(x, 0.0, layers::TELEGRAPHS),
+                ..default()
+            },
+            telegraph,
+        )),
+        Zone::Circle { center, radius } => commands.spawn((
+            MaterialMesh2dBundle {
+                mesh: meshes.add(shape::Circle::new(radius).into()).into(),
+                material: materials.add(ColorMaterial::from(color)),
+                transform: Transform::from_translation(center.extend(layers::TELEGRAPHS)),
+                ..default()
+            },
+            telegraph,
+        )),
+    };
+}
+
+/// Fades telegraph zones in, pulsing unless reduce motion holds them steady, and fires
+/// each one's attack when its warning is up.
+#[allow(clippy::type_complexity)]
+fn telegraph_zones(
+    mut commands: Commands,
+    mut query: Query<(
+        Entity,
+        &mut TelegraphZone,
+        Option<&mut Sprite>,
+        Option<&Handle<ColorMaterial>>,
+    )>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    fire: EnemyFire,
+    play_area: Res<PlayArea>,
+    (time, time_scale): (Res<Time>, Res<TimeScale>),
+) {
+    let mut room = fire.room();
+    for (entity, mut telegraph, sprite, material) in query.iter_mut() {
+        if telegraph
+            .timer
+            .tick(time.delta().mul_f32(time_scale.0))
+            .finished()
+        {
+            commands.despawn_if_exists(entity);
+            let landing = telegraph
+                .pattern
+                .landing(telegraph.zone, play_area.half_size.y);
+            let landing = thin(landing, room);
+            room -= landing.len();
+            let kind = EnemyBulletKind::of(&telegraph.pattern);
+            for (position, velocity) in landing {
+                spawn_enemy_bullet(&mut commands, position.extend(0.0), velocity, kind);
+            }
+            continue;
+        }
+
+        let alpha = if fire.settings.reduce_motion {
+            TELEGRAPH_ALPHA
+        } else {
+            let pulse =
+                0.8 + 0.2 * (telegraph.timer.elapsed_secs() * TELEGRAPH_PULSE_HZ * TAU).sin();
+            TELEGRAPH_ALPHA * telegraph.timer.percent() * pulse
+        };
+        if let Some(mut sprite) = sprite {
+            sprite.color.set_a(alpha);
+        }
+        if let Some(material) = material.and_then(|handle| materials.get_mut(handle)) {
+            material.color.set_a(alpha);
+        }
+    }
+}
+
+/// Spawns in `kind`'s normal colour; `enemy_bullet_tint` swaps in the colorblind
+/// palette.
+fn spawn_enemy_bullet(
+    commands: &mut Commands,
+    position: Vec3,
+    velocity: Vec2,
+    kind: EnemyBulletKind,
+) -> Entity {
+    commands
+        .spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    custom_size: Some(ENEMY_BULLET_SIZE),
+                    color: kind.color(),
+                    ..default()
+                },
+                transform: Transform::from_translation(
+                    position.truncate().extend(layers::ENEMY_BULLETS),
+                ),
+                ..default()
+            },
+            EnemyBullet {
+                velocity,
+                kind,
+                lifetime: Timer::from_seconds(BULLET_MAX_LIFETIME_SECS, TimerMode::Once),
+            },
+        ))
+        .id()
+}
+
+/// Turns homing bullets after the player, and lets them burn out once their lifetime
+/// is up so one that missed can't circle forever.
+fn homing_bullets(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut EnemyBullet, &mut HomingBullet, &Transform)>,
+    player_query: Query<&Transform, With<Player>>,
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+) {
+    let delta = time.delta().mul_f32(time_scale.0);
+    let target = player_query
+        .get_single()
+        .map(|transform| transform.translation.truncate());
+    for (entity, mut bullet, mut homing, transform) in query.iter_mut() {
+        if homing.lifetime.tick(delta).finished() {
+            commands.despawn_if_exists(entity);
+            continue;
+        }
+        if let Ok(target) = target {
+            let toward = target - transform.translation.truncate();
+            bullet.velocity = steer(
+                bullet.velocity,
+                toward,
+                homing.turn_rate * delta.as_secs_f32(),
+            );
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn bullet_boss_collision(
+    mut commands: Commands,
+    bullet_query: Query<(Entity, &Bullet, &Transform)>,
+    // Shots pass straight through a boss that is still making its entrance, or
+    // already on its way out.
+    mut boss_query: Query<
+        (Entity, &mut Boss, &Transform, &Sprite),
+        (Without<BossIntro>, Without<BossDefeat>),
+    >,
+    settings: Res<Settings>,
+) {
+    // A mini-boss can overlap the boss; a shot is spent on whichever it reaches first.
+    let mut spent = HashSet::new();
+
+    for (boss_entity, mut boss, boss_transform, boss_sprite) in boss_query.iter_mut() {
+        let half_size = boss_size(boss_sprite) / 2.0;
+        for (bullet_entity, bullet, bullet_transform) in bullet_query.iter() {
+            if spent.contains(&bullet_entity) {
+                continue;
+            }
+            let offset = (bullet_transform.translation - boss_transform.translation).truncate();
+
+            if offset.x.abs() < half_size.x && offset.y.abs() < half_size.y {
+                spent.insert(bullet_entity);
+                commands.despawn_if_exists(bullet_entity);
+                boss.health = boss.health.saturating_sub(bullet.damage);
+
+                if boss.health == 0 {
+                    commands
+                        .entity(boss_entity)
+                        .remove::<BossFlash>()
+                        .insert(BossDefeat::new(settings.reduce_motion));
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// Mini-bosses are drawn smaller than the boss and are hit wherever they are drawn.
+fn boss_size(sprite: &Sprite) -> Vec2 {
+    sprite.custom_size.unwrap_or(BOSS_SIZE)
+}
+
+/// Plays out a `BossDefeat`: time slows, blasts go off across the boss as it fades,
+/// and at the end it is removed, scored and announced. A mini-boss leaves a power-up
+/// behind instead of a banner. Reduce motion keeps time at full speed.
+#[allow(clippy::type_complexity)]
+fn boss_defeat(
+    mut commands: Commands,
+    mut query: Query<(
+        Entity,
+        &mut BossDefeat,
+        &Transform,
+        &mut Sprite,
+        Has<MiniBoss>,
+    )>,
+    bullet_query: Query<Entity, Or<(With<EnemyBullet>, With<TelegraphZone>)>>,
+    (mut score, mut punch, mut time_scale): (ResMut<Score>, ResMut<CameraPunch>, ResMut<TimeScale>),
+    (mut destroyed_events, mut hit_stop, mut shake): (
+        EventWriter<EnemyDestroyed>,
+        ResMut<HitStop>,
+        ResMut<ScreenShake>,
+    ),
+    (settings, time, mut game_rng): (Res<Settings>, Res<Time>, ResMut<GameRng>),
+    scoring: Res<ScoringConfig>,
+) {
+    let mut rng = rand::thread_rng();
+    for (entity, mut defeat, transform, mut sprite, mini_boss) in query.iter_mut() {
+        // The boss's last volley, or an attack it marked out, shouldn't get a parting
+        // shot in while things slow down.
+        if defeat.is_added() {
+            for bullet in bullet_query.iter() {
+                commands.despawn_if_exists(bullet);
+            }
+        }
+        if !settings.reduce_motion {
+            time_scale.0 = time_scale.0.min(BOSS_DEFEAT_TIME_SCALE);
+        }
+
+        defeat.timer.tick(time.delta());
+        sprite.color.set_a(defeat.timer.percent_left());
+        let blasts = defeat
+            .blast_timer
+            .tick(time.delta())
+            .times_finished_this_tick();
+        let size = boss_size(&sprite);
+        for _ in 0..blasts {
+            let offset = Vec2::new(
+                rng.gen_range(-0.5..0.5) * size.x,
+                rng.gen_range(-0.5..0.5) * size.y,
+            );
+            spawn_sparks(
+                &mut commands,
+                transform.translation + offset.extend(0.0),
+                BOSS_DEFEAT_COLOR,
+            );
+            punch.trigger(BOSS_DEFEAT_BLAST_PUNCH);
+        }
+        if !defeat.timer.finished() {
+            continue;
+        }
+
+        commands.despawn_if_exists(entity);
+        if mini_boss {
+            score.0 += scoring.mini_boss_kill;
+            punch.trigger(BOSS_DEFEAT_BLAST_PUNCH);
+            destroyed_events.send(EnemyDestroyed {
+                kind: None,
+                name: MINI_BOSS_NAME,
+                position: transform.translation.truncate(),
+                points: scoring.mini_boss_kill,
+                multiplier: 1,
+            });
+            let kind = *PowerUpKind::ALL.choose(&mut game_rng.0).unwrap();
+            spawn_power_up(&mut commands, transform.translation, kind);
+            continue;
+        }
+        score.0 += scoring.boss_kill;
+        punch.trigger(BOSS_KILL_PUNCH);
+        hit_stop.trigger(BOSS_KILL_HIT_STOP_SECS);
+        shake.add(BOSS_KILL_SHAKE);
+        destroyed_events.send(EnemyDestroyed {
+            kind: None,
+            name: "Boss",
+            position: transform.translation.truncate(),
+            points: scoring.boss_kill,
+            multiplier: 1,
+        });
+        commands.spawn((
+            Text2dBundle {
+                text: Text::from_section(
+                    "BOSS DEFEATED",
+                    TextStyle {
+                        font_size: 48.0,
+                        color: BOSS_DEFEAT_COLOR,
+                        ..default()
+                    },
+                ),
+                transform: Transform::from_xyz(0.0, 40.0, layers::PARTICLES),
+                ..default()
+            },
+            ChainPopup(Timer::from_seconds(
+                BOSS_DEFEAT_BANNER_SECS,
+                TimerMode::Once,
+            )),
+        ));
+    }
+}
+
+fn camera_punch(
+    mut punch: ResMut<CameraPunch>,
+    mut projection_query: Query<&mut OrthographicProjection>,
+    settings: Res<Settings>,
+    time: Res<Time>,
+) {
+    if punch.remaining <= 0.0 {
+        return;
+    }
+    punch.remaining = (punch.remaining - time.delta_seconds()).max(0.0);
+
+    // Snaps in on the kill, then eases out over the rest of the punch.
+    let t = punch.remaining / CAMERA_PUNCH_SECS;
+    let zoom = if settings.reduce_motion {
+        0.0
+    } else {
+        punch.magnitude * t * t * settings.screen_shake
+    };
+    for mut projection in projection_query.iter_mut() {
+        projection.scale = 1.0 - zoom;
+    }
+}
+
+/// Square of the trauma, so small knocks barely register and big ones really throw the
+/// view about.
+fn camera_shake(
+    mut shake: ResMut<ScreenShake>,
+    mut camera_query: Query<&mut Transform, With<Camera2d>>,
+    settings: Res<Settings>,
+    time: Res<Time>,
+) {
+    shake.trauma = (shake.trauma - SHAKE_DECAY_PER_SEC * time.delta_seconds()).max(0.0);
+    let strength = if settings.reduce_motion {
+        0.0
+    } else {
+        shake.trauma * shake.trauma * settings.screen_shake
+    };
+    let mut rng = rand::thread_rng();
+    let offset = Vec2::from_angle(rng.gen_range(0.0..TAU)) * SHAKE_MAX_OFFSET * strength;
+    for mut transform in camera_query.iter_mut() {
+        transform.translation.x = offset.x;
+        transform.translation.y = offset.y;
+    }
+}
+
+fn boss_phase_transition(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut Boss, &mut Sprite, &Transform), Without<BossDefeat>>,
+    (game_audio, play_area): (Res<GameAudio>, Res<PlayArea>),
+    settings: Res<Settings>,
+    mut hit_stop: ResMut<HitStop>,
+) {
+    for (entity, mut boss, mut sprite, transform) in query.iter_mut() {
+        let health_fraction = boss.health as f32 / boss.max_health as f32;
+        let next_phase = boss.phase + 1;
+
+        if next_phase < boss.phases.len()
+            && health_fraction <= boss.phases[next_phase].health_fraction
+        {
+            boss.phase = next_phase;
+            boss.volleys = 0;
+            let interval = boss.phases[next_phase].fire_interval;
+            boss.fire_timer = Timer::from_seconds(interval, TimerMode::Repeating);
+
+            sprite.color = Color::WHITE;
+            commands.entity(entity).insert(BossFlash {
+                timer: Timer::from_seconds(0.3, TimerMode::Once),
+            });
+            play_sound_panned(
+                &mut commands,
+                &game_audio.boss_phase,
+                settings.sfx_volume,
+                sound_pan(transform.translation.x, &play_area),
+            );
+            hit_stop.trigger(BOSS_PHASE_HIT_STOP_SECS);
+        }
+    }
+}
+
+/// Runs down each boss's enrage timer, throbbing it red over the last few seconds and
+/// announcing the moment it snaps. An enraged boss stays red, but still flashes white
+/// on a phase change.
+#[allow(clippy::type_complexity)]
+fn boss_enrage(
+    mut commands: Commands,
+    mut query: Query<
+        (
+            &mut BossEnrage,
+            &mut Sprite,
+            &Transform,
+            Has<MiniBoss>,
+            Has<BossFlash>,
+        ),
+        Without<BossDefeat>,
+    >,
+    (game_audio, play_area): (Res<GameAudio>, Res<PlayArea>),
+    settings: Res<Settings>,
+    (time, time_scale): (Res<Time>, Res<TimeScale>),
+    mut shake: ResMut<ScreenShake>,
+) {
+    for (mut enrage, mut sprite, transform, mini_boss, flashing) in query.iter_mut() {
+        let was_enraged = enrage.enraged();
+        enrage.timer.tick(time.delta().mul_f32(time_scale.0));
+        if enrage.enraged() && !was_enraged {
+            play_sound_panned(
+                &mut commands,
+                &game_audio.boss_phase,
+                settings.sfx_volume,
+                sound_pan(transform.translation.x, &play_area),
+            );
+            shake.add(BOSS_ENRAGE_SHAKE);
+            commands.spawn((
+                Text2dBundle {
+                    text: Text::from_section(
+                        "ENRAGED",
+                        TextStyle {
+                            font_size: 40.0,
+                            color: BOSS_ENRAGE_COLOR,
+                            ..default()
+                        },
+                    ),
+                    transform: Transform::from_xyz(0.0, 40.0, layers::PARTICLES),
+                    ..default()
+                },
+                ChainPopup(Timer::from_seconds(
+                    BOSS_ENRAGE_BANNER_SECS,
+                    TimerMode::Once,
+                )),
+            ));
+        }
+
+        let remaining = enrage.timer.remaining_secs();
+        if flashing || remaining > BOSS_ENRAGE_WARNING_SECS {
+            continue;
+        }
+        let base = if mini_boss {
+            MINI_BOSS_COLOR
+        } else {
+            BOSS_COLOR
+        };
+        // Throbs faster as the timer runs out; reduce motion holds it halfway.
+        let t = if enrage.enraged() {
+            1.0
+        } else if settings.reduce_motion {
+            0.5
+        } else {
+            let phase = (BOSS_ENRAGE_WARNING_SECS - remaining).powi(2);
+            (phase * 0.5).sin() * 0.5 + 0.5
+        };
+        sprite.color = lerp_color(base, BOSS_ENRAGE_COLOR, t);
+    }
+}
+
+/// Counts down on real time, since the time it stops is the game's own. Reduce motion
+/// cancels a stop as soon as it starts.
+fn tick_hit_stop(mut hit_stop: ResMut<HitStop>, settings: Res<Settings>, time: Res<Time>) {
+    hit_stop.remaining = if settings.reduce_motion {
+        0.0
+    } else {
+        (hit_stop.remaining - time.delta_seconds()).max(0.0)
+    };
+}
+
+fn boss_flash(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut BossFlash, &mut Sprite)>,
+    time: Res<Time>,
+) {
+    for (entity, mut flash, mut sprite) in query.iter_mut() {
+        if flash.timer.tick(time.delta()).finished() {
+            sprite.color = BOSS_COLOR;
+            commands.entity(entity).remove::<BossFlash>();
+        }
+    }
+}
+
+fn enemy_bullet_movement(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut EnemyBullet, &mut Transform)>,
+    play_area: Res<PlayArea>,
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+) {
+    let delta = time.delta().mul_f32(time_scale.0);
+    let reach = play_area.off_screen(ENEMY_BULLET_SIZE);
+    for (entity, mut bullet, mut transform) in query.iter_mut() {
+        transform.translation += bullet.velocity.extend(0.0) * delta.as_secs_f32();
+
+        let position = transform.translation;
+        if position.x.abs() > reach.x
+            || position.y.abs() > reach.y
+            || bullet.lifetime.tick(delta).finished()
+        {
+            commands.despawn_if_exists(entity);
+        }
+    }
+}
+
+/// A thief that reaches the ship lifts score off it instead of a life, and the amount
+/// floats away with it.
+#[allow(clippy::type_complexity)]
+fn thief_contact(
+    mut commands: Commands,
+    mut score: ResMut<Score>,
+    director: Res<SpawnDirector>,
+    mut thief_query: Query<(&mut Thief, &Enemy, &Transform), (Without<Frozen>, Without<SpawnFade>)>,
+    player_query: Query<&Transform, (With<Player>, Without<Invincible>)>,
+) {
+    let Ok(player_transform) = player_query.get_single() else {
+        return;
+    };
+
+    for (mut thief, enemy, transform) in thief_query.iter_mut() {
+        // Shot down this frame, with its despawn still to be applied.
+        if enemy.health == 0 {
+            continue;
+        }
+        if thief.stolen.is_some()
+            || transform.translation.distance(player_transform.translation) > THIEF_CONTACT_DISTANCE
+        {
+            continue;
+        }
+        let stolen = director.balance.thief_steal.min(score.0);
+        score.0 -= stolen;
+        thief.stolen = Some(stolen);
+        commands.spawn((
+            Text2dBundle {
+                text: Text::from_section(
+                    format!("-{stolen}"),
+                    TextStyle {
+                        font_size: 24.0,
+                        color: THIEF_STEAL_COLOR,
+                        ..default()
+                    },
+                ),
+                transform: Transform::from_translation(
+                    (transform.translation.truncate() + Vec2::Y * 30.0).extend(layers::PARTICLES),
+                ),
+                ..default()
+            },
+            ChainPopup(Timer::from_seconds(CHAIN_POPUP_SECS, TimerMode::Once)),
+        ));
+    }
+}
+
+/// With reflect active, enemy bullets reaching the aura come back up as standard
+/// player shots, or simply vanish once `Balance::player_bullet_cap` is reached.
+/// Reflecting works through invincibility; hits don't.
+fn enemy_bullet_player_collision(
+    mut commands: Commands,
+    mut hit_events: EventWriter<PlayerHit>,
+    bullet_query: Query<(Entity, &Transform, &EnemyBullet)>,
+    player_query: Query<(&Transform, &Sprite, Has<Invincible>), With<Player>>,
+    player_bullet_query: Query<(), With<Bullet>>,
+    power_ups: Res<ActivePowerUps>,
+    director: Res<SpawnDirector>,
+) {
+    let Ok((player_transform, player_sprite, invincible)) = player_query.get_single() else {
+        return;
+    };
+    let radius = player_hit_radius(player_sprite);
+    let cap = director.balance.player_bullet_cap as usize;
+    let mut player_bullets = player_bullet_query.iter().count();
+
+    for (entity, transform, bullet) in bullet_query.iter() {
+        let distance = transform.translation.distance(player_transform.translation);
+        if power_ups.reflect.is_some() && distance < REFLECT_RADIUS {
+            commands.despawn_if_exists(entity);
+            if cap == 0 || player_bullets < cap {
+                let velocity = -bullet.velocity;
+                spawn_bullet(
+                    &mut commands,
+                    BulletKind::Standard,
+                    transform.translation.truncate(),
+                    Vec2::new(velocity.x, velocity.y.abs()),
+                    1,
+                    None,
+                );
+                player_bullets += 1;
+            }
+        } else if !invincible && distance < radius {
+            commands.despawn_if_exists(entity);
+            hit_events.send(PlayerHit);
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn graze_enemy_bullets(
+    mut commands: Commands,
+    director: Res<SpawnDirector>,
+    mut meter: ResMut<GrazeMeter>,
+    bullet_query: Query<(Entity, &Transform), (With<EnemyBullet>, Without<Grazed>)>,
+    player_query: Query<(Entity, &Transform, &Sprite), (With<Player>, Without<Invincible>)>,
+) {
+    let Ok((player, player_transform, player_sprite)) = player_query.get_single() else {
+        return;
+    };
+    let radius = player_hit_radius(player_sprite);
+
+    for (entity, transform) in bullet_query.iter() {
+        let distance = transform.translation.distance(player_transform.translation);
+        if (radius..GRAZE_RADIUS).contains(&distance) {
+            commands.entity(entity).try_insert(Grazed);
+            meter.0 += director.balance.graze_fill;
+        }
+    }
+
+    if meter.0 >= 1.0 {
+        meter.0 = 0.0;
+        commands
+            .entity(player)
+            .insert(Invincible::from_seconds(GRAZE_SHIELD_SECS));
+    }
+}
+
+fn death_effects(
+    mut commands: Commands,
+    mut destroyed_events: EventReader<EnemyDestroyed>,
+    settings: Res<Settings>,
+    governor: Res<PerformanceGovernor>,
+) {
+    let mut rng = rand::thread_rng();
+    for event in destroyed_events.read() {
+        let Some(kind) = event.kind else {
+            continue;
+        };
+        let enemy_color = if settings.colorblind {
+            kind.colorblind_color()
+        } else {
+            kind.color()
+        };
+        for layer in kind.death_effect().particles() {
+            let count = if settings.reduce_motion {
+                (layer.count as f32 * REDUCED_DEATH_PARTICLES).ceil() as usize
+            } else {
+                layer.count
+            };
+            let count = governor.budget(&settings, count);
+            for _ in 0..count {
+                let angle = layer.heading + rng.gen_range(-layer.spread..=layer.spread);
+                commands.spawn((
+                    SpriteBundle {
+                        sprite: Sprite {
+                            custom_size: Some(layer.size),
+                            color: layer.color.unwrap_or(enemy_color),
+                            ..default()
+                        },
+                        transform: Transform::from_translation(
+                            event.position.extend(layers::PARTICLES),
+                        )
+                        .with_rotation(Quat::from_rotation_z(angle)),
+                        ..default()
+                    },
+                    Particle {
+                        velocity: Vec2::from_angle(angle)
+                            * rng.gen_range(layer.speed.0..layer.speed.1),
+                        angular_velocity: rng.gen_range(-layer.spin..=layer.spin),
+                        gravity: layer.gravity,
+                        lifetime: Timer::from_seconds(layer.secs, TimerMode::Once),
+                    },
+                ));
+            }
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn explosion_knockback(
+    mut commands: Commands,
+    mut destroyed_events: EventReader<EnemyDestroyed>,
+    mut query: Query<
+        (Entity, &Transform, Option<&mut Knockback>),
+        (With<Enemy>, Without<Entering>),
+    >,
+) {
+    for event in destroyed_events.read() {
+        for (entity, transform, knockback) in query.iter_mut() {
+            let offset = transform.translation.truncate() - event.position;
+            let distance = offset.length();
+            // The enemy that exploded is still here until its despawn is applied.
+            if !(1.0..KNOCKBACK_RADIUS).contains(&distance) {
+                continue;
+            }
+
+            let impulse = offset / distance * KNOCKBACK_SPEED * (1.0 - distance / KNOCKBACK_RADIUS);
+            match knockback {
+                Some(mut knockback) => {
+                    knockback.0 = (knockback.0 + impulse).clamp_length_max(KNOCKBACK_MAX_SPEED);
+                }
+                None => {
+                    commands.entity(entity).try_insert(Knockback(impulse));
+                }
+            }
+        }
+    }
+}
+
+fn bullet_vs_enemy_bullet(
+    mut commands: Commands,
+    settings: Res<Settings>,
+    mut score: ResMut<Score>,
+    scoring: Res<ScoringConfig>,
+    bullet_query: Query<(Entity, &Bullet, &Transform, &Sprite)>,
+    enemy_bullet_query: Query<(Entity, &Transform), With<EnemyBullet>>,
+) {
+    if !settings.bullet_cancel {
+        return;
+    }
+    // Each enemy bullet can only take one player bullet down with it.
+    let mut cancelled = HashSet::new();
+
+    for (bullet_entity, bullet, bullet_transform, sprite) in bullet_query.iter() {
+        let position = bullet_transform.translation.truncate();
+        for (enemy_bullet, transform) in enemy_bullet_query.iter() {
+            if cancelled.contains(&enemy_bullet)
+                || !aabb_overlap(
+                    position,
+                    sprite.custom_size.unwrap_or(bullet.kind.size()),
+                    transform.translation.truncate(),
+                    ENEMY_BULLET_SIZE,
+                )
+            {
+                continue;
+            }
+            cancelled.insert(enemy_bullet);
+            commands.despawn_if_exists(bullet_entity);
+            commands.despawn_if_exists(enemy_bullet);
+            spawn_sparks(&mut commands, transform.translation, ENEMY_BULLET_COLOR);
+            score.0 += scoring.bullet_cancel;
+            break;
+        }
+    }
+}
+
+fn spawn_power_up(commands: &mut Commands, position: Vec3, kind: PowerUpKind) {
+    let color = match kind {
+        PowerUpKind::Bounce => Color::GREEN,
+        PowerUpKind::RapidFire => RAPID_FIRE_COLOR,
+        PowerUpKind::Pierce => PIERCE_COLOR,
+        PowerUpKind::AllRange => ALL_RANGE_COLOR,
+        PowerUpKind::Flame => FLAME_COLOR,
+        PowerUpKind::Reflect => REFLECT_COLOR,
+        PowerUpKind::Barrier => BARRIER_COLOR,
+    };
+
+    commands.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                custom_size: Some(POWER_UP_SIZE),
+                color,
+                ..default()
+            },
+            transform: Transform::from_translation(position.truncate().extend(layers::POWER_UPS)),
+            ..default()
+        },
+        PowerUp { kind },
+    ));
+}
+
+/// Scores a kill's points at once, or with score chips on, scatters them as chips
+/// for the player to go and collect.
+fn award_kill(
+    commands: &mut Commands,
+    score: &mut Score,
+    settings: &Settings,
+    position: Vec3,
+    points: u32,
+) {
+    if !settings.score_chips {
+        score.0 += points;
+        return;
+    }
+    let count = (points / SCORE_CHIP_POINTS_EACH).clamp(1, SCORE_CHIP_MAX_COUNT);
+    for i in 0..count {
+        // Any remainder rides on the first chip.
+        let value = points / count + if i == 0 { points % count } else { 0 };
+        let spread = i as f32 - (count - 1) as f32 / 2.0;
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    custom_size: Some(SCORE_CHIP_SIZE),
+                    color: SCORE_CHIP_COLOR,
+                    ..default()
+                },
+                transform: Transform::from_translation(
+                    position.truncate().extend(layers::POWER_UPS),
+                ),
+                ..default()
+            },
+            ScoreChip {
+                value,
+                velocity: Vec2::new(spread * SCORE_CHIP_SPREAD_SPEED, SCORE_CHIP_POP_SPEED),
+                lifetime: Timer::from_seconds(SCORE_CHIP_LIFETIME_SECS, TimerMode::Once),
+            },
+        ));
+    }
+}
+
+/// Moves chips, banks the ones the ship touches, and pays out a share for any that
+/// run out or fall off the bottom first. Chips fade over their last second.
+fn score_chips(
+    mut commands: Commands,
+    mut chip_query: Query<(Entity, &mut ScoreChip, &mut Transform, &mut Sprite)>,
+    player_query: Query<&Transform, (With<Player>, Without<ScoreChip>)>,
+    mut score: ResMut<Score>,
+    (time, play_area): (Res<Time>, Res<PlayArea>),
+) {
+    let player = player_query.get_single().ok();
+    let dt = time.delta_seconds();
+    let bottom = -play_area.off_screen(SCORE_CHIP_SIZE).y;
+
+    for (entity, mut chip, mut transform, mut sprite) in chip_query.iter_mut() {
+        chip.velocity.y = (chip.velocity.y - SCORE_CHIP_GRAVITY * dt).max(-SCORE_CHIP_FALL_SPEED);
+        transform.translation += chip.velocity.extend(0.0) * dt;
+
+        if player.is_some_and(|player| {
+            player.translation.distance(transform.translation) < SCORE_CHIP_PICKUP_RADIUS
+        }) {
+            score.0 += chip.value;
+            commands.despawn_if_exists(entity);
+            continue;
+        }
+        if chip.lifetime.tick(time.delta()).finished() || transform.translation.y < bottom {
+            score.0 += (chip.value as f32 * SCORE_CHIP_EXPIRED_SHARE) as u32;
+            commands.despawn_if_exists(entity);
+            continue;
+        }
+        sprite.color.set_a(chip.lifetime.remaining_secs().min(1.0));
+    }
+}
+
+fn power_up_movement(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut Transform), With<PowerUp>>,
+    play_area: Res<PlayArea>,
+    time: Res<Time>,
+) {
+    let bottom = -play_area.off_screen(POWER_UP_SIZE).y;
+    for (entity, mut transform) in query.iter_mut() {
+        transform.translation.y -= 80.0 * time.delta_seconds();
+
+        if transform.translation.y < bottom {
+            commands.despawn_if_exists(entity);
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn magnet_pickups(
+    radius: Res<MagnetRadius>,
+    player_query: Query<&Transform, With<Player>>,
+    mut power_up_query: Query<
+        &mut Transform,
+        (Or<(With<PowerUp>, With<ScoreChip>)>, Without<Player>),
+    >,
+    time: Res<Time>,
+) {
+    let Ok(player_transform) = player_query.get_single() else {
+        return;
+    };
+    let max_step = MAGNET_PULL_SPEED * time.delta_seconds();
+
+    for mut transform in power_up_query.iter_mut() {
+        let offset = (player_transform.translation - transform.translation).truncate();
+        if offset.length() < radius.0 {
+            transform.translation += offset.clamp_length_max(max_step).extend(0.0);
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn update_magnet_ring(
+    radius: Res<MagnetRadius>,
+    player_query: Query<&Transform, With<Player>>,
+    power_up_query: Query<(), Or<(With<PowerUp>, With<ScoreChip>)>>,
+    mut ring_query: Query<(&mut Transform, &mut Visibility), (With<MagnetRing>, Without<Player>)>,
+) {
+    let Ok((mut transform, mut visibility)) = ring_query.get_single_mut() else {
+        return;
+    };
+    let Ok(player_transform) = player_query.get_single() else {
+        return;
+    };
+
+    *visibility = if power_up_query.is_empty() {
+        Visibility::Hidden
+    } else {
+        Visibility::Visible
+    };
+    transform.translation = player_transform
+        .translation
+        .truncate()
+        .extend(layers::MAGNET_RING);
+    transform.scale = Vec3::splat(radius.0);
+}
+
+/// Follows the ship while reflect is active, shimmering unless reduce motion is on.
+#[allow(clippy::type_complexity)]
+fn update_reflect_aura(
+    power_ups: Res<ActivePowerUps>,
+    settings: Res<Settings>,
+    time: Res<Time>,
+    player_query: Query<&Transform, With<Player>>,
+    mut aura_query: Query<
+        (&mut Transform, &mut Visibility, &Handle<ColorMaterial>),
+        (With<ReflectAura>, Without<Player>),
+    >,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let Ok((mut transform, mut visibility, material)) = aura_query.get_single_mut() else {
+        return;
+    };
+    let Ok(player_transform) = player_query.get_single() else {
+        return;
+    };
+
+    if power_ups.reflect.is_none() {
+        *visibility = Visibility::Hidden;
+        return;
+    }
+    *visibility = Visibility::Visible;
+    transform.translation = player_transform
+        .translation
+        .truncate()
+        .extend(layers::AURAS);
+    if let Some(material) = materials.get_mut(material) {
+        let shimmer = if settings.reduce_motion {
+            0.0
+        } else {
+            REFLECT_SHIMMER * (time.elapsed_seconds() * 6.0).sin()
+        };
+        material.color.set_a(REFLECT_AURA_ALPHA + shimmer);
+    }
+}
+
+fn govern_performance(
+    mut governor: ResMut<PerformanceGovernor>,
+    diagnostics: Res<DiagnosticsStore>,
+    time: Res<Time<Real>>,
+) {
+    let Some(fps) = diagnostics
+        .get(FrameTimeDiagnosticsPlugin::FPS)
+        .and_then(|fps| fps.smoothed())
+    else {
+        return;
+    };
+    let crossing = if governor.struggling {
+        fps > GOVERNOR_HIGH_FPS
+    } else {
+        fps < GOVERNOR_LOW_FPS
+    };
+    if !crossing {
+        governor.crossing = 0.0;
+        return;
+    }
+    governor.crossing += time.delta_seconds();
+    if governor.crossing >= GOVERNOR_HOLD_SECS {
+        governor.struggling = !governor.struggling;
+        governor.crossing = 0.0;
+        info!(
+            "effects {} at {fps:.0} fps",
+            if governor.struggling {
+                "thinned"
+            } else {
+                "restored"
+            }
+        );
+    }
+}
+
+/// Only runs while playing, so time in menus and paused doesn't count.
+fn track_play_time(mut play_time: ResMut<PlayTime>, time: Res<Time<Real>>) {
+    let secs = time.delta_seconds_f64();
+    play_time.session += secs;
+    play_time.unbanked += secs;
+}
+
+/// Once the app is quitting, from the menu or by the window being closed, counts the
+/// run in progress so one quit partway through isn't lost: its play time is banked
+/// and its score can still set the high score. Sandbox runs and replays, which never
+/// set one at game over either, leave the high score alone.
+fn bank_on_exit(
+    mut exit_events: EventReader<AppExit>,
+    mut close_events: EventReader<WindowCloseRequested>,
+    mut banked: Local<bool>,
+    (mut play_time, mut profile): (ResMut<PlayTime>, ResMut<Profile>),
+    mut high_score: ResMut<HighScore>,
+    (score, sandbox, replay_mode): (Res<Score>, Res<Sandbox>, Res<ReplayMode>),
+) {
+    let quitting = exit_events.read().count() + close_events.read().count() > 0;
+    if !quitting || *banked {
+        return;
+    }
+    *banked = true;
+    play_time.bank(&mut profile);
+    if !sandbox.on && !replay_mode.is_on() && score.0 > high_score.0 {
+        high_score.0 = score.0;
+    }
+}
+
+/// Directory `save_on_exit` writes into. Empty, meaning the working directory, outside
+/// of tests.
+#[derive(Resource, Default)]
+struct SaveDir(PathBuf);
+
+/// Writes out everything persisted once `bank_on_exit` has counted the run. Each save
+/// only logs a failure, so none of them can hold up the exit. Settings are left alone
+/// during a replay, which swaps in the recorded ones.
+fn save_on_exit(
+    mut exit_events: EventReader<AppExit>,
+    mut close_events: EventReader<WindowCloseRequested>,
+    mut saved: Local<bool>,
+    (profile, save_dir): (Res<Profile>, Res<SaveDir>),
+    (settings, high_score, replay_mode): (Res<Settings>, Res<HighScore>, Res<ReplayMode>),
+) {
+    let quitting = exit_events.read().count() + close_events.read().count() > 0;
+    if !quitting || *saved {
+        return;
+    }
+    *saved = true;
+    profile.save_in(&save_dir.0);
+    high_score.save_in(&save_dir.0);
+    if !replay_mode.is_on() {
+        settings.save_in(&save_dir.0);
+    }
+}
+
+fn apply_profile(profile: Res<Profile>, mut radius: ResMut<MagnetRadius>) {
+    radius.0 = profile.magnet_radius();
+}
+
+fn player_power_up_collision(
+    mut commands: Commands,
+    mut power_ups: ResMut<ActivePowerUps>,
+    mut profile: ResMut<Profile>,
+    player_query: Query<&Transform, With<Player>>,
+    power_up_query: Query<(Entity, &Transform, &PowerUp)>,
+    barrier_query: Query<Entity, With<Barrier>>,
+) {
+    if let Ok(player_transform) = player_query.get_single() {
+        for (entity, transform, power_up) in power_up_query.iter() {
+            if player_transform.translation.distance(transform.translation) < 35.0 {
+                commands.despawn_if_exists(entity);
+
+                if power_up.kind == PowerUpKind::Barrier {
+                    for barrier in barrier_query.iter() {
+                        commands.despawn_if_exists(barrier);
+                    }
+                    spawn_barrier(&mut commands, player_transform.translation.truncate());
+                } else {
+                    power_ups.apply_power_up(power_up.kind);
+                }
+                // Saved with the rest of the profile at game over or on quitting.
+                *profile
+                    .bypass_change_detection()
+                    .stats
+                    .pickups
+                    .entry(power_up.kind)
+                    .or_default() += 1;
+            }
+        }
+    }
+}
+
+fn spawn_barrier(commands: &mut Commands, player_position: Vec2) {
+    let position = player_position + Vec2::Y * BARRIER_OFFSET;
+    commands
+        .spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    custom_size: Some(BARRIER_SIZE),
+                    color: BARRIER_COLOR,
+                    ..default()
+                },
+                transform: Transform::from_translation(position.extend(layers::BARRIERS)),
+                ..default()
+            },
+            Barrier {
+                hits_remaining: BARRIER_HITS,
+                timer: Timer::from_seconds(BARRIER_SECS, TimerMode::Once),
+            },
+        ))
+        .with_children(|parent| {
+            // Spread evenly along the bar, leaning alternate ways.
+            for i in 0..BARRIER_CRACKS {
+                let x = BARRIER_SIZE.x * ((i as f32 + 0.5) / BARRIER_CRACKS as f32 - 0.5);
+                let tilt = if i % 2 == 0 { 0.5 } else { -0.5 };
+                parent.spawn((
+                    SpriteBundle {
+                        sprite: Sprite {
+                            custom_size: Some(Vec2::new(2.0, BARRIER_SIZE.y * 1.5)),
+                            color: Color::BLACK,
+                            ..default()
+                        },
+                        transform: Transform::from_xyz(x, 0.0, 0.1)
+                            .with_rotation(Quat::from_rotation_z(tilt)),
+                        visibility: Visibility::Hidden,
+                        ..default()
+                    },
+                    BarrierCrack(i),
+                ));
+            }
+        });
+}
+
+/// Enemy bullets that reach a barrier stop there, each taking a hit off it. It breaks
+/// once out of hits, and fades away over the last quarter of its time.
+fn barrier_block(
+    mut commands: Commands,
+    mut barrier_query: Query<(Entity, &mut Barrier, &Transform, &mut Sprite, &Children)>,
+    mut crack_query: Query<(&BarrierCrack, &mut Visibility)>,
+    bullet_query: Query<(Entity, &Transform), With<EnemyBullet>>,
+    time: Res<Time>,
+) {
+    let reach = (BARRIER_SIZE + ENEMY_BULLET_SIZE) / 2.0;
+    for (entity, mut barrier, transform, mut sprite, children) in barrier_query.iter_mut() {
+        let centre = transform.translation.truncate();
+        for (bullet, bullet_transform) in bullet_query.iter() {
+            if barrier.hits_remaining == 0 {
+                break;
+            }
+            let offset = (bullet_transform.translation.truncate() - centre).abs();
+            if offset.x < reach.x && offset.y < reach.y {
+                commands.despawn_if_exists(bullet);
+                spawn_sparks(&mut commands, bullet_transform.translation, BARRIER_COLOR);
+                barrier.hits_remaining -= 1;
+            }
+        }
+
+        let cracks = (BARRIER_HITS - barrier.hits_remaining) * BARRIER_CRACKS / BARRIER_HITS;
+        for &child in children.iter() {
+            if let Ok((crack, mut visibility)) = crack_query.get_mut(child) {
+                *visibility = if crack.0 < cracks {
+                    Visibility::Inherited
+                } else {
+                    Visibility::Hidden
+                };
+            }
+        }
+
+        barrier.timer.tick(time.delta());
+        sprite
+            .color
+            .set_a((barrier.timer.percent_left() * 4.0).min(1.0));
+        if barrier.hits_remaining == 0 || barrier.timer.finished() {
+            commands.despawn_if_exists(entity);
+        }
+    }
+}
+
+fn tick_power_ups(mut power_ups: ResMut<ActivePowerUps>, time: Res<Time>) {
+    let power_ups = &mut *power_ups;
+    for slot in [
+        &mut power_ups.bounce,
+        &mut power_ups.rapid_fire,
+        &mut power_ups.pierce,
+        &mut power_ups.all_range,
+        &mut power_ups.flame,
+        &mut power_ups.reflect,
+    ] {
+        if let Some(timer) = slot.as_mut()
+            && timer.tick(time.delta()).finished()
+        {
+            *slot = None;
+        }
+    }
+    if power_ups.rapid_fire.is_none() {
+        power_ups.rapid_fire_tier = 0;
+    }
+}
+
+fn rapid_fire_feedback(
+    power_ups: Res<ActivePowerUps>,
+    mut player_query: Query<&mut Sprite, With<Player>>,
+    mut gauge_query: Query<&mut Visibility, With<RapidFireGauge>>,
+    mut fill_query: Query<&mut Style, With<RapidFireGaugeFill>>,
+) {
+    let timer = power_ups.rapid_fire.as_ref();
+
+    // Only the colour is touched; alpha belongs to the invincibility flash.
+    if let Ok(mut sprite) = player_query.get_single_mut() {
+        let color = if timer.is_some() {
+            RAPID_FIRE_COLOR
+        } else {
+            PLAYER_COLOR
+        };
+        sprite.color = color.with_a(sprite.color.a());
+    }
+    if let Ok(mut visibility) = gauge_query.get_single_mut() {
+        *visibility = if timer.is_some() {
+            Visibility::Visible
+        } else {
+            Visibility::Hidden
+        };
+    }
+    if let (Some(timer), Ok(mut style)) = (timer, fill_query.get_single_mut()) {
+        style.width = Val::Percent(timer.percent_left() * 100.0);
+    }
+}
+
+fn enemy_mine_laying(
+    mut commands: Commands,
+    mut query: Query<(&mut MineLayer, &Transform), Without<Frozen>>,
+    settings: Res<Settings>,
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+) {
+    if !settings.mines {
+        return;
+    }
+    for (mut layer, transform) in query.iter_mut() {
+        if layer
+            .timer
+            .tick(time.delta().mul_f32(time_scale.0))
+            .just_finished()
+        {
+            commands.spawn((
+                SpriteBundle {
+                    sprite: Sprite {
+                        custom_size: Some(Vec2::new(16.0, 16.0)),
+                        color: Color::PURPLE,
+                        ..default()
+                    },
+                    transform: Transform::from_translation(
+                        transform.translation.truncate().extend(layers::MINES),
+                    ),
+                    ..default()
+                },
+                Mine {
+                    lifetime: Timer::from_seconds(MINE_LIFETIME_SECS, TimerMode::Once),
+                },
+            ));
+        }
+    }
+}
+
+/// Removes hazards the settings have turned off, and power-ups a run modifier has,
+/// including any brought back by resuming a saved run.
+#[allow(clippy::type_complexity)]
+fn clear_disabled_hazards(
+    mut commands: Commands,
+    (settings, modifiers): (Res<Settings>, Res<Modifiers>),
+    bullet_query: Query<Entity, With<EnemyBullet>>,
+    mine_query: Query<Entity, Or<(With<Mine>, With<Blast>)>>,
+    power_up_query: Query<Entity, With<PowerUp>>,
+) {
+    if !modifiers.power_ups() {
+        for entity in power_up_query.iter() {
+            commands.despawn_if_exists(entity);
+        }
+    }
+    if !settings.enemy_bullets {
+        for entity in bullet_query.iter() {
+            commands.despawn_if_exists(entity);
+        }
+    }
+    if !settings.mines {
+        for entity in mine_query.iter() {
+            commands.despawn_if_exists(entity);
+        }
+    }
+}
+
+fn mine_proximity(
+    mut commands: Commands,
+    (mut score, mut destroyed_events): (ResMut<Score>, EventWriter<EnemyDestroyed>),
+    mut hit_events: EventWriter<PlayerHit>,
+    mut mine_query: Query<(Entity, &mut Mine, &Transform)>,
+    player_query: Query<(&Transform, Has<Invincible>), With<Player>>,
+    enemy_query: Query<(Entity, &Enemy, &Transform, Option<&Thief>)>,
+    (time, settings): (Res<Time>, Res<Settings>),
+) {
+    let Ok((player_transform, invincible)) = player_query.get_single() else {
+        return;
+    };
+    // Overlapping blasts must not score the same enemy twice.
+    let mut destroyed = HashSet::new();
+
+    for (mine_entity, mut mine, mine_transform) in mine_query.iter_mut() {
+        let position = mine_transform.translation;
+
+        if position.distance(player_transform.translation) < MINE_TRIGGER_RADIUS {
+            commands.despawn_if_exists(mine_entity);
+            spawn_blast(&mut commands, position);
+
+            if !invincible && position.distance(player_transform.translation) < MINE_BLAST_RADIUS {
+                hit_events.send(PlayerHit);
+            }
+            // The blast takes out any enemies caught in it.
+            for (enemy_entity, enemy, enemy_transform, thief) in enemy_query.iter() {
+                if position.distance(enemy_transform.translation) < MINE_BLAST_RADIUS
+                    && destroyed.insert(enemy_entity)
+                {
+                    commands.despawn_if_exists(enemy_entity);
+                    let points = 10 + thief.map_or(0, Thief::refund);
+                    award_kill(
+                        &mut commands,
+                        &mut score,
+                        &settings,
+                        enemy_transform.translation,
+                        points,
+                    );
+                    destroyed_events.send(EnemyDestroyed {
+                        kind: Some(enemy.kind),
+                        name: enemy.kind.name(),
+                        position: enemy_transform.translation.truncate(),
+                        points,
+                        multiplier: 1,
+                    });
+                }
+            }
+        } else if mine.lifetime.tick(time.delta()).finished() {
+            commands.despawn_if_exists(mine_entity);
+        }
+    }
+}
+
+fn spawn_blast(commands: &mut Commands, position: Vec3) {
+    commands.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                custom_size: Some(Vec2::splat(MINE_BLAST_RADIUS * 2.0)),
+                color: Color::rgba(1.0, 0.6, 0.0, 0.5),
+                ..default()
+            },
+            transform: Transform::from_translation(position.truncate().extend(layers::BLASTS)),
+            ..default()
+        },
+        Blast {
+            timer: Timer::from_seconds(0.3, TimerMode::Once),
+        },
+    ));
+}
+
+fn blast_fade(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut Blast, &mut Sprite)>,
+    time: Res<Time>,
+) {
+    for (entity, mut blast, mut sprite) in query.iter_mut() {
+        blast.timer.tick(time.delta());
+        sprite.color.set_a(0.5 * blast.timer.percent_left());
+
+        if blast.timer.finished() {
+            commands.despawn_if_exists(entity);
+        }
+    }
+}
+
+/// A hit that leaves lives to spare sends the ship back to the start with fresh
+/// invincibility, clearing whatever was waiting around it. A fatal one with a rewind
+/// left sends it back in time instead, to where it was `REWIND_SECS` ago.
+#[allow(clippy::type_complexity)]
+fn player_hit(
+    mut commands: Commands,
+    mut hit_events: EventReader<PlayerHit>,
+    (mut lives, mut power_ups, mut shake): (
+        ResMut<Lives>,
+        ResMut<ActivePowerUps>,
+        ResMut<ScreenShake>,
+    ),
+    (mut next_state, mut reason, mut rewind): (
+        ResMut<NextState<GameState>>,
+        ResMut<GameOverReason>,
+        ResMut<Rewind>,
+    ),
+    mut player_query: Query<(Entity, &mut Transform), (With<Player>, Without<Invincible>)>,
+    hazard_query: Query<
+        (Entity, &Transform, Has<EnemyBullet>),
+        (
+            Or<(With<Enemy>, With<EnemyBullet>, With<Mine>, With<Blast>)>,
+            Without<Player>,
+        ),
+    >,
+    (cheats, sandbox, settings): (Res<DebugCheats>, Res<Sandbox>, Res<Settings>),
+) {
+    if hit_events.is_empty() {
+        return;
+    }
+    // Several hazards can land in the same frame; only the first one costs a life.
+    hit_events.clear();
+
+    let Ok((player, mut transform)) = player_query.get_single_mut() else {
+        return;
+    };
+    if cheats.invincible || sandbox.invincible {
+        return;
+    }
+    shake.add(PLAYER_HIT_SHAKE);
+    let rewound = if lives.0 <= 1 { rewind.take() } else { None };
+    let (start, toast, toast_color) = match rewound {
+        Some(state) => {
+            *power_ups = state.power_ups;
+            let toast = format!("Rewound ({} left)", rewind.uses);
+            (state.translation, toast, REWIND_TOAST_COLOR)
+        }
+        None => {
+            lives.0 = lives.0.saturating_sub(1);
+            if lives.0 == 0 {
+                *reason = GameOverReason::ShipDestroyed;
+                next_state.set(GameState::GameOver);
+                return;
+            }
+            if !settings.keep_power_ups {
+                *power_ups = ActivePowerUps::default();
+            }
+            let start = Vec3::new(0.0, -200.0, layers::PLAYER);
+            (start, "Ship Restored".to_string(), RESPAWN_TOAST_COLOR)
+        }
+    };
+
+    transform.translation = start;
+    commands
+        .entity(player)
+        .insert(Invincible::from_seconds(HIT_IFRAME_SECS))
+        .remove::<Dashing>();
+    for (entity, hazard, is_bullet) in hazard_query.iter() {
+        if (is_bullet && settings.clear_bullets_on_hit)
+            || hazard.translation.truncate().distance(start.truncate()) < RESPAWN_CLEAR_RADIUS
+        {
+            commands.despawn_if_exists(entity);
+        }
+    }
+    commands.spawn((
+        Text2dBundle {
+            text: Text::from_section(
+                toast,
+                TextStyle {
+                    font_size: 22.0,
+                    color: toast_color,
+                    ..default()
+                },
+            ),
+            transform: Transform::from_translation(
+                (start.truncate() + Vec2::Y * 40.0).extend(layers::PARTICLES),
+            ),
+            ..default()
+        },
+        ChainPopup(Timer::from_seconds(CHAIN_POPUP_SECS, TimerMode::Once)),
+    ));
+}
+
+/// Keeps the last `REWIND_SECS` of the ship for `player_hit` to rewind to. A run with
+/// no rewinds left doesn't bother.
+fn record_rewind(
+    mut rewind: ResMut<Rewind>,
+    player_query: Query<&Transform, With<Player>>,
+    power_ups: Res<ActivePowerUps>,
+    time: Res<Time>,
+) {
+    let Ok(transform) = player_query.get_single() else {
+        return;
+    };
+    if rewind.uses == 0 {
+        return;
+    }
+    rewind.clock += time.delta_seconds();
+    let now = rewind.clock;
+    while rewind
+        .history
+        .front()
+        .is_some_and(|(taken, _)| now - taken > REWIND_SECS)
+    {
+        rewind.history.pop_front();
+    }
+    rewind.history.push_back((
+        now,
+        RewindState {
+            translation: transform.translation,
+            power_ups: power_ups.clone(),
+        },
+    ));
+}
+
+/// Clears the field of enemy fire as the run ends, with `clear_bullets_on_hit` on.
+fn clear_enemy_bullets(
+    mut commands: Commands,
+    settings: Res<Settings>,
+    bullet_query: Query<Entity, With<EnemyBullet>>,
+) {
+    if !settings.clear_bullets_on_hit {
+        return;
+    }
+    for entity in bullet_query.iter() {
+        commands.despawn_if_exists(entity);
+    }
+}
+
+/// Charges the escape penalty, and ends the run once more enemies have escaped than
+/// the difficulty allows.
+fn track_escapes(
+    mut escaped_events: EventReader<EnemyEscaped>,
+    mut escapes: ResMut<Escapes>,
+    (mut score, scoring): (ResMut<Score>, Res<ScoringConfig>),
+    difficulty: Res<Difficulty>,
+    mut reason: ResMut<GameOverReason>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let escaped = escaped_events.read().count() as u32;
+    escapes.0 += escaped;
+    score.0 = score.0.saturating_sub(escaped * scoring.escape_penalty);
+    if escapes.0 > Escapes::quota(&difficulty) {
+        *reason = GameOverReason::TooManyEscaped;
+        next_state.set(GameState::GameOver);
+    }
+}
+
+fn invincibility_flash(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut Invincible, &mut Sprite)>,
+    settings: Res<Settings>,
+    time: Res<Time>,
+) {
+    for (entity, mut invincible, mut sprite) in query.iter_mut() {
+        if invincible.timer.tick(time.delta()).finished() {
+            sprite.color.set_a(1.0);
+            commands.entity(entity).remove::<Invincible>();
+        } else if settings.reduce_motion {
+            sprite.color.set_a(0.5);
+        } else {
+            let blink_on = (invincible.timer.elapsed_secs() * 20.0).sin() > 0.0;
+            sprite.color.set_a(if blink_on { 1.0 } else { 0.2 });
+        }
+    }
+}
+
+fn bullet_time(
+    controls: Controls,
+    mut bullet_time: ResMut<BulletTime>,
+    mut time_scale: ResMut<TimeScale>,
+    hit_stop: Res<HitStop>,
+    time: Res<Time>,
+) {
+    let dt = time.delta_seconds();
+
+    bullet_time.active = controls.pressed(GameAction::BulletTime) && bullet_time.gauge > 0.0;
+    if bullet_time.active {
+        bullet_time.gauge = (bullet_time.gauge - BULLET_TIME_DRAIN_PER_SEC * dt).max(0.0);
+    } else {
+        bullet_time.gauge = (bullet_time.gauge + BULLET_TIME_REFILL_PER_SEC * dt).min(1.0);
+    }
+
+    time_scale.0 = if hit_stop.remaining > 0.0 {
+        0.0
+    } else if bullet_time.active {
+        BULLET_TIME_SCALE
+    } else {
+        1.0
+    };
+}
+
+fn update_bullet_time_ui(
+    bullet_time: Res<BulletTime>,
+    mut gauge_query: Query<&mut Style, With<BulletTimeGauge>>,
+    mut tint_query: Query<&mut Visibility, With<BulletTimeTint>>,
+) {
+    if let Ok(mut style) = gauge_query.get_single_mut() {
+        style.width = Val::Percent(bullet_time.gauge * 100.0);
+    }
+    if let Ok(mut visibility) = tint_query.get_single_mut() {
+        *visibility = if bullet_time.active {
+            Visibility::Visible
+        } else {
+            Visibility::Hidden
+        };
+    }
+}
+
+/// Freezes every enemy in the play area when the pulse is pressed and recharged.
+/// Enemies still entering the formation are out of its reach.
+#[allow(clippy::type_complexity)]
+fn freeze_pulse(
+    mut commands: Commands,
+    controls: Controls,
+    mut pulse: ResMut<FreezePulse>,
+    enemy_query: Query<(Entity, &Transform), (With<Enemy>, Without<Entering>)>,
+    (play_area, director): (Res<PlayArea>, Res<SpawnDirector>),
+    time: Res<Time>,
+) {
+    pulse.cooldown.tick(time.delta());
+    if !controls.just_pressed(GameAction::Freeze) || !pulse.cooldown.finished() {
+        return;
+    }
+    pulse.cooldown.reset();
+
+    for (entity, transform) in enemy_query.iter() {
+        let position = transform.translation.truncate();
+        if position.abs().cmpgt(play_area.half_size).any() {
+            continue;
+        }
+        commands.entity(entity).try_insert(Frozen {
+            timer: Timer::from_seconds(director.balance.freeze_secs, TimerMode::Once),
+        });
+        spawn_ice_crystals(&mut commands, position);
+    }
+}
+
+/// Runs on gameplay time, so bullet time stretches a freeze out along with everything
+/// else the enemies do.
+fn thaw_frozen(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut Frozen, &Transform)>,
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+) {
+    for (entity, mut frozen, transform) in query.iter_mut() {
+        if frozen
+            .timer
+            .tick(time.delta().mul_f32(time_scale.0))
+            .finished()
+        {
+            commands.entity(entity).remove::<Frozen>();
+            spawn_ice_crystals(&mut commands, transform.translation.truncate());
+        }
+    }
+}
+
+fn spawn_ice_crystals(commands: &mut Commands, position: Vec2) {
+    let mut rng = rand::thread_rng();
+    for i in 0..ICE_CRYSTAL_COUNT {
+        let angle = TAU * i as f32 / ICE_CRYSTAL_COUNT as f32 + rng.gen_range(-0.3..0.3);
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    custom_size: Some(Vec2::new(7.0, 3.0)),
+                    color: FROZEN_COLOR,
+                    ..default()
+                },
+                transform: Transform::from_translation(position.extend(layers::PARTICLES))
+                    .with_rotation(Quat::from_rotation_z(angle)),
+                ..default()
+            },
+            Particle {
+                velocity: Vec2::from_angle(angle) * rng.gen_range(30.0..60.0),
+                angular_velocity: rng.gen_range(-4.0..4.0),
+                gravity: 0.0,
+                lifetime: Timer::from_seconds(0.5, TimerMode::Once),
+            },
+        ));
+    }
+}
+
+fn update_freeze_gauge(
+    pulse: Res<FreezePulse>,
+    mut query: Query<&mut Style, With<FreezeGaugeFill>>,
+) {
+    if let Ok(mut style) = query.get_single_mut() {
+        style.width = Val::Percent(pulse.cooldown.percent() * 100.0);
+    }
+}
+
+fn update_graze_meter(meter: Res<GrazeMeter>, mut query: Query<&mut Style, With<GrazeMeterFill>>) {
+    if let Ok(mut style) = query.get_single_mut() {
+        style.width = Val::Percent(meter.0.min(1.0) * 100.0);
+    }
+}
+
+fn update_wave_progress(
+    waves: Res<WaveManager>,
+    enemy_query: Query<(), With<Enemy>>,
+    boss_query: Query<(), With<Boss>>,
+    mut ui_query: Query<&mut Visibility, With<WaveProgressUi>>,
+    mut fill_query: Query<&mut Style, With<WaveProgressFill>>,
+    mut text_query: Query<&mut Text, With<WaveText>>,
+) {
+    // Boss fights have their own health bar.
+    if let Ok(mut visibility) = ui_query.get_single_mut() {
+        *visibility = if boss_query.is_empty() {
+            Visibility::Visible
+        } else {
+            Visibility::Hidden
+        };
+    }
+    if let Ok(mut style) = fill_query.get_single_mut() {
+        let alive = enemy_query.iter().count() as u32;
+        style.width = Val::Percent(waves.progress(alive) * 100.0);
+    }
+    if let Ok(mut text) = text_query.get_single_mut() {
+        text.sections[0].value = format!("Wave {}", waves.wave);
+    }
+}
+
+fn update_combo(
+    mut missed_events: EventReader<Missed>,
+    mut escaped_events: EventReader<EnemyEscaped>,
+    settings: Res<Settings>,
+    mut combo: ResMut<Combo>,
+) {
+    for _ in missed_events.read() {
+        if settings.miss_penalty {
+            combo.count -= combo.count / 4;
+        }
+    }
+    if escaped_events.read().count() > 0 {
+        combo.count = 0;
+    }
+}
+
+/// Plays a rising chime each time the multiplier tiers up. `last_multiplier` follows
+/// the combo back down when it breaks, so the next climb starts from the low pitch again.
+fn enemy_spawn_sound(
+    mut commands: Commands,
+    query: Query<(&Enemy, &Transform), Added<Enemy>>,
+    (game_audio, play_area): (Res<GameAudio>, Res<PlayArea>),
+    settings: Res<Settings>,
+    time: Res<Time>,
+    mut last_played: Local<Option<f32>>,
+) {
+    if query.is_empty() {
+        return;
+    }
+    let now = time.elapsed_seconds();
+    if last_played.is_some_and(|last| now - last < SPAWN_CUE_COOLDOWN_SECS) {
+        return;
+    }
+    *last_played = Some(now);
+
+    // From the middle of everything that arrived together.
+    let count = query.iter().count() as f32;
+    let x = query
+        .iter()
+        .map(|(_, transform)| transform.translation.x)
+        .sum::<f32>()
+        / count;
+    let pan = sound_pan(x, &play_area);
+    if query
+        .iter()
+        .any(|(enemy, _)| enemy.kind != EnemyKind::Basic)
+    {
+        let volume = settings.sfx_volume * SPECIAL_SPAWN_CUE_VOLUME;
+        play_sound_panned(&mut commands, &game_audio.special_spawn, volume, pan);
+    } else {
+        let volume = settings.sfx_volume * SPAWN_CUE_VOLUME;
+        play_sound_panned(&mut commands, &game_audio.enemy_spawn, volume, pan);
+    }
+}
+
+fn combo_milestone_sound(
+    mut commands: Commands,
+    combo: Res<Combo>,
+    game_audio: Res<GameAudio>,
+    settings: Res<Settings>,
+    mut last_multiplier: Local<u32>,
+) {
+    if !combo.is_changed() {
+        return;
+    }
+    let multiplier = combo.multiplier();
+    if multiplier > *last_multiplier && multiplier > 1 {
+        let speed = 1.0 + COMBO_PITCH_STEP * (multiplier - 2) as f32;
+        play_sound_at_speed(
+            &mut commands,
+            &game_audio.combo_milestone,
+            settings.sfx_volume,
+            speed,
+        );
+    }
+    *last_multiplier = multiplier;
+}
+
+fn update_kill_feed(
+    mut destroyed_events: EventReader<EnemyDestroyed>,
+    mut feed: ResMut<KillFeed>,
+    settings: Res<Settings>,
+    mut ui_query: Query<&mut Visibility, With<KillFeedUi>>,
+    mut line_query: Query<(&KillFeedLine, &mut Text)>,
+    time: Res<Time>,
+) {
+    for kill in destroyed_events.read() {
+        let line = if kill.multiplier > 1 {
+            format!("{} +{} (x{})", kill.name, kill.points, kill.multiplier)
+        } else {
+            format!("{} +{}", kill.name, kill.points)
+        };
+        feed.0
+            .push_front((line, Timer::from_seconds(KILL_FEED_SECS, TimerMode::Once)));
+    }
+    feed.0.truncate(KILL_FEED_LEN);
+    for (_, timer) in feed.0.iter_mut() {
+        timer.tick(time.delta());
+    }
+    feed.0.retain(|(_, timer)| !timer.finished());
+
+    if let Ok(mut visibility) = ui_query.get_single_mut() {
+        *visibility = if settings.kill_feed {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+    for (line, mut text) in line_query.iter_mut() {
+        let section = &mut text.sections[0];
+        match feed.0.get(line.0) {
+            Some((value, timer)) => {
+                section.value.clone_from(value);
+                section.style.color.set_a(timer.remaining_secs().min(1.0));
+            }
+            None => section.value.clear(),
+        }
+    }
+}
+
+fn update_combo_text(
+    combo: Res<Combo>,
+    mut missed_events: EventReader<Missed>,
+    settings: Res<Settings>,
+    mut query: Query<(&mut Text, &mut ComboText)>,
+    time: Res<Time>,
+) {
+    let Ok((mut text, mut combo_text)) = query.get_single_mut() else {
+        return;
+    };
+    if missed_events.read().count() > 0 && settings.miss_penalty && combo.count > 0 {
+        combo_text.miss_flash.reset();
+    }
+    combo_text.miss_flash.tick(time.delta());
+
+    let section = &mut text.sections[0];
+    section.value = if combo.count > 0 {
+        format!("Combo {} (x{})", combo.count, combo.multiplier())
+    } else {
+        String::new()
+    };
+    section.style.color = if combo_text.miss_flash.finished() {
+        Color::WHITE
+    } else {
+        Color::RED
+    };
+}
+
+/// Lights the screen border once the multiplier passes x1, brighter and warmer with
+/// each tier, and lets it fade when the combo breaks or play stops.
+fn update_combo_glow(
+    combo: Res<Combo>,
+    state: Res<State<GameState>>,
+    settings: Res<Settings>,
+    mut query: Query<(&mut ComboGlow, &mut BorderColor)>,
+    time: Res<Time>,
+) {
+    let Ok((mut glow, mut border)) = query.get_single_mut() else {
+        return;
+    };
+    let tier = combo.multiplier() as usize - 1;
+    let playing = matches!(state.get(), GameState::Playing | GameState::Paused);
+    let target = if playing && tier > 0 {
+        glow.color = COMBO_GLOW_COLORS[(tier - 1).min(COMBO_GLOW_COLORS.len() - 1)];
+        COMBO_GLOW_MAX_ALPHA * tier as f32 / (MAX_COMBO_MULTIPLIER - 1) as f32
+    } else {
+        0.0
+    };
+    let blend = (COMBO_GLOW_FADE_SPEED * time.delta_seconds()).min(1.0);
+    glow.alpha += (target - glow.alpha) * blend;
+
+    let alpha = if settings.reduce_motion {
+        glow.alpha * COMBO_GLOW_REDUCED_SHARE
+    } else {
+        let pulse = (time.elapsed_seconds() * COMBO_GLOW_PULSE_HZ * TAU).sin();
+        glow.alpha * (1.0 + COMBO_GLOW_PULSE * pulse)
+    };
+    border.0 = glow.color.with_a(alpha.clamp(0.0, 1.0));
+}
+
+/// Shows lives out of the configured start, which a resumed run or a setting turned
+/// down mid-run can leave below what is left.
+fn update_lives_text(
+    lives: Res<Lives>,
+    settings: Res<Settings>,
+    modifiers: Res<Modifiers>,
+    mut query: Query<&mut Text, With<LivesText>>,
+) {
+    if let Ok(mut text) = query.get_single_mut() {
+        let max = modifiers.starting_lives(&settings).max(lives.0);
+        text.sections[0].value = format!("Lives: {}/{max}", lives.0);
+    }
+}
+
+fn update_escapes_text(
+    escapes: Res<Escapes>,
+    difficulty: Res<Difficulty>,
+    mut query: Query<&mut Text, With<EscapesText>>,
+) {
+    if let Ok(mut text) = query.get_single_mut() {
+        text.sections[0].value = format!("Escapes Left: {}", escapes.remaining(&difficulty));
+    }
+}
+
+fn update_bullet_indicator(
+    director: Res<SpawnDirector>,
+    bullet_query: Query<(), With<Bullet>>,
+    mut query: Query<(&mut Text, &mut Visibility), With<BulletIndicator>>,
+) {
+    let Ok((mut text, mut visibility)) = query.get_single_mut() else {
+        return;
+    };
+    let cap = director.balance.player_bullet_cap as usize;
+    if cap == 0 {
+        *visibility = Visibility::Hidden;
+        return;
+    }
+    *visibility = Visibility::Visible;
+    let left = cap.saturating_sub(bullet_query.iter().count());
+    text.sections[0].value = format!("Shots: {left}/{cap}");
+}
+
+/// Counts the shown score up towards `Score`, which stays the real value. A score that
+/// drops, as on restart, snaps straight down, and reduce motion always snaps.
+fn update_score_text(
+    score: Res<Score>,
+    bonus: Res<SurvivalBonus>,
+    settings: Res<Settings>,
+    time: Res<Time>,
+    mut displayed: Local<f32>,
+    mut query: Query<&mut Text, With<ScoreText>>,
+) {
+    let target = score.0 as f32;
+    if settings.reduce_motion || target < *displayed || target - *displayed < 1.0 {
+        *displayed = target;
+    } else {
+        *displayed +=
+            (target - *displayed) * (1.0 - (-SCORE_COUNT_RATE * time.delta_seconds()).exp());
+    }
+
+    if let Ok(mut text) = query.get_single_mut() {
+        text.sections[0].value = format!(
+            "Score: {} ({} survival)",
+            thousands(*displayed as u32),
+            thousands(bonus.earned)
+        );
+    }
+}
+
+/// Scales up whatever score was earned since last frame by the run's modifiers, from
+/// every source at once. Losses go through as they are, and a freshly inserted score
+/// (a new or resumed run) is taken as it stands. Fractions carry over.
+fn apply_score_modifiers(
+    mut score: ResMut<Score>,
+    modifiers: Res<Modifiers>,
+    mut last: Local<(u32, f32)>,
+) {
+    let (last_score, carry) = &mut *last;
+    if !score.is_added() && score.0 > *last_score {
+        let earned = (score.0 - *last_score) as f32 * modifiers.score() + *carry;
+        *carry = earned.fract();
+        score.0 = *last_score + earned as u32;
+    }
+    *last_score = score.0;
+}
+
+/// `12340` as `"12,340"`.
+fn thousands(value: u32) -> String {
+    let digits = value.to_string();
+    let mut grouped = String::with_capacity(digits.len() + digits.len() / 3);
+    for (index, digit) in digits.chars().enumerate() {
+        if index > 0 && (digits.len() - index).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+fn update_window_title(
+    score: Res<Score>,
+    title: Res<WindowTitle>,
+    mut window_query: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    if let Ok(mut window) = window_query.get_single_mut() {
+        window.title = format!("{} — Score: {}", title.0, score.0);
+    }
+}
+
+fn reset_window_title(
+    title: Res<WindowTitle>,
+    mut window_query: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    if let Ok(mut window) = window_query.get_single_mut() {
+        window.title = title.0.clone();
+    }
+}
+
+fn adjust_brightness(keyboard_input: Res<Input<KeyCode>>, mut settings: ResMut<Settings>) {
+    let step = if keyboard_input.just_pressed(KeyCode::Equals) {
+        BRIGHTNESS_STEP
+    } else if keyboard_input.just_pressed(KeyCode::Minus) {
+        -BRIGHTNESS_STEP
+    } else {
+        return;
+    };
+
+    settings.brightness = (settings.brightness + step).clamp(MIN_BRIGHTNESS, 1.0);
+    settings.save();
+}
+
+fn apply_key_bindings(settings: Res<Settings>, mut bindings: ResMut<KeyBindings>) {
+    *bindings = settings.key_bindings.clone();
+}
+
+fn apply_hud_settings(settings: Res<Settings>, mut hud: ResMut<HudConfig>) {
+    hud.set_if_neq(HudConfig::new(&settings));
+}
+
+/// Moves each `HudAnchor` into its group's corner and scales the HUD's text and gauges.
+/// A column on the right hugs its right edge, and on the left its left one.
+fn layout_hud(
+    hud: Res<HudConfig>,
+    mut anchor_query: Query<(&HudAnchor, &mut Style)>,
+    mut text_query: Query<(&HudText, &mut Text)>,
+) {
+    for (anchor, mut style) in anchor_query.iter_mut() {
+        let (left, top) = hud.corner(anchor.group);
+        let top = top != anchor.far_edge;
+        let offset = anchor.offset * hud.scale;
+        (style.left, style.right) = if left {
+            (Val::Px(offset.x), Val::Auto)
+        } else {
+            (Val::Auto, Val::Px(offset.x))
+        };
+        (style.top, style.bottom) = if top {
+            (Val::Px(offset.y), Val::Auto)
+        } else {
+            (Val::Auto, Val::Px(offset.y))
+        };
+        if let Some(size) = anchor.size {
+            style.width = Val::Px(size.x * hud.scale);
+            style.height = Val::Px(size.y * hud.scale);
+        }
+        if style.flex_direction == FlexDirection::Column {
+            style.align_items = if left {
+                AlignItems::FlexStart
+            } else {
+                AlignItems::FlexEnd
+            };
+        }
+    }
+    for (hud_text, mut text) in text_query.iter_mut() {
+        for section in &mut text.sections {
+            section.style.font_size = hud_text.0 * hud.scale;
+        }
+    }
+}
+
+/// Positions are all relative to the centre of the screen, and bounds follow the window
+/// through `PlayArea` each frame, so a new resolution mid-run only changes how much is
+/// in view.
+fn apply_window_settings(
+    settings: Res<Settings>,
+    mut ui_scale: ResMut<UiScale>,
+    mut window_query: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    ui_scale.0 = settings.ui_scale as f64;
+    if let Ok(mut window) = window_query.get_single_mut() {
+        let (width, height) = settings.resolution;
+        if window.resolution.width() != width as f32 || window.resolution.height() != height as f32
+        {
+            window.resolution.set(width as f32, height as f32);
+        }
+        window.mode = if settings.fullscreen {
+            WindowMode::BorderlessFullscreen
+        } else {
+            WindowMode::Windowed
+        };
+        window.present_mode = if settings.vsync {
+            PresentMode::AutoVsync
+        } else {
+            PresentMode::AutoNoVsync
+        };
+    }
+}
+
+/// Fits the camera's viewport to the letterbox aspect ratio, centred in the window,
+/// and sizes the bars to cover what is left over. Runs every frame to follow resizes.
+fn apply_letterbox(
+    settings: Res<Settings>,
+    mut play_area: ResMut<PlayArea>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    mut camera_query: Query<&mut Camera, With<Camera2d>>,
+    mut bar_query: Query<(&LetterboxBar, &mut Style)>,
+) {
+    let (Ok(window), Ok(mut camera)) = (window_query.get_single(), camera_query.get_single_mut())
+    else {
+        return;
+    };
+    let size = Vec2::new(
+        window.physical_width() as f32,
+        window.physical_height() as f32,
+    );
+    // Minimised.
+    if size.min_element() < 1.0 {
+        return;
+    }
+    let view = match settings.letterbox.aspect() {
+        Some(aspect) if size.x / size.y > aspect => Vec2::new(size.y * aspect, size.y),
+        Some(aspect) => Vec2::new(size.x, size.x / aspect),
+        None => size,
+    }
+    .floor();
+
+    let viewport = (view != size).then(|| Viewport {
+        physical_position: ((size - view) / 2.0).floor().as_uvec2(),
+        physical_size: view.as_uvec2(),
+        ..default()
+    });
+    let bounds = |viewport: &Option<Viewport>| {
+        viewport
+            .as_ref()
+            .map(|viewport| (viewport.physical_position, viewport.physical_size))
+    };
+    if bounds(&camera.viewport) != bounds(&viewport) {
+        camera.viewport = viewport;
+    }
+    play_area.set_if_neq(PlayArea {
+        half_size: view / window.scale_factor() as f32 / 2.0,
+    });
+
+    // Percentages, so the bars line up with the viewport whatever the UI scale.
+    let bar = (size - view) / size * 50.0;
+    let pillarbox = bar.x > 0.0;
+    for (side, mut style) in bar_query.iter_mut() {
+        let edge = |start: bool| if start { Val::Px(0.0) } else { Val::Auto };
+        let start = *side == LetterboxBar::Start;
+        style.set_if_neq(Style {
+            position_type: PositionType::Absolute,
+            left: edge(start || !pillarbox),
+            right: edge(!start && pillarbox),
+            top: edge(start || pillarbox),
+            bottom: edge(!start && !pillarbox),
+            width: Val::Percent(if pillarbox { bar.x } else { 100.0 }),
+            height: Val::Percent(if pillarbox { 100.0 } else { bar.y }),
+            ..default()
+        });
+    }
+}
+
+fn apply_brightness(
+    settings: Res<Settings>,
+    mut overlay_query: Query<&mut BackgroundColor, With<BrightnessOverlay>>,
+) {
+    if let Ok(mut background) = overlay_query.get_single_mut() {
+        background.0.set_a(1.0 - settings.brightness);
+    }
+}
+
+fn update_music_intensity(
+    game_audio: Res<GameAudio>,
+    asset_server: Res<AssetServer>,
+    enemy_query: Query<(), With<Enemy>>,
+    boss_query: Query<Has<BossIntro>, With<Boss>>,
+    mut stem_query: Query<(&mut MusicStem, &AudioSink)>,
+    (settings, waves): (Res<Settings>, Res<WaveManager>),
+    time: Res<Time>,
+) {
+    // Fall back to the base track alone if any of the layer stems is missing.
+    let layered = game_audio.music_stems[1..].iter().all(|stem| {
+        *stem != Handle::default() && asset_server.get_load_state(stem) != Some(LoadState::Failed)
+    });
+    let enemy_count = enemy_query.iter().count();
+    let blend = (MUSIC_FADE_SPEED * time.delta_seconds()).min(1.0);
+
+    let boss = boss_query.get_single().ok();
+
+    for (mut stem, sink) in stem_query.iter_mut() {
+        // The music drops out under the intro stinger, then comes back in full for the fight.
+        let audible = match boss {
+            Some(true) => false,
+            Some(false) => stem.layer == 0 || layered,
+            None => {
+                stem.layer == 0 || (layered && enemy_count >= MUSIC_LAYER_THRESHOLDS[stem.layer])
+            }
+        };
+        let target = match (audible, waves.break_timer.is_some()) {
+            (false, _) => 0.0,
+            (true, true) => MUSIC_BREAK_VOLUME,
+            (true, false) => 1.0,
+        };
+
+        stem.volume += (target - stem.volume) * blend;
+        sink.set_volume(stem.volume * settings.music_volume);
+    }
+}
+
+fn particle_update(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut Particle, &mut Transform, &mut Sprite)>,
+    time: Res<Time>,
+) {
+    let dt = time.delta_seconds();
+
+    for (entity, mut particle, mut transform, mut sprite) in query.iter_mut() {
+        particle.velocity.y += particle.gravity * dt;
+        transform.translation += particle.velocity.extend(0.0) * dt;
+        transform.rotate_z(particle.angular_velocity * dt);
+
+        particle.lifetime.tick(time.delta());
+        sprite.color.set_a(particle.lifetime.percent_left());
+
+        if particle.lifetime.finished() {
+            commands.despawn_if_exists(entity);
+        }
+    }
+}
+
+fn spawn_confetti(commands: &mut Commands, count: usize) {
+    let mut rng = rand::thread_rng();
+    let colors = [
+        Color::RED,
+        Color::YELLOW,
+        Color::GREEN,
+        Color::CYAN,
+        Color::FUCHSIA,
+    ];
+
+    for _ in 0..count {
+        let angle = rng.gen_range(0.3..(std::f32::consts::PI - 0.3));
+        let speed = rng.gen_range(250.0..550.0);
+
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    custom_size: Some(Vec2::new(8.0, 4.0)),
+                    color: *colors.choose(&mut rng).unwrap(),
+                    ..default()
+                },
+                transform: Transform::from_xyz(
+                    rng.gen_range(-100.0..100.0),
+                    -250.0,
+                    layers::PARTICLES,
+                ),
+                ..default()
+            },
+            Particle {
+                velocity: Vec2::from_angle(angle) * speed,
+                angular_velocity: rng.gen_range(-10.0..10.0),
+                gravity: CONFETTI_GRAVITY,
+                lifetime: Timer::from_seconds(rng.gen_range(1.5..2.5), TimerMode::Once),
+            },
+        ));
+    }
+}
+
+fn game_over(
+    mut commands: Commands,
+    score: Res<Score>,
+    mut high_score: ResMut<HighScore>,
+    settings: Res<Settings>,
+    (reason, waves, modifiers): (Res<GameOverReason>, Res<WaveManager>, Res<Modifiers>),
+    mut profile: ResMut<Profile>,
+    (kills, daily, mut play_time, governor): (
+        Res<RunKills>,
+        Res<DailyChallenge>,
+        ResMut<PlayTime>,
+        Res<PerformanceGovernor>,
+    ),
+) {
+    commands.spawn((
+        TextBundle::from_section(
+            "Game Over!",
+            TextStyle {
+                font_size: 50.0,
+                color: Color::RED,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            left: Val::Px(300.0),
+            top: Val::Px(250.0),
+            ..default()
+        }),
+        GameOverScreen,
+    ));
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(130.0),
+                    width: Val::Percent(100.0),
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                ..default()
+            },
+            GameOverScreen,
+        ))
+        .with_children(|parent| {
+            let mut message = reason.message().to_string();
+            if !modifiers.0.is_empty() {
+                message += &format!("\nModifiers: {}", modifiers.names().join(", "));
+            }
+            parent.spawn(
+                TextBundle::from_section(
+                    message,
+                    TextStyle {
+                        font_size: 30.0,
+                        color: Color::WHITE,
+                        ..default()
+                    },
+                )
+                .with_text_alignment(TextAlignment::Center),
+            );
+        });
+
+    discard_save();
+    let earned = score.0 / SCORE_PER_COIN;
+    profile.coins += earned;
+    for (&kind, &count) in &kills.by_kind {
+        *profile.kills.entry(kind).or_default() += count;
+    }
+    profile.boss_kills += kills.bosses;
+    profile.stats.record_run(score.0, waves.wave);
+    let daily_line = daily.day.map(|day| {
+        let date = format_day(day);
+        let best = profile.daily_best.entry(date.clone()).or_default();
+        *best = (*best).max(score.0);
+        format!("Daily Challenge {date}, best today {best}")
+    });
+    play_time.bank(&mut profile);
+    profile.save();
+
+    let total = kills.total();
+    let kill_line = if total == 0 {
+        "No kills".to_string()
+    } else {
+        format!("{total} kills: {}", kills.breakdown())
+    };
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(470.0),
+                    width: Val::Percent(100.0),
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                ..default()
+            },
+            GameOverScreen,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                kill_line,
+                TextStyle {
+                    font_size: 18.0,
+                    color: Color::GRAY,
+                    ..default()
+                },
+            ));
+        });
+    if let Some(daily_line) = daily_line {
+        commands
+            .spawn((
+                NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        top: Val::Px(495.0),
+                        width: Val::Percent(100.0),
+                        justify_content: JustifyContent::Center,
+                        ..default()
+                    },
+                    ..default()
+                },
+                GameOverScreen,
+            ))
+            .with_children(|parent| {
+                parent.spawn(TextBundle::from_section(
+                    daily_line,
+                    TextStyle {
+                        font_size: 18.0,
+                        color: DAILY_CHALLENGE_COLOR,
+                        ..default()
+                    },
+                ));
+            });
+    }
+    spawn_play_time_line(
+        &mut commands,
+        play_time.summary(&profile),
+        520.0,
+        GameOverScreen,
+    );
+    commands.spawn((
+        TextBundle::from_section(
+            format!("+{earned} coins"),
+            TextStyle {
+                font_size: 24.0,
+                color: Color::GOLD,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            left: Val::Px(350.0),
+            top: Val::Px(430.0),
+            ..default()
+        }),
+        GameOverScreen,
+    ));
+
+    let menu = Menu::new(
+        vec![
+            MenuAction::Restart,
+            MenuAction::UpgradeMagnet,
+            MenuAction::Quit,
+        ],
+        None,
+    );
+    spawn_menu(&mut commands, &menu, 310.0, GameOverScreen);
+    commands.insert_resource(menu);
+
+    if score.0 > high_score.0 {
+        high_score.0 = score.0;
+        high_score.save();
+
+        commands.spawn((
+            TextBundle::from_section(
+                "NEW HIGH SCORE!",
+                TextStyle {
+                    font_size: 40.0,
+                    color: Color::GOLD,
+                    ..default()
+                },
+            )
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                left: Val::Px(255.0),
+                top: Val::Px(190.0),
+                ..default()
+            }),
+            GameOverScreen,
+        ));
+        if !settings.reduce_motion {
+            spawn_confetti(&mut commands, governor.budget(&settings, CONFETTI_COUNT));
+        }
+    }
+}
+
+/// Steps in before game over with a continue, if they are on and there is one the
+/// player can afford. Running out of time is final.
+fn offer_continue(
+    mut next_state: ResMut<NextState<GameState>>,
+    reason: Res<GameOverReason>,
+    continues: Res<Continues>,
+    profile: Res<Profile>,
+    settings: Res<Settings>,
+) {
+    if next_state.0 == Some(GameState::GameOver)
+        && settings.continues
+        && *reason != GameOverReason::TimeUp
+        && profile.coins >= continues.cost()
+    {
+        next_state.set(GameState::Continue);
+    }
+}
+
+fn show_continue_screen(
+    mut commands: Commands,
+    mut continues: ResMut<Continues>,
+    profile: Res<Profile>,
+) {
+    continues.countdown.reset();
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(150.0),
+                    width: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                ..default()
+            },
+            ContinueScreen,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "Continue?",
+                TextStyle {
+                    font_size: 50.0,
+                    color: Color::GOLD,
+                    ..default()
+                },
+            ));
+            parent.spawn((
+                TextBundle::from_section(
+                    format!("{CONTINUE_COUNTDOWN_SECS:.0}"),
+                    TextStyle {
+                        font_size: 60.0,
+                        color: Color::WHITE,
+                        ..default()
+                    },
+                ),
+                ContinueCountdownText,
+            ));
+            let cost = match continues.cost() {
+                0 => format!("Free ({} left)", FREE_CONTINUES - continues.used),
+                cost => format!("{cost} of your {} coins", profile.coins),
+            };
+            parent.spawn(TextBundle::from_section(
+                cost,
+                TextStyle {
+                    font_size: 24.0,
+                    color: Color::WHITE,
+                    ..default()
+                },
+            ));
+        });
+
+    let menu = Menu::new(vec![MenuAction::TakeContinue, MenuAction::GiveUp], None);
+    spawn_menu(&mut commands, &menu, 360.0, ContinueScreen);
+    commands.insert_resource(menu);
+}
+
+/// Counts in real time; letting the offer run out is the same as giving up.
+fn continue_countdown(
+    mut continues: ResMut<Continues>,
+    mut text_query: Query<&mut Text, With<ContinueCountdownText>>,
+    mut next_state: ResMut<NextState<GameState>>,
+    time: Res<Time>,
+) {
+    if continues.countdown.tick(time.delta()).finished() {
+        next_state.set(GameState::GameOver);
+    }
+    if let Ok(mut text) = text_query.get_single_mut() {
+        text.sections[0].value = format!("{:.0}", continues.countdown.remaining_secs().ceil());
+    }
+}
+
+/// Picks the run back up on the same wave with full lives and a cut in score, after
+/// clearing the field so the ship isn't lost again straight away.
+#[allow(clippy::type_complexity)]
+fn handle_continue_menu(
+    mut commands: Commands,
+    mut selected_events: EventReader<MenuSelected>,
+    (mut continues, mut profile, settings, modifiers): (
+        ResMut<Continues>,
+        ResMut<Profile>,
+        Res<Settings>,
+        Res<Modifiers>,
+    ),
+    (mut score, mut lives, mut escapes): (ResMut<Score>, ResMut<Lives>, ResMut<Escapes>),
+    mut player_query: Query<(Entity, &mut Transform), With<Player>>,
+    hazard_query: Query<
+        Entity,
+        Or<(
+            With<Enemy>,
+            With<EnemyBullet>,
+            With<Mine>,
+            With<Blast>,
+            With<TelegraphZone>,
+        )>,
+    >,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    for MenuSelected(action) in selected_events.read() {
+        match action {
+            MenuAction::TakeContinue => {}
+            MenuAction::GiveUp => {
+                next_state.set(GameState::GameOver);
+                continue;
+            }
+            _ => continue,
+        }
+        let cost = continues.cost();
+        if profile.coins < cost {
+            continue;
+        }
+        if cost > 0 {
+            profile.coins -= cost;
+            profile.save();
+        }
+        continues.used += 1;
+
+        score.0 = (score.0 as f32 * CONTINUE_SCORE_FACTOR) as u32;
+        lives.0 = modifiers.starting_lives(&settings);
+        escapes.0 = 0;
+        for entity in hazard_query.iter() {
+            commands.despawn_if_exists(entity);
+        }
+        if let Ok((player, mut transform)) = player_query.get_single_mut() {
+            transform.translation = Vec3::new(0.0, -200.0, layers::PLAYER);
+            commands
+                .entity(player)
+                .insert(Invincible::from_seconds(HIT_IFRAME_SECS))
+                .remove::<Dashing>();
+        }
+        next_state.set(GameState::Playing);
+    }
+}
+
+fn toggle_debug_cheats(keyboard_input: Res<Input<KeyCode>>, mut cheats: ResMut<DebugCheats>) {
+    let ctrl = keyboard_input.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+    let shift = keyboard_input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+
+    if ctrl && shift && keyboard_input.just_pressed(KeyCode::D) {
+        cheats.enabled = !cheats.enabled;
+        if !cheats.enabled {
+            cheats.invincible = false;
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn debug_cheats(
+    mut commands: Commands,
+    keyboard_input: Res<Input<KeyCode>>,
+    mut cheats: ResMut<DebugCheats>,
+    mut score: ResMut<Score>,
+    (boss_config, director, play_area): (Res<BossConfig>, Res<SpawnDirector>, Res<PlayArea>),
+    clear_query: Query<
+        Entity,
+        Or<(
+            With<Enemy>,
+            With<EnemyBullet>,
+            With<Mine>,
+            With<Boss>,
+            With<Portal>,
+        )>,
+    >,
+) {
+    if !cheats.enabled {
+        return;
+    }
+
+    if keyboard_input.just_pressed(KeyCode::F1) {
+        cheats.invincible = !cheats.invincible;
+    }
+    if keyboard_input.just_pressed(KeyCode::F2) {
+        score.0 += 100;
+    }
+    if keyboard_input.just_pressed(KeyCode::F3) {
+        let y = play_area.enemy_spawn_y(&director.balance);
+        spawn_enemy_at(&mut commands, EnemyKind::Miner, Vec2::new(0.0, y));
+    }
+    if keyboard_input.just_pressed(KeyCode::F4) {
+        spawn_boss_entity(&mut commands, &boss_config);
+    }
+    if keyboard_input.just_pressed(KeyCode::F5) {
+        for entity in clear_query.iter() {
+            commands.despawn_if_exists(entity);
+        }
+    }
+}
+
+fn in_sandbox(sandbox: Res<Sandbox>) -> bool {
+    sandbox.on
+}
+
+#[allow(clippy::type_complexity)]
+fn sandbox_controls(
+    mut commands: Commands,
+    keyboard_input: Res<Input<KeyCode>>,
+    mut sandbox: ResMut<Sandbox>,
+    mut power_ups: ResMut<ActivePowerUps>,
+    (boss_config, director, play_area): (Res<BossConfig>, Res<SpawnDirector>, Res<PlayArea>),
+    boss_query: Query<(), With<Boss>>,
+    clear_query: Query<
+        Entity,
+        Or<(
+            With<Enemy>,
+            With<EnemyBullet>,
+            With<Mine>,
+            With<Boss>,
+            With<Portal>,
+            With<TelegraphZone>,
+        )>,
+    >,
+) {
+    let digits = [
+        KeyCode::Key1,
+        KeyCode::Key2,
+        KeyCode::Key3,
+        KeyCode::Key4,
+        KeyCode::Key5,
+        KeyCode::Key6,
+        KeyCode::Key7,
+        KeyCode::Key8,
+        KeyCode::Key9,
+        KeyCode::Key0,
+        KeyCode::Minus,
+        KeyCode::Equals,
+    ];
+    let y = play_area.enemy_spawn_y(&director.balance);
+    for (key, kind) in digits.into_iter().zip(EnemyKind::ALL) {
+        if keyboard_input.just_pressed(key) {
+            let x = rand::thread_rng().gen_range(-300.0..300.0);
+            spawn_enemy_at(&mut commands, kind, Vec2::new(x, y));
+        }
+    }
+    if keyboard_input.just_pressed(KeyCode::B) && boss_query.is_empty() {
+        spawn_boss_entity(&mut commands, &boss_config);
+    }
+    if keyboard_input.just_pressed(KeyCode::I) {
+        sandbox.invincible = !sandbox.invincible;
+    }
+    for (key, direction) in [(KeyCode::Q, -1), (KeyCode::E, 1)] {
+        if keyboard_input.just_pressed(key) {
+            sandbox.step_weapon(direction);
+            *power_ups = ActivePowerUps::default();
+        }
+    }
+    if keyboard_input.just_pressed(KeyCode::R) {
+        for entity in clear_query.iter() {
+            commands.despawn_if_exists(entity);
+        }
+    }
+
+    if keyboard_input.just_pressed(KeyCode::T) {
+        sandbox.preview = match sandbox.preview {
+            Some(_) => None,
+            None => PatternPreview::new(&boss_config, 0),
+        };
+    }
+    let Some(preview) = sandbox.preview else {
+        return;
+    };
+    let step = |less: KeyCode, more: KeyCode| {
+        keyboard_input.just_pressed(more) as i32 - keyboard_input.just_pressed(less) as i32
+    };
+    let source_step = step(KeyCode::BracketLeft, KeyCode::BracketRight);
+    if source_step != 0 {
+        let len = preview_sources(&boss_config).len() as i32;
+        let source = (preview.source as i32 + source_step).rem_euclid(len);
+        sandbox.preview = PatternPreview::new(&boss_config, source as usize);
+        return;
+    }
+    let (count_step, speed_step, angle_step) = (
+        step(KeyCode::Z, KeyCode::X),
+        step(KeyCode::C, KeyCode::V),
+        step(KeyCode::N, KeyCode::M),
+    );
+    if (count_step, speed_step, angle_step) == (0, 0, 0) {
+        return;
+    }
+    let mut pattern = preview.pattern;
+    let (count, speed, angle) = pattern.params_mut();
+    *count = count
+        .saturating_add_signed(count_step)
+        .clamp(1, MAX_PREVIEW_COUNT);
+    *speed = (*speed + PREVIEW_SPEED_STEP * speed_step as f32).max(PREVIEW_SPEED_STEP);
+    if let Some((_, angle)) = angle {
+        *angle = (*angle + PREVIEW_ANGLE_STEP * angle_step as f32).max(0.0);
+    }
+    sandbox.preview = Some(PatternPreview { pattern, ..preview });
+}
+
+/// Keeps an emitter at the top of the screen for as long as the sandbox has a pattern
+/// preview picked, firing it at the player on the attack's own interval. Picking or
+/// tweaking starts the rhythm over.
+#[allow(clippy::type_complexity)]
+fn pattern_preview(
+    mut commands: Commands,
+    sandbox: Res<Sandbox>,
+    mut emitter_query: Query<(Entity, &mut PatternEmitter, &Transform)>,
+    player_query: Query<&Transform, With<Player>>,
+    fire: EnemyFire,
+    (mut meshes, mut materials): (ResMut<Assets<Mesh>>, ResMut<Assets<ColorMaterial>>),
+    (play_area, time, time_scale): (Res<PlayArea>, Res<Time>, Res<TimeScale>),
+) {
+    let Some(preview) = sandbox.preview.filter(|_| sandbox.on) else {
+        for (entity, ..) in emitter_query.iter() {
+            commands.despawn_if_exists(entity);
+        }
+        return;
+    };
+    let Ok((_, mut emitter, transform)) = emitter_query.get_single_mut() else {
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    custom_size: Some(Vec2::splat(PREVIEW_EMITTER_SIZE)),
+                    color: SANDBOX_PANEL_COLOR,
+                    ..default()
+                },
+                transform: Transform::from_xyz(
+                    0.0,
+                    play_area.half_size.y - PREVIEW_EMITTER_INSET,
+                    layers::ENEMIES,
+                )
+                .with_rotation(Quat::from_rotation_z(FRAC_PI_4)),
+                ..default()
+            },
+            PatternEmitter {
+                fire_timer: Timer::from_seconds(preview.fire_interval, TimerMode::Repeating),
+                volleys: 0,
+            },
+        ));
+        return;
+    };
+    if sandbox.is_changed() {
+        emitter.fire_timer = Timer::from_seconds(preview.fire_interval, TimerMode::Repeating);
+        emitter.volleys = 0;
+    }
+    if !emitter
+        .fire_timer
+        .tick(time.delta().mul_f32(time_scale.0))
+        .just_finished()
+    {
+        return;
+    }
+
+    let target = player_query
+        .get_single()
+        .map_or(Vec2::ZERO, |transform| transform.translation.truncate());
+    fire_pattern(
+        &mut commands,
+        (&mut meshes, &mut materials),
+        &fire,
+        preview.pattern,
+        (transform.translation, target),
+        emitter.volleys,
+        (fire.room(), play_area.half_size.y),
+    );
+    emitter.volleys += 1;
+}
+
+/// Keeps a sandbox run from scoring or ending, and its picked weapon from running out.
+fn sandbox_rules(
+    sandbox: Res<Sandbox>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut score: ResMut<Score>,
+    (mut lives, settings): (ResMut<Lives>, Res<Settings>),
+    mut escapes: ResMut<Escapes>,
+    mut power_ups: ResMut<ActivePowerUps>,
+) {
+    if next_state.0 == Some(GameState::GameOver) {
+        next_state.0 = None;
+    }
+    // Only written when they differ, as this carries on while paused.
+    if score.0 != 0 {
+        score.0 = 0;
+    }
+    if lives.0 != settings.starting_lives {
+        lives.0 = settings.starting_lives;
+    }
+    if escapes.0 != 0 {
+        escapes.0 = 0;
+    }
+    if let Some(weapon) = sandbox.weapon
+        && !power_ups.is_active(weapon)
+    {
+        power_ups.grant(weapon);
+    }
+}
+
+fn update_sandbox_panel(
+    sandbox: Res<Sandbox>,
+    boss_config: Res<BossConfig>,
+    mut query: Query<(&mut Text, &mut Visibility), With<SandboxPanel>>,
+) {
+    let Ok((mut text, mut visibility)) = query.get_single_mut() else {
+        return;
+    };
+    *visibility = if sandbox.on {
+        Visibility::Visible
+    } else {
+        Visibility::Hidden
+    };
+    if !sandbox.on || !sandbox.is_changed() {
+        return;
+    }
+    let on_off = |value: bool| if value { "On" } else { "Off" };
+    text.sections[0].value = format!(
+        "SANDBOX\n\
+         0-9,-,= Spawn an enemy\n\
+         B    Spawn the boss\n\
+         I    Invincible: {}\n\
+         Q/E  Weapon: {}\n\
+         R    Clear the scene\n\
+         T    Pattern preview: {}",
+        on_off(sandbox.invincible),
+        sandbox.weapon.map_or("None", PowerUpKind::name),
+        on_off(sandbox.preview.is_some()),
+    );
+    if let Some(preview) = sandbox.preview {
+        let sources = preview_sources(&boss_config);
+        let name = sources.get(preview.source).map_or("", |(name, ..)| name);
+        let mut pattern = preview.pattern;
+        let (count, speed, angle) = pattern.params_mut();
+        text.sections[0].value += &format!(
+            "\n\
+             [/]  {name}: {}\n\
+             Z/X  Count: {count}\n\
+             C/V  Speed: {speed:.0}",
+            preview.pattern.name(),
+        );
+        if let Some((label, angle)) = angle {
+            text.sections[0].value += &format!("\nN/M  {label}: {angle:.2}");
+        }
+    }
+}
+
+/// Expires old spawn markers and, in debug mode, draws the rest fading with age. The
+/// dots sit just inside the top edge, since enemies spawn on it.
+fn spawn_debug(
+    cheats: Res<DebugCheats>,
+    mut heatmap: ResMut<SpawnHeatmap>,
+    time: Res<Time>,
+    mut gizmos: Gizmos,
+) {
+    let now = time.elapsed_seconds();
+    heatmap
+        .0
+        .retain(|&(_, spawned_at)| now - spawned_at < SPAWN_MARKER_SECS);
+    if !cheats.enabled {
+        return;
+    }
+    for &(position, spawned_at) in &heatmap.0 {
+        let fade = 1.0 - (now - spawned_at) / SPAWN_MARKER_SECS;
+        gizmos.circle_2d(
+            Vec2::new(position.x, position.y - 10.0),
+            6.0,
+            SPAWN_MARKER_COLOR.with_a(fade),
+        );
+    }
+}
+
+fn update_cheats_indicator(
+    cheats: Res<DebugCheats>,
+    mut query: Query<(&mut Text, &mut Visibility), With<CheatsIndicator>>,
+) {
+    if let Ok((mut text, mut visibility)) = query.get_single_mut() {
+        *visibility = if cheats.enabled {
+            Visibility::Visible
+        } else {
+            Visibility::Hidden
+        };
+        text.sections[0].value = if cheats.invincible {
+            "CHEATS (invincible)".into()
+        } else {
+            "CHEATS".into()
+        };
+    }
+}
+
+fn toggle_pause(
+    controls: Controls,
+    state: Res<State<GameState>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if controls.keyboard.just_pressed(KeyCode::Escape) || controls.just_pressed(GameAction::Pause) {
+        next_state.set(match state.get() {
+            GameState::Paused => GameState::Playing,
+            _ => GameState::Paused,
+        });
+    }
+}
+
+/// Escape belongs to the quit dialog while it is up, backing out of it rather than
+/// out of the pause menu too.
+fn quit_dialog_closed(dialog_query: Query<(), With<QuitDialog>>) -> bool {
+    dialog_query.is_empty()
+}
+
+/// Resuming stays manual so the game never springs back to life on refocus.
+fn pause_on_focus_lost(
+    mut focus_events: EventReader<WindowFocused>,
+    settings: Res<Settings>,
+    state: Res<State<GameState>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let lost_focus = focus_events.read().any(|event| !event.focused);
+    if lost_focus && settings.auto_pause && *state.get() == GameState::Playing {
+        next_state.set(GameState::Paused);
+    }
+}
+
+fn show_pause_screen(
+    mut commands: Commands,
+    pending_save: Res<PendingSave>,
+    (sandbox, daily): (Res<Sandbox>, Res<DailyChallenge>),
+    (play_time, profile): (Res<PlayTime>, Res<Profile>),
+) {
+    commands.spawn((
+        TextBundle::from_section(
+            "Paused",
+            TextStyle {
+                font_size: 40.0,
+                color: Color::WHITE,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            left: Val::Px(335.0),
+            top: Val::Px(240.0),
+            ..default()
+        }),
+        PauseScreen,
+    ));
+    spawn_play_time_line(
+        &mut commands,
+        play_time.summary(&profile),
+        200.0,
+        PauseScreen,
+    );
+
+    let items = if pending_save.0.is_some() {
+        vec![
+            MenuAction::ContinueRun,
+            MenuAction::NewRun,
+            MenuAction::Settings,
+            MenuAction::Quit,
+        ]
+    } else {
+        vec![
+            MenuAction::Resume,
+            MenuAction::Settings,
+            MenuAction::Stats,
+            MenuAction::Modifiers,
+            MenuAction::Restart,
+            if daily.day.is_some() {
+                MenuAction::LeaveDailyChallenge
+            } else {
+                MenuAction::DailyChallenge
+            },
+            if sandbox.on {
+                MenuAction::LeaveSandbox
+            } else {
+                MenuAction::Sandbox
+            },
+            MenuAction::Quit,
+        ]
+    };
+    // An offered run stays up until Continue or New Run is picked, so backing out
+    // can't throw it away by accident.
+    let back = pending_save.0.is_none().then_some(MenuAction::Resume);
+    let menu = Menu::new(items, back);
+    spawn_menu(&mut commands, &menu, 300.0, PauseScreen);
+    commands.insert_resource(menu);
+}
+
+fn spawn_play_time_line(commands: &mut Commands, line: String, top: f32, screen: impl Component) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(top),
+                    width: Val::Percent(100.0),
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                ..default()
+            },
+            screen,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                line,
+                TextStyle {
+                    font_size: 18.0,
+                    color: Color::GRAY,
+                    ..default()
+                },
+            ));
+        });
+}
+
+fn show_settings_screen(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::from_section(
+            "Settings",
+            TextStyle {
+                font_size: 40.0,
+                color: Color::WHITE,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            left: Val::Px(325.0),
+            top: Val::Px(15.0),
+            ..default()
+        }),
+        SettingsScreen,
+    ));
+
+    let menu = Menu::new(
+        vec![
+            MenuAction::MusicVolume,
+            MenuAction::SfxVolume,
+            MenuAction::Brightness,
+            MenuAction::Difficulty,
+            MenuAction::BaseDifficulty,
+            MenuAction::StartingLives,
+            MenuAction::Rewinds,
+            MenuAction::AdaptiveDifficulty,
+            MenuAction::ShipSpeed,
+            MenuAction::FireRate,
+            MenuAction::Fullscreen,
+            MenuAction::Resolution,
+            MenuAction::Letterbox,
+            MenuAction::UiScale,
+            MenuAction::HudLayout,
+            MenuAction::HudScale,
+            MenuAction::Vsync,
+            MenuAction::Shadows,
+            MenuAction::EffectsQuality,
+            MenuAction::StaticBackground,
+            MenuAction::Colorblind,
+            MenuAction::ReduceMotion,
+            MenuAction::ScreenShake,
+            MenuAction::AutoFire,
+            MenuAction::AimAssist,
+            MenuAction::AutoPause,
+            MenuAction::ConfirmQuitSetting,
+            MenuAction::MissPenalty,
+            MenuAction::WrapMode,
+            MenuAction::MoveSpread,
+            MenuAction::ElasticEdges,
+            MenuAction::KillFeed,
+            MenuAction::BulletCancel,
+            MenuAction::DodgingEnemies,
+            MenuAction::KeepPowerUps,
+            MenuAction::ClearBulletsOnHit,
+            MenuAction::ScoreChips,
+            MenuAction::Continues,
+            MenuAction::EnemyBullets,
+            MenuAction::Mines,
+            MenuAction::Analytics,
+            MenuAction::Controls,
+            MenuAction::ResetSettings,
+            MenuAction::Back,
+        ],
+        Some(MenuAction::Back),
+    );
+    spawn_menu(&mut commands, &menu, 65.0, SettingsScreen);
+    commands.insert_resource(menu);
+}
+
+/// Spawns `menu`'s items as a centred column `top` pixels down, tagged with the
+/// owning screen's marker so they are cleaned up along with it.
+fn spawn_menu(commands: &mut Commands, menu: &Menu, top: f32, screen: impl Component) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(top),
+                    bottom: Val::Px(10.0),
+                    width: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    // A menu too long for the window carries on in a second column.
+                    flex_wrap: FlexWrap::Wrap,
+                    align_items: AlignItems::Center,
+                    align_content: AlignContent::Center,
+                    row_gap: Val::Px(2.0),
+                    column_gap: Val::Px(40.0),
+                    // Room for the focus label.
+                    padding: UiRect::bottom(Val::Px(MENU_FOCUS_LABEL_HEIGHT)),
+                    ..default()
+                },
+                ..default()
+            },
+            screen,
+        ))
+        .with_children(|parent| {
+            for index in 0..menu.items.len() {
+                parent.spawn((
+                    // The label is filled in by `highlight_menu`.
+                    TextBundle::from_section(
+                        String::new(),
+                        TextStyle {
+                            font_size: 24.0,
+                            color: Color::WHITE,
+                            ..default()
+                        },
+                    )
+                    .with_style(Style {
+                        padding: UiRect::horizontal(Val::Px(6.0)),
+                        ..default()
+                    }),
+                    MenuItem { index },
+                ));
+            }
+            parent.spawn((
+                TextBundle::from_section(
+                    String::new(),
+                    TextStyle {
+                        font_size: 16.0,
+                        color: Color::GRAY,
+                        ..default()
+                    },
+                )
+                .with_style(Style {
+                    position_type: PositionType::Absolute,
+                    bottom: Val::Px(0.0),
+                    ..default()
+                }),
+                MenuFocusLabel,
+            ));
+        });
+}
+
+fn show_controls_screen(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::from_section(
+            "Controls",
+            TextStyle {
+                font_size: 40.0,
+                color: Color::WHITE,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            left: Val::Px(325.0),
+            top: Val::Px(40.0),
+            ..default()
+        }),
+        ControlsScreen,
+    ));
+
+    let items = GameAction::ALL
+        .into_iter()
+        .map(MenuAction::Rebind)
+        .chain([MenuAction::ResetBindings, MenuAction::Back])
+        .collect();
+    let menu = Menu::new(items, Some(MenuAction::Back));
+    spawn_menu(&mut commands, &menu, 110.0, ControlsScreen);
+    commands.insert_resource(menu);
+    commands.insert_resource(Rebinding::default());
+
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 20.0,
+                color: Color::ORANGE,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            left: Val::Px(10.0),
+            bottom: Val::Px(10.0),
+            ..default()
+        }),
+        RebindWarningText,
+        ControlsScreen,
+    ));
+}
+
+fn show_modifiers_screen(mut commands: Commands, modifiers: Res<ModifierConfig>) {
+    commands.spawn((
+        TextBundle::from_section(
+            "Modifiers",
+            TextStyle {
+                font_size: 40.0,
+                color: Color::WHITE,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            left: Val::Px(320.0),
+            top: Val::Px(40.0),
+            ..default()
+        }),
+        ModifiersScreen,
+    ));
+    spawn_play_time_line(
+        &mut commands,
+        "Picked modifiers apply from the next run".into(),
+        90.0,
+        ModifiersScreen,
+    );
+
+    let items = (0..modifiers.0.len())
+        .map(MenuAction::ToggleModifier)
+        .chain([MenuAction::Back])
+        .collect();
+    let menu = Menu::new(items, Some(MenuAction::Back));
+    spawn_menu(&mut commands, &menu, 130.0, ModifiersScreen);
+    commands.insert_resource(menu);
+}
+
+/// Lifetime totals from the profile, or a note that there are none yet.
+fn show_stats_screen(mut commands: Commands, profile: Res<Profile>, play_time: Res<PlayTime>) {
+    commands.spawn((
+        TextBundle::from_section(
+            "Stats",
+            TextStyle {
+                font_size: 40.0,
+                color: Color::WHITE,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            left: Val::Px(350.0),
+            top: Val::Px(40.0),
+            ..default()
+        }),
+        StatsScreen,
+    ));
+
+    let stats = &profile.stats;
+    let lines = if stats.games == 0 {
+        vec!["No finished runs yet. Totals start with your first game over.".to_string()]
+    } else {
+        let kills: u32 = profile.kills.values().sum();
+        vec![
+            format!("Games played: {}", stats.games),
+            format!("Total kills: {kills} ({} bosses)", profile.boss_kills),
+            format!("Best score: {}", stats.best_score),
+            format!("Best wave: {}", stats.best_wave),
+            format!(
+                "Play time: {}",
+                format_play_time(profile.play_secs + play_time.unbanked)
+            ),
+            format!(
+                "Favorite weapon: {}",
+                stats
+                    .favorite_weapon()
+                    .map_or("None yet", PowerUpKind::name)
+            ),
+        ]
+    };
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(120.0),
+                    width: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    row_gap: Val::Px(8.0),
+                    ..default()
+                },
+                ..default()
+            },
+            StatsScreen,
+        ))
+        .with_children(|parent| {
+            for line in lines {
+                parent.spawn(TextBundle::from_section(
+                    line,
+                    TextStyle {
+                        font_size: 24.0,
+                        color: Color::WHITE,
+                        ..default()
+                    },
+                ));
+            }
+        });
+
+    let menu = Menu::new(vec![MenuAction::Back], Some(MenuAction::Back));
+    spawn_menu(&mut commands, &menu, 400.0, StatsScreen);
+    commands.insert_resource(menu);
+}
+
+fn not_rebinding(rebinding: Res<Rebinding>) -> bool {
+    rebinding.action.is_none()
+}
+
+fn capture_rebind(
+    mut keyboard_input: ResMut<Input<KeyCode>>,
+    mut rebinding: ResMut<Rebinding>,
+    mut settings: ResMut<Settings>,
+) {
+    let Some(action) = rebinding.action else {
+        return;
+    };
+    let Some(&key) = keyboard_input.get_just_pressed().next() else {
+        return;
+    };
+    // Swallow the key so it doesn't also drive the menu this frame.
+    keyboard_input.clear_just_pressed(key);
+
+    if key == KeyCode::Escape {
+        *rebinding = Rebinding::default();
+        return;
+    }
+    if RESERVED_KEYS.contains(&key) {
+        rebinding.warning = Some(format!("{key:?} is reserved for menus"));
+        return;
+    }
+    if let Some(other) = settings.key_bindings.action_for(key)
+        && other != action
+    {
+        rebinding.warning = Some(format!("{key:?} is already bound to {}", other.label()));
+        return;
+    }
+
+    *settings.key_bindings.key_mut(action) = key;
+    settings.save();
+    *rebinding = Rebinding::default();
+}
+
+fn menu_navigation(
+    keyboard_input: Res<Input<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    mut menu: ResMut<Menu>,
+    mut selected_events: EventWriter<MenuSelected>,
+    mut adjusted_events: EventWriter<MenuAdjusted>,
+) {
+    if menu.items.is_empty() {
+        return;
+    }
+    let gamepad_pressed = |button_type| {
+        gamepads
+            .iter()
+            .any(|gamepad| gamepad_buttons.just_pressed(GamepadButton::new(gamepad, button_type)))
+    };
+
+    let count = menu.items.len();
+    let shift = keyboard_input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    let tab = keyboard_input.just_pressed(KeyCode::Tab);
+    if keyboard_input.any_just_pressed([KeyCode::Up, KeyCode::W])
+        || (tab && shift)
+        || gamepad_pressed(GamepadButtonType::DPadUp)
+    {
+        menu.selected = (menu.selected + count - 1) % count;
+    }
+    if keyboard_input.any_just_pressed([KeyCode::Down, KeyCode::S])
+        || (tab && !shift)
+        || gamepad_pressed(GamepadButtonType::DPadDown)
+    {
+        menu.selected = (menu.selected + 1) % count;
+    }
+    if keyboard_input.just_pressed(KeyCode::Home) {
+        menu.selected = 0;
+    }
+    if keyboard_input.just_pressed(KeyCode::End) {
+        menu.selected = count - 1;
+    }
+
+    let action = menu.items[menu.selected];
+    if keyboard_input.any_just_pressed([KeyCode::Left, KeyCode::A])
+        || gamepad_pressed(GamepadButtonType::DPadLeft)
+    {
+        adjusted_events.send(MenuAdjusted {
+            action,
+            direction: -1.0,
+        });
+    }
+    if keyboard_input.any_just_pressed([KeyCode::Right, KeyCode::D])
+        || gamepad_pressed(GamepadButtonType::DPadRight)
+    {
+        adjusted_events.send(MenuAdjusted {
+            action,
+            direction: 1.0,
+        });
+    }
+
+    // Space is left out so a held fire button can't skip the game-over screen.
+    if keyboard_input.just_pressed(KeyCode::Return) || gamepad_pressed(GamepadButtonType::South) {
+        selected_events.send(MenuSelected(action));
+    } else if (keyboard_input.any_just_pressed([KeyCode::Escape, KeyCode::Back])
+        || gamepad_pressed(GamepadButtonType::East))
+        && let Some(back) = menu.back
+    {
+        selected_events.send(MenuSelected(back));
+    }
+}
+
+/// Writes out each item's label, marking the focused one dark on gold with arrows
+/// either side, so it stands out without relying on colour. Labels are also handed
+/// to the platform's screen reader as they change. Items hidden under a dialog are
+/// left alone and kept from the screen reader.
+#[allow(clippy::type_complexity)]
+fn highlight_menu(
+    mut commands: Commands,
+    menu: Res<Menu>,
+    (settings, profile, rebinding, modifiers): (
+        Res<Settings>,
+        Res<Profile>,
+        Res<Rebinding>,
+        Res<ModifierConfig>,
+    ),
+    mut item_query: Query<(
+        Entity,
+        &MenuItem,
+        &mut Text,
+        &mut BackgroundColor,
+        &Visibility,
+        Has<AccessibilityNode>,
+    )>,
+    mut focus_label_query: Query<&mut Text, (With<MenuFocusLabel>, Without<MenuItem>)>,
+) {
+    let mut focus_line = String::new();
+    for (entity, item, mut text, mut background, visibility, has_node) in item_query.iter_mut() {
+        if visibility == Visibility::Hidden {
+            if has_node {
+                commands.entity(entity).remove::<AccessibilityNode>();
+            }
+            continue;
+        }
+        let Some(&action) = menu.items.get(item.index) else {
+            continue;
+        };
+        let label = match action {
+            MenuAction::Rebind(game_action) if rebinding.action == Some(game_action) => {
+                format!("{}: press a key (Esc to cancel)", game_action.label())
+            }
+            _ => action.label(&settings, &profile, &modifiers),
+        };
+
+        let focused = item.index == menu.selected;
+        if focused {
+            focus_line = format!("{label} ({} of {})", item.index + 1, menu.items.len());
+        }
+        let (value, color, back) = if focused {
+            (format!("> {label} <"), Color::BLACK, MENU_FOCUS_COLOR)
+        } else {
+            (label.clone(), Color::WHITE, Color::NONE)
+        };
+        // Only touched when they change, to spare relayout and screen reader updates.
+        if text.sections[0].value != value || !has_node {
+            let section = &mut text.sections[0];
+            section.value = value;
+            section.style.color = color;
+            background.0 = back;
+            let mut node = NodeBuilder::new(Role::MenuItem);
+            node.set_name(label);
+            commands
+                .entity(entity)
+                .try_insert(AccessibilityNode::from(node));
+        }
+    }
+    for mut text in focus_label_query.iter_mut() {
+        if text.sections[0].value != focus_line {
+            text.sections[0].value = focus_line.clone();
+        }
+    }
+}
+
+/// Points screen reader focus at the focused menu item, or nowhere once no menu is up.
+/// Runs once the frame's despawns are through, since focus on an entity that is gone
+/// would take the accessibility tree down with it.
+fn sync_menu_focus(
+    menu: Res<Menu>,
+    item_query: Query<(Entity, &MenuItem), With<AccessibilityNode>>,
+    mut focus: ResMut<Focus>,
+) {
+    let focused = item_query
+        .iter()
+        .find(|(_, item)| item.index == menu.selected)
+        .map(|(entity, _)| entity);
+    if focus.0 != focused {
+        focus.0 = focused;
+    }
+}
+
+fn handle_menu_action(
+    mut commands: Commands,
+    mut selected_events: EventReader<MenuSelected>,
+    state: Res<State<GameState>>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut restart_events: EventWriter<RestartRun>,
+    (mut sandbox, mut daily): (ResMut<Sandbox>, ResMut<DailyChallenge>),
+    launch_options: Res<LaunchOptions>,
+) {
+    for MenuSelected(action) in selected_events.read() {
+        match action {
+            // Going into or out of the sandbox or the daily challenge starts over, the
+            // same as a restart. The two don't mix, since the sandbox never scores.
+            MenuAction::Sandbox | MenuAction::LeaveSandbox => {
+                *sandbox = Sandbox {
+                    on: *action == MenuAction::Sandbox,
+                    ..default()
+                };
+                if daily.day.take().is_some() {
+                    commands.insert_resource(launch_options.rng());
+                }
+                discard_save();
+                restart_events.send(RestartRun);
+                next_state.set(GameState::Playing);
+            }
+            MenuAction::DailyChallenge | MenuAction::LeaveDailyChallenge => {
+                *sandbox = Sandbox::default();
+                daily.day = (*action == MenuAction::DailyChallenge).then(DailyChallenge::today);
+                // Left to carry on from the day's seed, the next run would be one
+                // anybody could predict.
+                if daily.day.is_none() {
+                    commands.insert_resource(launch_options.rng());
+                }
+                discard_save();
+                restart_events.send(RestartRun);
+                next_state.set(GameState::Playing);
+            }
+            // Leaving game over for Playing runs `reset_game`; a paused run asks for it,
+            // and drops the save made on pausing since that run is being abandoned.
+            MenuAction::Restart if *state.get() == GameState::Paused => {
+                discard_save();
+                restart_events.send(RestartRun);
+                next_state.set(GameState::Playing);
+            }
+            MenuAction::Resume | MenuAction::Restart => next_state.set(GameState::Playing),
+            MenuAction::Settings => next_state.set(GameState::Settings),
+            MenuAction::Controls => next_state.set(GameState::Controls),
+            MenuAction::Stats => next_state.set(GameState::Stats),
+            MenuAction::Modifiers => next_state.set(GameState::Modifiers),
+            MenuAction::Back => next_state.set(match state.get() {
+                GameState::Controls => GameState::Settings,
+                _ => GameState::Paused,
+            }),
+            _ => {}
+        }
+    }
+}
+
+/// Quit either closes the game straight away or, with the setting on, swaps in a
+/// yes/no menu until the player answers. The covered screen's items are hidden rather
+/// than despawned, so backing out leaves it exactly as it was.
+#[allow(clippy::type_complexity)]
+fn handle_quit_menu(
+    mut commands: Commands,
+    mut selected_events: EventReader<MenuSelected>,
+    mut exit_events: EventWriter<AppExit>,
+    (settings, mut menu, mut prompt): (Res<Settings>, ResMut<Menu>, ResMut<QuitPrompt>),
+    mut item_query: Query<&mut Visibility, Or<(With<MenuItem>, With<MenuFocusLabel>)>>,
+    dialog_query: Query<Entity, With<QuitDialog>>,
+) {
+    for MenuSelected(action) in selected_events.read() {
+        match action {
+            MenuAction::Quit if settings.confirm_quit => {
+                for mut visibility in item_query.iter_mut() {
+                    *visibility = Visibility::Hidden;
+                }
+                // No comes first, so a second press of Enter can't quit by accident.
+                let dialog = Menu::new(
+                    vec![MenuAction::CancelQuit, MenuAction::ConfirmQuit],
+                    Some(MenuAction::CancelQuit),
+                );
+                spawn_menu(&mut commands, &dialog, 340.0, QuitDialog);
+                prompt.covered = Some(std::mem::replace(&mut *menu, dialog));
+                commands.spawn((
+                    TextBundle::from_section(
+                        "Quit the game?",
+                        TextStyle {
+                            font_size: 30.0,
+                            color: Color::WHITE,
+                            ..default()
+                        },
+                    )
+                    .with_style(Style {
+                        position_type: PositionType::Absolute,
+                        left: Val::Px(310.0),
+                        top: Val::Px(300.0),
+                        ..default()
+                    }),
+                    QuitDialog,
+                ));
+            }
+            MenuAction::Quit | MenuAction::ConfirmQuit => exit_events.send(AppExit),
+            MenuAction::CancelQuit => {
+                for entity in dialog_query.iter() {
+                    commands.entity(entity).despawn_recursive();
+                }
+                for mut visibility in item_query.iter_mut() {
+                    *visibility = Visibility::Inherited;
+                }
+                if let Some(covered) = prompt.covered.take() {
+                    *menu = covered;
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn handle_controls_menu(
+    mut selected_events: EventReader<MenuSelected>,
+    mut rebinding: ResMut<Rebinding>,
+    mut settings: ResMut<Settings>,
+) {
+    for MenuSelected(action) in selected_events.read() {
+        match *action {
+            MenuAction::Rebind(game_action) => {
+                rebinding.action = Some(game_action);
+                rebinding.warning = None;
+            }
+            MenuAction::ResetBindings => {
+                settings.key_bindings = KeyBindings::default();
+                settings.save();
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Picks or drops a modifier for the next run, on confirm or left and right alike.
+fn handle_modifiers_menu(
+    mut selected_events: EventReader<MenuSelected>,
+    mut adjusted_events: EventReader<MenuAdjusted>,
+    mut settings: ResMut<Settings>,
+    modifiers: Res<ModifierConfig>,
+) {
+    let actions = selected_events
+        .read()
+        .map(|MenuSelected(action)| *action)
+        .chain(adjusted_events.read().map(|event| event.action));
+    let mut changed = false;
+    for action in actions {
+        let MenuAction::ToggleModifier(index) = action else {
+            continue;
+        };
+        let Some(modifier) = modifiers.0.get(index) else {
+            continue;
+        };
+        let picked = &mut settings.bypass_change_detection().modifiers;
+        if let Some(position) = picked.iter().position(|name| *name == modifier.name) {
+            picked.remove(position);
+        } else {
+            picked.push(modifier.name.clone());
+        }
+        changed = true;
+    }
+    if changed {
+        settings.set_changed();
+        settings.save();
+    }
+}
+
+fn handle_shop_menu(mut selected_events: EventReader<MenuSelected>, mut profile: ResMut<Profile>) {
+    for MenuSelected(action) in selected_events.read() {
+        if *action == MenuAction::UpgradeMagnet
+            && profile.bypass_change_detection().buy_magnet_upgrade()
+        {
+            profile.set_changed();
+            profile.save();
+        }
+    }
+}
+
+fn update_rebind_warning(
+    rebinding: Res<Rebinding>,
+    mut query: Query<&mut Text, With<RebindWarningText>>,
+) {
+    if let Ok(mut text) = query.get_single_mut() {
+        text.sections[0].value = rebinding.warning.clone().unwrap_or_default();
+    }
+}
+
+/// Applies settings changes as soon as they are made and saves them straight away.
+fn handle_settings_menu(
+    mut selected_events: EventReader<MenuSelected>,
+    mut adjusted_events: EventReader<MenuAdjusted>,
+    mut settings: ResMut<Settings>,
+) {
+    // Change detection is handled by hand so that menu input with nothing to adjust
+    // doesn't re-apply and re-save the settings.
+    let mut changed = false;
+    for MenuSelected(action) in selected_events.read() {
+        changed |= match action {
+            MenuAction::ResetSettings => {
+                *settings.bypass_change_detection() = Settings::default();
+                true
+            }
+            // Confirming a slider does nothing; toggles flip either way.
+            MenuAction::MusicVolume
+            | MenuAction::SfxVolume
+            | MenuAction::Brightness
+            | MenuAction::Difficulty
+            | MenuAction::BaseDifficulty
+            | MenuAction::StartingLives
+            | MenuAction::Rewinds
+            | MenuAction::Resolution
+            | MenuAction::UiScale
+            | MenuAction::HudScale
+            | MenuAction::ShipSpeed
+            | MenuAction::FireRate
+            | MenuAction::ScreenShake
+            | MenuAction::MoveSpread => false,
+            action => settings.bypass_change_detection().adjust(*action, 1.0),
+        };
+    }
+    for event in adjusted_events.read() {
+        changed |= settings
+            .bypass_change_detection()
+            .adjust(event.action, event.direction);
+    }
+
+    if changed {
+        settings.set_changed();
+        settings.save();
+    }
+}
+
+/// Runs last each frame, once every system has had its chance to ask for a new state.
+/// A fading change is taken back out of `NextState` and only put through once the
+/// screen has gone black, so the next screen's content is never seen swapping in;
+/// the screen then fades back in over it. Reduce motion puts changes straight through.
+fn fade_transitions(
+    mut fade: ResMut<ScreenFade>,
+    state: Res<State<GameState>>,
+    mut next_state: ResMut<NextState<GameState>>,
+    settings: Res<Settings>,
+    time: Res<Time<Real>>,
+    mut overlay_query: Query<(&mut BackgroundColor, &mut Visibility), With<FadeOverlay>>,
+) {
+    // A change asked for partway through a fade replaces the one waiting.
+    if let Some(next) = next_state.0 {
+        fade.target = None;
+        if !settings.reduce_motion && state.get().fades_to(next) {
+            next_state.0 = None;
+            fade.target = Some(next);
+        }
+    }
+    if settings.reduce_motion {
+        fade.alpha = 0.0;
+    }
+
+    let step = time.delta_seconds() / SCREEN_FADE_SECS;
+    if let Some(target) = fade.target {
+        fade.alpha = (fade.alpha + step).min(1.0);
+        if fade.alpha == 1.0 {
+            next_state.set(target);
+            fade.target = None;
+        }
+    } else if fade.alpha > 0.0 {
+        fade.alpha = (fade.alpha - step).max(0.0);
+    }
+
+    if let Ok((mut color, mut visibility)) = overlay_query.get_single_mut() {
+        color.0.set_a(fade.alpha);
+        *visibility = if fade.alpha > 0.0 {
+            Visibility::Visible
+        } else {
+            Visibility::Hidden
+        };
+    }
+}
+
+/// Whether anything was pressed this frame, on a keyboard, mouse or gamepad.
+fn any_input_pressed(
+    keyboard_input: &Input<KeyCode>,
+    mouse_buttons: &Input<MouseButton>,
+    gamepad_buttons: &Input<GamepadButton>,
+) -> bool {
+    keyboard_input.get_just_pressed().next().is_some()
+        || mouse_buttons.get_just_pressed().next().is_some()
+        || gamepad_buttons.get_just_pressed().next().is_some()
+}
+
+/// Counts how long the pause or game-over screen has sat untouched, and starts attract
+/// mode once it has been `ATTRACT_IDLE_SECS`.
+fn enter_attract_mode(
+    state: Res<State<GameState>>,
+    keyboard_input: Res<Input<KeyCode>>,
+    mouse_buttons: Res<Input<MouseButton>>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    time: Res<Time>,
+    mut next_attract: ResMut<NextState<AttractMode>>,
+    mut idle_secs: Local<f32>,
+) {
+    let on_menu = matches!(state.get(), GameState::Paused | GameState::GameOver);
+    if !on_menu
+        || state.is_changed()
+        || any_input_pressed(&keyboard_input, &mouse_buttons, &gamepad_buttons)
+    {
+        *idle_secs = 0.0;
+        return;
+    }
+    *idle_secs += time.delta_seconds();
+    if *idle_secs >= ATTRACT_IDLE_SECS {
+        *idle_secs = 0.0;
+        next_attract.set(AttractMode::On);
+    }
+}
+
+/// Ends attract mode on any press, and swallows the press so it doesn't also pick
+/// something on the menu underneath.
+fn leave_attract_mode(
+    mut keyboard_input: ResMut<Input<KeyCode>>,
+    mut mouse_buttons: ResMut<Input<MouseButton>>,
+    mut gamepad_buttons: ResMut<Input<GamepadButton>>,
+    mut next_attract: ResMut<NextState<AttractMode>>,
+) {
+    if any_input_pressed(&keyboard_input, &mouse_buttons, &gamepad_buttons) {
+        keyboard_input.clear();
+        mouse_buttons.clear();
+        gamepad_buttons.clear();
+        next_attract.set(AttractMode::Off);
+    }
+}
+
+fn show_attract_screen(mut commands: Commands) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    row_gap: Val::Px(40.0),
+                    ..default()
+                },
+                background_color: Color::rgba(0.0, 0.0, 0.0, 0.9).into(),
+                // Over every screen, but still under the fade between them.
+                z_index: ZIndex::Global(1),
+                ..default()
+            },
+            AttractScreen {
+                slide: 0,
+                timer: Timer::from_seconds(ATTRACT_SLIDE_SECS, TimerMode::Repeating),
+            },
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font_size: 36.0,
+                        color: Color::WHITE,
+                        ..default()
+                    },
+                )
+                .with_text_alignment(TextAlignment::Center),
+                AttractText,
+            ));
+            parent.spawn(TextBundle::from_section(
+                "Press any key",
+                TextStyle {
+                    font_size: 20.0,
+                    color: Color::GRAY,
+                    ..default()
+                },
+            ));
+        });
+}
+
+/// Moves the slideshow on every `ATTRACT_SLIDE_SECS`, looping back to the title.
+fn attract_slideshow(
+    mut screen_query: Query<&mut AttractScreen>,
+    mut text_query: Query<&mut Text, With<AttractText>>,
+    high_score: Res<HighScore>,
+    settings: Res<Settings>,
+    time: Res<Time>,
+) {
+    let (Ok(mut screen), Ok(mut text)) =
+        (screen_query.get_single_mut(), text_query.get_single_mut())
+    else {
+        return;
+    };
+    if screen.timer.tick(time.delta()).just_finished() {
+        screen.slide = (screen.slide + 1) % ATTRACT_SLIDES;
+    } else if !screen.is_added() {
+        return;
+    }
+    text.sections[0].value = match screen.slide {
+        0 => BASE_WINDOW_TITLE.to_uppercase(),
+        1 => format!("HIGH SCORE\n{}", thousands(high_score.0)),
+        _ => GameAction::ALL
+            .into_iter()
+            .map(|action| {
+                format!(
+                    "{}: {:?}",
+                    action.label(),
+                    settings.key_bindings.key(action)
+                )
+            })
+            .fold("CONTROLS".to_string(), |slide, line| slide + "\n" + &line),
+    };
+}
+
+fn despawn_screen<T: Component>(mut commands: Commands, query: Query<Entity, With<T>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn reset_game(
+    mut commands: Commands,
+    entity_query: Query<
+        Entity,
+        Or<(
+            With<Enemy>,
+            With<Bullet>,
+            With<PowerUp>,
+            With<ScoreChip>,
+            With<Mine>,
+            With<Blast>,
+            With<Boss>,
+            With<EnemyBullet>,
+            With<Particle>,
+            With<Afterimage>,
+            With<Portal>,
+            With<TelegraphZone>,
+            With<ChainPopup>,
+            With<Barrier>,
+            Or<(With<GameOverScreen>, With<QuitDialog>)>,
+        )>,
+    >,
+    mut player_query: Query<(Entity, &mut Player, &mut Transform, &mut Sprite)>,
+    (launch_options, settings, daily): (Res<LaunchOptions>, Res<Settings>, Res<DailyChallenge>),
+    mut director: ResMut<SpawnDirector>,
+    mut timeline: ResMut<SpawnTimeline>,
+    modifier_config: Res<ModifierConfig>,
+) {
+    for entity in entity_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    if let Ok((player, mut ship, mut transform, mut sprite)) = player_query.get_single_mut() {
+        ship.speed = PLAYER_SPEED * settings.ship_speed;
+        ship.fire_interval = PLAYER_FIRE_INTERVAL_SECS / settings.fire_rate;
+        transform.translation = Vec3::new(0.0, -200.0, layers::PLAYER);
+        sprite.color.set_a(1.0);
+        commands.entity(player).remove::<(Invincible, Dashing)>();
+    }
+
+    // Daily attempts are ranked against each other in `Profile::daily_best`, so they
+    // all play unmodified.
+    let modifiers = if daily.day.is_some() {
+        Modifiers::default()
+    } else {
+        Modifiers::pick(&modifier_config, &settings.modifiers)
+    };
+    commands.insert_resource(Score(0));
+    commands.insert_resource(Lives(modifiers.starting_lives(&settings)));
+    commands.insert_resource(modifiers);
+    commands.insert_resource(BaseDifficulty::for_run(&settings, &daily));
+    commands.insert_resource(ClearColor(BACKGROUND_COLOR));
+    commands.insert_resource(launch_options.power_ups());
+    commands.insert_resource(BulletTime::default());
+    commands.insert_resource(FreezePulse::default());
+    commands.insert_resource(TimeScale::default());
+    // A kill just before the restart mustn't carry its freeze or kick into the new run.
+    commands.insert_resource(HitStop::default());
+    commands.insert_resource(CameraPunch::default());
+    commands.insert_resource(ScreenShake::default());
+    // Every attempt at the day's challenge meets the same spawns from the start.
+    if let Some(day) = daily.day {
+        commands.insert_resource(DailyChallenge::rng(day));
+        commands.insert_resource(Difficulty::default());
+    } else {
+        commands.insert_resource(launch_options.difficulty(&settings));
+    }
+    commands.insert_resource(DangerTier::default());
+    commands.insert_resource(BossSchedule::default());
+    commands.insert_resource(WaveManager::default());
+    commands.insert_resource(Combo::default());
+    commands.insert_resource(KillFeed::default());
+    commands.insert_resource(Escapes::default());
+    commands.insert_resource(Continues::default());
+    commands.insert_resource(Rewind::new(settings.rewinds));
+    commands.insert_resource(GrazeMeter::default());
+    commands.insert_resource(SurvivalBonus::default());
+    commands.insert_resource(RunStats::default());
+    commands.insert_resource(RunKills::default());
+    commands.insert_resource(Dda::default());
+    commands.insert_resource(RunAnalytics::default());
+    commands.insert_resource(GameOverReason::default());
+    commands.insert_resource(GracePeriod::new(&director.balance));
+    director.reset();
+    timeline.seek(0.0);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::ecs::system::{CommandQueue, RunSystemOnce};
+    use bevy::time::TimeUpdateStrategy;
+
+    /// Moves the clock on by one frame of `secs`.
+    fn tick(world: &mut World, secs: f32) {
+        world
+            .resource_mut::<Time>()
+            .advance_by(Duration::from_secs_f32(secs));
+    }
+
+    fn despawn_all<T: Component>(world: &mut World) {
+        let entities: Vec<Entity> = world
+            .query_filtered::<Entity, With<T>>()
+            .iter(world)
+            .collect();
+        for entity in entities {
+            world.despawn(entity);
+        }
+    }
+
+    fn director_world(level: f32) -> World {
+        let mut world = World::new();
+        world.insert_resource(Time::<()>::default());
+        world.insert_resource(SpawnDirector::default());
+        world.insert_resource(Difficulty {
+            level,
+            speed_multiplier: 1.0,
+        });
+        world.insert_resource(BaseDifficulty(1.0));
+        world.insert_resource(EntranceConfig::default());
+        world.insert_resource(WaveManager {
+            total: u32::MAX,
+            ..default()
+        });
+        world.insert_resource(GameRng(StdRng::seed_from_u64(7)));
+        world.insert_resource(SpawnHeatmap::default());
+        world.init_resource::<PlayArea>();
+        world
+    }
+
+    fn test_player() -> Player {
+        Player {
+            speed: PLAYER_SPEED,
+            fire_interval: PLAYER_FIRE_INTERVAL_SECS,
+            velocity_x: 0.0,
+            recoil_x: 0.0,
+            shoot_timer: Timer::from_seconds(PLAYER_FIRE_INTERVAL_SECS, TimerMode::Repeating),
+            fire_buffer: 0.0,
+            dash_cooldown: ready_timer(DASH_COOLDOWN_SECS),
+            gun_offsets: vec![CENTER_GUN],
+        }
+    }
+
+    fn spawn_test_player(world: &mut World, position: Vec2) -> Entity {
+        world
+            .spawn((
+                test_player(),
+                Sprite {
+                    custom_size: Some(Vec2::from(Balance::default().player_size)),
+                    ..default()
+                },
+                Transform::from_translation(position.extend(layers::PLAYER)),
+            ))
+            .id()
+    }
+
+    fn spawn_test_enemy_bullet(world: &mut World, position: Vec2) -> Entity {
+        world
+            .spawn((
+                EnemyBullet {
+                    velocity: Vec2::ZERO,
+                    kind: EnemyBulletKind::Aimed,
+                    lifetime: Timer::from_seconds(BULLET_MAX_LIFETIME_SECS, TimerMode::Once),
+                },
+                Transform::from_translation(position.extend(layers::ENEMY_BULLETS)),
+            ))
+            .id()
+    }
+
+    /// Enemies the director sends over `secs` at 60 frames a second. The field is
+    /// cleared every frame, so only the budget holds it back.
+    fn spawns_over(world: &mut World, secs: f32) -> u32 {
+        for _ in 0..(secs * 60.0) as u32 {
+            tick(world, 1.0 / 60.0);
+            world.run_system_once(spawn_enemies);
+            despawn_all::<Enemy>(world);
+        }
+        world.resource::<WaveManager>().spawned
+    }
+
+    #[test]
+    fn higher_difficulty_spawns_more() {
+        let normal = spawns_over(&mut director_world(1.0), 30.0);
+        let hard = spawns_over(&mut director_world(2.0), 30.0);
+        assert!(normal > 0);
+        assert!(
+            hard > normal,
+            "{hard} spawns at 2.0 against {normal} at 1.0"
+        );
+    }
+
+    fn bullet_hits_player(invincible: bool) -> (usize, bool) {
+        let mut world = World::new();
+        world.init_resource::<Events<PlayerHit>>();
+        world.init_resource::<ActivePowerUps>();
+        world.init_resource::<SpawnDirector>();
+        let player = spawn_test_player(&mut world, Vec2::ZERO);
+        if invincible {
+            world
+                .entity_mut(player)
+                .insert(Invincible::from_seconds(HIT_IFRAME_SECS));
+        }
+        let bullet = spawn_test_enemy_bullet(&mut world, Vec2::ZERO);
+        world.run_system_once(enemy_bullet_player_collision);
+        (
+            world.resource::<Events<PlayerHit>>().len(),
+            world.get_entity(bullet).is_some(),
+        )
+    }
+
+    #[test]
+    fn invincible_player_ignores_bullets() {
+        assert_eq!(bullet_hits_player(true), (0, true));
+        assert_eq!(bullet_hits_player(false), (1, false));
+    }
+
+    fn launch(args: &str) -> LaunchOptions {
+        LaunchOptions::from_args(args.split_whitespace().map(String::from))
+    }
+
+    #[test]
+    fn launch_options_parse_loadout() {
+        let options = launch("--difficulty 3 --weapon pierce --seed 42 --god");
+        assert_eq!(options.difficulty, Some(3.0));
+        assert_eq!(options.weapon, Some(PowerUpKind::Pierce));
+        assert_eq!(options.seed, Some(42));
+        assert!(options.god_mode);
+        assert!(!options.sandbox);
+    }
+
+    #[test]
+    fn launch_options_fall_back_on_bad_values() {
+        let options = launch("--difficulty lots --weapon laser --seed -1 --bogus");
+        assert_eq!(options.difficulty, None);
+        assert_eq!(options.weapon, None);
+        assert_eq!(options.seed, None);
+        assert!(!options.god_mode);
+
+        let options = launch("--difficulty 99");
+        assert_eq!(options.difficulty, Some(MAX_DIFFICULTY));
+    }
+
+    fn combat_world() -> World {
+        let mut world = World::new();
+        world.insert_resource(Time::<()>::default());
+        world.insert_resource(Score(0));
+        world.init_resource::<Combo>();
+        world.init_resource::<ScoringConfig>();
+        world.init_resource::<CameraPunch>();
+        world.init_resource::<HitStop>();
+        world.insert_resource(Settings {
+            score_chips: false,
+            ..default()
+        });
+        world.insert_resource(GameRng(StdRng::seed_from_u64(7)));
+        world.init_resource::<Events<ChainKill>>();
+        world.init_resource::<Events<EnemyDestroyed>>();
+        world.init_resource::<SpawnDirector>();
+        world
+    }
+
+    fn spawn_test_enemy(world: &mut World, kind: EnemyKind, position: Vec2) -> Entity {
+        world
+            .spawn((
+                Enemy {
+                    speed: ENEMY_SPEED,
+                    kind,
+                    health: kind.max_health(),
+                },
+                Sprite {
+                    custom_size: Some(kind.size()),
+                    ..default()
+                },
+                Transform::from_translation(position.extend(layers::ENEMIES)),
+            ))
+            .id()
+    }
+
+    fn spawn_test_bullet(world: &mut World, position: Vec2, damage: u32) -> Entity {
+        world
+            .spawn((
+                Bullet {
+                    kind: BulletKind::Standard,
+                    velocity: Vec2::Y * 500.0,
+                    damage,
+                    lifetime: Timer::from_seconds(BULLET_MAX_LIFETIME_SECS, TimerMode::Once),
+                },
+                Sprite {
+                    custom_size: Some(BulletKind::Standard.size()),
+                    ..default()
+                },
+                Transform::from_translation(position.extend(layers::PLAYER_BULLETS)),
+            ))
+            .id()
+    }
+
+    #[test]
+    fn despawn_if_exists_tolerates_repeats() {
+        let mut world = World::new();
+        let entity = world.spawn_empty().id();
+        let mut queue = CommandQueue::default();
+        let mut commands = Commands::new(&mut queue, &world);
+        commands.despawn_if_exists(entity);
+        commands.despawn_if_exists(entity);
+        queue.apply(&mut world);
+        assert!(world.get_entity(entity).is_none());
+    }
+
+    #[test]
+    fn two_bullets_kill_an_enemy_once() {
+        let mut world = combat_world();
+        let enemy = spawn_test_enemy(&mut world, EnemyKind::Swarmer, Vec2::ZERO);
+        let first = spawn_test_bullet(&mut world, Vec2::ZERO, 1);
+        let second = spawn_test_bullet(&mut world, Vec2::ZERO, 1);
+        world.run_system_once(bullet_enemy_collision);
+
+        assert!(world.get_entity(enemy).is_none());
+        assert_eq!(world.resource::<Events<EnemyDestroyed>>().len(), 1);
+        // One bullet was spent on the kill; the other passes on through.
+        let left = [first, second]
+            .iter()
+            .filter(|&&bullet| world.get_entity(bullet).is_some())
+            .count();
+        assert_eq!(left, 1);
+    }
+
+    #[test]
+    fn partial_damage_scores_only_the_kill() {
+        let mut world = combat_world();
+        let enemy = spawn_test_enemy(&mut world, EnemyKind::Gunner, Vec2::ZERO);
+        spawn_test_bullet(&mut world, Vec2::ZERO, 2);
+        world.run_system_once(bullet_enemy_collision);
+
+        assert_eq!(world.get::<Enemy>(enemy).unwrap().health, 1);
+        assert_eq!(world.resource::<Score>().0, 0);
+        assert!(world.resource::<Events<EnemyDestroyed>>().is_empty());
+
+        spawn_test_bullet(&mut world, Vec2::ZERO, 2);
+        world.run_system_once(bullet_enemy_collision);
+
+        assert!(world.get_entity(enemy).is_none());
+        assert!(world.resource::<Score>().0 > 0);
+        assert_eq!(world.resource::<Events<EnemyDestroyed>>().len(), 1);
+    }
+
+    #[test]
+    fn each_escape_is_reported_once() {
+        let mut world = World::new();
+        world.init_resource::<PlayArea>();
+        world.init_resource::<Settings>();
+        world.init_resource::<Events<EnemyEscaped>>();
+        let bottom = -PlayArea::default().off_screen(EnemyKind::Basic.size()).y;
+        spawn_test_enemy(&mut world, EnemyKind::Basic, Vec2::new(-50.0, bottom - 1.0));
+        spawn_test_enemy(&mut world, EnemyKind::Basic, Vec2::new(50.0, bottom - 1.0));
+        let stayed = spawn_test_enemy(&mut world, EnemyKind::Basic, Vec2::new(0.0, bottom + 1.0));
+
+        world.run_system_once(enemy_escape);
+        world.run_system_once(enemy_escape);
+
+        assert_eq!(world.resource::<Events<EnemyEscaped>>().len(), 2);
+        assert!(world.get_entity(stayed).is_some());
+    }
+
+    #[test]
+    fn a_long_frame_spawns_no_more_than_the_cap() {
+        let mut world = director_world(MAX_DIFFICULTY);
+        let cap = world
+            .resource::<SpawnDirector>()
+            .balance
+            .max_spawns_per_frame as usize;
+        tick(&mut world, 30.0);
+        world.run_system_once(spawn_enemies);
+        assert_eq!(world.resource::<SpawnHeatmap>().0.len(), cap);
+
+        // The rest of the budget is still there for the frames after.
+        tick(&mut world, 1.0 / 60.0);
+        world.run_system_once(spawn_enemies);
+        assert!(world.resource::<SpawnHeatmap>().0.len() > cap);
+    }
+
+    /// How far a basic enemy moves in one tenth of a second at `speed_multiplier`.
+    fn enemy_step(speed_multiplier: f32) -> f32 {
+        let mut world = World::new();
+        world.insert_resource(Time::<()>::default());
+        world.init_resource::<PlayArea>();
+        world.init_resource::<TimeScale>();
+        world.init_resource::<Settings>();
+        world.insert_resource(Difficulty {
+            level: 1.0,
+            speed_multiplier,
+        });
+        world.insert_resource(BaseDifficulty(1.0));
+        world.init_resource::<Modifiers>();
+        world.insert_resource(GameRng(StdRng::seed_from_u64(7)));
+        world.init_resource::<SpawnDirector>();
+        let enemy = spawn_test_enemy(&mut world, EnemyKind::Basic, Vec2::ZERO);
+
+        tick(&mut world, 0.1);
+        world.run_system_once(enemy_movement);
+        -world.get::<Transform>(enemy).unwrap().translation.y
+    }
+
+    #[test]
+    fn speed_multiplier_scales_enemy_movement() {
+        let normal = enemy_step(1.0);
+        assert!(normal > 0.0);
+        assert!((enemy_step(2.0) - 2.0 * normal).abs() < 1e-3);
+    }
+
+    #[test]
+    fn gunners_at_the_cap_hold_their_fire() {
+        let mut world = World::new();
+        world.insert_resource(Time::<()>::default());
+        world.init_resource::<TimeScale>();
+        world.init_resource::<Difficulty>();
+        world.init_resource::<Settings>();
+        world.insert_resource(EnemyBulletCap {
+            base: 5,
+            per_level: 0,
+        });
+        spawn_test_player(&mut world, Vec2::new(0.0, -200.0));
+        spawn_test_enemy_bullet(&mut world, Vec2::new(0.0, 100.0));
+        for i in 0..10 {
+            world.spawn((
+                Gunner {
+                    cooldown: Timer::from_seconds(GUNNER_FIRE_INTERVAL_SECS, TimerMode::Repeating),
+                    wind_up: Some(Timer::from_seconds(0.01, TimerMode::Once)),
+                },
+                Transform::from_xyz(i as f32 * 40.0 - 200.0, 200.0, layers::ENEMIES),
+            ));
+        }
+
+        for _ in 0..3 {
+            tick(&mut world, 1.0);
+            world.run_system_once(gunner_shooting);
+        }
+        let live = world
+            .query_filtered::<(), With<EnemyBullet>>()
+            .iter(&world)
+            .count();
+        assert_eq!(live, 5);
+    }
+
+    #[test]
+    fn player_stays_in_bounds_with_either_edge() {
+        let limit = PlayArea::default().half_size.x - 50.0;
+        for elastic_edges in [false, true] {
+            let mut world = World::new();
+            world.init_resource::<PlayArea>();
+            world.insert_resource(Settings {
+                elastic_edges,
+                ..default()
+            });
+            let player = spawn_test_player(&mut world, Vec2::ZERO);
+            for x in [-1000.0, -limit - 1.0, limit + 0.5, 1000.0] {
+                let mut ship = world.entity_mut(player);
+                ship.get_mut::<Player>().unwrap().velocity_x = PLAYER_SPEED * f32::signum(x);
+                ship.get_mut::<Transform>().unwrap().translation.x = x;
+                world.run_system_once(confine_player_movement);
+
+                let ship = world.entity(player);
+                assert!(ship.get::<Transform>().unwrap().translation.x.abs() <= limit);
+                // Bouncing only ever pushes back inward.
+                let recoil = ship.get::<Player>().unwrap().recoil_x;
+                assert!(recoil * x <= 0.0);
+                assert_eq!(recoil != 0.0, elastic_edges);
+            }
+        }
+    }
+
+    #[test]
+    fn escaping_past_the_quota_ends_the_run() {
+        let mut world = World::new();
+        world.init_resource::<Events<EnemyEscaped>>();
+        world.init_resource::<Escapes>();
+        world.insert_resource(Score(1000));
+        world.init_resource::<ScoringConfig>();
+        world.init_resource::<Difficulty>();
+        world.init_resource::<GameOverReason>();
+        world.init_resource::<NextState<GameState>>();
+        let quota = Escapes::quota(&Difficulty::default());
+        // Registered, so its event reader carries over between the two runs.
+        let system = world.register_system(track_escapes);
+
+        for _ in 0..quota {
+            world.send_event(EnemyEscaped);
+        }
+        world.run_system(system).unwrap();
+        assert_eq!(world.resource::<NextState<GameState>>().0, None);
+
+        world.send_event(EnemyEscaped);
+        world.run_system(system).unwrap();
+        assert_eq!(world.resource::<Escapes>().0, quota + 1);
+        assert_eq!(
+            world.resource::<NextState<GameState>>().0,
+            Some(GameState::GameOver)
+        );
+        assert_eq!(
+            *world.resource::<GameOverReason>(),
+            GameOverReason::TooManyEscaped
+        );
+    }
+
+    /// Health an armored enemy has left after one shot `offset` from its centre.
+    fn armored_health_after_shot(offset: Vec2) -> u32 {
+        let mut world = combat_world();
+        let enemy = spawn_test_enemy(&mut world, EnemyKind::Armored, Vec2::ZERO);
+        world.entity_mut(enemy).insert(WeakPoint {
+            offset: Vec2::new(0.0, -14.0),
+            phase: 0.0,
+        });
+        let bullet = spawn_test_bullet(&mut world, offset, 1);
+        world.run_system_once(bullet_enemy_collision);
+        assert!(world.get_entity(bullet).is_none());
+        world.get::<Enemy>(enemy).unwrap().health
+    }
+
+    #[test]
+    fn only_the_weak_point_takes_damage() {
+        let full = EnemyKind::Armored.max_health();
+        assert_eq!(armored_health_after_shot(Vec2::new(0.0, -14.0)), full - 1);
+        assert_eq!(armored_health_after_shot(Vec2::new(0.0, 14.0)), full);
+        assert_eq!(armored_health_after_shot(Vec2::new(18.0, -14.0)), full);
+    }
+
+    fn timeline(times: &[f32]) -> SpawnTimeline {
+        SpawnTimeline {
+            events: times
+                .iter()
+                .enumerate()
+                .map(|(i, &time)| TimelineEvent {
+                    time,
+                    spawn: SpawnChoice::default(),
+                    x: i as f32,
+                })
+                .collect(),
+            ..default()
+        }
+    }
+
+    /// The `x` of each event `advance` hands back, standing in for its position in the list.
+    fn fired(timeline: &mut SpawnTimeline, secs: f32) -> Vec<f32> {
+        timeline.advance(secs).iter().map(|event| event.x).collect()
+    }
+
+    #[test]
+    fn timeline_fires_events_in_order_when_due() {
+        let mut timeline = timeline(&[0.5, 1.0, 1.0, 2.5]);
+        assert_eq!(fired(&mut timeline, 0.4), Vec::<f32>::new());
+        assert_eq!(fired(&mut timeline, 0.1), vec![0.0]);
+        assert_eq!(fired(&mut timeline, 0.6), vec![1.0, 2.0]);
+        assert_eq!(fired(&mut timeline, 1.0), Vec::<f32>::new());
+        // A long frame fires everything it passes, still in order.
+        assert_eq!(fired(&mut timeline, 5.0), vec![3.0]);
+        assert_eq!(fired(&mut timeline, 5.0), Vec::<f32>::new());
+    }
+
+    #[test]
+    fn timeline_seek_skips_passed_events() {
+        let mut timeline = timeline(&[0.5, 1.0, 2.5]);
+        timeline.seek(1.0);
+        assert_eq!(fired(&mut timeline, 2.0), vec![2.0]);
+        timeline.seek(0.0);
+        assert_eq!(fired(&mut timeline, 0.5), vec![0.0]);
+    }
+
+    /// What `player_hit` needs, with `lives` to lose.
+    fn hit_world(lives: u32) -> World {
+        let mut world = World::new();
+        world.init_resource::<Events<PlayerHit>>();
+        world.insert_resource(Lives(lives));
+        world.init_resource::<ActivePowerUps>();
+        world.init_resource::<ScreenShake>();
+        world.init_resource::<NextState<GameState>>();
+        world.init_resource::<GameOverReason>();
+        world.init_resource::<Rewind>();
+        world.init_resource::<DebugCheats>();
+        world.init_resource::<Sandbox>();
+        world.init_resource::<Settings>();
+        world.init_resource::<SpawnDirector>();
+        world
+    }
+
+    #[test]
+    fn quick_successive_hits_cost_one_life() {
+        let mut world = hit_world(3);
+        let mut frame = Schedule::default();
+        frame.add_systems((enemy_bullet_player_collision, player_hit).chain());
+
+        let start = Vec2::new(0.0, -200.0);
+        let player = spawn_test_player(&mut world, start);
+        // Two bullets landing together, then another the frame after the respawn.
+        spawn_test_enemy_bullet(&mut world, start);
+        spawn_test_enemy_bullet(&mut world, start);
+        frame.run(&mut world);
+        assert_eq!(world.resource::<Lives>().0, 2);
+        assert!(world.get::<Invincible>(player).is_some());
+
+        let position = world
+            .get::<Transform>(player)
+            .unwrap()
+            .translation
+            .truncate();
+        spawn_test_enemy_bullet(&mut world, position);
+        frame.run(&mut world);
+        assert_eq!(world.resource::<Lives>().0, 2);
+    }
+
+    #[test]
+    fn jittered_sprites_keep_the_fixed_hitbox() {
+        let mut world = combat_world();
+        let enemy = spawn_test_enemy(&mut world, EnemyKind::Basic, Vec2::ZERO);
+        // Drawn half again as big, but a shot past the real edge still misses.
+        world.get_mut::<Sprite>(enemy).unwrap().custom_size = Some(EnemyKind::Basic.size() * 1.5);
+        let edge = EnemyKind::Basic.hitbox(false, &Balance::default()).x / 2.0;
+        let bullet = spawn_test_bullet(&mut world, Vec2::new(edge + 2.0, 0.0), 1);
+        world.run_system_once(bullet_enemy_collision);
+        assert!(world.get_entity(bullet).is_some());
+
+        let bullet = spawn_test_bullet(&mut world, Vec2::new(edge - 2.0, 0.0), 1);
+        world.run_system_once(bullet_enemy_collision);
+        assert!(world.get_entity(bullet).is_none());
+    }
+
+    #[test]
+    fn ship_tuning_is_clamped_to_playable_ranges() {
+        let mut settings: Settings =
+            serde_json::from_str(r#"{"ship_speed": 9.0, "fire_rate": 0.0}"#).unwrap();
+        settings.clamp();
+        assert_eq!(settings.ship_speed, MAX_SHIP_SPEED);
+        assert_eq!(settings.fire_rate, MIN_FIRE_RATE);
+
+        for _ in 0..50 {
+            assert!(settings.adjust(MenuAction::FireRate, 1.0));
+            assert!(settings.adjust(MenuAction::ShipSpeed, -1.0));
+        }
+        assert_eq!(settings.fire_rate, MAX_FIRE_RATE);
+        assert_eq!(settings.ship_speed, MIN_SHIP_SPEED);
+    }
+
+    #[test]
+    fn sandbox_runs_neither_score_nor_end() {
+        let mut world = World::new();
+        world.insert_resource(Sandbox {
+            on: true,
+            weapon: Some(PowerUpKind::Pierce),
+            ..default()
+        });
+        let mut next_state = NextState::default();
+        next_state.set(GameState::GameOver);
+        world.insert_resource(next_state);
+        world.insert_resource(Score(250));
+        world.insert_resource(Lives(0));
+        world.init_resource::<Settings>();
+        world.insert_resource(Escapes(9));
+        world.init_resource::<ActivePowerUps>();
+
+        world.run_system_once(sandbox_rules);
+
+        assert_eq!(world.resource::<NextState<GameState>>().0, None);
+        assert_eq!(world.resource::<Score>().0, 0);
+        assert_eq!(
+            world.resource::<Lives>().0,
+            Settings::default().starting_lives
+        );
+        assert_eq!(world.resource::<Escapes>().0, 0);
+        assert!(
+            world
+                .resource::<ActivePowerUps>()
+                .is_active(PowerUpKind::Pierce)
+        );
+    }
+
+    fn destroyed(kind: Option<EnemyKind>, name: &'static str) -> EnemyDestroyed {
+        EnemyDestroyed {
+            name,
+            kind,
+            position: Vec2::ZERO,
+            points: 10,
+            multiplier: 1,
+        }
+    }
+
+    #[test]
+    fn kills_are_counted_by_kind() {
+        let mut world = World::new();
+        world.init_resource::<RunKills>();
+        world.init_resource::<Events<EnemyDestroyed>>();
+        for kind in [EnemyKind::Basic, EnemyKind::Diver, EnemyKind::Basic] {
+            world.send_event(destroyed(Some(kind), kind.name()));
+        }
+        world.send_event(destroyed(None, MINI_BOSS_NAME));
+        world.send_event(destroyed(None, "Boss"));
+        world.run_system_once(track_kills);
+
+        let kills = world.resource::<RunKills>();
+        assert_eq!(kills.by_kind[&EnemyKind::Basic], 2);
+        assert_eq!(kills.by_kind[&EnemyKind::Diver], 1);
+        assert!(!kills.by_kind.contains_key(&EnemyKind::Gunner));
+        assert_eq!((kills.mini_bosses, kills.bosses), (1, 1));
+        assert_eq!(kills.total(), 5);
+        assert_eq!(
+            kills.breakdown(),
+            format!("Basic 2, Diver 1, {MINI_BOSS_NAME} 1, Boss 1")
+        );
+    }
+
+    fn shooting_world() -> (World, Entity) {
+        let mut world = World::new();
+        world.insert_resource(Time::<()>::default());
+        world.insert_resource(GameAudio {
+            music_stems: Vec::new(),
+            boss_phase: default(),
+            boss_intro: default(),
+            combo_milestone: default(),
+            danger_rising: default(),
+            enemy_spawn: default(),
+            special_spawn: default(),
+            weapon_fire: HashMap::new(),
+        });
+        world.init_resource::<PlayArea>();
+        world.init_resource::<Input<KeyCode>>();
+        world.init_resource::<KeyBindings>();
+        world.init_resource::<TouchControls>();
+        world.init_resource::<ActivePowerUps>();
+        world.init_resource::<Aim>();
+        world.init_resource::<Settings>();
+        world.insert_resource(GameRng(StdRng::seed_from_u64(7)));
+        world.init_resource::<SpawnDirector>();
+        let player = spawn_test_player(&mut world, Vec2::new(0.0, -200.0));
+        (world, player)
+    }
+
+    fn bullet_count(world: &mut World) -> usize {
+        world
+            .query_filtered::<(), With<Bullet>>()
+            .iter(world)
+            .count()
+    }
+
+    /// Taps fire once with `secs` left until the next shot is due, then lets the
+    /// timer run out. Returns how many bullets that fired.
+    fn tap_before_shot(secs: f32) -> usize {
+        let (mut world, player) = shooting_world();
+        let fire = world.resource::<KeyBindings>().key(GameAction::Fire);
+        world
+            .get_mut::<Player>(player)
+            .unwrap()
+            .shoot_timer
+            .set_elapsed(Duration::from_secs_f32(
+                PLAYER_FIRE_INTERVAL_SECS - secs - 0.01,
+            ));
+
+        world.resource_mut::<Input<KeyCode>>().press(fire);
+        tick(&mut world, 0.01);
+        world.run_system_once(player_shooting);
+        let mut keyboard = world.resource_mut::<Input<KeyCode>>();
+        keyboard.release(fire);
+        keyboard.clear();
+
+        let mut waited = 0.0;
+        while waited < secs + 0.05 {
+            tick(&mut world, 0.01);
+            world.run_system_once(player_shooting);
+            waited += 0.01;
+        }
+        bullet_count(&mut world)
+    }
+
+    #[test]
+    fn early_taps_fire_when_the_shot_is_due() {
+        assert!(tap_before_shot(FIRE_BUFFER_SECS / 2.0) > 0);
+        assert_eq!(tap_before_shot(FIRE_BUFFER_SECS * 2.0), 0);
+    }
+
+    #[test]
+    fn weighted_pick_skips_zero_weights() {
+        let mut rng = StdRng::seed_from_u64(7);
+        assert_eq!(weighted_pick(&mut rng, &[("a", 0.0), ("b", 0.0)]), None);
+        for _ in 0..100 {
+            assert_eq!(
+                weighted_pick(&mut rng, &[("a", 0.0), ("b", 1.0), ("c", 0.0)]),
+                Some("b")
+            );
+        }
+    }
+
+    /// Share of single spawns at `level` that are basic enemies, with every kind open.
+    fn basic_share(level: f32) -> f32 {
+        let mut rng = StdRng::seed_from_u64(7);
+        let unlocked: HashSet<EnemyKind> = EnemyKind::ALL.into_iter().collect();
+        let weights = Balance::default().spawn_weights;
+        let (mut singles, mut basics) = (0, 0);
+        for _ in 0..5000 {
+            if let SpawnChoice::Single(kind) =
+                roll_spawn_choice(&mut rng, &weights, level, &unlocked)
+            {
+                singles += 1;
+                basics += (kind == EnemyKind::Basic) as u32;
+            }
+        }
+        basics as f32 / singles as f32
+    }
+
+    #[test]
+    fn harder_runs_roll_fewer_basic_enemies() {
+        let early = basic_share(1.0);
+        let late = basic_share(MAX_DIFFICULTY);
+        assert!(
+            late < early - 0.1,
+            "{late} basic late against {early} early"
+        );
+    }
+
+    #[test]
+    fn stuck_bullets_burn_out() {
+        let mut world = World::new();
+        world.insert_resource(Time::<()>::default());
+        world.init_resource::<PlayArea>();
+        world.init_resource::<Settings>();
+        world.init_resource::<TimeScale>();
+        world.init_resource::<Events<Missed>>();
+        let bullet = spawn_test_bullet(&mut world, Vec2::ZERO, 1);
+        world.get_mut::<Bullet>(bullet).unwrap().velocity = Vec2::ZERO;
+        let enemy_bullet = spawn_test_enemy_bullet(&mut world, Vec2::ZERO);
+        let mut frame = Schedule::default();
+        frame.add_systems((bullet_movement, enemy_bullet_movement));
+
+        tick(&mut world, BULLET_MAX_LIFETIME_SECS - 0.5);
+        frame.run(&mut world);
+        assert!(world.get_entity(bullet).is_some());
+        assert!(world.get_entity(enemy_bullet).is_some());
+
+        tick(&mut world, 1.0);
+        frame.run(&mut world);
+        assert!(world.get_entity(bullet).is_none());
+        assert!(world.get_entity(enemy_bullet).is_none());
+    }
+
+    #[test]
+    fn sideways_shots_leave_wholly_out_of_view() {
+        let mut world = World::new();
+        world.insert_resource(Time::<()>::default());
+        world.init_resource::<PlayArea>();
+        world.init_resource::<Settings>();
+        world.init_resource::<Events<Missed>>();
+        let edge = world.resource::<PlayArea>().half_size.x;
+        let bullet = spawn_test_bullet(&mut world, Vec2::new(edge, 0.0), 1);
+        world.get_mut::<Bullet>(bullet).unwrap().velocity = Vec2::X * 500.0;
+
+        // Past the edge, but with half of its length still in view.
+        tick(&mut world, 0.01);
+        world.run_system_once(bullet_movement);
+        assert!(world.get_entity(bullet).is_some());
+
+        tick(&mut world, 0.2);
+        world.run_system_once(bullet_movement);
+        assert!(world.get_entity(bullet).is_none());
+        assert_eq!(world.resource::<Events<Missed>>().len(), 1);
+    }
+
+    #[test]
+    fn play_time_reads_as_hours_minutes_seconds() {
+        assert_eq!(format_play_time(0.0), "0:00:00");
+        assert_eq!(format_play_time(65.9), "0:01:05");
+        assert_eq!(
+            format_play_time(3.0 * 3600.0 + 40.0 * 60.0 + 51.0),
+            "3:40:51"
+        );
+    }
+
+    #[test]
+    fn pausing_stops_the_play_clock() {
+        let mut world = World::new();
+        world.init_resource::<PlayTime>();
+        let mut real = Time::<Real>::default();
+        real.update_with_duration(Duration::ZERO);
+        world.insert_resource(real);
+        let mut frame = Schedule::default();
+        frame.add_systems(track_play_time.run_if(in_state(GameState::Playing)));
+        let mut play_for = |world: &mut World, state: GameState, secs: f32| {
+            world.insert_resource(State::new(state));
+            world
+                .resource_mut::<Time<Real>>()
+                .update_with_duration(Duration::from_secs_f32(secs));
+            frame.run(world);
+        };
+
+        play_for(&mut world, GameState::Playing, 2.0);
+        play_for(&mut world, GameState::Paused, 30.0);
+        play_for(&mut world, GameState::Playing, 1.0);
+
+        let play_time = world.resource::<PlayTime>();
+        assert!((play_time.session - 3.0).abs() < 1e-6);
+        assert!((play_time.unbanked - 3.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn menu_focus_wraps_and_confirm_picks_it() {
+        let mut world = World::new();
+        world.init_resource::<Input<KeyCode>>();
+        world.init_resource::<Gamepads>();
+        world.init_resource::<Input<GamepadButton>>();
+        world.insert_resource(Menu::new(
+            vec![MenuAction::Resume, MenuAction::Restart, MenuAction::Quit],
+            None,
+        ));
+        world.init_resource::<Events<MenuSelected>>();
+        world.init_resource::<Events<MenuAdjusted>>();
+        let press = |world: &mut World, key: KeyCode| {
+            let mut keyboard = world.resource_mut::<Input<KeyCode>>();
+            keyboard.clear();
+            keyboard.release_all();
+            keyboard.press(key);
+            world.run_system_once(menu_navigation);
+            world.resource::<Menu>().selected
+        };
+
+        assert_eq!(press(&mut world, KeyCode::Up), 2);
+        assert_eq!(press(&mut world, KeyCode::Down), 0);
+        assert_eq!(press(&mut world, KeyCode::Down), 1);
+        assert!(world.resource::<Events<MenuSelected>>().is_empty());
+
+        press(&mut world, KeyCode::Return);
+        let selected: Vec<MenuAction> = world
+            .resource_mut::<Events<MenuSelected>>()
+            .drain()
+            .map(|MenuSelected(action)| action)
+            .collect();
+        assert_eq!(selected, vec![MenuAction::Restart]);
+    }
+
+    fn enemy_bullets_left(world: &mut World) -> usize {
+        world
+            .query_filtered::<(), With<EnemyBullet>>()
+            .iter(world)
+            .count()
+    }
+
+    #[test]
+    fn losing_a_life_clears_enemy_fire() {
+        let mut world = hit_world(3);
+        spawn_test_player(&mut world, Vec2::new(0.0, -200.0));
+        for x in [-300.0, 0.0, 300.0] {
+            spawn_test_enemy_bullet(&mut world, Vec2::new(x, 250.0));
+        }
+        world.send_event(PlayerHit);
+        world.run_system_once(player_hit);
+        assert_eq!(world.resource::<Lives>().0, 2);
+        assert_eq!(enemy_bullets_left(&mut world), 0);
+    }
+
+    #[test]
+    fn game_over_clears_enemy_fire_unless_kept() {
+        for clear_bullets_on_hit in [true, false] {
+            let mut world = World::new();
+            world.insert_resource(Settings {
+                clear_bullets_on_hit,
+                ..default()
+            });
+            spawn_test_enemy_bullet(&mut world, Vec2::ZERO);
+            world.run_system_once(clear_enemy_bullets);
+            let expected = if clear_bullets_on_hit { 0 } else { 1 };
+            assert_eq!(enemy_bullets_left(&mut world), expected);
+        }
+    }
+
+    #[test]
+    fn picking_up_a_held_power_up_refreshes_it() {
+        let mut power_ups = ActivePowerUps::default();
+        power_ups.apply_power_up(PowerUpKind::Pierce);
+        let timer = power_ups.pierce.as_mut().unwrap();
+        timer.tick(Duration::from_secs_f32(POWER_UP_DURATION_SECS / 2.0));
+
+        power_ups.apply_power_up(PowerUpKind::Pierce);
+        let remaining = power_ups.pierce.as_ref().unwrap().remaining_secs();
+        assert!((remaining - POWER_UP_DURATION_SECS).abs() < 1e-4);
+    }
+
+    #[test]
+    fn extending_power_ups_add_time_up_to_the_cap() {
+        let mut power_ups = ActivePowerUps::default();
+        power_ups.apply_power_up(PowerUpKind::Flame);
+        power_ups.apply_power_up(PowerUpKind::Flame);
+        let remaining = power_ups.flame.as_ref().unwrap().remaining_secs();
+        let expected = (2.0 * POWER_UP_DURATION_SECS).min(MAX_POWER_UP_STACK_SECS);
+        assert!((remaining - expected).abs() < 1e-4);
+
+        for _ in 0..20 {
+            power_ups.apply_power_up(PowerUpKind::Flame);
+        }
+        let remaining = power_ups.flame.as_ref().unwrap().remaining_secs();
+        assert!((remaining - MAX_POWER_UP_STACK_SECS).abs() < 1e-4);
+    }
+
+    #[test]
+    fn rapid_fire_pickups_advance_its_tier() {
+        let mut power_ups = ActivePowerUps::default();
+        assert_eq!(power_ups.rapid_fire_factor(), 1.0);
+        power_ups.apply_power_up(PowerUpKind::RapidFire);
+        assert_eq!(power_ups.rapid_fire_tier, 1);
+        let first = power_ups.rapid_fire_factor();
+
+        power_ups.apply_power_up(PowerUpKind::RapidFire);
+        assert_eq!(power_ups.rapid_fire_tier, 2);
+        assert!(power_ups.rapid_fire_factor() < first);
+
+        for _ in 0..10 {
+            power_ups.apply_power_up(PowerUpKind::RapidFire);
+        }
+        assert_eq!(power_ups.rapid_fire_tier, RAPID_FIRE_MAX_TIER);
+    }
+
+    fn kill_score(scoring: ScoringConfig) -> u32 {
+        let mut world = combat_world();
+        world.insert_resource(scoring);
+        spawn_test_enemy(&mut world, EnemyKind::Swarmer, Vec2::ZERO);
+        spawn_test_bullet(&mut world, Vec2::ZERO, 1);
+        world.run_system_once(bullet_enemy_collision);
+        world.resource::<Score>().0
+    }
+
+    #[test]
+    fn kills_score_what_the_config_says() {
+        assert_eq!(kill_score(ScoringConfig::default()), 10);
+        let scoring = ScoringConfig {
+            kill: 35,
+            ..default()
+        };
+        assert_eq!(kill_score(scoring), 35);
+    }
+
+    /// Worst imbalance between left and right spawns over any 20 in a row, out of
+    /// 2000, with `balancing` as the side-balancing strength.
+    fn worst_side_imbalance(balancing: f32) -> i32 {
+        let mut director = SpawnDirector::default();
+        director.balance.spawn_side_balancing = balancing;
+        let mut rng = StdRng::seed_from_u64(7);
+        let sides: Vec<i32> = (0..2000)
+            .map(|_| balanced_spawn_x(&mut director, &mut rng, 350.0).signum() as i32)
+            .collect();
+        assert!(sides.iter().sum::<i32>().abs() < 100);
+        sides
+            .windows(20)
+            .map(|window| window.iter().sum::<i32>().abs())
+            .max()
+            .unwrap()
+    }
+
+    #[test]
+    fn balanced_spawns_keep_both_sides_busy() {
+        let balanced = worst_side_imbalance(1.0);
+        let random = worst_side_imbalance(0.0);
+        assert!(balanced <= 8, "{balanced} more on one side out of 20");
+        assert!(balanced < random);
+    }
+
+    #[test]
+    fn cycle_wraps_past_either_end() {
+        let all = [1, 2, 3];
+        assert_eq!(cycle(&all, 1, 1.0), 2);
+        assert_eq!(cycle(&all, 3, 1.0), 1);
+        assert_eq!(cycle(&all, 1, -1.0), 3);
+        assert_eq!(cycle(&all, 2, -1.0), 1);
+        assert_eq!(WrapMode::Off.step(-1.0), WrapMode::Everything);
+    }
+
+    /// High score left after quitting mid-run on `score`, from a best of 100.
+    fn high_score_after_quit(score: u32, sandbox: bool) -> u32 {
+        let mut world = World::new();
+        world.init_resource::<Events<AppExit>>();
+        world.init_resource::<Events<WindowCloseRequested>>();
+        world.init_resource::<PlayTime>();
+        world.init_resource::<Profile>();
+        world.insert_resource(HighScore(100));
+        world.insert_resource(Score(score));
+        world.insert_resource(Sandbox {
+            on: sandbox,
+            ..default()
+        });
+        world.init_resource::<ReplayMode>();
+        let system = world.register_system(bank_on_exit);
+
+        world.run_system(system).unwrap();
+        assert_eq!(world.resource::<HighScore>().0, 100);
+        world.send_event(AppExit);
+        world.run_system(system).unwrap();
+        world.resource::<HighScore>().0
+    }
+
+    #[test]
+    fn quitting_mid_run_can_set_the_high_score() {
+        assert_eq!(high_score_after_quit(250, false), 250);
+        assert_eq!(high_score_after_quit(50, false), 100);
+        assert_eq!(high_score_after_quit(250, true), 100);
+    }
+
+    #[test]
+    fn quitting_banks_play_time_once() {
+        let mut world = World::new();
+        world.init_resource::<Events<AppExit>>();
+        world.init_resource::<Events<WindowCloseRequested>>();
+        world.insert_resource(PlayTime {
+            session: 30.0,
+            unbanked: 30.0,
+        });
+        world.init_resource::<Profile>();
+        world.insert_resource(HighScore(0));
+        world.insert_resource(Score(0));
+        world.init_resource::<Sandbox>();
+        world.init_resource::<ReplayMode>();
+        let system = world.register_system(bank_on_exit);
+
+        world.send_event(AppExit);
+        world.run_system(system).unwrap();
+        world.resource_mut::<PlayTime>().unbanked = 5.0;
+        world.send_event(AppExit);
+        world.run_system(system).unwrap();
+        assert_eq!(world.resource::<Profile>().play_secs, 30.0);
+    }
+
+    #[test]
+    fn quitting_saves_once() {
+        let dir = std::env::temp_dir().join(format!("save-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let mut world = World::new();
+        world.init_resource::<Events<AppExit>>();
+        world.init_resource::<Events<WindowCloseRequested>>();
+        world.init_resource::<Profile>();
+        world.insert_resource(SaveDir(dir.clone()));
+        world.init_resource::<Settings>();
+        world.insert_resource(HighScore(250));
+        world.init_resource::<ReplayMode>();
+        let system = world.register_system(save_on_exit);
+        let saved = [PROFILE_PATH, HIGH_SCORE_PATH, SETTINGS_PATH].map(|name| dir.join(name));
+
+        world.run_system(system).unwrap();
+        assert!(saved.iter().all(|path| !path.exists()));
+        world.send_event(AppExit);
+        world.run_system(system).unwrap();
+        assert_eq!(fs::read_to_string(&saved[1]).unwrap(), "250");
+        assert!(saved.iter().all(|path| path.exists()));
+
+        // A close request after the exit mustn't write anything again.
+        for path in &saved {
+            fs::remove_file(path).unwrap();
+        }
+        world.send_event(WindowCloseRequested {
+            window: Entity::PLACEHOLDER,
+        });
+        world.run_system(system).unwrap();
+        assert!(saved.iter().all(|path| !path.exists()));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// Frames at 60 a second before the director can afford its first spawn.
+    fn frames_to_first_spawn(base: f32) -> u32 {
+        let mut world = director_world(1.0);
+        world.insert_resource(BaseDifficulty(base));
+        (1..)
+            .find(|_| {
+                tick(&mut world, 1.0 / 60.0);
+                world.run_system_once(spawn_enemies);
+                world.resource::<WaveManager>().spawned > 0
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn double_base_difficulty_halves_the_spawn_interval() {
+        let normal = frames_to_first_spawn(1.0);
+        let doubled = frames_to_first_spawn(2.0);
+        assert!(normal > 10);
+        assert!(
+            doubled.abs_diff(normal / 2) <= 1,
+            "{doubled} frames against {normal}"
+        );
+    }
+
+    #[test]
+    fn daily_challenges_ignore_the_base_difficulty() {
+        let settings = Settings {
+            base_difficulty: 1.5,
+            ..default()
+        };
+        let daily = DailyChallenge { day: Some(20_000) };
+        assert_eq!(BaseDifficulty::for_run(&settings, &daily).0, 1.0);
+        let off = DailyChallenge::default();
+        assert_eq!(BaseDifficulty::for_run(&settings, &off).0, 1.5);
+    }
+
+    #[test]
+    fn every_gun_fires_each_volley() {
+        let (mut world, player) = shooting_world();
+        world.resource_mut::<Settings>().auto_fire = true;
+        let guns = vec![Vec2::new(-12.0, 20.0), Vec2::new(12.0, 20.0)];
+        world.get_mut::<Player>(player).unwrap().gun_offsets = guns.clone();
+
+        tick(&mut world, PLAYER_FIRE_INTERVAL_SECS);
+        world.run_system_once(player_shooting);
+
+        let mut muzzles: Vec<Vec2> = world
+            .query_filtered::<&Transform, With<Bullet>>()
+            .iter(&world)
+            .map(|transform| transform.translation.truncate() - Vec2::new(0.0, -200.0))
+            .collect();
+        muzzles.sort_by(|a, b| a.x.total_cmp(&b.x));
+        assert_eq!(muzzles, guns);
+    }
+
+    #[test]
+    fn pausing_leaves_the_run_alone() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .insert_resource(State::new(GameState::Paused))
+            .add_state::<GameState>()
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+                100,
+            )))
+            .insert_resource(Score(500))
+            .init_resource::<SurvivalBonus>()
+            .insert_resource(GracePeriod::new(&Balance::default()))
+            .init_resource::<PlayTime>()
+            .insert_resource(Rewind::new(1))
+            .insert_resource(HitStop { remaining: 0.5 })
+            .insert_resource(FreezePulse {
+                cooldown: Timer::from_seconds(FREEZE_COOLDOWN_SECS, TimerMode::Once),
+            })
+            .init_resource::<Sandbox>()
+            .init_resource::<ReplayMode>()
+            .add_plugins(GameplayPlugin);
+
+        for _ in 0..50 {
+            app.update();
+        }
+
+        let world = &app.world;
+        assert!(world.resource::<Time>().elapsed_seconds() > 4.0);
+        assert_eq!(world.resource::<Score>().0, 500);
+        assert_eq!(world.resource::<SurvivalBonus>().timer.elapsed_secs(), 0.0);
+        assert_eq!(world.resource::<GracePeriod>().0.elapsed_secs(), 0.0);
+        assert_eq!(world.resource::<PlayTime>().session, 0.0);
+        assert_eq!(world.resource::<Rewind>().clock, 0.0);
+        assert_eq!(world.resource::<HitStop>().remaining, 0.5);
+        assert_eq!(world.resource::<FreezePulse>().cooldown.elapsed_secs(), 0.0);
+    }
+
+    #[test]
+    fn off_screen_allows_for_the_size_and_margin() {
+        let play_area = PlayArea {
+            half_size: Vec2::new(400.0, 225.0),
+        };
+        assert_eq!(
+            play_area.off_screen(Vec2::new(40.0, 20.0)),
+            Vec2::new(420.0 + OFF_SCREEN_MARGIN, 235.0 + OFF_SCREEN_MARGIN)
+        );
+
+        // Letterboxed, enemies still come in just above the top, whatever their kind.
+        let balance = Balance::default();
+        let spawn_y = play_area.enemy_spawn_y(&balance);
+        assert!(spawn_y < 300.0);
+        for kind in EnemyKind::ALL {
+            let size = kind.hitbox(false, &balance) * (1.0 + ENEMY_SIZE_JITTER);
+            assert!(spawn_y >= play_area.off_screen(size).y);
+        }
+    }
+
+    #[test]
+    fn escapes_wait_for_the_whole_sprite() {
+        let mut world = World::new();
+        world.init_resource::<PlayArea>();
+        world.init_resource::<Settings>();
+        world.init_resource::<Events<EnemyEscaped>>();
+        let tall = Vec2::new(40.0, 100.0);
+        let bottom = -PlayArea::default().off_screen(tall).y;
+        let enemy = spawn_test_enemy(&mut world, EnemyKind::Basic, Vec2::new(0.0, bottom + 1.0));
+        world.get_mut::<Sprite>(enemy).unwrap().custom_size = Some(tall);
+
+        world.run_system_once(enemy_escape);
+        assert!(world.get_entity(enemy).is_some());
+
+        world.get_mut::<Transform>(enemy).unwrap().translation.y = bottom - 1.0;
+        world.run_system_once(enemy_escape);
+        assert!(world.get_entity(enemy).is_none());
+    }
+
+    #[test]
+    fn a_lingering_piercing_shot_hits_once_per_window() {
+        let mut world = combat_world();
+        let enemy = spawn_test_enemy(&mut world, EnemyKind::Carrier, Vec2::ZERO);
+        let bullet = spawn_test_bullet(&mut world, Vec2::ZERO, 1);
+        world.entity_mut(bullet).insert(Pierce::default());
+        let health = EnemyKind::Carrier.max_health();
+
+        // Whole frames overlapping the enemy, short of the window.
+        let frames = (HIT_IMMUNITY_SECS * 60.0) as u32 - 1;
+        for _ in 0..frames {
+            tick(&mut world, 1.0 / 60.0);
+            world.run_system_once(bullet_enemy_collision);
+        }
+        assert!(world.get_entity(bullet).is_some());
+        assert_eq!(world.get::<Enemy>(enemy).unwrap().health, health - 1);
+
+        // Once the window is up the same shot lands again.
+        tick(&mut world, HIT_IMMUNITY_SECS);
+        world.run_system_once(bullet_enemy_collision);
+        assert_eq!(world.get::<Enemy>(enemy).unwrap().health, health - 2);
+        assert!(world.get::<Pierce>(bullet).unwrap().landed);
+    }
+
+    #[test]
+    fn a_shot_coming_back_cannot_hit_again_inside_the_window() {
+        let mut world = combat_world();
+        let enemy = spawn_test_enemy(&mut world, EnemyKind::Carrier, Vec2::ZERO);
+        let bullet = spawn_test_bullet(&mut world, Vec2::ZERO, 1);
+        world.entity_mut(bullet).insert(Pierce::default());
+        let health = EnemyKind::Carrier.max_health();
+        let move_bullet = |world: &mut World, position: Vec2| {
+            world.get_mut::<Transform>(bullet).unwrap().translation = position.extend(0.0);
+            tick(world, HIT_IMMUNITY_SECS / 4.0);
+            world.run_system_once(bullet_enemy_collision);
+        };
+
+        // Through the enemy, clear of it, then back into it, as after a bounce.
+        move_bullet(&mut world, Vec2::ZERO);
+        move_bullet(&mut world, Vec2::new(0.0, 200.0));
+        move_bullet(&mut world, Vec2::ZERO);
+        assert_eq!(world.get::<Enemy>(enemy).unwrap().health, health - 1);
+
+        // The window is only for that shot; another one lands straight away.
+        spawn_test_bullet(&mut world, Vec2::ZERO, 1);
+        tick(&mut world, 1.0 / 60.0);
+        world.run_system_once(bullet_enemy_collision);
+        assert_eq!(world.get::<Enemy>(enemy).unwrap().health, health - 2);
+    }
+
+    #[test]
+    fn sustained_fire_holds_the_configured_rate() {
+        let (mut world, _) = shooting_world();
+        world.resource_mut::<Settings>().auto_fire = true;
+        // Uneven frames, each shorter than the interval but rarely a whole fraction of it.
+        let deltas = [1.0 / 144.0, 1.0 / 30.0, 1.0 / 60.0, 0.021, 1.0 / 90.0];
+        let mut elapsed = 0.0;
+        let mut shots = 0;
+        for frame in 0..600 {
+            let delta = deltas[frame % deltas.len()];
+            elapsed += delta;
+            tick(&mut world, delta);
+            world.run_system_once(player_shooting);
+            shots += bullet_count(&mut world);
+            despawn_all::<Bullet>(&mut world);
+        }
+        let expected = elapsed / PLAYER_FIRE_INTERVAL_SECS;
+        assert!(
+            (shots as f32 - expected).abs() <= 1.0,
+            "{shots} shots in {elapsed}s, expected {expected}"
+        );
+    }
+
+    /// A stretch of play driven by `mode`: the recorded keys flow through the ship's
+    /// movement and guns into spawns, collisions and score, seeded from `options`.
+    fn replay_world(options: &LaunchOptions, mode: ReplayMode) -> (World, Schedule) {
+        let (mut world, _) = shooting_world();
+        world.insert_resource(options.rng());
+        world.insert_resource(mode);
+        world.init_resource::<Events<AppExit>>();
+        world.insert_resource(Score(0));
+        world.init_resource::<Combo>();
+        world.init_resource::<ScoringConfig>();
+        world.init_resource::<CameraPunch>();
+        world.init_resource::<HitStop>();
+        world.init_resource::<Events<ChainKill>>();
+        world.init_resource::<Events<EnemyDestroyed>>();
+        world.init_resource::<Events<Missed>>();
+        world.insert_resource(Difficulty {
+            level: 1.0,
+            speed_multiplier: 1.0,
+        });
+        world.insert_resource(BaseDifficulty(1.0));
+        world.init_resource::<EntranceConfig>();
+        world.insert_resource(WaveManager {
+            total: u32::MAX,
+            ..default()
+        });
+        world.init_resource::<SpawnHeatmap>();
+        world.init_resource::<TimeScale>();
+        world.init_resource::<Modifiers>();
+        let mut frame = Schedule::default();
+        frame.add_systems(
+            (
+                replay::play_input,
+                replay::record_keys,
+                player_movement,
+                player_shooting,
+                bullet_movement,
+                spawn_enemies,
+                spawn_fades,
+                enemy_movement,
+                bullet_enemy_collision,
+            )
+                .chain(),
+        );
+        (world, frame)
+    }
+
+    #[test]
+    fn a_replay_reaches_the_recorded_score() {
+        let path = std::env::temp_dir().join(format!("replay-test-{}.json", std::process::id()));
+        let mut options = LaunchOptions {
+            seed: Some(11),
+            record: Some(path.clone()),
+            ..default()
+        };
+        let mut settings = Settings::default();
+        let mut magnet_radius = 0.0;
+        let mode = ReplayMode::start(&mut options, &mut settings, &mut magnet_radius);
+        let (mut world, mut frame) = replay_world(&options, mode);
+        let fire = world.resource::<KeyBindings>().key(GameAction::Fire);
+        for step in 0..900 {
+            let mut input = world.resource_mut::<Input<KeyCode>>();
+            input.clear();
+            input.release_all();
+            if step / 40 % 3 != 2 {
+                input.press(fire);
+            }
+            input.press(if step % 90 < 45 {
+                KeyCode::A
+            } else {
+                KeyCode::D
+            });
+            tick(&mut world, 1.0 / 60.0);
+            frame.run(&mut world);
+        }
+        let recorded = world.resource::<Score>().0;
+        assert!(recorded > 0);
+        world.send_event(AppExit);
+        world.run_system_once(replay::finish_replay);
+
+        let mut options = LaunchOptions {
+            replay: Some(path.clone()),
+            ..default()
+        };
+        let mode = ReplayMode::start(&mut options, &mut settings, &mut magnet_radius);
+        let _ = std::fs::remove_file(&path);
+        assert!(matches!(mode, ReplayMode::Playing { .. }));
+        let (mut world, mut frame) = replay_world(&options, mode);
+        let mut frames = 0;
+        while world.resource::<Events<AppExit>>().is_empty() {
+            tick(&mut world, 1.0 / 60.0);
+            frame.run(&mut world);
+            frames += 1;
+        }
+        assert_eq!(frames, 900);
+        assert_eq!(world.resource::<Score>().0, recorded);
+    }
+
+    #[test]
+    fn restarting_clears_hit_stop_and_camera_kicks() {
+        let mut world = World::new();
+        world.init_resource::<LaunchOptions>();
+        world.init_resource::<Settings>();
+        world.init_resource::<DailyChallenge>();
+        world.init_resource::<SpawnDirector>();
+        world.init_resource::<SpawnTimeline>();
+        world.init_resource::<ModifierConfig>();
+        world.insert_resource(HitStop { remaining: 0.1 });
+        let mut punch = CameraPunch::default();
+        punch.trigger(LEADER_KILL_PUNCH);
+        world.insert_resource(punch);
+        world.insert_resource(ScreenShake { trauma: 1.0 });
+
+        world.run_system_once(reset_game);
+
+        assert_eq!(world.resource::<HitStop>().remaining, 0.0);
+        assert_eq!(world.resource::<CameraPunch>().remaining, 0.0);
+        assert_eq!(world.resource::<ScreenShake>().trauma, 0.0);
+    }
+
+    #[test]
+    fn every_power_up_kind_can_drop() {
+        let mut world = combat_world();
+        for i in 0..400 {
+            let position = Vec2::new((i % 20) as f32 * 100.0, (i / 20) as f32 * 100.0);
+            spawn_test_enemy(&mut world, EnemyKind::Swarmer, position);
+            spawn_test_bullet(&mut world, position, 1);
+        }
+        world.run_system_once(bullet_enemy_collision);
+
+        let dropped: HashSet<PowerUpKind> = world
+            .query::<&PowerUp>()
+            .iter(&world)
+            .map(|power_up| power_up.kind)
+            .collect();
+        assert_eq!(dropped, HashSet::from(PowerUpKind::ALL));
+    }
+}
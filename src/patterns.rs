@@ -0,0 +1,199 @@
+//! Bullet patterns shared by bosses and any other enemy that fires.
+
+use bevy::prelude::*;
+use serde::Deserialize;
+use std::f32::consts::TAU;
+
+/// Width of a homing fan, in radians.
+const HOMING_SPREAD: f32 = 1.6;
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub enum Pattern {
+    /// A fan of `count` bullets centred on the target, `spread` radians wide.
+    Aimed { count: u32, spread: f32, speed: f32 },
+    /// Evenly spaced bullets whose starting angle advances by `turn` each volley.
+    Spiral { count: u32, speed: f32, turn: f32 },
+    /// A full ring of bullets.
+    Radial { count: u32, speed: f32 },
+    /// A fan of `count` slow bullets that turn after the target by at most
+    /// `turn_rate` radians a second, and burn out after `lifetime` seconds.
+    Homing {
+        count: u32,
+        speed: f32,
+        turn_rate: f32,
+        lifetime: f32,
+    },
+    /// A curtain of `count` bullets dropped straight down a column `width` wide, lined
+    /// up on the target. Telegraphed.
+    Column { count: u32, width: f32, speed: f32 },
+    /// A ring of `count` bullets bursting out of a circle `radius` wide, centred on the
+    /// target. Telegraphed.
+    Burst { count: u32, radius: f32, speed: f32 },
+}
+
+/// Where a telegraphed attack will land, marked out before it does.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Zone {
+    Column { x: f32, width: f32 },
+    Circle { center: Vec2, radius: f32 },
+}
+
+impl Pattern {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Pattern::Aimed { .. } => "Aimed",
+            Pattern::Spiral { .. } => "Spiral",
+            Pattern::Radial { .. } => "Radial",
+            Pattern::Homing { .. } => "Homing",
+            Pattern::Column { .. } => "Column",
+            Pattern::Burst { .. } => "Burst",
+        }
+    }
+
+    /// The bullet count and speed every pattern has, and the angle in radians shaping
+    /// the ones that have one, named: a fan's spread, a spiral's turn per volley or a
+    /// homing bullet's turn rate.
+    pub fn params_mut(&mut self) -> (&mut u32, &mut f32, Option<(&'static str, &mut f32)>) {
+        match self {
+            Pattern::Aimed {
+                count,
+                spread,
+                speed,
+            } => (count, speed, Some(("Spread", spread))),
+            Pattern::Spiral { count, speed, turn } => (count, speed, Some(("Turn", turn))),
+            Pattern::Radial { count, speed } => (count, speed, None),
+            Pattern::Homing {
+                count,
+                speed,
+                turn_rate,
+                ..
+            } => (count, speed, Some(("Turn Rate", turn_rate))),
+            Pattern::Column { count, speed, .. } | Pattern::Burst { count, speed, .. } => {
+                (count, speed, None)
+            }
+        }
+    }
+
+    /// Whether the numbers make sense; a homing lifetime must be positive to time.
+    pub fn is_valid(&self) -> bool {
+        match *self {
+            Pattern::Homing {
+                turn_rate,
+                lifetime,
+                ..
+            } => turn_rate >= 0.0 && lifetime.is_finite() && lifetime > 0.0,
+            Pattern::Column { width, .. } => width > 0.0,
+            Pattern::Burst { radius, .. } => radius > 0.0,
+            _ => true,
+        }
+    }
+
+    /// Bullet velocities for one volley fired from `origin`. `volley` is the number of
+    /// volleys already fired, which rotating patterns use to advance their angle.
+    pub fn velocities(&self, origin: Vec2, target: Vec2, volley: u32) -> Vec<Vec2> {
+        match *self {
+            Pattern::Aimed {
+                count,
+                spread,
+                speed,
+            } => {
+                let aim = (target - origin).try_normalize().unwrap_or(Vec2::NEG_Y);
+                let base_angle = aim.y.atan2(aim.x);
+                let (start, step) = if count > 1 {
+                    (base_angle - spread / 2.0, spread / (count - 1) as f32)
+                } else {
+                    (base_angle, 0.0)
+                };
+
+                (0..count)
+                    .map(|i| Vec2::from_angle(start + step * i as f32) * speed)
+                    .collect()
+            }
+            Pattern::Spiral { count, speed, turn } => ring(count, speed, turn * volley as f32),
+            Pattern::Radial { count, speed } => ring(count, speed, 0.0),
+            // Fanned wide so the bullets come round at the target from either side.
+            Pattern::Homing { count, speed, .. } => Pattern::Aimed {
+                count,
+                spread: HOMING_SPREAD,
+                speed,
+            }
+            .velocities(origin, target, volley),
+            Pattern::Column { count, speed, .. } => vec![Vec2::new(0.0, -speed); count as usize],
+            Pattern::Burst { count, speed, .. } => ring(count, speed, 0.0),
+        }
+    }
+
+    /// The zone to warn of before firing at `target`, for patterns that land somewhere
+    /// rather than leaving from the shooter.
+    pub fn zone(&self, target: Vec2) -> Option<Zone> {
+        match *self {
+            Pattern::Column { width, .. } => Some(Zone::Column { x: target.x, width }),
+            Pattern::Burst { radius, .. } => Some(Zone::Circle {
+                center: target,
+                radius,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Starting positions and velocities of the bullets a telegraphed pattern fires
+    /// once `zone` lands. Columns drop from `top`.
+    pub fn landing(&self, zone: Zone, top: f32) -> Vec<(Vec2, Vec2)> {
+        let velocities = self.velocities(Vec2::ZERO, Vec2::ZERO, 0);
+        let count = velocities.len();
+        match zone {
+            Zone::Column { x, width } => velocities
+                .into_iter()
+                .enumerate()
+                .map(|(i, velocity)| {
+                    let offset = width * ((i as f32 + 0.5) / count as f32 - 0.5);
+                    (Vec2::new(x + offset, top), velocity)
+                })
+                .collect(),
+            Zone::Circle { center, .. } => velocities
+                .into_iter()
+                .map(|velocity| (center, velocity))
+                .collect(),
+        }
+    }
+}
+
+/// Turns `velocity` toward `target_direction` by no more than `max_turn` radians,
+/// keeping its speed.
+pub fn steer(velocity: Vec2, target_direction: Vec2, max_turn: f32) -> Vec2 {
+    let angle = velocity.angle_between(target_direction);
+    if angle.is_nan() {
+        return velocity;
+    }
+    Vec2::from_angle(angle.clamp(-max_turn, max_turn)).rotate(velocity)
+}
+
+/// Keeps at most `max` of a volley's velocities, spaced evenly through it so a thinned
+/// ring or fan keeps its shape.
+pub fn thin<T: Copy>(velocities: Vec<T>, max: usize) -> Vec<T> {
+    let len = velocities.len();
+    if len <= max {
+        return velocities;
+    }
+    (0..max).map(|i| velocities[i * len / max]).collect()
+}
+
+fn ring(count: u32, speed: f32, offset: f32) -> Vec<Vec2> {
+    let step = TAU / count.max(1) as f32;
+    (0..count)
+        .map(|i| Vec2::from_angle(offset + step * i as f32) * speed)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn thin_keeps_volleys_under_the_limit() {
+        let volley: Vec<u32> = (0..12).collect();
+        assert_eq!(thin(volley.clone(), 20), volley);
+        assert_eq!(thin(volley.clone(), 4), vec![0, 3, 6, 9]);
+        assert!(thin(volley, 0).is_empty());
+    }
+}
@@ -0,0 +1,346 @@
+//! Recording a session's input to a file with `--record`, and playing it back with
+//! `--replay`.
+//!
+//! A replay holds the seed and launch options, the settings that shape play, and the
+//! keys held and aim direction on every frame. While recording or playing back, time
+//! advances by a fixed `REPLAY_STEP` each frame and auto-pause is off, so the same
+//! input lands on the same frame both times. Touch and gamepad movement are not
+//! recorded, and shop purchases use whatever profile is on disk.
+
+use bevy::app::AppExit;
+use bevy::prelude::*;
+use bevy::time::TimeUpdateStrategy;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::{Aim, LaunchOptions, Score, Settings};
+
+/// Bumped whenever `Replay` changes shape; replays from another version are refused.
+const REPLAY_VERSION: u32 = 1;
+const REPLAY_STEP: Duration = Duration::from_nanos(1_000_000_000 / 60);
+
+#[derive(Serialize, Deserialize)]
+pub struct Replay {
+    version: u32,
+    /// Gameplay changes between releases would play the same input out differently.
+    game_version: String,
+    options: LaunchOptions,
+    settings: Settings,
+    magnet_radius: f32,
+    frames: Vec<ReplayFrame>,
+    /// Score when recording stopped, checked against the score playback ends on.
+    final_score: Option<u32>,
+}
+
+/// Input for `repeat` frames in a row.
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+struct ReplayFrame {
+    keys: Vec<KeyCode>,
+    aim: Option<Vec2>,
+    repeat: u32,
+}
+
+#[derive(Resource, Default)]
+pub enum ReplayMode {
+    #[default]
+    Off,
+    Recording {
+        path: PathBuf,
+        replay: Replay,
+    },
+    Playing {
+        replay: Replay,
+        /// Index into `Replay::frames`, and how many of its repeats have been played.
+        frame: usize,
+        repeat: u32,
+    },
+}
+
+impl ReplayMode {
+    /// Sets up recording or playback from the command line. Playback swaps in the
+    /// recorded options, settings and magnet radius before the app is built.
+    pub fn start(
+        options: &mut LaunchOptions,
+        settings: &mut Settings,
+        magnet_radius: &mut f32,
+    ) -> Self {
+        if let Some(path) = options.replay.clone() {
+            return match load(&path) {
+                Ok(replay) => {
+                    *options = replay.options.clone();
+                    *settings = replay.settings.clone();
+                    *magnet_radius = replay.magnet_radius;
+                    ReplayMode::Playing {
+                        replay,
+                        frame: 0,
+                        repeat: 0,
+                    }
+                }
+                Err(err) => {
+                    eprintln!("not playing {}: {err}", path.display());
+                    ReplayMode::Off
+                }
+            };
+        }
+
+        let Some(path) = options.record.clone() else {
+            return ReplayMode::Off;
+        };
+        options.seed.get_or_insert_with(rand::random);
+        settings.auto_pause = false;
+        ReplayMode::Recording {
+            path,
+            replay: Replay {
+                version: REPLAY_VERSION,
+                game_version: env!("CARGO_PKG_VERSION").into(),
+                options: options.clone(),
+                settings: settings.clone(),
+                magnet_radius: *magnet_radius,
+                frames: Vec::new(),
+                final_score: None,
+            },
+        }
+    }
+
+    pub fn is_on(&self) -> bool {
+        !matches!(self, ReplayMode::Off)
+    }
+
+    /// Fixed time steps for recording and playback; live play keeps real time.
+    pub fn time_strategy(&self) -> TimeUpdateStrategy {
+        if self.is_on() {
+            TimeUpdateStrategy::ManualDuration(REPLAY_STEP)
+        } else {
+            TimeUpdateStrategy::Automatic
+        }
+    }
+}
+
+fn load(path: &PathBuf) -> Result<Replay, String> {
+    let contents = fs::read_to_string(path).map_err(|err| err.to_string())?;
+    let replay: Replay = serde_json::from_str(&contents).map_err(|err| err.to_string())?;
+    if replay.version != REPLAY_VERSION {
+        return Err(format!(
+            "replay is from format version {} (expected {REPLAY_VERSION})",
+            replay.version
+        ));
+    }
+    if replay.game_version != env!("CARGO_PKG_VERSION") {
+        return Err(format!(
+            "replay was recorded with version {} of the game",
+            replay.game_version
+        ));
+    }
+    Ok(replay)
+}
+
+pub fn replaying(mode: Res<ReplayMode>) -> bool {
+    matches!(*mode, ReplayMode::Playing { .. })
+}
+
+/// Notes the keys held this frame, before anything in `Update` can consume them.
+pub fn record_keys(mut mode: ResMut<ReplayMode>, keyboard_input: Res<Input<KeyCode>>) {
+    let ReplayMode::Recording { replay, .. } = &mut *mode else {
+        return;
+    };
+    let mut keys: Vec<KeyCode> = keyboard_input.get_pressed().copied().collect();
+    keys.sort_unstable();
+    replay.frames.push(ReplayFrame {
+        keys,
+        aim: None,
+        repeat: 1,
+    });
+}
+
+/// Fills in the aim this frame's shots used, once `update_aim` has run.
+pub fn record_aim(mut mode: ResMut<ReplayMode>, aim: Res<Aim>) {
+    if let ReplayMode::Recording { replay, .. } = &mut *mode
+        && let Some(frame) = replay.frames.last_mut()
+    {
+        frame.aim = aim.0;
+    }
+}
+
+/// When the app closes, writes a recording out with runs of identical frames merged,
+/// or checks a finished playback against the score it was recorded with.
+pub fn finish_replay(
+    mut exit_events: EventReader<AppExit>,
+    mut mode: ResMut<ReplayMode>,
+    score: Res<Score>,
+) {
+    if exit_events.is_empty() {
+        return;
+    }
+    exit_events.clear();
+    let (path, replay) = match &mut *mode {
+        ReplayMode::Off => return,
+        ReplayMode::Playing { replay, .. } => {
+            match replay.final_score {
+                Some(expected) if expected != score.0 => {
+                    warn!(
+                        "replay diverged: final score {} (recorded {expected})",
+                        score.0
+                    )
+                }
+                _ => info!("replay finished with score {}", score.0),
+            }
+            return;
+        }
+        ReplayMode::Recording { path, replay } => (path, replay),
+    };
+
+    replay.frames = merge_frames(std::mem::take(&mut replay.frames));
+    replay.final_score = Some(score.0);
+
+    let result = serde_json::to_string(replay)
+        .map_err(|err| err.to_string())
+        .and_then(|contents| fs::write(&*path, contents).map_err(|err| err.to_string()));
+    match result {
+        Ok(()) => info!("recorded replay to {}", path.display()),
+        Err(err) => warn!("could not write replay to {}: {err}", path.display()),
+    }
+}
+
+/// Folds runs of identical frames into one with a `repeat` count.
+fn merge_frames(recorded: Vec<ReplayFrame>) -> Vec<ReplayFrame> {
+    let mut frames: Vec<ReplayFrame> = Vec::new();
+    for frame in recorded {
+        match frames.last_mut() {
+            Some(last) if last.keys == frame.keys && last.aim == frame.aim => last.repeat += 1,
+            _ => frames.push(frame),
+        }
+    }
+    frames
+}
+
+/// Stands in for live keyboard and aim input with the recorded frame, quitting at the
+/// end of the last one.
+pub fn play_input(
+    mut mode: ResMut<ReplayMode>,
+    mut keyboard_input: ResMut<Input<KeyCode>>,
+    mut aim: ResMut<Aim>,
+    mut exit: EventWriter<AppExit>,
+) {
+    let ReplayMode::Playing {
+        replay,
+        frame,
+        repeat,
+    } = &mut *mode
+    else {
+        return;
+    };
+    // An empty recording has nothing to play.
+    let Some(current) = replay.frames.get(*frame) else {
+        exit.send(AppExit);
+        return;
+    };
+
+    // Rebuilt from scratch so live key presses can't leak in, keeping just-pressed
+    // and just-released true only on the frames the recording changed.
+    let held = match (*repeat, frame.checked_sub(1)) {
+        (0, Some(previous)) => replay.frames[previous].keys.clone(),
+        (0, None) => Vec::new(),
+        _ => current.keys.clone(),
+    };
+    keyboard_input.reset_all();
+    for key in &current.keys {
+        keyboard_input.press(*key);
+        if held.contains(key) {
+            keyboard_input.clear_just_pressed(*key);
+        }
+    }
+    for key in held.iter().filter(|key| !current.keys.contains(key)) {
+        keyboard_input.press(*key);
+        keyboard_input.clear_just_pressed(*key);
+        keyboard_input.release(*key);
+    }
+    aim.0 = current.aim;
+
+    *repeat += 1;
+    if *repeat >= current.repeat {
+        *frame += 1;
+        *repeat = 0;
+        if *frame == replay.frames.len() {
+            exit.send(AppExit);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::ecs::system::RunSystemOnce;
+
+    fn frame(keys: &[KeyCode], aim: Option<Vec2>) -> ReplayFrame {
+        ReplayFrame {
+            keys: keys.to_vec(),
+            aim,
+            repeat: 1,
+        }
+    }
+
+    fn recording() -> Vec<ReplayFrame> {
+        let up = Some(Vec2::Y);
+        vec![
+            frame(&[], None),
+            frame(&[KeyCode::Space], None),
+            frame(&[KeyCode::Space], None),
+            frame(&[KeyCode::A, KeyCode::Space], None),
+            frame(&[KeyCode::A, KeyCode::Space], up),
+            frame(&[], up),
+            frame(&[], up),
+            frame(&[], up),
+        ]
+    }
+
+    #[test]
+    fn merging_keeps_every_frame() {
+        let merged = merge_frames(recording());
+        assert_eq!(merged.len(), 5);
+        let total: u32 = merged.iter().map(|frame| frame.repeat).sum();
+        assert_eq!(total as usize, recording().len());
+    }
+
+    #[test]
+    fn playback_feeds_the_recorded_input_back() {
+        let mut world = World::new();
+        world.init_resource::<Input<KeyCode>>();
+        world.init_resource::<Aim>();
+        world.init_resource::<Events<AppExit>>();
+        world.insert_resource(ReplayMode::Playing {
+            replay: Replay {
+                version: REPLAY_VERSION,
+                game_version: env!("CARGO_PKG_VERSION").into(),
+                options: LaunchOptions::default(),
+                settings: Settings::default(),
+                magnet_radius: 0.0,
+                frames: merge_frames(recording()),
+                final_score: None,
+            },
+            frame: 0,
+            repeat: 0,
+        });
+
+        let mut previous: Vec<KeyCode> = Vec::new();
+        for recorded in recording() {
+            assert!(world.resource::<Events<AppExit>>().is_empty());
+            world.run_system_once(play_input);
+
+            let input = world.resource::<Input<KeyCode>>();
+            let mut held: Vec<KeyCode> = input.get_pressed().copied().collect();
+            held.sort_unstable();
+            assert_eq!(held, recorded.keys);
+            for key in &recorded.keys {
+                assert_eq!(input.just_pressed(*key), !previous.contains(key));
+            }
+            for key in &previous {
+                assert_eq!(input.just_released(*key), !recorded.keys.contains(key));
+            }
+            assert_eq!(world.resource::<Aim>().0, recorded.aim);
+            previous = recorded.keys;
+        }
+        assert_eq!(world.resource::<Events<AppExit>>().len(), 1);
+    }
+}
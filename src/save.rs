@@ -0,0 +1,536 @@
+//! Saving a run when the game is paused, and offering to resume it on the next launch.
+//!
+//! A snapshot holds the run's resources and the position of everything in flight.
+//! Short-lived state is not kept: mines and blasts are dropped, burning enemies go
+//! out, and gunners, divers and the boss restart their current attack from the
+//! beginning. A mini-boss isn't kept either; it comes back at full health once the
+//! resumed wave is cleared. The random number generator isn't kept, so a resumed daily
+//! challenge still counts for its day but no longer matches everyone else's spawns.
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use rand::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::time::Duration;
+
+use crate::{
+    ActivePowerUps, BaseDifficulty, Boss, BossConfig, BossSchedule, Bounce, Bullet, BulletKind,
+    BulletTime, Combo, Continues, DailyChallenge, Dda, Difficulty, Dive, ENEMY_SPEED, Enemy,
+    EnemyBullet, EnemyBulletKind, EnemyKind, Escapes, FormationMember, GameRng, GameState,
+    GrazeMeter, Incendiary, Leader, Lives, MenuAction, MenuSelected, MiniBoss, Modifiers,
+    POWER_UP_DURATION_SECS, Pierce, Player, Portal, PowerUp, PowerUpKind, SCATTER_SPEED, Scatter,
+    Score, ScoreChip, SpawnDirector, SpawnTimeline, SurvivalBonus, Sweep, WaveManager, layers,
+    promote_to_leader, spawn_boss_entity, spawn_bullet, spawn_enemy_at, spawn_enemy_bullet,
+    spawn_power_up,
+};
+
+const SAVE_PATH: &str = "savegame.json";
+/// Bumped whenever `SavedGame` changes shape; saves from another version are rejected.
+const SAVE_VERSION: u32 = 20;
+
+#[derive(Serialize, Deserialize)]
+pub struct SavedGame {
+    version: u32,
+    score: u32,
+    lives: u32,
+    difficulty: f32,
+    speed_multiplier: f32,
+    /// Adaptive difficulty's share of the two above.
+    difficulty_offset: f32,
+    base_difficulty: f32,
+    combo: u32,
+    escapes: u32,
+    continues_used: u32,
+    graze: f32,
+    survival_earned: u32,
+    bullet_time: f32,
+    wave: u32,
+    wave_total: u32,
+    wave_spawned: u32,
+    next_boss_score: u32,
+    spawn_budget: f32,
+    /// How far into the level's spawn timeline the run had got.
+    timeline_secs: f32,
+    /// Seconds left on each power-up, if active.
+    bounce: Option<f32>,
+    rapid_fire: Option<f32>,
+    rapid_fire_tier: u32,
+    pierce: Option<f32>,
+    all_range: Option<f32>,
+    flame: Option<f32>,
+    reflect: Option<f32>,
+    player_x: f32,
+    enemies: Vec<SavedEnemy>,
+    boss: Option<SavedBoss>,
+    bullets: Vec<SavedBullet>,
+    enemy_bullets: Vec<SavedEnemyBullet>,
+    power_ups: Vec<SavedPowerUp>,
+    /// The daily challenge being played, if this run is one.
+    daily_day: Option<u32>,
+    /// The run's modifiers as they were when it started, whatever the list says now.
+    modifiers: Modifiers,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedEnemy {
+    kind: EnemyKind,
+    position: Vec2,
+    health: u32,
+    leader: bool,
+    /// Which way a leader's formation was marching.
+    sweep: Option<f32>,
+    /// Index into `SavedGame::enemies` of this enemy's formation leader.
+    formation: Option<usize>,
+    scattering: bool,
+    /// Divers that already made their run carry on down instead of lining up again.
+    dived: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedBoss {
+    position: Vec2,
+    health: u32,
+    phase: usize,
+    direction: f32,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedBullet {
+    kind: BulletKind,
+    position: Vec2,
+    velocity: Vec2,
+    damage: u32,
+    bounces: Option<u32>,
+    /// Kills so far, for bullets fired with pierce active.
+    pierce_chain: Option<u32>,
+    /// Whether a piercing bullet has hit anything yet.
+    pierce_landed: bool,
+    incendiary: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedEnemyBullet {
+    position: Vec2,
+    velocity: Vec2,
+    kind: EnemyBulletKind,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedPowerUp {
+    kind: PowerUpKind,
+    position: Vec2,
+}
+
+/// A compatible save found at launch, until the player resumes it or starts over.
+#[derive(Resource)]
+pub struct PendingSave(pub Option<SavedGame>);
+
+impl PendingSave {
+    pub fn load() -> Self {
+        let Ok(contents) = fs::read_to_string(SAVE_PATH) else {
+            return PendingSave(None);
+        };
+        let saved = match serde_json::from_str::<SavedGame>(&contents) {
+            Ok(saved) if saved.version == SAVE_VERSION => Some(saved),
+            Ok(saved) => {
+                warn!(
+                    "ignoring saved game from version {} (expected {SAVE_VERSION})",
+                    saved.version
+                );
+                None
+            }
+            Err(err) => {
+                warn!("ignoring unreadable saved game: {err}");
+                None
+            }
+        };
+        PendingSave(saved)
+    }
+}
+
+/// Opens on the pause menu, where the saved run is offered, instead of straight into play.
+pub fn offer_saved_game(pending: Res<PendingSave>, mut next_state: ResMut<NextState<GameState>>) {
+    if pending.0.is_some() {
+        next_state.set(GameState::Paused);
+    }
+}
+
+/// Removes the save once its run has been resumed, abandoned or lost.
+pub fn discard_save() {
+    if let Err(err) = fs::remove_file(SAVE_PATH)
+        && err.kind() != std::io::ErrorKind::NotFound
+    {
+        warn!("failed to remove saved game: {err}");
+    }
+}
+
+#[derive(SystemParam)]
+pub struct RunResources<'w> {
+    score: Res<'w, Score>,
+    lives: Res<'w, Lives>,
+    difficulty: Res<'w, Difficulty>,
+    base: Res<'w, BaseDifficulty>,
+    dda: Res<'w, Dda>,
+    combo: Res<'w, Combo>,
+    escapes: Res<'w, Escapes>,
+    continues: Res<'w, Continues>,
+    graze: Res<'w, GrazeMeter>,
+    survival: Res<'w, SurvivalBonus>,
+    bullet_time: Res<'w, BulletTime>,
+    waves: Res<'w, WaveManager>,
+    boss_schedule: Res<'w, BossSchedule>,
+    director: Res<'w, SpawnDirector>,
+    timeline: Res<'w, SpawnTimeline>,
+    power_ups: Res<'w, ActivePowerUps>,
+    daily: Res<'w, DailyChallenge>,
+    modifiers: Res<'w, Modifiers>,
+}
+
+#[allow(clippy::type_complexity)]
+pub fn save_game(
+    run: RunResources,
+    pending: Res<PendingSave>,
+    player_query: Query<&Transform, With<Player>>,
+    enemy_query: Query<(
+        Entity,
+        &Enemy,
+        &Transform,
+        Has<Leader>,
+        Option<&Sweep>,
+        Option<&FormationMember>,
+        Has<Scatter>,
+        Option<&Dive>,
+    )>,
+    boss_query: Query<(&Boss, &Transform), Without<MiniBoss>>,
+    bullet_query: Query<(
+        &Bullet,
+        &Transform,
+        Option<&Bounce>,
+        Option<&Pierce>,
+        Has<Incendiary>,
+    )>,
+    other_query: Query<(
+        &Transform,
+        Option<&EnemyBullet>,
+        Option<&PowerUp>,
+        Option<&Portal>,
+        Option<&ScoreChip>,
+    )>,
+) {
+    // The run on screen behind the launch prompt is a fresh one; saving it would
+    // overwrite the run being offered.
+    if pending.0.is_some() {
+        return;
+    }
+
+    let enemy_entities: Vec<Entity> = enemy_query.iter().map(|(entity, ..)| entity).collect();
+    let enemies = enemy_query
+        .iter()
+        .map(
+            |(_, enemy, transform, leader, sweep, member, scattering, dive)| SavedEnemy {
+                kind: enemy.kind,
+                position: transform.translation.truncate(),
+                health: enemy.health,
+                leader,
+                sweep: sweep.map(|sweep| sweep.direction),
+                formation: member.and_then(|member| {
+                    enemy_entities
+                        .iter()
+                        .position(|&entity| entity == member.leader)
+                }),
+                scattering,
+                dived: matches!(dive, Some(Dive::Diving { .. } | Dive::Resumed)),
+            },
+        )
+        .collect();
+    // Portals are not saved; the enemies still behind them are spawned again instead.
+    let unreleased: u32 = other_query
+        .iter()
+        .filter_map(|(_, _, _, portal, _)| portal.filter(|portal| !portal.emerged))
+        .map(|portal| portal.spawn.enemy_count())
+        .sum();
+    // Score chips aren't saved, so any still out are banked at full value.
+    let chips: u32 = other_query
+        .iter()
+        .filter_map(|(.., chip)| chip)
+        .map(|chip| chip.value)
+        .sum();
+    let remaining = |timer: &Option<Timer>| timer.as_ref().map(|timer| timer.remaining_secs());
+
+    let saved = SavedGame {
+        version: SAVE_VERSION,
+        score: run.score.0 + chips,
+        lives: run.lives.0,
+        difficulty: run.difficulty.level,
+        speed_multiplier: run.difficulty.speed_multiplier,
+        difficulty_offset: run.dda.offset,
+        base_difficulty: run.base.0,
+        combo: run.combo.count,
+        escapes: run.escapes.0,
+        continues_used: run.continues.used,
+        graze: run.graze.0,
+        survival_earned: run.survival.earned,
+        bullet_time: run.bullet_time.gauge,
+        wave: run.waves.wave,
+        wave_total: run.waves.total,
+        wave_spawned: run.waves.spawned - unreleased,
+        next_boss_score: run.boss_schedule.next_score,
+        spawn_budget: run.director.budget,
+        timeline_secs: run.timeline.elapsed,
+        bounce: remaining(&run.power_ups.bounce),
+        rapid_fire: remaining(&run.power_ups.rapid_fire),
+        rapid_fire_tier: run.power_ups.rapid_fire_tier,
+        pierce: remaining(&run.power_ups.pierce),
+        all_range: remaining(&run.power_ups.all_range),
+        flame: remaining(&run.power_ups.flame),
+        reflect: remaining(&run.power_ups.reflect),
+        player_x: player_query
+            .get_single()
+            .map_or(0.0, |transform| transform.translation.x),
+        enemies,
+        boss: boss_query
+            .get_single()
+            .ok()
+            .map(|(boss, transform)| SavedBoss {
+                position: transform.translation.truncate(),
+                health: boss.health,
+                phase: boss.phase,
+                direction: boss.direction,
+            }),
+        bullets: bullet_query
+            .iter()
+            .map(
+                |(bullet, transform, bounce, pierce, incendiary)| SavedBullet {
+                    kind: bullet.kind,
+                    position: transform.translation.truncate(),
+                    velocity: bullet.velocity,
+                    damage: bullet.damage,
+                    bounces: bounce.map(|bounce| bounce.remaining),
+                    pierce_chain: pierce.map(|pierce| pierce.chain),
+                    pierce_landed: pierce.is_some_and(|pierce| pierce.landed),
+                    incendiary,
+                },
+            )
+            .collect(),
+        enemy_bullets: other_query
+            .iter()
+            .filter_map(|(transform, bullet, ..)| {
+                bullet.map(|bullet| SavedEnemyBullet {
+                    position: transform.translation.truncate(),
+                    velocity: bullet.velocity,
+                    // Homing isn't saved, so it comes back flying straight.
+                    kind: match bullet.kind {
+                        EnemyBulletKind::Homing => EnemyBulletKind::Pattern,
+                        kind => kind,
+                    },
+                })
+            })
+            .collect(),
+        power_ups: other_query
+            .iter()
+            .filter_map(|(transform, _, power_up, ..)| {
+                power_up.map(|power_up| SavedPowerUp {
+                    kind: power_up.kind,
+                    position: transform.translation.truncate(),
+                })
+            })
+            .collect(),
+        daily_day: run.daily.day,
+        modifiers: run.modifiers.clone(),
+    };
+
+    let result = serde_json::to_string(&saved)
+        .map_err(|err| err.to_string())
+        .and_then(|json| fs::write(SAVE_PATH, json).map_err(|err| err.to_string()));
+    if let Err(err) = result {
+        warn!("failed to save game: {err}");
+    }
+}
+
+pub fn handle_save_menu(
+    mut commands: Commands,
+    mut selected_events: EventReader<MenuSelected>,
+    mut pending: ResMut<PendingSave>,
+    mut player_query: Query<&mut Transform, With<Player>>,
+    boss_config: Res<BossConfig>,
+    mut rng: ResMut<GameRng>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    for MenuSelected(action) in selected_events.read() {
+        match action {
+            MenuAction::ContinueRun => {
+                if let Some(saved) = pending.0.take() {
+                    if let Ok(mut transform) = player_query.get_single_mut() {
+                        transform.translation.x = saved.player_x;
+                    }
+                    restore(&mut commands, saved, &boss_config, &mut rng.0);
+                }
+            }
+            MenuAction::NewRun => pending.0 = None,
+            _ => continue,
+        }
+        discard_save();
+        next_state.set(GameState::Playing);
+    }
+}
+
+/// Whether the launch prompt is up with a saved run on offer.
+pub fn save_offered(pending: Res<PendingSave>) -> bool {
+    pending.0.is_some()
+}
+
+/// Leaving the launch prompt for play without choosing also starts a new run. Escape
+/// and the pause key don't leave it; they are ignored while it is up.
+pub fn drop_pending_save(mut pending: ResMut<PendingSave>) {
+    pending.0 = None;
+}
+
+fn restore(commands: &mut Commands, saved: SavedGame, boss_config: &BossConfig, rng: &mut StdRng) {
+    let power_up_timer = |remaining: Option<f32>| {
+        remaining.map(|remaining| {
+            // Stacked time can run past a single duration.
+            let duration = remaining.max(POWER_UP_DURATION_SECS);
+            let mut timer = Timer::from_seconds(duration, TimerMode::Once);
+            timer.set_elapsed(Duration::from_secs_f32(duration - remaining));
+            timer
+        })
+    };
+
+    commands.insert_resource(Score(saved.score));
+    commands.insert_resource(DailyChallenge {
+        day: saved.daily_day,
+    });
+    commands.insert_resource(Lives(saved.lives));
+    commands.insert_resource(saved.modifiers);
+    commands.insert_resource(Difficulty {
+        level: saved.difficulty,
+        speed_multiplier: saved.speed_multiplier,
+    });
+    commands.insert_resource(Dda {
+        offset: saved.difficulty_offset,
+    });
+    commands.insert_resource(BaseDifficulty(saved.base_difficulty));
+    commands.insert_resource(Combo { count: saved.combo });
+    commands.insert_resource(Escapes(saved.escapes));
+    commands.insert_resource(Continues {
+        used: saved.continues_used,
+        ..default()
+    });
+    commands.insert_resource(GrazeMeter(saved.graze));
+    commands.insert_resource(SurvivalBonus {
+        earned: saved.survival_earned,
+        ..default()
+    });
+    commands.insert_resource(BulletTime {
+        gauge: saved.bullet_time,
+        active: false,
+    });
+    commands.insert_resource(WaveManager {
+        total: saved.wave_total,
+        spawned: saved.wave_spawned,
+        ..WaveManager::new(saved.wave.max(1))
+    });
+    commands.insert_resource(BossSchedule {
+        next_score: saved.next_boss_score,
+    });
+    commands.insert_resource(ActivePowerUps {
+        bounce: power_up_timer(saved.bounce),
+        rapid_fire: power_up_timer(saved.rapid_fire),
+        pierce: power_up_timer(saved.pierce),
+        all_range: power_up_timer(saved.all_range),
+        flame: power_up_timer(saved.flame),
+        reflect: power_up_timer(saved.reflect),
+        rapid_fire_tier: saved.rapid_fire_tier,
+    });
+    let (spawn_budget, timeline_secs) = (saved.spawn_budget, saved.timeline_secs);
+    commands.add(move |world: &mut World| {
+        world.resource_mut::<SpawnDirector>().budget = spawn_budget;
+        world.resource_mut::<SpawnTimeline>().seek(timeline_secs);
+    });
+
+    let enemies: Vec<Entity> = saved
+        .enemies
+        .iter()
+        .map(|enemy| {
+            let entity = spawn_enemy_at(commands, enemy.kind, enemy.position);
+            commands.entity(entity).insert(Enemy {
+                speed: ENEMY_SPEED,
+                kind: enemy.kind,
+                health: enemy.health.clamp(1, enemy.kind.max_health()),
+            });
+            entity
+        })
+        .collect();
+    for (enemy, &entity) in saved.enemies.iter().zip(&enemies) {
+        if enemy.leader {
+            promote_to_leader(commands, entity);
+            if let Some(direction) = enemy.sweep {
+                commands.entity(entity).insert(Sweep { direction });
+            }
+        }
+        if let Some(&leader) = enemy.formation.and_then(|index| enemies.get(index)) {
+            commands.entity(entity).insert(FormationMember { leader });
+        }
+        if enemy.scattering {
+            commands.entity(entity).insert(Scatter {
+                velocity_x: rng.gen_range(-SCATTER_SPEED..SCATTER_SPEED),
+                timer: Timer::from_seconds(0.4, TimerMode::Repeating),
+            });
+        }
+        if enemy.dived {
+            commands.entity(entity).insert(Dive::Resumed);
+        }
+    }
+
+    if let Some(saved_boss) = saved.boss {
+        let boss = spawn_boss_entity(commands, boss_config);
+        let phase = saved_boss.phase.min(boss_config.phases.len() - 1);
+        commands.entity(boss).insert((
+            Transform::from_translation(saved_boss.position.extend(layers::ENEMIES)),
+            Boss {
+                health: saved_boss.health.clamp(1, boss_config.health),
+                max_health: boss_config.health,
+                phase,
+                phases: boss_config.phases.clone(),
+                direction: saved_boss.direction,
+                fire_timer: Timer::from_seconds(
+                    boss_config.phases[phase].fire_interval,
+                    TimerMode::Repeating,
+                ),
+                volleys: 0,
+            },
+        ));
+    }
+
+    for bullet in saved.bullets {
+        let entity = spawn_bullet(
+            commands,
+            bullet.kind,
+            bullet.position,
+            bullet.velocity,
+            bullet.damage,
+            bullet.bounces,
+        );
+        if let Some(chain) = bullet.pierce_chain {
+            commands.entity(entity).insert(Pierce {
+                chain,
+                landed: bullet.pierce_landed,
+            });
+        }
+        if bullet.incendiary {
+            commands.entity(entity).insert(Incendiary);
+        }
+    }
+    for bullet in saved.enemy_bullets {
+        spawn_enemy_bullet(
+            commands,
+            bullet.position.extend(0.0),
+            bullet.velocity,
+            bullet.kind,
+        );
+    }
+    for power_up in saved.power_ups {
+        spawn_power_up(commands, power_up.position.extend(0.0), power_up.kind);
+    }
+}